@@ -0,0 +1,57 @@
+use soroban_sdk::contracterror;
+
+/// Error codes the contract returns; matches `lp_staking`'s own enum.
+#[contracterror(export = false)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum ContractError {
+    AlreadyInitialized = 1,
+    NotInitialized = 2,
+    Unauthorized = 3,
+    PoolAlreadyExists = 4,
+    PoolNotFound = 5,
+    InvalidProof = 6,
+    AlreadyStakedThisEpoch = 7,
+    NoStakeFound = 8,
+    NoRewardsToClaim = 9,
+    InsufficientRewardBalance = 10,
+    InvalidAmount = 11,
+    NoMerkleRoot = 12,
+    StaleEpoch = 13,
+    IdentityNotBound = 14,
+    ListenerLimitReached = 15,
+    InsufficientRunway = 16,
+    InvalidConfig = 17,
+    PoolInUse = 18,
+    CannotRescueRewardToken = 19,
+    ContractPaused = 20,
+    NotPaused = 21,
+    TimelockNotElapsed = 22,
+    NoPendingWithdrawal = 23,
+    BatchTooLarge = 24,
+    AlreadyActivated = 25,
+    StakerNotEmpty = 26,
+    PoolCapExceeded = 27,
+    BelowMinimumStake = 28,
+    AddressBlocked = 29,
+    ClaimsFrozen = 30,
+    NotCompliant = 31,
+    WrongPoolMode = 32,
+    InvalidAmmPool = 33,
+    RootTooSoon = 34,
+    RootTooLate = 35,
+    RootExpired = 36,
+    StakerNotExpired = 37,
+    SnapshotNotIncreasing = 38,
+    SnapshotInFuture = 39,
+    PoolPaused = 40,
+    BelowMinimumClaim = 41,
+    PoolMismatch = 42,
+    ProofTooLong = 43,
+    RateTooHigh = 44,
+    CooldownActive = 45,
+    DeclaredTotalExceeded = 46,
+    CampaignNotFound = 47,
+    RewardTransferFailed = 48,
+    NoPendingMigration = 49,
+}
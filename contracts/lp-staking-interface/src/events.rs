@@ -0,0 +1,203 @@
+//! Topics and payloads of the events the contract publishes. The contract
+//! emits exactly these types, so an indexer decoding with them cannot drift
+//! from the emitter. Topics are `(name, ...)` as noted on each constant;
+//! events without a payload struct carry the single value noted.
+
+use soroban_sdk::{contracttype, symbol_short, Address, BytesN, Symbol};
+
+/// `(CLAIMED, user, pool_index)` with [`Claimed`].
+pub const CLAIMED: Symbol = symbol_short!("claim");
+/// `(POOL_EARMARKED, pool_index)` with [`PoolEarmarked`].
+pub const POOL_EARMARKED: Symbol = symbol_short!("earmark");
+/// `(CAMPAIGN_CREATED, pool_index, campaign_id)` with [`CampaignCreated`].
+pub const CAMPAIGN_CREATED: Symbol = symbol_short!("campaign");
+/// `(CAMPAIGN_RECLAIMED, pool_index, campaign_id)` with the amount (i128).
+pub const CAMPAIGN_RECLAIMED: Symbol = symbol_short!("camp_recl");
+/// `(REVENUE_DEPOSITED, token, pool_index)` with the amount (i128).
+pub const REVENUE_DEPOSITED: Symbol = symbol_short!("rev_dep");
+/// `(REVENUE_CLAIMED, user, pool_index)` with [`RevenueClaimed`].
+pub const REVENUE_CLAIMED: Symbol = symbol_short!("rev_claim");
+/// `(CLAIM_SHORTFALL, user, pool_index)` with the shortfall (i128).
+pub const CLAIM_SHORTFALL: Symbol = symbol_short!("claim_sh");
+/// `(STAKER_PRUNED, user, pool_index)` with [`StakerPruned`].
+pub const STAKER_PRUNED: Symbol = symbol_short!("pruned");
+/// `(STAKER_EXPIRED, user, pool_index)` with [`StakerExpired`].
+pub const STAKER_EXPIRED: Symbol = symbol_short!("expired");
+/// `(BOUNTY_PAID, caller, action)` with the bounty (i128).
+pub const BOUNTY_PAID: Symbol = symbol_short!("bounty");
+/// `(REWARDS_SWEPT, user, pool_index)` with the amount (i128).
+pub const REWARDS_SWEPT: Symbol = symbol_short!("swept");
+/// `(EXPIRING_SOON, user, pool_index)` with [`ExpiringSoon`].
+pub const EXPIRING_SOON: Symbol = symbol_short!("expiring");
+/// `(STAKE_REDUCED, user, pool_index)` with [`StakeReduced`].
+pub const STAKE_REDUCED: Symbol = symbol_short!("reduced");
+/// `(STAKER_SLASHED, user, pool_index)` with [`StakerSlashed`].
+pub const STAKER_SLASHED: Symbol = symbol_short!("slashed");
+/// `(TOKENS_RESCUED, token)` with [`TokensRescued`].
+pub const TOKENS_RESCUED: Symbol = symbol_short!("rescue");
+/// `(ADDRESS_BLOCKED, user)` with `freeze_claims` (bool).
+pub const ADDRESS_BLOCKED: Symbol = symbol_short!("blocked");
+/// `(ADDRESS_UNBLOCKED, user)` with no data.
+pub const ADDRESS_UNBLOCKED: Symbol = symbol_short!("unblocked");
+/// `(REWARDS_FORFEITED, user, pool_index)` with the amount (i128).
+pub const REWARDS_FORFEITED: Symbol = symbol_short!("forfeit");
+/// `(REWARD_NOTIFIED, pool_index)` with [`RewardNotified`].
+pub const REWARD_NOTIFIED: Symbol = symbol_short!("notify");
+/// `(CARRY_RECLAIMED, pool_index)` with the amount (i128).
+pub const CARRY_RECLAIMED: Symbol = symbol_short!("reclaim");
+/// `(POOL_MIGRATED, pool_index)` with [`PoolMigrated`].
+pub const POOL_MIGRATED: Symbol = symbol_short!("poolmig");
+/// `(APPROVAL_RECORDED, action)` with [`ApprovalRecorded`].
+pub const APPROVAL_RECORDED: Symbol = symbol_short!("approve");
+/// `(PAUSED,)` with the new state (bool).
+pub const PAUSED: Symbol = symbol_short!("paused");
+/// `(POOL_PAUSED, pool_index)` with the new state (bool).
+pub const POOL_PAUSED: Symbol = symbol_short!("poolpause");
+/// `(EMERGENCY_QUEUED,)` with [`EmergencyQueued`].
+pub const EMERGENCY_QUEUED: Symbol = symbol_short!("emrg_q");
+/// `(REWARD_TOKEN_MIGRATED,)` with [`RewardTokenMigrated`].
+pub const REWARD_TOKEN_MIGRATED: Symbol = symbol_short!("tokenmig");
+/// `(TIME_UNACCRUED, pool_index)` with [`TimeUnaccrued`].
+pub const TIME_UNACCRUED: Symbol = symbol_short!("unaccrued");
+/// `(EPOCH_ROTATED, pool_index)` with [`EpochRotated`].
+pub const EPOCH_ROTATED: Symbol = symbol_short!("rotated");
+
+/// Reward claim. `destination` is None when the admin hides claim
+/// destinations.
+#[contracttype(export = false)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Claimed {
+    pub amount: i128,
+    pub destination: Option<Address>,
+}
+
+/// `earmark` is the pool's earmarked balance after adding `amount`.
+#[contracttype(export = false)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PoolEarmarked {
+    pub amount: i128,
+    pub earmark: i128,
+}
+
+#[contracttype(export = false)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CampaignCreated {
+    pub sponsor: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub start: u64,
+    pub end: u64,
+}
+
+#[contracttype(export = false)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RevenueClaimed {
+    pub token: Address,
+    pub amount: i128,
+}
+
+#[contracttype(export = false)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StakerPruned {
+    pub caller: Address,
+    pub bounty: i128,
+}
+
+/// `pending` stays claimable after the stake is dropped.
+#[contracttype(export = false)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StakerExpired {
+    pub stake: i128,
+    pub pending: i128,
+}
+
+#[contracttype(export = false)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExpiringSoon {
+    pub deadline: u64,
+    pub pending: i128,
+}
+
+#[contracttype(export = false)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StakeReduced {
+    pub amount: i128,
+    pub remaining: i128,
+}
+
+#[contracttype(export = false)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StakerSlashed {
+    pub reason_code: u32,
+    pub stake: i128,
+    pub forfeited: i128,
+}
+
+#[contracttype(export = false)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TokensRescued {
+    pub to: Address,
+    pub amount: i128,
+}
+
+#[contracttype(export = false)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RewardNotified {
+    pub amount: i128,
+    pub rate: i128,
+    pub period_finish: u64,
+}
+
+#[contracttype(export = false)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PoolMigrated {
+    pub old_id: BytesN<32>,
+    pub new_id: BytesN<32>,
+}
+
+#[contracttype(export = false)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ApprovalRecorded {
+    pub caller: Address,
+    pub approvals: u32,
+    pub threshold: u32,
+}
+
+#[contracttype(export = false)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EmergencyQueued {
+    pub amount: i128,
+    pub unlock_time: u64,
+}
+
+/// `exchange_ratio` is new-token stroops per old-token stroop times 1e7.
+#[contracttype(export = false)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RewardTokenMigrated {
+    pub old_token: Address,
+    pub new_token: Address,
+    pub exchange_ratio: i128,
+}
+
+/// Seconds a staked pool accrued nothing for. `paused` when LMNR earmarked
+/// for the pool sat unemitted, otherwise nothing was budgeted.
+#[contracttype(export = false)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TimeUnaccrued {
+    pub seconds: u64,
+    pub paused: bool,
+}
+
+/// A root post rolling a pool from `old_epoch_id` (0 for its first root)
+/// to `new_epoch_id`. `total_staked` and `acc_reward_per_share` are the
+/// pool's state as the old epoch closed, which carries into the new one.
+#[contracttype(export = false)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EpochRotated {
+    pub old_epoch_id: u64,
+    pub new_epoch_id: u64,
+    pub total_staked: i128,
+    pub acc_reward_per_share: i128,
+    pub snapshot_ledger: u32,
+    pub root: BytesN<32>,
+}
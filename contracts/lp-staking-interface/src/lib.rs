@@ -0,0 +1,63 @@
+#![no_std]
+//! Client interface of the lp-staking contract for other Soroban contracts
+//! (governance, vaults) that read positions or claim on a user's behalf.
+//! Holds the entry points, error codes and types integrators need without
+//! depending on the contract crate itself, plus the event topics and
+//! payloads (`events`) the contract publishes, for indexers.
+//!
+//! Nothing here is exported to a contract spec (`export = false`): the
+//! contract links this crate for its events and defines the same types.
+//!
+//! ```ignore
+//! let staking = LpStakingClient::new(&env, &staking_id);
+//! let weight = staking.get_staker_info(&user, &pool_index).weight;
+//! ```
+
+mod errors;
+pub mod events;
+mod types;
+
+pub use errors::ContractError;
+pub use types::{MerkleRootData, PoolState, StakerInfo};
+
+use soroban_sdk::{contractclient, Address, Env};
+
+#[contractclient(name = "LpStakingClient")]
+pub trait LpStakingInterface {
+    /// Unclaimed rewards for a user in a pool.
+    fn pending_reward(env: Env, user: Address, pool_index: u32) -> i128;
+
+    /// Stake details for a user; traps if they have no position.
+    fn get_staker_info(env: Env, user: Address, pool_index: u32) -> StakerInfo;
+
+    /// `get_staker_info` returning None when the user has no position.
+    fn find_staker_info(env: Env, user: Address, pool_index: u32) -> Option<StakerInfo>;
+
+    /// Pool accumulator state.
+    fn get_pool_state(env: Env, pool_index: u32) -> PoolState;
+
+    /// Current epoch Merkle root for a pool.
+    fn get_merkle_root(env: Env, pool_index: u32) -> MerkleRootData;
+
+    /// Number of registered pools.
+    fn get_pool_count(env: Env) -> u32;
+
+    /// Addresses with a stake proven in the pool's current epoch.
+    fn get_active_staker_count(env: Env, pool_index: u32) -> u32;
+
+    /// Lifetime rewards `user` has claimed from a pool.
+    fn get_user_claimed(env: Env, user: Address, pool_index: u32) -> i128;
+
+    /// Claim `user`'s rewards to their own address; needs `user`'s auth.
+    /// Returns the amount claimed.
+    fn claim(env: Env, user: Address, pool_index: u32) -> Result<i128, ContractError>;
+
+    /// Claim `user`'s rewards to `to`; needs `user`'s auth. Returns the
+    /// amount claimed.
+    fn claim_to(
+        env: Env,
+        user: Address,
+        pool_index: u32,
+        to: Address,
+    ) -> Result<i128, ContractError>;
+}
@@ -0,0 +1,36 @@
+use soroban_sdk::{contracttype, BytesN};
+
+/// A user's position in a pool. `weight` is their share of the pool's
+/// `total_staked`; `streak` counts consecutive epochs re-proven.
+#[contracttype(export = false)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StakerInfo {
+    pub staked_amount: i128,
+    pub reward_debt: i128,
+    pub pending_rewards: i128,
+    pub epoch_id: u64,
+    pub cooldown_until: u64,
+    pub weight: i128,
+    pub streak: u32,
+}
+
+#[contracttype(export = false)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PoolState {
+    pub acc_reward_per_share: i128,
+    pub total_staked: i128,
+    pub last_reward_time: u64,
+    pub prev_acc_reward_per_share: i128,
+}
+
+#[contracttype(export = false)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MerkleRootData {
+    pub root: BytesN<32>,
+    pub epoch_id: u64,
+    pub snapshot_ledger: u32,
+    pub posted_at: u64,
+    pub leaf_version: u32,
+    pub epoch_duration: u64,
+    pub declared_total: i128,
+}
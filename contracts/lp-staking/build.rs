@@ -0,0 +1,48 @@
+//! Writes the `contractmeta!` entries `src/lib.rs` includes, so
+//! `stellar contract inspect` on a deployed wasm shows which build it is.
+//! The SDK already records the rustc (`rsver`) and SDK (`rssdkver`)
+//! versions; this adds the crate version and git commit. The commit is
+//! `LP_STAKING_GIT_HASH` when set (e.g. by CI building from a tarball),
+//! else the checkout's HEAD, and is passed on to `get_version` too.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=LP_STAKING_GIT_HASH");
+
+    let version = env::var("CARGO_PKG_VERSION").unwrap();
+    let mut meta = format!("soroban_sdk::contractmeta!(key = \"binver\", val = {version:?});\n");
+
+    let git_hash = env::var("LP_STAKING_GIT_HASH").ok().or_else(git_head);
+    if let Some(hash) = git_hash {
+        println!("cargo:rustc-env=LP_STAKING_GIT_HASH={hash}");
+        meta += &format!("soroban_sdk::contractmeta!(key = \"commit\", val = {hash:?});\n");
+    }
+
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap()).join("contractmeta.rs");
+    fs::write(out, meta).unwrap();
+}
+
+/// HEAD of the enclosing git checkout, if there is one. Asks cargo to rerun
+/// when HEAD or any ref moves.
+fn git_head() -> Option<String> {
+    let git_dir = git(&["rev-parse", "--git-dir"])?;
+    for path in ["HEAD", "refs", "packed-refs"] {
+        println!("cargo:rerun-if-changed={git_dir}/{path}");
+    }
+    git(&["rev-parse", "HEAD"])
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    let value = stdout.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
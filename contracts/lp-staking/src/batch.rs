@@ -0,0 +1,53 @@
+use soroban_sdk::{Env, IntoVal, TryFromVal, Val, Vec};
+
+use crate::errors::ContractError;
+
+/// Maximum items accepted by a single batch call.
+pub const MAX_BATCH_SIZE: u32 = 50;
+
+/// Result code for an item that succeeded.
+pub const ITEM_OK: u32 = 0;
+/// Result code for an item after a failure that was not attempted.
+pub const ITEM_SKIPPED: u32 = u32::MAX;
+
+/// Apply `f` to `items` strictly in input order and return one result code
+/// per item: `ITEM_OK`, the failing `ContractError` code, or `ITEM_SKIPPED`.
+///
+/// Processing stops at the first failing item. With `atomic` the whole call
+/// fails with that item's error and every item is rolled back; otherwise
+/// items before the failure stay committed and items after it are skipped.
+/// Each item must validate before writing so a failed item leaves no
+/// partial state.
+pub fn run<T, F>(
+    env: &Env,
+    items: &Vec<T>,
+    atomic: bool,
+    mut f: F,
+) -> Result<Vec<u32>, ContractError>
+where
+    T: IntoVal<Env, Val> + TryFromVal<Env, Val> + Clone,
+    F: FnMut(T) -> Result<(), ContractError>,
+{
+    if items.len() > MAX_BATCH_SIZE {
+        return Err(ContractError::BatchTooLarge);
+    }
+
+    let mut results = Vec::new(env);
+    let mut failed = false;
+    for item in items.iter() {
+        if failed {
+            results.push_back(ITEM_SKIPPED);
+            continue;
+        }
+        match f(item) {
+            Ok(()) => results.push_back(ITEM_OK),
+            Err(err) if atomic => return Err(err),
+            Err(err) => {
+                failed = true;
+                results.push_back(err as u32);
+            }
+        }
+    }
+
+    Ok(results)
+}
@@ -0,0 +1,158 @@
+use soroban_sdk::{token, Address, Env, Map};
+
+use crate::rewards::{compute_reward_debt, pool_precision};
+use crate::storage::{self, Campaign, RevenueShare};
+
+/// Maximum unreclaimed sponsor campaigns running on one pool.
+pub const MAX_CAMPAIGNS: u32 = 5;
+
+/// Seconds after a campaign's end that stakers have to claim before the
+/// sponsor can reclaim what is left.
+pub const CLAIM_WINDOW: u64 = 30 * 86_400;
+
+/// Pool whose stake weight a pool's stakers earn campaigns on: the Merkle
+/// pool for the deposit side of a hybrid pool, else itself.
+fn campaign_pool(env: &Env, pool_index: u32) -> u32 {
+    storage::get_pool_config(env, pool_index)
+        .shared_with
+        .unwrap_or(pool_index)
+}
+
+/// Sponsor tokens `campaign` has released by `at`.
+fn released_at(campaign: &Campaign, at: u64) -> i128 {
+    let at = at.clamp(campaign.start, campaign.end);
+    let elapsed = (at - campaign.start) as i128;
+    campaign.amount * elapsed / (campaign.end - campaign.start) as i128
+}
+
+/// Credit `campaign` with what it released between its last update and
+/// `now` across `total_staked` weight.
+pub fn advance(campaign: &mut Campaign, total_staked: i128, precision: i128, now: u64) {
+    let released = released_at(campaign, now) - released_at(campaign, campaign.last_update);
+    campaign.last_update = campaign.last_update.max(now);
+    if released <= 0 {
+        return;
+    }
+    if total_staked > 0 {
+        let per_share = released * precision / total_staked;
+        campaign.acc_per_share += per_share;
+        campaign.unallocated += released - per_share * total_staked / precision;
+    } else {
+        campaign.unallocated += released;
+    }
+}
+
+/// Bring the pool's running campaigns up to `now` over the weight staked
+/// since their last update. Runs from `rewards::update_pool`, so before
+/// every change to `total_staked`.
+pub fn update(env: &Env, pool_index: u32, total_staked: i128, now: u64) {
+    let active = storage::get_active_campaigns(env, pool_index);
+    if active.is_empty() {
+        return;
+    }
+    let precision = pool_precision(env, pool_index);
+    for id in active.iter() {
+        let Some(mut campaign) = storage::get_campaign(env, pool_index, id) else {
+            continue;
+        };
+        if now <= campaign.start || campaign.last_update >= campaign.end {
+            continue;
+        }
+        advance(&mut campaign, total_staked, precision, now);
+        storage::set_campaign(env, pool_index, id, &campaign);
+    }
+}
+
+/// Credit what each running campaign paid `old_weight` since `user`'s last
+/// settlement and rebase on `new_weight`. Shares of campaigns since
+/// reclaimed are dropped.
+pub fn settle(env: &Env, user: &Address, pool_index: u32, old_weight: i128, new_weight: i128) {
+    let campaign_pool = campaign_pool(env, pool_index);
+    let active = storage::get_active_campaigns(env, campaign_pool);
+    let shares = storage::get_campaign_shares(env, user, pool_index);
+    if active.is_empty() && shares.is_empty() {
+        return;
+    }
+    let precision = pool_precision(env, campaign_pool);
+    let mut settled = Map::new(env);
+    for id in active.iter() {
+        let Some(campaign) = storage::get_campaign(env, campaign_pool, id) else {
+            continue;
+        };
+        let mut share = shares.get(id).unwrap_or_default();
+        share.owed +=
+            compute_reward_debt(old_weight, campaign.acc_per_share, precision) - share.debt;
+        share.debt = compute_reward_debt(new_weight, campaign.acc_per_share, precision);
+        if share != RevenueShare::default() {
+            settled.set(id, share);
+        }
+    }
+    storage::set_campaign_shares(env, user, pool_index, &settled);
+}
+
+/// What each running campaign owes `user` in a pool as of now, by
+/// campaign id, without writing anything.
+pub fn pending(env: &Env, user: &Address, pool_index: u32) -> Map<u32, i128> {
+    let campaign_pool = campaign_pool(env, pool_index);
+    let weight = if storage::has_staker(env, user, pool_index) {
+        storage::get_staker(env, user, pool_index).weight
+    } else {
+        0
+    };
+    let total_staked = storage::get_pool_state(env, campaign_pool).total_staked;
+    let precision = pool_precision(env, campaign_pool);
+    let now = env.ledger().timestamp();
+    let shares = storage::get_campaign_shares(env, user, pool_index);
+
+    let mut owed = Map::new(env);
+    for id in storage::get_active_campaigns(env, campaign_pool).iter() {
+        let Some(mut campaign) = storage::get_campaign(env, campaign_pool, id) else {
+            continue;
+        };
+        if now > campaign.start {
+            advance(&mut campaign, total_staked, precision, now);
+        }
+        let share = shares.get(id).unwrap_or_default();
+        let amount = share.owed + compute_reward_debt(weight, campaign.acc_per_share, precision)
+            - share.debt;
+        if amount > 0 {
+            owed.set(id, amount);
+        }
+    }
+    owed
+}
+
+/// Pay `user` what campaigns owe them in a pool to `to`, once their shares
+/// are settled. Returns the amount paid per campaign id.
+pub fn pay(env: &Env, user: &Address, pool_index: u32, to: &Address) -> Map<u32, i128> {
+    let campaign_pool = campaign_pool(env, pool_index);
+    let mut shares = storage::get_campaign_shares(env, user, pool_index);
+    let mut paid = Map::new(env);
+    for (id, mut share) in shares.iter() {
+        if share.owed <= 0 {
+            continue;
+        }
+        let Some(mut campaign) = storage::get_campaign(env, campaign_pool, id) else {
+            continue;
+        };
+        // A token that rejects the transfer (frozen trustline, clawback)
+        // keeps its share owed rather than blocking the rest of the claim
+        let transfer = token::Client::new(env, &campaign.token).try_transfer(
+            &env.current_contract_address(),
+            to,
+            &share.owed,
+        );
+        if !matches!(transfer, Ok(Ok(()))) {
+            continue;
+        }
+        campaign.paid += share.owed;
+        storage::set_campaign(env, campaign_pool, id, &campaign);
+        paid.set(id, share.owed);
+        share.owed = 0;
+        shares.set(id, share);
+    }
+    if !paid.is_empty() {
+        storage::set_campaign_shares(env, user, pool_index, &shares);
+    }
+    paid
+}
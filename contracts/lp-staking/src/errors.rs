@@ -17,4 +17,154 @@ pub enum ContractError {
     InvalidAmount = 11,
     NoMerkleRoot = 12,
     StaleEpoch = 13,
+    IdentityNotBound = 14,
+    ListenerLimitReached = 15,
+    InsufficientRunway = 16,
+    InvalidConfig = 17,
+    PoolInUse = 18,
+    CannotRescueRewardToken = 19,
+    ContractPaused = 20,
+    NotPaused = 21,
+    TimelockNotElapsed = 22,
+    NoPendingWithdrawal = 23,
+    BatchTooLarge = 24,
+    AlreadyActivated = 25,
+    StakerNotEmpty = 26,
+    PoolCapExceeded = 27,
+    BelowMinimumStake = 28,
+    AddressBlocked = 29,
+    ClaimsFrozen = 30,
+    NotCompliant = 31,
+    WrongPoolMode = 32,
+    InvalidAmmPool = 33,
+    RootTooSoon = 34,
+    RootTooLate = 35,
+    RootExpired = 36,
+    StakerNotExpired = 37,
+    SnapshotNotIncreasing = 38,
+    SnapshotInFuture = 39,
+    PoolPaused = 40,
+    BelowMinimumClaim = 41,
+    PoolMismatch = 42,
+    ProofTooLong = 43,
+    RateTooHigh = 44,
+    CooldownActive = 45,
+    DeclaredTotalExceeded = 46,
+    CampaignNotFound = 47,
+    RewardTransferFailed = 48,
+    NoPendingMigration = 49,
+}
+
+impl ContractError {
+    /// Look up an error by its numeric code.
+    pub fn from_code(code: u32) -> Option<ContractError> {
+        match code {
+            1 => Some(ContractError::AlreadyInitialized),
+            2 => Some(ContractError::NotInitialized),
+            3 => Some(ContractError::Unauthorized),
+            4 => Some(ContractError::PoolAlreadyExists),
+            5 => Some(ContractError::PoolNotFound),
+            6 => Some(ContractError::InvalidProof),
+            7 => Some(ContractError::AlreadyStakedThisEpoch),
+            8 => Some(ContractError::NoStakeFound),
+            9 => Some(ContractError::NoRewardsToClaim),
+            10 => Some(ContractError::InsufficientRewardBalance),
+            11 => Some(ContractError::InvalidAmount),
+            12 => Some(ContractError::NoMerkleRoot),
+            13 => Some(ContractError::StaleEpoch),
+            14 => Some(ContractError::IdentityNotBound),
+            15 => Some(ContractError::ListenerLimitReached),
+            16 => Some(ContractError::InsufficientRunway),
+            17 => Some(ContractError::InvalidConfig),
+            18 => Some(ContractError::PoolInUse),
+            19 => Some(ContractError::CannotRescueRewardToken),
+            20 => Some(ContractError::ContractPaused),
+            21 => Some(ContractError::NotPaused),
+            22 => Some(ContractError::TimelockNotElapsed),
+            23 => Some(ContractError::NoPendingWithdrawal),
+            24 => Some(ContractError::BatchTooLarge),
+            25 => Some(ContractError::AlreadyActivated),
+            26 => Some(ContractError::StakerNotEmpty),
+            27 => Some(ContractError::PoolCapExceeded),
+            28 => Some(ContractError::BelowMinimumStake),
+            29 => Some(ContractError::AddressBlocked),
+            30 => Some(ContractError::ClaimsFrozen),
+            31 => Some(ContractError::NotCompliant),
+            32 => Some(ContractError::WrongPoolMode),
+            33 => Some(ContractError::InvalidAmmPool),
+            34 => Some(ContractError::RootTooSoon),
+            35 => Some(ContractError::RootTooLate),
+            36 => Some(ContractError::RootExpired),
+            37 => Some(ContractError::StakerNotExpired),
+            38 => Some(ContractError::SnapshotNotIncreasing),
+            39 => Some(ContractError::SnapshotInFuture),
+            40 => Some(ContractError::PoolPaused),
+            41 => Some(ContractError::BelowMinimumClaim),
+            42 => Some(ContractError::PoolMismatch),
+            43 => Some(ContractError::ProofTooLong),
+            44 => Some(ContractError::RateTooHigh),
+            45 => Some(ContractError::CooldownActive),
+            46 => Some(ContractError::DeclaredTotalExceeded),
+            47 => Some(ContractError::CampaignNotFound),
+            48 => Some(ContractError::RewardTransferFailed),
+            49 => Some(ContractError::NoPendingMigration),
+            _ => None,
+        }
+    }
+
+    /// Short, stable identifier surfaced by `explain`. Wallets show it
+    /// verbatim, so never rename an existing entry.
+    pub fn explain(&self) -> &'static str {
+        match self {
+            ContractError::AlreadyInitialized => "ALREADY_INITIALIZED",
+            ContractError::NotInitialized => "NOT_INITIALIZED",
+            ContractError::Unauthorized => "NOT_ADMIN",
+            ContractError::PoolAlreadyExists => "POOL_EXISTS",
+            ContractError::PoolNotFound => "POOL_NOT_FOUND",
+            ContractError::InvalidProof => "PROOF_INVALID",
+            ContractError::AlreadyStakedThisEpoch => "ALREADY_STAKED_EPOCH",
+            ContractError::NoStakeFound => "NO_STAKE",
+            ContractError::NoRewardsToClaim => "NOTHING_TO_CLAIM",
+            ContractError::InsufficientRewardBalance => "REWARDS_UNDERFUNDED",
+            ContractError::InvalidAmount => "AMOUNT_INVALID",
+            ContractError::NoMerkleRoot => "NO_SNAPSHOT_YET",
+            ContractError::StaleEpoch => "PROOF_WRONG_EPOCH",
+            ContractError::IdentityNotBound => "IDENTITY_NOT_BOUND",
+            ContractError::ListenerLimitReached => "LISTENER_LIMIT",
+            ContractError::InsufficientRunway => "RUNWAY_TOO_SHORT",
+            ContractError::InvalidConfig => "CONFIG_INVALID",
+            ContractError::PoolInUse => "POOL_IN_USE",
+            ContractError::CannotRescueRewardToken => "TOKEN_PROTECTED",
+            ContractError::ContractPaused => "PAUSED",
+            ContractError::NotPaused => "NOT_PAUSED",
+            ContractError::TimelockNotElapsed => "TIMELOCK_PENDING",
+            ContractError::NoPendingWithdrawal => "NO_PENDING_WITHDRAWAL",
+            ContractError::BatchTooLarge => "BATCH_TOO_LARGE",
+            ContractError::AlreadyActivated => "ALREADY_ACTIVATED",
+            ContractError::StakerNotEmpty => "STAKER_NOT_EMPTY",
+            ContractError::PoolCapExceeded => "POOL_CAP_EXCEEDED",
+            ContractError::BelowMinimumStake => "BELOW_MINIMUM_STAKE",
+            ContractError::AddressBlocked => "ADDRESS_BLOCKED",
+            ContractError::ClaimsFrozen => "CLAIMS_FROZEN",
+            ContractError::NotCompliant => "NOT_COMPLIANT",
+            ContractError::WrongPoolMode => "WRONG_POOL_MODE",
+            ContractError::InvalidAmmPool => "INVALID_AMM_POOL",
+            ContractError::RootTooSoon => "ROOT_TOO_SOON",
+            ContractError::RootTooLate => "ROOT_TOO_LATE",
+            ContractError::RootExpired => "ROOT_EXPIRED",
+            ContractError::StakerNotExpired => "STAKER_NOT_EXPIRED",
+            ContractError::SnapshotNotIncreasing => "SNAPSHOT_NOT_INCREASING",
+            ContractError::SnapshotInFuture => "SNAPSHOT_IN_FUTURE",
+            ContractError::PoolPaused => "POOL_PAUSED",
+            ContractError::BelowMinimumClaim => "BELOW_MINIMUM_CLAIM",
+            ContractError::PoolMismatch => "PROOF_WRONG_POOL",
+            ContractError::ProofTooLong => "PROOF_TOO_LONG",
+            ContractError::RateTooHigh => "RATE_ABOVE_BOUND",
+            ContractError::CooldownActive => "STAKE_COOLDOWN",
+            ContractError::DeclaredTotalExceeded => "DECLARED_TOTAL_EXCEEDED",
+            ContractError::CampaignNotFound => "CAMPAIGN_NOT_FOUND",
+            ContractError::RewardTransferFailed => "REWARD_TRANSFER_FAILED",
+            ContractError::NoPendingMigration => "NO_PENDING_MIGRATION",
+        }
+    }
 }
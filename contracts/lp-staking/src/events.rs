@@ -0,0 +1,289 @@
+//! Publishers for the contract's events. Topics and payload types come from
+//! `lp_staking_interface::events`, which indexers decode with.
+
+use lp_staking_interface::events::*;
+use soroban_sdk::{Address, BytesN, Env, Symbol};
+
+use crate::storage::{Campaign, PoolState};
+
+/// Reward claim. `destination` is omitted (None) when the admin has enabled
+/// destination hiding, so `claim_to` payouts are not linked to the staker by
+/// the event stream.
+pub fn claimed(env: &Env, user: &Address, pool_index: u32, amount: i128, destination: Option<Address>) {
+    env.events().publish(
+        (CLAIMED, user.clone(), pool_index),
+        Claimed {
+            amount,
+            destination,
+        },
+    );
+}
+
+/// LMNR earmarked for a pool's emissions; `earmark` is the pool's new
+/// earmarked balance.
+pub fn pool_earmarked(env: &Env, pool_index: u32, amount: i128, earmark: i128) {
+    env.events().publish(
+        (POOL_EARMARKED, pool_index),
+        PoolEarmarked { amount, earmark },
+    );
+}
+
+/// Sponsor campaign `campaign_id` opened on a pool.
+pub fn campaign_created(env: &Env, pool_index: u32, campaign_id: u32, campaign: &Campaign) {
+    env.events().publish(
+        (CAMPAIGN_CREATED, pool_index, campaign_id),
+        CampaignCreated {
+            sponsor: campaign.sponsor.clone(),
+            token: campaign.token.clone(),
+            amount: campaign.amount,
+            start: campaign.start,
+            end: campaign.end,
+        },
+    );
+}
+
+/// Sponsor took back what campaign `campaign_id` had not paid out.
+pub fn campaign_reclaimed(env: &Env, pool_index: u32, campaign_id: u32, amount: i128) {
+    env.events()
+        .publish((CAMPAIGN_RECLAIMED, pool_index, campaign_id), amount);
+}
+
+/// Fee revenue in `token` deposited to a pool's pot.
+pub fn revenue_deposited(env: &Env, token: &Address, pool_index: u32, amount: i128) {
+    env.events()
+        .publish((REVENUE_DEPOSITED, token.clone(), pool_index), amount);
+}
+
+/// Fee revenue in `token` paid to `user` from a pool.
+pub fn revenue_claimed(env: &Env, user: &Address, pool_index: u32, token: &Address, amount: i128) {
+    env.events().publish(
+        (REVENUE_CLAIMED, user.clone(), pool_index),
+        RevenueClaimed {
+            token: token.clone(),
+            amount,
+        },
+    );
+}
+
+/// Partial payout: `shortfall` stays pending until the contract is funded.
+pub fn claim_shortfall(env: &Env, user: &Address, pool_index: u32, shortfall: i128) {
+    env.events()
+        .publish((CLAIM_SHORTFALL, user.clone(), pool_index), shortfall);
+}
+
+/// Empty staker entry removed by `caller`, who was paid `bounty`.
+pub fn staker_pruned(env: &Env, user: &Address, pool_index: u32, caller: &Address, bounty: i128) {
+    env.events().publish(
+        (STAKER_PRUNED, user.clone(), pool_index),
+        StakerPruned {
+            caller: caller.clone(),
+            bounty,
+        },
+    );
+}
+
+/// Stake of `user` dropped after missing too many epochs; `pending`
+/// stays claimable.
+pub fn staker_expired(env: &Env, user: &Address, pool_index: u32, stake: i128, pending: i128) {
+    env.events().publish(
+        (STAKER_EXPIRED, user.clone(), pool_index),
+        StakerExpired { stake, pending },
+    );
+}
+
+/// Keeper bounty paid to `caller` for a maintenance `action`.
+pub fn bounty_paid(env: &Env, caller: &Address, action: Symbol, bounty: i128) {
+    env.events()
+        .publish((BOUNTY_PAID, caller.clone(), action), bounty);
+}
+
+/// `amount` of `user`'s rewards swept back to the fund after going
+/// unclaimed past the claim deadline.
+pub fn rewards_swept(env: &Env, user: &Address, pool_index: u32, amount: i128) {
+    env.events()
+        .publish((REWARDS_SWEPT, user.clone(), pool_index), amount);
+}
+
+/// `pending` rewards of `user` become sweepable at `deadline`.
+pub fn expiring_soon(env: &Env, user: &Address, pool_index: u32, deadline: u64, pending: i128) {
+    env.events().publish(
+        (EXPIRING_SOON, user.clone(), pool_index),
+        ExpiringSoon { deadline, pending },
+    );
+}
+
+/// Staker voluntarily reduced their stake by `amount` to `remaining`, for
+/// keepers to cross-check against the LP position.
+pub fn stake_reduced(env: &Env, user: &Address, pool_index: u32, amount: i128, remaining: i128) {
+    env.events().publish(
+        (STAKE_REDUCED, user.clone(), pool_index),
+        StakeReduced { amount, remaining },
+    );
+}
+
+/// Admin slashed `user`'s stake for `reason_code`, writing off `forfeited`
+/// pending rewards.
+pub fn staker_slashed(
+    env: &Env,
+    user: &Address,
+    pool_index: u32,
+    reason_code: u32,
+    stake: i128,
+    forfeited: i128,
+) {
+    env.events().publish(
+        (STAKER_SLASHED, user.clone(), pool_index),
+        StakerSlashed {
+            reason_code,
+            stake,
+            forfeited,
+        },
+    );
+}
+
+/// Admin rescued tokens sent to the contract by mistake.
+pub fn tokens_rescued(env: &Env, token: &Address, to: &Address, amount: i128) {
+    env.events().publish(
+        (TOKENS_RESCUED, token.clone()),
+        TokensRescued {
+            to: to.clone(),
+            amount,
+        },
+    );
+}
+
+/// `user` added to the blocklist, with claims frozen or forfeited.
+pub fn address_blocked(env: &Env, user: &Address, freeze_claims: bool) {
+    env.events()
+        .publish((ADDRESS_BLOCKED, user.clone()), freeze_claims);
+}
+
+/// `user` removed from the blocklist.
+pub fn address_unblocked(env: &Env, user: &Address) {
+    env.events().publish((ADDRESS_UNBLOCKED, user.clone()), ());
+}
+
+/// A blocked staker's pending rewards were written off instead of paid.
+pub fn rewards_forfeited(env: &Env, user: &Address, pool_index: u32, amount: i128) {
+    env.events()
+        .publish((REWARDS_FORFEITED, user.clone(), pool_index), amount);
+}
+
+/// Emissions controller scheduled `rate` per second for a pool until
+/// `period_finish`, after funding `amount`.
+pub fn reward_notified(env: &Env, pool_index: u32, amount: i128, rate: i128, period_finish: u64) {
+    env.events().publish(
+        (REWARD_NOTIFIED, pool_index),
+        RewardNotified {
+            amount,
+            rate,
+            period_finish,
+        },
+    );
+}
+
+/// Carried idle rewards of a pool returned to the unreserved balance.
+pub fn carry_reclaimed(env: &Env, pool_index: u32, amount: i128) {
+    env.events().publish((CARRY_RECLAIMED, pool_index), amount);
+}
+
+/// Pool index rebound from `old_id` to `new_id` (e.g. a reissued asset).
+pub fn pool_migrated(env: &Env, pool_index: u32, old_id: &BytesN<32>, new_id: &BytesN<32>) {
+    env.events().publish(
+        (POOL_MIGRATED, pool_index),
+        PoolMigrated {
+            old_id: old_id.clone(),
+            new_id: new_id.clone(),
+        },
+    );
+}
+
+/// A sensitive admin call collected an approval without yet reaching its
+/// quorum. `action` is the hash approvals are collected under.
+pub fn approval_recorded(
+    env: &Env,
+    action: &BytesN<32>,
+    caller: &Address,
+    approvals: u32,
+    threshold: u32,
+) {
+    env.events().publish(
+        (APPROVAL_RECORDED, action.clone()),
+        ApprovalRecorded {
+            caller: caller.clone(),
+            approvals,
+            threshold,
+        },
+    );
+}
+
+/// Global pause toggled by the admin.
+pub fn paused(env: &Env, paused: bool) {
+    env.events().publish((PAUSED,), paused);
+}
+
+/// Single-pool pause toggled by the admin.
+pub fn pool_paused(env: &Env, pool_index: u32, paused: bool) {
+    env.events().publish((POOL_PAUSED, pool_index), paused);
+}
+
+/// Emergency withdrawal queued; executable from `unlock_time`.
+pub fn emergency_queued(env: &Env, amount: i128, unlock_time: u64) {
+    env.events().publish(
+        (EMERGENCY_QUEUED,),
+        EmergencyQueued {
+            amount,
+            unlock_time,
+        },
+    );
+}
+
+/// Payout token switched from `old_token` to `new_token`, with everything
+/// owed restated at `exchange_ratio` (new-token stroops per old, times 1e7).
+pub fn reward_token_migrated(
+    env: &Env,
+    old_token: &Address,
+    new_token: &Address,
+    exchange_ratio: i128,
+) {
+    env.events().publish(
+        (REWARD_TOKEN_MIGRATED,),
+        RewardTokenMigrated {
+            old_token: old_token.clone(),
+            new_token: new_token.clone(),
+            exchange_ratio,
+        },
+    );
+}
+
+/// `update_pool` passed over `seconds` in which the staked pool accrued
+/// nothing; `paused` if it held an unowed earmark meanwhile.
+pub fn time_unaccrued(env: &Env, pool_index: u32, seconds: u64, paused: bool) {
+    env.events().publish(
+        (TIME_UNACCRUED, pool_index),
+        TimeUnaccrued { seconds, paused },
+    );
+}
+
+/// Root posted for `pool_index`, summarising the epoch it closed so
+/// indexers can rebuild epoch history from events alone.
+pub fn epoch_rotated(
+    env: &Env,
+    pool_index: u32,
+    state: &PoolState,
+    new_epoch_id: u64,
+    snapshot_ledger: u32,
+    root: &BytesN<32>,
+) {
+    env.events().publish(
+        (EPOCH_ROTATED, pool_index),
+        EpochRotated {
+            old_epoch_id: new_epoch_id - 1,
+            new_epoch_id,
+            total_staked: state.total_staked,
+            acc_reward_per_share: state.acc_reward_per_share,
+            snapshot_ledger,
+            root: root.clone(),
+        },
+    );
+}
@@ -0,0 +1,70 @@
+use soroban_sdk::{symbol_short, Address, BytesN, Env, IntoVal, InvokeError, Val, Vec};
+
+use crate::storage;
+
+/// Maximum number of epoch listeners the admin can register.
+pub const MAX_EPOCH_LISTENERS: u32 = 5;
+
+/// Best-effort `on_epoch(pool_index, epoch_id, root)` call to every
+/// registered listener. A listener that traps or returns an error is
+/// skipped so it can never block root posting.
+pub fn notify_epoch_listeners(env: &Env, pool_index: u32, epoch_id: u64, root: &BytesN<32>) {
+    let listeners = storage::get_epoch_listeners(env);
+    for listener in listeners.iter() {
+        let args: Vec<Val> = (pool_index, epoch_id, root.clone()).into_val(env);
+        let _ = env.try_invoke_contract::<(), InvokeError>(&listener, &symbol_short!("on_epoch"), args);
+    }
+}
+
+/// Best-effort `on_stake(user, pool_index, staked_amount)` call to the stake
+/// hook after a position changes, with the position's new size.
+pub fn notify_stake_changed(env: &Env, user: &Address, pool_index: u32, staked_amount: i128) {
+    if let Some(hook) = storage::get_stake_hook(env) {
+        let args: Vec<Val> = (user.clone(), pool_index, staked_amount).into_val(env);
+        let _ = env.try_invoke_contract::<(), InvokeError>(&hook, &symbol_short!("on_stake"), args);
+    }
+}
+
+/// Best-effort `on_claim(user, pool_index, amount)` call to the stake hook
+/// after rewards are paid out.
+pub fn notify_claimed(env: &Env, user: &Address, pool_index: u32, amount: i128) {
+    if let Some(hook) = storage::get_stake_hook(env) {
+        let args: Vec<Val> = (user.clone(), pool_index, amount).into_val(env);
+        let _ = env.try_invoke_contract::<(), InvokeError>(&hook, &symbol_short!("on_claim"), args);
+    }
+}
+
+/// Ask the configured compliance hook whether `user` may stake or receive
+/// rewards via `check(user) -> bool`. Passes when no hook is set. Unlike
+/// epoch listeners this fails closed: a hook that traps or returns anything
+/// but `true` rejects the user.
+pub fn is_compliant(env: &Env, user: &Address) -> bool {
+    let hook = match storage::get_compliance_hook(env) {
+        Some(hook) => hook,
+        None => return true,
+    };
+    let args: Vec<Val> = (user.clone(),).into_val(env);
+    matches!(
+        env.try_invoke_contract::<bool, InvokeError>(&hook, &symbol_short!("check"), args),
+        Ok(Ok(true))
+    )
+}
+
+/// Share token of an Aquarius AMM pool via its `share_id()`, or `None` if
+/// `pool` doesn't answer like one.
+pub fn aqua_share_token(env: &Env, pool: &Address) -> Option<Address> {
+    let args: Vec<Val> = Vec::new(env);
+    match env.try_invoke_contract::<Address, InvokeError>(pool, &symbol_short!("share_id"), args) {
+        Ok(Ok(share_token)) => Some(share_token),
+        _ => None,
+    }
+}
+
+/// `lp_price(pool_id)` from the rate oracle, or `None` if the call fails.
+pub fn oracle_lp_price(env: &Env, oracle: &Address, pool_id: &BytesN<32>) -> Option<i128> {
+    let args: Vec<Val> = (pool_id.clone(),).into_val(env);
+    match env.try_invoke_contract::<i128, InvokeError>(oracle, &symbol_short!("lp_price"), args) {
+        Ok(Ok(price)) => Some(price),
+        _ => None,
+    }
+}
@@ -0,0 +1,27 @@
+//! The original deployment, as `seed_from_legacy` reads it. It predates the
+//! pool config, liability and staker registry views, and returns roots and
+//! staker entries in their pre-v4 and pre-v3 layouts.
+
+use soroban_sdk::{contractclient, Address, BytesN, Env};
+
+use crate::storage::{MerkleRootDataV3, PoolState, StakerInfoV2};
+
+// Only the generated client is used; nothing implements the trait
+#[allow(dead_code)]
+#[contractclient(name = "LegacyClient")]
+pub trait LegacyInterface {
+    /// Number of registered pools.
+    fn get_pool_count(env: Env) -> u32;
+
+    /// Pool hash at a given index.
+    fn get_pool_id(env: Env, pool_index: u32) -> BytesN<32>;
+
+    /// Pool accumulator state, in the same layout as today.
+    fn get_pool_state(env: Env, pool_index: u32) -> PoolState;
+
+    /// Current epoch Merkle root; traps if the pool never had one.
+    fn get_merkle_root(env: Env, pool_index: u32) -> MerkleRootDataV3;
+
+    /// Stake details for a user; traps if they have no position.
+    fn get_staker_info(env: Env, user: Address, pool_index: u32) -> StakerInfoV2;
+}
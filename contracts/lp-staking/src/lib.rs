@@ -1,16 +1,59 @@
 #![no_std]
 
+mod batch;
+mod campaigns;
 mod errors;
+mod events;
+mod hooks;
+mod legacy;
 mod merkle;
+mod migration;
 mod rewards;
 mod storage;
+pub mod testutils;
+mod token_migration;
 
+#[cfg(test)]
+mod proptests;
 #[cfg(test)]
 mod test;
 
 use errors::ContractError;
-use soroban_sdk::{contract, contractimpl, token, Address, BytesN, Env, Vec};
-use storage::{MerkleRootData, PoolState, StakerInfo};
+use soroban_sdk::xdr::ToXdr;
+use soroban_sdk::{
+    contract, contractimpl, symbol_short, token, Address, Bytes, BytesN, Env, IntoVal, Map, String,
+    Symbol, Val, Vec,
+};
+use storage::{
+    Accounting, AdminAction, AdminSet, BountyUsage, Campaign, Checkpoint, ClaimDeadline,
+    ClaimStats, ClaimedLeaves, ContractConfig, GlobalStats, IdentityBinding, IdentityProof,
+    KeeperBounties, MerkleRootData, OracleSignature, Payout, PendingTokenMigration,
+    PendingWithdrawal, PoolConfig, PoolState, ProvenTotal, RateBounds, RateCheckpoint, RateOracle,
+    RevenuePot, RewardSchedule, RootOracles, SlashRecord, StakeRequest, StakeUpdate, StakerInfo,
+    TtlConfig, UnaccruedTime, VersionInfo, Warmup,
+};
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+const GIT_HASH: Option<&str> = option_env!("LP_STAKING_GIT_HASH");
+
+// Crate version and git commit as contract metadata (see build.rs)
+include!(concat!(env!("OUT_DIR"), "/contractmeta.rs"));
+
+const SECONDS_PER_DAY: i128 = 86_400;
+const SECONDS_PER_YEAR: i128 = 365 * SECONDS_PER_DAY;
+const BPS_DENOMINATOR: i128 = 10_000;
+const EMERGENCY_WITHDRAW_DELAY: u64 = 3 * 86_400; // seconds
+const SEED_PAGE_SIZE: u32 = 25; // Addresses per seed_from_legacy call
+const MAX_LP_DECIMALS: u32 = 18;
+const MAX_REWARD_DECIMALS: u32 = 18;
+const MAX_SLASH_LOG: u32 = 50; // Oldest slash record dropped beyond this
+const MAX_ADMIN_LOG: u32 = 50; // Oldest admin action dropped beyond this
+const MAX_REVENUE_TOKENS: u32 = 4; // Tokens with a revenue pot in one pool
+const EXPIRY_NOTICE: u64 = 7 * 86_400; // `poke_deadlines` warns this far ahead
+const MAX_KEEPER_BOUNTY: i128 = 10_0000000; // 10 LMNR
+const MAX_ROOT_ORACLES: u32 = 10;
+const TOKEN_MIGRATION_DELAY: u64 = 7 * 86_400; // seconds
+const MAX_DIAGNOSED_POOLS: u32 = 4; // Other pools a failed proof is tried against
 
 #[contract]
 pub struct LpStakingContract;
@@ -34,6 +77,7 @@ impl LpStakingContract {
         storage::set_lmnr_token(&env, &lmnr_token);
         storage::set_reward_rate(&env, reward_rate_per_sec);
         storage::set_pool_count(&env, 0);
+        storage::set_schema_version(&env, migration::SCHEMA_VERSION);
         storage::extend_instance_ttl(&env);
 
         Ok(())
@@ -43,26 +87,124 @@ impl LpStakingContract {
     pub fn add_pool(env: Env, admin: Address, pool_id: BytesN<32>) -> Result<u32, ContractError> {
         Self::require_admin(&env, &admin)?;
         storage::extend_instance_ttl(&env);
+        Self::register_pool(&env, &pool_id)
+    }
+
+    /// Admin-only: rebind a pool index to a new SDEX pool hash, e.g. after
+    /// an asset is reissued. Staker, reward and root state stay with the
+    /// index; only the id lookup moves.
+    pub fn migrate_pool_id(
+        env: Env,
+        admin: Address,
+        pool_index: u32,
+        new_pool_id: BytesN<32>,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+        Self::log_admin_action(
+            &env,
+            &admin,
+            symbol_short!("pool_id"),
+            (pool_index, new_pool_id.clone()),
+        );
 
-        if storage::has_pool_id_index(&env, &pool_id) {
+        if storage::has_pool_id_index(&env, &new_pool_id) {
             return Err(ContractError::PoolAlreadyExists);
         }
+        let old_pool_id = storage::get_pool_id(&env, pool_index);
+        storage::remove_pool_id_index(&env, &old_pool_id);
+        storage::set_pool_id(&env, pool_index, &new_pool_id);
+        storage::set_pool_id_index(&env, &new_pool_id, pool_index);
 
-        let index = storage::get_pool_count(&env);
-        storage::set_pool_id(&env, index, &pool_id);
-        storage::set_pool_id_index(&env, &pool_id, index);
-        storage::set_pool_state(
-            &env,
-            index,
-            &PoolState {
-                acc_reward_per_share: 0,
-                total_staked: 0,
-                last_reward_time: env.ledger().timestamp(),
-                prev_acc_reward_per_share: 0,
-            },
-        );
-        storage::set_pool_count(&env, index + 1);
+        events::pool_migrated(&env, pool_index, &old_pool_id, &new_pool_id);
+        Ok(())
+    }
+
+    /// Register an SDEX pool whose stakers are paid in a partner `reward_token`
+    /// instead of LMNR. The pool earns nothing from the global rate; it is
+    /// funded and emitted through `fund_pool` (or an epoch budget drawn from
+    /// the partner balance). The token's decimals are recorded so the reward
+    /// accumulator uses a matching precision.
+    pub fn add_pool_with_reward_token(
+        env: Env,
+        admin: Address,
+        pool_id: BytesN<32>,
+        reward_token: Address,
+    ) -> Result<u32, ContractError> {
+        Self::require_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
 
+        if Self::is_protected_token(&env, &reward_token) {
+            return Err(ContractError::InvalidConfig);
+        }
+        let reward_decimals = match token::Client::new(&env, &reward_token).try_decimals() {
+            Ok(Ok(decimals)) if decimals <= MAX_REWARD_DECIMALS => decimals,
+            _ => return Err(ContractError::InvalidConfig),
+        };
+
+        let index = Self::register_pool(&env, &pool_id)?;
+        let mut config = storage::get_pool_config(&env, index);
+        config.reward_token = Some(reward_token);
+        storage::set_pool_config(&env, index, &config);
+        storage::set_reward_decimals(&env, index, reward_decimals);
+        Ok(index)
+    }
+
+    /// Register a deposit-mode pool for a Soroban AMM whose LP share is a
+    /// token. Stakers escrow the token with `stake_deposit` instead of
+    /// proving SDEX balances, and rewards accrue continuously without epochs.
+    /// The pool id is derived from the token address.
+    pub fn add_deposit_pool(
+        env: Env,
+        admin: Address,
+        lp_token: Address,
+    ) -> Result<u32, ContractError> {
+        Self::require_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        Self::register_deposit_pool(&env, &lp_token, None)
+    }
+
+    /// Register an Aquarius AMM pool as a deposit-mode pool. The share token
+    /// is read from the pool's `share_id()` and must be a token; stakers
+    /// then escrow Aqua LP shares with `stake_deposit`.
+    pub fn add_aqua_pool(
+        env: Env,
+        admin: Address,
+        aqua_pool: Address,
+    ) -> Result<u32, ContractError> {
+        Self::require_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+
+        let share_token =
+            hooks::aqua_share_token(&env, &aqua_pool).ok_or(ContractError::InvalidAmmPool)?;
+        Self::register_deposit_pool(&env, &share_token, Some(aqua_pool))
+    }
+
+    /// Give a Merkle pool a deposit side for a Soroban AMM market on the same
+    /// pair. Returns the index of a deposit-mode pool whose stakers share the
+    /// Merkle pool's accumulator and total_staked, so proven SDEX balances and
+    /// escrowed `lp_token` compete for one reward stream. The token must use
+    /// the Merkle pool's LP decimals.
+    pub fn add_hybrid_deposit(
+        env: Env,
+        admin: Address,
+        merkle_pool: u32,
+        lp_token: Address,
+    ) -> Result<u32, ContractError> {
+        Self::require_admin(&env, &admin)?;
+        Self::require_valid_pool(&env, merkle_pool)?;
+        Self::require_merkle_pool(&env, merkle_pool)?;
+        storage::extend_instance_ttl(&env);
+
+        let merkle_decimals = storage::get_pool_config(&env, merkle_pool).lp_decimals;
+        let index = Self::register_deposit_pool(&env, &lp_token, None)?;
+        let mut config = storage::get_pool_config(&env, index);
+        if config.lp_decimals != merkle_decimals {
+            return Err(ContractError::InvalidConfig);
+        }
+        config.shared_with = Some(merkle_pool);
+        storage::set_pool_config(&env, index, &config);
         Ok(index)
     }
 
@@ -72,12 +214,24 @@ impl LpStakingContract {
         Self::require_admin(&env, &admin)?;
         Self::require_valid_pool(&env, pool_index)?;
         storage::extend_instance_ttl(&env);
+        Self::log_admin_action(&env, &admin, symbol_short!("rm_pool"), pool_index);
 
         // Settle any accrued rewards before deactivation
         let mut state = rewards::update_pool(&env, pool_index);
         state.total_staked = 0;
         storage::set_pool_state(&env, pool_index, &state);
 
+        let mut config = storage::get_pool_config(&env, pool_index);
+        config.removed = true;
+        storage::set_pool_config(&env, pool_index, &config);
+
+        // Only what the pool still owes stays earmarked
+        let earmark = storage::get_pool_earmark(&env, pool_index);
+        let liability = storage::get_pool_liability(&env, pool_index);
+        if earmark > liability {
+            storage::set_pool_earmark(&env, pool_index, liability);
+        }
+
         Ok(())
     }
 
@@ -90,477 +244,4788 @@ impl LpStakingContract {
         root: BytesN<32>,
         snapshot_ledger: u32,
     ) -> Result<(), ContractError> {
-        Self::require_admin(&env, &admin)?;
+        Self::require_co_admin(&env, &admin)?;
         Self::require_valid_pool(&env, pool_index)?;
+        Self::require_merkle_pool(&env, pool_index)?;
         storage::extend_instance_ttl(&env);
-
-        // Settle rewards at current accumulator, preserve total_staked
-        let mut state = rewards::update_pool(&env, pool_index);
-        state.prev_acc_reward_per_share = state.acc_reward_per_share;
-        // NOTE: We no longer reset total_staked - existing stakes carry over
-        storage::set_pool_state(&env, pool_index, &state);
-
-        // Determine next epoch_id
-        let epoch_id = if storage::has_merkle_root(&env, pool_index) {
-            storage::get_merkle_root(&env, pool_index).epoch_id + 1
-        } else {
-            1
-        };
-
-        storage::set_merkle_root(
+        Self::log_admin_action(
             &env,
-            pool_index,
-            &MerkleRootData {
-                root,
-                epoch_id,
-                snapshot_ledger,
-                posted_at: env.ledger().timestamp(),
-            },
+            &admin,
+            symbol_short!("root"),
+            (pool_index, root.clone(), snapshot_ledger),
         );
 
-        Ok(())
+        let leaf_version = merkle::LEAF_VERSION as u32;
+        Self::post_root(&env, pool_index, root, snapshot_ledger, leaf_version, 0, 0)
     }
 
-    /// Update the global reward rate (LMNR stroops per second).
-    /// Updates all active pools' accumulators before changing rate.
-    pub fn set_reward_rate(
+    /// Admin-only: `set_merkle_root` for several pools in one call, each
+    /// entry being `(pool_index, root, snapshot_ledger)`. All rotations land
+    /// or none do, so pools can't be left on mismatched epochs. A pool may
+    /// appear once.
+    pub fn set_merkle_roots(
         env: Env,
         admin: Address,
-        new_rate: i128,
+        roots: Vec<(u32, BytesN<32>, u32)>,
     ) -> Result<(), ContractError> {
-        Self::require_admin(&env, &admin)?;
+        Self::require_co_admin(&env, &admin)?;
         storage::extend_instance_ttl(&env);
+        Self::log_admin_action(&env, &admin, symbol_short!("roots"), roots.clone());
 
-        // Update all pools to current time before changing rate
-        let pool_count = storage::get_pool_count(&env);
-        for i in 0..pool_count {
-            rewards::update_pool(&env, i);
+        if roots.len() > batch::MAX_BATCH_SIZE {
+            return Err(ContractError::BatchTooLarge);
+        }
+        let mut posted: Vec<u32> = Vec::new(&env);
+        let leaf_version = merkle::LEAF_VERSION as u32;
+        for (pool_index, root, snapshot_ledger) in roots.iter() {
+            if posted.contains(pool_index) {
+                return Err(ContractError::InvalidConfig);
+            }
+            Self::require_valid_pool(&env, pool_index)?;
+            Self::require_merkle_pool(&env, pool_index)?;
+            Self::post_root(&env, pool_index, root, snapshot_ledger, leaf_version, 0, 0)?;
+            posted.push_back(pool_index);
         }
-
-        storage::set_reward_rate(&env, new_rate);
         Ok(())
     }
 
-    /// Transfer admin role to a new address.
-    pub fn set_admin(env: Env, admin: Address, new_admin: Address) -> Result<(), ContractError> {
-        Self::require_admin(&env, &admin)?;
+    /// Post a root attested by the root oracles (see `set_root_oracles`), so
+    /// the snapshot service can rotate epochs without an admin key. At least
+    /// the threshold of distinct oracle keys must sign
+    /// `merkle::root_attestation_payload` for the epoch the root opens, so
+    /// signatures cannot be replayed; any invalid signature rejects the
+    /// post. Anyone may submit it.
+    pub fn set_merkle_root_signed(
+        env: Env,
+        pool_index: u32,
+        root: BytesN<32>,
+        snapshot_ledger: u32,
+        signatures: Vec<OracleSignature>,
+    ) -> Result<(), ContractError> {
+        Self::require_valid_pool(&env, pool_index)?;
+        Self::require_merkle_pool(&env, pool_index)?;
         storage::extend_instance_ttl(&env);
-        storage::set_admin(&env, &new_admin);
-        Ok(())
+
+        let oracles = storage::get_root_oracles(&env).ok_or(ContractError::Unauthorized)?;
+        let payload = merkle::root_attestation_payload(
+            &env,
+            &env.current_contract_address(),
+            pool_index,
+            Self::next_epoch_id(&env, pool_index),
+            &root,
+            snapshot_ledger,
+        );
+        let mut signers: Vec<BytesN<32>> = Vec::new(&env);
+        for attestation in signatures.iter() {
+            if !oracles.keys.contains(&attestation.public_key)
+                || signers.contains(&attestation.public_key)
+            {
+                return Err(ContractError::Unauthorized);
+            }
+            env.crypto()
+                .ed25519_verify(&attestation.public_key, &payload, &attestation.signature);
+            signers.push_back(attestation.public_key);
+        }
+        if signers.len() < oracles.threshold {
+            return Err(ContractError::Unauthorized);
+        }
+
+        let leaf_version = merkle::LEAF_VERSION as u32;
+        Self::post_root(&env, pool_index, root, snapshot_ledger, leaf_version, 0, 0)
     }
 
-    /// Admin-only: swap the reward token (LMNR SAC) to a new address.
-    /// Used for the LMNR → xLMNR migration. Admin should withdraw existing
-    /// reward balance and notify stakers to claim pending rewards before
-    /// calling this — pending rewards denominated in the old token become
-    /// unclaimable once the pointer changes.
-    pub fn set_lmnr_token(env: Env, admin: Address, new_token: Address) -> Result<(), ContractError> {
+    /// Admin-only: set or clear the oracle keys whose signatures
+    /// `set_merkle_root_signed` accepts and how many must sign each root.
+    /// Up to 10 distinct keys, with a threshold between 1 and their count.
+    pub fn set_root_oracles(
+        env: Env,
+        admin: Address,
+        oracles: Option<RootOracles>,
+    ) -> Result<(), ContractError> {
         Self::require_admin(&env, &admin)?;
         storage::extend_instance_ttl(&env);
-        storage::set_lmnr_token(&env, &new_token);
+        Self::log_admin_action(&env, &admin, symbol_short!("root_orcl"), oracles.clone());
+
+        if let Some(config) = &oracles {
+            let count = config.keys.len();
+            if count > MAX_ROOT_ORACLES || config.threshold == 0 || config.threshold > count {
+                return Err(ContractError::InvalidConfig);
+            }
+            for (i, key) in config.keys.iter().enumerate() {
+                if config.keys.first_index_of(&key) != Some(i as u32) {
+                    return Err(ContractError::InvalidConfig);
+                }
+            }
+        }
+        storage::set_root_oracles(&env, &oracles);
         Ok(())
     }
 
-    /// Admin-only: upgrade contract WASM to a new version.
-    pub fn upgrade(env: Env, admin: Address, new_wasm_hash: BytesN<32>) -> Result<(), ContractError> {
-        Self::require_admin(&env, &admin)?;
-        env.deployer().update_current_contract_wasm(new_wasm_hash);
-        Ok(())
+    /// Admin-only: `set_merkle_root` declaring `declared_total`, the sum of
+    /// every leaf's balance in the snapshot. Stakes proven against the root
+    /// can then add up to at most that total, which bounds what a corrupted
+    /// tree or duplicated leaves can pay out.
+    pub fn set_merkle_root_with_total(
+        env: Env,
+        admin: Address,
+        pool_index: u32,
+        root: BytesN<32>,
+        snapshot_ledger: u32,
+        declared_total: i128,
+    ) -> Result<(), ContractError> {
+        Self::require_co_admin(&env, &admin)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        Self::require_merkle_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+        Self::log_admin_action(
+            &env,
+            &admin,
+            symbol_short!("root_tot"),
+            (pool_index, root.clone(), snapshot_ledger, declared_total),
+        );
+
+        if declared_total <= 0 {
+            return Err(ContractError::InvalidAmount);
+        }
+        let leaf_version = merkle::LEAF_VERSION as u32;
+        Self::post_root(
+            &env,
+            pool_index,
+            root,
+            snapshot_ledger,
+            leaf_version,
+            0,
+            declared_total,
+        )
     }
 
-    /// Admin-only: reconcile a staker's balance without requiring a Merkle proof.
-    /// Used by the cron to auto-adjust stakers who changed their LP holdings.
-    pub fn update_stake(
+    /// Admin-only: `set_merkle_root` for a tree of time-weighted average
+    /// balances over an epoch of `epoch_duration` seconds
+    /// (`merkle::compute_twab_leaf`). Stakers prove their TWAB as
+    /// `lp_balance`. Each root selects its own leaf layout, so a pool can
+    /// move between the two from one epoch to the next. A nonzero
+    /// `declared_total` bounds proven stakes as in
+    /// `set_merkle_root_with_total`.
+    pub fn set_twab_root(
         env: Env,
         admin: Address,
-        user: Address,
         pool_index: u32,
-        new_amount: i128,
+        root: BytesN<32>,
+        snapshot_ledger: u32,
+        epoch_duration: u64,
+        declared_total: i128,
     ) -> Result<(), ContractError> {
-        Self::require_admin(&env, &admin)?;
+        Self::require_co_admin(&env, &admin)?;
         Self::require_valid_pool(&env, pool_index)?;
+        Self::require_merkle_pool(&env, pool_index)?;
         storage::extend_instance_ttl(&env);
+        Self::log_admin_action(
+            &env,
+            &admin,
+            symbol_short!("twab_root"),
+            (
+                pool_index,
+                root.clone(),
+                snapshot_ledger,
+                epoch_duration,
+                declared_total,
+            ),
+        );
 
-        if new_amount < 0 {
+        if epoch_duration == 0 {
+            return Err(ContractError::InvalidConfig);
+        }
+        if declared_total < 0 {
             return Err(ContractError::InvalidAmount);
         }
+        let leaf_version = merkle::TWAB_LEAF_VERSION as u32;
+        Self::post_root(
+            &env,
+            pool_index,
+            root,
+            snapshot_ledger,
+            leaf_version,
+            epoch_duration,
+            declared_total,
+        )
+    }
 
-        // Update pool accumulator
-        let state = rewards::update_pool(&env, pool_index);
+    /// Roll the pool over to a new epoch under `root`.
+    fn post_root(
+        env: &Env,
+        pool_index: u32,
+        root: BytesN<32>,
+        snapshot_ledger: u32,
+        leaf_version: u32,
+        epoch_duration: u64,
+        declared_total: i128,
+    ) -> Result<(), ContractError> {
+        Self::check_epoch_cadence(env, pool_index)?;
+        Self::check_snapshot_ledger(env, pool_index, snapshot_ledger)?;
 
-        // Get current epoch_id (needed for new staker records)
-        let current_epoch_id = if storage::has_merkle_root(&env, pool_index) {
-            storage::get_merkle_root(&env, pool_index).epoch_id
-        } else {
-            0
-        };
+        // Settle rewards at current accumulator, preserve total_staked
+        let mut state = rewards::update_pool(env, pool_index);
+        state.prev_acc_reward_per_share = state.acc_reward_per_share;
+        // NOTE: We no longer reset total_staked - existing stakes carry over
+        storage::set_pool_state(env, pool_index, &state);
+        rewards::record_checkpoint(env, pool_index, &state);
+        rewards::distribute_revenue(env, pool_index, state.total_staked);
+        Self::retarget_oracle_rate(env, pool_index, &state);
+        let config = storage::get_pool_config(env, pool_index);
+        if config.epoch_budget > 0 {
+            Self::extend_reward_schedule(
+                env,
+                pool_index,
+                config.epoch_budget,
+                config.budget_duration,
+            );
+        }
 
-        if storage::has_staker(&env, &user, pool_index) {
-            let staker = storage::get_staker(&env, &user, pool_index);
+        // The superseded root moves to temporary storage
+        let epoch_id = Self::next_epoch_id(env, pool_index);
+        if epoch_id > 1 {
+            let previous = storage::get_merkle_root(env, pool_index);
+            storage::set_historical_root(env, pool_index, &previous);
+        }
 
-            // Check if staker's epoch is current
-            let is_current_epoch = current_epoch_id > 0 && staker.epoch_id == current_epoch_id;
+        storage::set_merkle_root(
+            env,
+            pool_index,
+            &MerkleRootData {
+                root: root.clone(),
+                epoch_id,
+                snapshot_ledger,
+                posted_at: env.ledger().timestamp(),
+                leaf_version,
+                epoch_duration,
+                declared_total,
+            },
+        );
 
-            // Settle pending rewards
-            let pending = if is_current_epoch {
-                rewards::calculate_pending(&state, &staker)
-            } else {
-                rewards::calculate_pending_stale(&state, &staker)
-            };
+        events::epoch_rotated(env, pool_index, &state, epoch_id, snapshot_ledger, &root);
+        hooks::notify_epoch_listeners(env, pool_index, epoch_id, &root);
 
-            let old_amount = staker.staked_amount;
+        Ok(())
+    }
 
-            // Update staker record
-            let new_debt = rewards::compute_reward_debt(new_amount, state.acc_reward_per_share);
-            storage::set_staker(
-                &env,
-                &user,
-                pool_index,
-                &StakerInfo {
-                    staked_amount: new_amount,
-                    reward_debt: new_debt,
-                    pending_rewards: pending,
-                    epoch_id: current_epoch_id,
-                },
-            );
+    /// Bring a pool's accumulator and liability up to date and record a
+    /// checkpoint of it, so quiet pools don't lag until the next stake or
+    /// claim. Anyone may call; the caller earns the checkpoint bounty (see
+    /// `set_keeper_bounties`) when the pool had not yet been updated this
+    /// second. Checkpoints are also taken at every epoch rollover. Returns
+    /// the bounty paid.
+    pub fn checkpoint(env: Env, caller: Address, pool_index: u32) -> Result<i128, ContractError> {
+        caller.require_auth();
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
 
-            // Adjust total_staked by the delta
-            let mut updated_state = storage::get_pool_state(&env, pool_index);
-            updated_state.total_staked = updated_state.total_staked - old_amount + new_amount;
-            storage::set_pool_state(&env, pool_index, &updated_state);
-        } else if new_amount > 0 {
-            // Create new staker entry
-            let new_debt = rewards::compute_reward_debt(new_amount, state.acc_reward_per_share);
-            storage::set_staker(
-                &env,
-                &user,
-                pool_index,
-                &StakerInfo {
-                    staked_amount: new_amount,
-                    reward_debt: new_debt,
-                    pending_rewards: 0,
-                    epoch_id: current_epoch_id,
-                },
-            );
+        let stale =
+            storage::get_pool_state(&env, pool_index).last_reward_time < env.ledger().timestamp();
+        let state = rewards::update_pool(&env, pool_index);
+        rewards::record_checkpoint(&env, pool_index, &state);
 
-            let mut updated_state = storage::get_pool_state(&env, pool_index);
-            updated_state.total_staked += new_amount;
-            storage::set_pool_state(&env, pool_index, &updated_state);
+        if !stale || storage::get_pool_config(&env, pool_index).removed {
+            return Ok(0);
         }
-        // If new_amount == 0 and staker doesn't exist, no-op
+        let bounty = storage::get_keeper_bounties(&env).checkpoint;
+        Ok(Self::pay_keeper_bounty(
+            &env,
+            &caller,
+            symbol_short!("checkpnt"),
+            bounty,
+        ))
+    }
+
+    /// `checkpoint` up to `limit` pools from index `start`, skipping removed
+    /// pools and the deposit side of hybrid pools, whose accumulator is
+    /// their Merkle pool's. Anyone may call; no bounty is paid. Returns the
+    /// number checkpointed.
+    pub fn checkpoint_all(env: Env, start: u32, limit: u32) -> Result<u32, ContractError> {
+        if limit > batch::MAX_BATCH_SIZE {
+            return Err(ContractError::BatchTooLarge);
+        }
+        storage::extend_instance_ttl(&env);
+
+        let end = start
+            .saturating_add(limit)
+            .min(storage::get_pool_count(&env));
+        let mut checkpointed = 0;
+        for pool_index in start..end {
+            let config = storage::get_pool_config(&env, pool_index);
+            if config.removed || config.shared_with.is_some() {
+                continue;
+            }
+            let state = rewards::update_pool(&env, pool_index);
+            rewards::record_checkpoint(&env, pool_index, &state);
+            checkpointed += 1;
+        }
+        Ok(checkpointed)
+    }
 
+    /// Admin-only: set or clear the keeper allowed to call `claim_batch`
+    /// and `poke_deadlines`.
+    pub fn set_keeper(
+        env: Env,
+        admin: Address,
+        keeper: Option<Address>,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        Self::log_admin_action(&env, &admin, symbol_short!("keeper"), keeper.clone());
+        storage::set_keeper(&env, &keeper);
         Ok(())
     }
 
-    /// Admin-only: withdraw LMNR from the contract.
-    pub fn withdraw(
+    /// Register a contract to be notified with `on_epoch(pool_index, epoch_id, root)`
+    /// whenever a new root is posted. Notification is best-effort.
+    pub fn add_epoch_listener(
         env: Env,
         admin: Address,
-        amount: i128,
+        listener: Address,
     ) -> Result<(), ContractError> {
         Self::require_admin(&env, &admin)?;
         storage::extend_instance_ttl(&env);
 
-        if amount <= 0 {
-            return Err(ContractError::InvalidAmount);
+        let mut listeners = storage::get_epoch_listeners(&env);
+        if listeners.contains(&listener) {
+            return Ok(());
         }
-
-        let lmnr_token = storage::get_lmnr_token(&env);
-        let token_client = token::Client::new(&env, &lmnr_token);
-
-        let contract_balance = token_client.balance(&env.current_contract_address());
-        if contract_balance < amount {
-            return Err(ContractError::InsufficientRewardBalance);
+        if listeners.len() >= hooks::MAX_EPOCH_LISTENERS {
+            return Err(ContractError::ListenerLimitReached);
         }
 
-        token_client.transfer(&env.current_contract_address(), &admin, &amount);
-
+        listeners.push_back(listener);
+        storage::set_epoch_listeners(&env, &listeners);
         Ok(())
     }
 
-    /// Transfer LMNR into the contract for reward distribution.
-    pub fn fund(env: Env, funder: Address, amount: i128) -> Result<(), ContractError> {
-        if amount <= 0 {
-            return Err(ContractError::InvalidAmount);
-        }
-        funder.require_auth();
+    /// Unregister an epoch listener. No-op if it was not registered.
+    pub fn remove_epoch_listener(
+        env: Env,
+        admin: Address,
+        listener: Address,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+
+        let mut listeners = storage::get_epoch_listeners(&env);
+        if let Some(i) = listeners.first_index_of(&listener) {
+            listeners.remove(i);
+            storage::set_epoch_listeners(&env, &listeners);
+        }
+        Ok(())
+    }
+
+    /// Update the global reward rate (LMNR stroops per second).
+    /// Records a `RateCheckpoint`; pools pick the new rate up on their next
+    /// update, having accrued at the old one until now.
+    /// The rate must be within the `set_rate_bounds` ceilings. Unless
+    /// `force` is set, the balance not already owed to stakers must also
+    /// cover the configured minimum runway at the new rate.
+    pub fn set_reward_rate(
+        env: Env,
+        admin: Address,
+        new_rate: i128,
+        force: bool,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        Self::log_admin_action(&env, &admin, symbol_short!("rate"), (new_rate, force));
+
+        if new_rate < 0 {
+            return Err(ContractError::InvalidAmount);
+        }
+        let bounds = storage::get_rate_bounds(&env);
+        if bounds.max_rate > 0 && new_rate > bounds.max_rate {
+            return Err(ContractError::RateTooHigh);
+        }
+        if bounds.max_daily_emission > 0 {
+            let emitting_pools = storage::get_emission_totals(&env).emitting_pools.max(1);
+            // A rate whose daily emission overflows is past any bound
+            let daily = new_rate
+                .checked_mul(emitting_pools as i128)
+                .and_then(|rate| rate.checked_mul(SECONDS_PER_DAY));
+            if daily.is_none_or(|daily| daily > bounds.max_daily_emission) {
+                return Err(ContractError::RateTooHigh);
+            }
+        }
+
+        let min_runway_days = storage::get_min_runway_days(&env);
+        if !force && min_runway_days > 0 {
+            let emitting_pools = storage::get_emission_totals(&env).emitting_pools as i128;
+            // A rate too large to even price is too large to fund
+            let required = new_rate
+                .checked_mul(emitting_pools)
+                .and_then(|rate| rate.checked_mul(min_runway_days as i128 * SECONDS_PER_DAY))
+                .ok_or(ContractError::InsufficientRunway)?;
+            let liability = rewards::outstanding_liability(&env);
+            if Self::reward_balance(env.clone()) - liability < required {
+                return Err(ContractError::InsufficientRunway);
+            }
+        }
+
+        // Pools pick the change up lazily on their next update_pool
+        let checkpoint = RateCheckpoint {
+            timestamp: env.ledger().timestamp(),
+            rate: new_rate,
+            cumulative: rewards::emission_index(&env),
+        };
+        storage::push_rate_history(&env, &checkpoint);
+        storage::set_rate_checkpoint(&env, &checkpoint);
+        storage::set_reward_rate(&env, new_rate);
+        Ok(())
+    }
+
+    /// `set_reward_rate` in LMNR stroops per pool per day, rounded down to a
+    /// whole per-second rate.
+    pub fn set_reward_rate_per_day(
+        env: Env,
+        admin: Address,
+        per_day: i128,
+        force: bool,
+    ) -> Result<(), ContractError> {
+        Self::set_reward_rate(env, admin, per_day / SECONDS_PER_DAY, force)
+    }
+
+    /// Admin-only: ceilings on the global reward rate, checked by every
+    /// `set_reward_rate`. 0 disables a bound.
+    pub fn set_rate_bounds(
+        env: Env,
+        admin: Address,
+        max_rate: i128,
+        max_daily_emission: i128,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        Self::log_admin_action(
+            &env,
+            &admin,
+            symbol_short!("rate_bnds"),
+            (max_rate, max_daily_emission),
+        );
+
+        if max_rate < 0 || max_daily_emission < 0 {
+            return Err(ContractError::InvalidAmount);
+        }
+        storage::set_rate_bounds(
+            &env,
+            &RateBounds {
+                max_rate,
+                max_daily_emission,
+            },
+        );
+        Ok(())
+    }
+
+    /// Admin-only: days of emissions the unowed balance must cover for
+    /// `set_reward_rate` to accept a new rate without `force`. 0 disables.
+    pub fn set_min_runway_days(env: Env, admin: Address, days: u32) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        storage::set_min_runway_days(&env, days);
+        Ok(())
+    }
+
+    /// Transfer the primary admin role to a new address. Needs the admin
+    /// quorum when one is set.
+    pub fn set_admin(env: Env, admin: Address, new_admin: Address) -> Result<(), ContractError> {
+        Self::require_co_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        if !Self::approve_sensitive(&env, &admin, symbol_short!("set_admin"), new_admin.clone()) {
+            return Ok(());
+        }
+        Self::log_admin_action(&env, &admin, symbol_short!("set_admin"), new_admin.clone());
+        storage::set_admin(&env, &new_admin);
+        Ok(())
+    }
+
+    /// Admin-only: set co-admins who share the admin role with the primary
+    /// admin, and how many of them (the primary admin counts as one) must
+    /// make the same sensitive call before it runs. An empty `members` list
+    /// returns to single-admin control. Needs the current quorum when one is
+    /// set.
+    pub fn set_admins(
+        env: Env,
+        admin: Address,
+        members: Vec<Address>,
+        threshold: u32,
+    ) -> Result<(), ContractError> {
+        Self::require_co_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+
+        let admin_set = if members.is_empty() {
+            None
+        } else {
+            let mut unique = Vec::new(&env);
+            for member in members.iter() {
+                if !unique.contains(&member) {
+                    unique.push_back(member);
+                }
+            }
+            if unique.len() != members.len() || threshold == 0 || threshold > members.len() + 1 {
+                return Err(ContractError::InvalidConfig);
+            }
+            Some(AdminSet { members, threshold })
+        };
+
+        if !Self::approve_sensitive(&env, &admin, symbol_short!("set_admns"), admin_set.clone()) {
+            return Ok(());
+        }
+        Self::log_admin_action(&env, &admin, symbol_short!("set_admns"), admin_set.clone());
+        storage::set_admin_set(&env, &admin_set);
+        Ok(())
+    }
+
+    /// Admin-only: swap the reward token (LMNR SAC) to a new address.
+    /// Used for the LMNR → xLMNR migration. Admin should withdraw existing
+    /// reward balance and notify stakers to claim pending rewards before
+    /// calling this — pending rewards denominated in the old token become
+    /// unclaimable once the pointer changes. `migrate_reward_token` converts
+    /// them instead. Needs the admin quorum when one is set.
+    pub fn set_lmnr_token(env: Env, admin: Address, new_token: Address) -> Result<(), ContractError> {
+        Self::require_co_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        if !Self::approve_sensitive(&env, &admin, symbol_short!("set_token"), new_token.clone()) {
+            return Ok(());
+        }
+        Self::log_admin_action(&env, &admin, symbol_short!("set_token"), new_token.clone());
+        storage::set_lmnr_token(&env, &new_token);
+        Ok(())
+    }
+
+    /// Admin-only: queue a switch of the payout token to `new_token`, e.g.
+    /// after LMNR is redeployed. `exchange_ratio` is new-token stroops per
+    /// old-token stroop times 1e7 (1_0000000 swaps one for one); the new
+    /// token must have LMNR's decimals. Requires the contract to be paused
+    /// and becomes executable by `migrate_reward_token` after
+    /// `TOKEN_MIGRATION_DELAY`. Needs the admin quorum when one is set.
+    /// Returns the unlock time, or 0 while approvals are still collected.
+    pub fn queue_reward_token_migration(
+        env: Env,
+        admin: Address,
+        new_token: Address,
+        exchange_ratio: i128,
+    ) -> Result<u64, ContractError> {
+        Self::require_co_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        let args = (new_token.clone(), exchange_ratio);
+        if !Self::approve_sensitive(&env, &admin, symbol_short!("tok_queue"), args) {
+            return Ok(0);
+        }
+
+        if !storage::is_paused(&env) {
+            return Err(ContractError::NotPaused);
+        }
+        if exchange_ratio <= 0 || Self::is_protected_token(&env, &new_token) {
+            return Err(ContractError::InvalidConfig);
+        }
+        Self::require_unaccounted_token(&env, &new_token)?;
+        match token::Client::new(&env, &new_token).try_decimals() {
+            Ok(Ok(decimals)) if decimals == storage::LMNR_DECIMALS => {}
+            _ => return Err(ContractError::InvalidConfig),
+        }
+
+        let unlock_time = env.ledger().timestamp() + TOKEN_MIGRATION_DELAY;
+        let pending = PendingTokenMigration {
+            new_token: new_token.clone(),
+            exchange_ratio,
+            unlock_time,
+        };
+        Self::log_admin_action(
+            &env,
+            &admin,
+            symbol_short!("tok_queue"),
+            (new_token, exchange_ratio),
+        );
+        storage::set_pending_token_migration(&env, &pending);
+        Ok(unlock_time)
+    }
+
+    /// Admin-only: execute the queued payout token switch once its timelock
+    /// has elapsed, with the same `new_token` and `exchange_ratio`. Settles
+    /// every pool, restates what stakers are owed and every LMNR budget at
+    /// the ratio, and sends the old token balance to the primary admin for
+    /// the swap. The contract must already hold the old balance's worth of
+    /// the new token. Needs the admin quorum when one is set. Returns the
+    /// old balance sent, or 0 while approvals are still collected.
+    pub fn migrate_reward_token(
+        env: Env,
+        admin: Address,
+        new_token: Address,
+        exchange_ratio: i128,
+    ) -> Result<i128, ContractError> {
+        Self::require_co_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        let args = (new_token.clone(), exchange_ratio);
+        if !Self::approve_sensitive(&env, &admin, symbol_short!("tok_migr"), args) {
+            return Ok(0);
+        }
+
+        if !storage::is_paused(&env) {
+            return Err(ContractError::NotPaused);
+        }
+        let pending =
+            storage::get_pending_token_migration(&env).ok_or(ContractError::NoPendingMigration)?;
+        if pending.new_token != new_token || pending.exchange_ratio != exchange_ratio {
+            return Err(ContractError::InvalidConfig);
+        }
+        if env.ledger().timestamp() < pending.unlock_time {
+            return Err(ContractError::TimelockNotElapsed);
+        }
+
+        let contract = env.current_contract_address();
+        let old_token = storage::get_lmnr_token(&env);
+        let old_client = token::Client::new(&env, &old_token);
+        let old_balance = old_client.balance(&contract);
+        let new_balance = token::Client::new(&env, &new_token).balance(&contract);
+        if new_balance < token_migration::convert(old_balance, exchange_ratio) {
+            return Err(ContractError::InsufficientRewardBalance);
+        }
+
+        Self::update_all_pools(&env);
+        token_migration::rescale_books(&env, exchange_ratio);
+        let mut ratios = storage::get_token_migrations(&env);
+        ratios.push_back(exchange_ratio);
+        storage::set_token_migrations(&env, &ratios);
+
+        if old_balance > 0 {
+            old_client.transfer(&contract, &storage::get_admin(&env), &old_balance);
+        }
+        Self::log_admin_action(
+            &env,
+            &admin,
+            symbol_short!("tok_migr"),
+            (new_token.clone(), exchange_ratio),
+        );
+        storage::set_lmnr_token(&env, &new_token);
+        storage::remove_pending_token_migration(&env);
+        events::reward_token_migrated(&env, &old_token, &new_token, exchange_ratio);
+        Ok(old_balance)
+    }
+
+    /// Admin-only: declare the decimals of a pool's LP amounts so the reward
+    /// accumulator uses a matching precision (see `rewards::precision_for_decimals`).
+    /// Only allowed before the pool has any stake or accrued rewards.
+    pub fn set_pool_lp_decimals(
+        env: Env,
+        admin: Address,
+        pool_index: u32,
+        lp_decimals: u32,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        if lp_decimals > MAX_LP_DECIMALS {
+            return Err(ContractError::InvalidConfig);
+        }
+
+        let state = storage::get_pool_state(&env, pool_index);
+        if state.total_staked != 0 || state.acc_reward_per_share != 0 {
+            return Err(ContractError::PoolInUse);
+        }
+
+        let mut config = storage::get_pool_config(&env, pool_index);
+        config.lp_decimals = lp_decimals;
+        storage::set_pool_config(&env, pool_index, &config);
+        Ok(())
+    }
+
+    /// Admin-only: cap the total LP a pool accepts (0 removes the cap),
+    /// counted in stake weight (see `rewards::stake_weight`). Stakes already
+    /// above a lowered cap are kept but cannot grow.
+    pub fn set_pool_max_staked(
+        env: Env,
+        admin: Address,
+        pool_index: u32,
+        max_total_staked: i128,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        if max_total_staked < 0 {
+            return Err(ContractError::InvalidAmount);
+        }
+
+        let mut config = storage::get_pool_config(&env, pool_index);
+        config.max_total_staked = max_total_staked;
+        storage::set_pool_config(&env, pool_index, &config);
+        Ok(())
+    }
+
+    /// Admin-only: count at most `cap` LP per address toward stake weight
+    /// in the pool (0 removes the cap). Larger positions are accepted but
+    /// earn as if they held `cap`. Applies to positions as they are next
+    /// proven or changed.
+    pub fn set_pool_stake_cap(
+        env: Env,
+        admin: Address,
+        pool_index: u32,
+        cap: i128,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        if cap < 0 {
+            return Err(ContractError::InvalidAmount);
+        }
+
+        let mut config = storage::get_pool_config(&env, pool_index);
+        config.stake_cap = cap;
+        storage::set_pool_config(&env, pool_index, &config);
+        Ok(())
+    }
+
+    /// Admin-only: smallest LP balance `stake` accepts in pools without
+    /// their own minimum (0 disables).
+    pub fn set_min_stake(env: Env, admin: Address, amount: i128) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+
+        if amount < 0 {
+            return Err(ContractError::InvalidAmount);
+        }
+        storage::set_min_stake(&env, amount);
+        Ok(())
+    }
+
+    /// Admin-only: smallest pending reward a claim pays out (0 disables).
+    /// Smaller amounts keep accruing until they reach it.
+    pub fn set_min_claim(env: Env, admin: Address, amount: i128) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+
+        if amount < 0 {
+            return Err(ContractError::InvalidAmount);
+        }
+        storage::set_min_claim(&env, amount);
+        Ok(())
+    }
+
+    /// Admin-only: override the global minimum stake for one pool, or fall
+    /// back to it again with `None`.
+    pub fn set_pool_min_stake(
+        env: Env,
+        admin: Address,
+        pool_index: u32,
+        amount: Option<i128>,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        if amount.is_some_and(|amount| amount < 0) {
+            return Err(ContractError::InvalidAmount);
+        }
+
+        let mut config = storage::get_pool_config(&env, pool_index);
+        config.min_stake = amount;
+        storage::set_pool_config(&env, pool_index, &config);
+        Ok(())
+    }
+
+    /// Admin-only: after unstaking from the pool, an address must wait
+    /// `seconds` before it can stake there again (0 disables the cooldown).
+    /// Applies to unstakes made after the change.
+    pub fn set_unstake_cooldown(
+        env: Env,
+        admin: Address,
+        pool_index: u32,
+        seconds: u64,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        let mut config = storage::get_pool_config(&env, pool_index);
+        config.unstake_cooldown = seconds;
+        storage::set_pool_config(&env, pool_index, &config);
+        Ok(())
+    }
+
+    /// Admin-only: a newly proven position (or the growth of one) earns
+    /// nothing for its first `seconds`, so a balance flash-positioned at
+    /// the snapshot ledger cannot collect a full epoch (0 disables this).
+    /// Accrual is settled pro rata by time: of what accrues between a
+    /// settlement inside the warm-up and the next one after it, only the
+    /// share past the warm-up is paid. Forfeited rewards stay in the
+    /// contract.
+    pub fn set_warmup_period(
+        env: Env,
+        admin: Address,
+        pool_index: u32,
+        seconds: u64,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        Self::require_merkle_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        let mut config = storage::get_pool_config(&env, pool_index);
+        config.warmup_period = seconds;
+        storage::set_pool_config(&env, pool_index, &config);
+        Ok(())
+    }
+
+    /// Admin-only: weight stakes in the pool by the square root of their LP
+    /// balance instead of linearly, so large positions earn proportionally
+    /// less. `max_total_staked` then caps the summed weights. Only allowed
+    /// before the pool has any stake or accrued rewards.
+    pub fn set_sqrt_weighting(
+        env: Env,
+        admin: Address,
+        pool_index: u32,
+        enabled: bool,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        Self::require_merkle_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        let state = storage::get_pool_state(&env, pool_index);
+        if state.total_staked != 0 || state.acc_reward_per_share != 0 {
+            return Err(ContractError::PoolInUse);
+        }
+
+        let mut config = storage::get_pool_config(&env, pool_index);
+        config.sqrt_weighting = enabled;
+        storage::set_pool_config(&env, pool_index, &config);
+        Ok(())
+    }
+
+    /// Admin-only: boost the stake weight of stakers who re-prove every
+    /// epoch by `bps_per_epoch` per consecutive epoch, up to `max_bps`
+    /// (at most 10_000, doubling the weight). Missing an epoch resets the
+    /// streak. Takes effect as stakes are next proven.
+    pub fn set_loyalty_bonus(
+        env: Env,
+        admin: Address,
+        pool_index: u32,
+        bps_per_epoch: u32,
+        max_bps: u32,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        Self::require_merkle_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        if max_bps as i128 > BPS_DENOMINATOR {
+            return Err(ContractError::InvalidConfig);
+        }
+
+        let mut config = storage::get_pool_config(&env, pool_index);
+        config.loyalty_step_bps = bps_per_epoch;
+        config.loyalty_max_bps = max_bps;
+        storage::set_pool_config(&env, pool_index, &config);
+        Ok(())
+    }
+
+    /// Admin-only: bound the time between roots posted for a pool, in
+    /// seconds (0 lifts either bound). A late root is rejected until the
+    /// maximum is raised, so the cron's schedule should sit well inside it.
+    pub fn set_epoch_cadence(
+        env: Env,
+        admin: Address,
+        pool_index: u32,
+        min_interval: u64,
+        max_interval: u64,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        Self::require_merkle_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        if max_interval != 0 && max_interval < min_interval {
+            return Err(ContractError::InvalidConfig);
+        }
+
+        let mut config = storage::get_pool_config(&env, pool_index);
+        config.min_epoch_interval = min_interval;
+        config.max_epoch_interval = max_interval;
+        storage::set_pool_config(&env, pool_index, &config);
+        Ok(())
+    }
+
+    /// Admin-only: stop accepting proofs against a pool's root once it is
+    /// older than `max_age` seconds (0 accepts them indefinitely).
+    pub fn set_max_root_age(
+        env: Env,
+        admin: Address,
+        pool_index: u32,
+        max_age: u64,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        Self::require_merkle_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        let mut config = storage::get_pool_config(&env, pool_index);
+        config.max_root_age = max_age;
+        storage::set_pool_config(&env, pool_index, &config);
+        Ok(())
+    }
+
+    /// Admin-only: switch the pool between sorted-pair proofs (`stake`) and
+    /// positional proofs (`stake_positional`). Takes effect from the next
+    /// stake, so change it alongside the root built in the new format.
+    pub fn set_positional_proofs(
+        env: Env,
+        admin: Address,
+        pool_index: u32,
+        enabled: bool,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        Self::require_merkle_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        let mut config = storage::get_pool_config(&env, pool_index);
+        config.positional_proofs = enabled;
+        storage::set_pool_config(&env, pool_index, &config);
+        Ok(())
+    }
+
+    /// Admin-only: let anyone expire a stake in this pool once it has gone
+    /// `epochs` roots without being re-proven (0 disables expiry).
+    pub fn set_expire_after_epochs(
+        env: Env,
+        admin: Address,
+        pool_index: u32,
+        epochs: u32,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        Self::require_merkle_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        let mut config = storage::get_pool_config(&env, pool_index);
+        config.expire_after_epochs = epochs;
+        storage::set_pool_config(&env, pool_index, &config);
+        Ok(())
+    }
+
+    /// Admin-only: pay the pool a fixed `budget` per epoch instead of the
+    /// global rate. Each root post streams one budget evenly over the next
+    /// `duration` seconds, rolling in whatever the previous epoch's stream
+    /// has not yet paid out. A budget of 0 returns the pool to the global
+    /// rate; a stream already running finishes out.
+    pub fn set_epoch_budget(
+        env: Env,
+        admin: Address,
+        pool_index: u32,
+        budget: i128,
+        duration: u64,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        Self::require_merkle_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+        Self::log_admin_action(
+            &env,
+            &admin,
+            symbol_short!("budget"),
+            (pool_index, budget, duration),
+        );
+
+        if budget < 0 {
+            return Err(ContractError::InvalidAmount);
+        }
+        if budget > 0 && duration == 0 {
+            return Err(ContractError::InvalidConfig);
+        }
+
+        // Book accrual under the current mode before switching
+        rewards::update_pool(&env, pool_index);
+        let mut config = storage::get_pool_config(&env, pool_index);
+        config.epoch_budget = budget;
+        config.budget_duration = duration;
+        storage::set_pool_config(&env, pool_index, &config);
+        Ok(())
+    }
+
+    /// Admin-only: omit the destination address from claim events.
+    pub fn set_hide_claim_destination(
+        env: Env,
+        admin: Address,
+        hide: bool,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        storage::set_hide_claim_destination(&env, hide);
+        Ok(())
+    }
+
+    /// Admin-only: bring stored data up to this wasm's schema version after
+    /// an `upgrade`. Steps already applied are skipped, so calling it again
+    /// is a no-op. Returns the schema version now stored.
+    pub fn migrate(env: Env, admin: Address) -> Result<u32, ContractError> {
+        Self::require_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        Ok(migration::migrate(&env))
+    }
+
+    /// Admin-only: upgrade contract WASM to a new version. Needs the admin
+    /// quorum when one is set.
+    pub fn upgrade(env: Env, admin: Address, new_wasm_hash: BytesN<32>) -> Result<(), ContractError> {
+        Self::require_co_admin(&env, &admin)?;
+        if !Self::approve_sensitive(
+            &env,
+            &admin,
+            symbol_short!("upgrade"),
+            new_wasm_hash.clone(),
+        ) {
+            return Ok(());
+        }
+        Self::log_admin_action(
+            &env,
+            &admin,
+            symbol_short!("upgrade"),
+            new_wasm_hash.clone(),
+        );
+        env.deployer().update_current_contract_wasm(new_wasm_hash);
+        Ok(())
+    }
+
+    /// Admin-only: reconcile a staker's balance without requiring a Merkle proof.
+    /// Used by the cron to auto-adjust stakers who changed their LP holdings.
+    /// Pools with verified updates enabled take `update_stake_verified` instead.
+    pub fn update_stake(
+        env: Env,
+        admin: Address,
+        user: Address,
+        pool_index: u32,
+        new_amount: i128,
+    ) -> Result<(), ContractError> {
+        Self::require_co_admin(&env, &admin)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        Self::require_unverified_updates(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+        Self::log_admin_action(
+            &env,
+            &admin,
+            symbol_short!("upd_stake"),
+            (user.clone(), pool_index, new_amount),
+        );
+
+        Self::apply_stake_update(&env, &user, pool_index, new_amount)
+    }
+
+    /// Admin-only: require every mid-epoch balance edit in the pool to be
+    /// proven against a correction root (`update_stake_verified`) instead of
+    /// set directly by `update_stake`.
+    pub fn set_verified_updates(
+        env: Env,
+        admin: Address,
+        pool_index: u32,
+        enabled: bool,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        Self::require_merkle_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        let mut config = storage::get_pool_config(&env, pool_index);
+        config.verified_updates = enabled;
+        storage::set_pool_config(&env, pool_index, &config);
+        Ok(())
+    }
+
+    /// Admin-only: post a correction root for the pool's current epoch,
+    /// committing to corrected balances from a snapshot at or after the main
+    /// root's. Leaves use the main root's format and epoch id. Replaces any
+    /// earlier correction; the next main root makes it stale.
+    pub fn set_correction_root(
+        env: Env,
+        admin: Address,
+        pool_index: u32,
+        root: BytesN<32>,
+        snapshot_ledger: u32,
+    ) -> Result<(), ContractError> {
+        Self::require_co_admin(&env, &admin)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        Self::require_merkle_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+        Self::log_admin_action(
+            &env,
+            &admin,
+            symbol_short!("corr_root"),
+            (pool_index, root.clone(), snapshot_ledger),
+        );
+
+        if !storage::has_merkle_root(&env, pool_index) {
+            return Err(ContractError::NoMerkleRoot);
+        }
+        let main = storage::get_merkle_root(&env, pool_index);
+        if snapshot_ledger > env.ledger().sequence() {
+            return Err(ContractError::SnapshotInFuture);
+        }
+        if snapshot_ledger < main.snapshot_ledger {
+            return Err(ContractError::SnapshotNotIncreasing);
+        }
+
+        storage::set_correction_root(
+            &env,
+            pool_index,
+            &MerkleRootData {
+                root,
+                epoch_id: main.epoch_id,
+                snapshot_ledger,
+                posted_at: env.ledger().timestamp(),
+                leaf_version: main.leaf_version,
+                epoch_duration: main.epoch_duration,
+                declared_total: main.declared_total,
+            },
+        );
+        Ok(())
+    }
+
+    /// Admin-only: `update_stake` backed by a proof of `new_amount` against
+    /// the pool's correction root for the current epoch, so the edit can be
+    /// checked against the published correction snapshot.
+    pub fn update_stake_verified(
+        env: Env,
+        admin: Address,
+        user: Address,
+        pool_index: u32,
+        new_amount: i128,
+        proof: Vec<BytesN<32>>,
+    ) -> Result<(), ContractError> {
+        Self::require_co_admin(&env, &admin)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        Self::require_merkle_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+        Self::log_admin_action(
+            &env,
+            &admin,
+            symbol_short!("upd_vrfd"),
+            (user.clone(), pool_index, new_amount),
+        );
+
+        Self::require_proof_depth(&proof)?;
+        let correction =
+            storage::get_correction_root(&env, pool_index).ok_or(ContractError::NoMerkleRoot)?;
+        if correction.epoch_id != storage::get_merkle_root(&env, pool_index).epoch_id {
+            return Err(ContractError::StaleEpoch);
+        }
+        let leaf = merkle::compute_root_leaf(
+            &env,
+            &env.current_contract_address(),
+            pool_index,
+            &user,
+            new_amount,
+            &correction,
+        );
+        if !merkle::verify_proof(&env, &leaf, &proof, &correction.root) {
+            return Err(ContractError::InvalidProof);
+        }
+
+        Self::apply_stake_update(&env, &user, pool_index, new_amount)
+    }
+
+    /// Admin-only: `update_stake` for many users in one pool. See
+    /// `batch::run` for processing order, result codes and `atomic`.
+    pub fn update_stakes(
+        env: Env,
+        admin: Address,
+        pool_index: u32,
+        updates: Vec<StakeUpdate>,
+        atomic: bool,
+    ) -> Result<Vec<u32>, ContractError> {
+        Self::require_co_admin(&env, &admin)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        Self::require_unverified_updates(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+        Self::log_admin_action(
+            &env,
+            &admin,
+            symbol_short!("upd_batch"),
+            (pool_index, updates.clone(), atomic),
+        );
+
+        batch::run(&env, &updates, atomic, |update| {
+            if update.new_amount < 0 {
+                return Err(ContractError::InvalidAmount);
+            }
+            Self::apply_stake_update(&env, &update.user, pool_index, update.new_amount)
+        })
+    }
+
+    /// Admin-only: pay out pending rewards to each listed staker's own
+    /// address. See `batch::run` for processing order, result codes and
+    /// `atomic`.
+    pub fn distribute(
+        env: Env,
+        admin: Address,
+        pool_index: u32,
+        users: Vec<Address>,
+        atomic: bool,
+    ) -> Result<Vec<u32>, ContractError> {
+        Self::require_admin(&env, &admin)?;
+        Self::require_not_paused(&env)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        batch::run(&env, &users, atomic, |user| {
+            Self::settle_claim(&env, &user, pool_index, &user).map(|_| ())
+        })
+    }
+
+    /// Admin or keeper: push pending rewards to each listed staker's own
+    /// address, e.g. at the end of a campaign. Users with nothing to pay
+    /// (no position, nothing pending, pending below the minimum claim,
+    /// claims frozen, or a reward token rejecting the transfer) are skipped;
+    /// any other failure rejects the whole batch. Returns the total paid.
+    pub fn claim_batch(
+        env: Env,
+        operator: Address,
+        pool_index: u32,
+        users: Vec<Address>,
+    ) -> Result<i128, ContractError> {
+        Self::require_admin_or_keeper(&env, &operator)?;
+        Self::require_not_paused(&env)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        if users.len() > batch::MAX_BATCH_SIZE {
+            return Err(ContractError::BatchTooLarge);
+        }
+
+        let mut total_paid: i128 = 0;
+        for user in users.iter() {
+            match Self::settle_claim(&env, &user, pool_index, &user) {
+                Ok(paid) => total_paid += paid,
+                Err(
+                    ContractError::NoStakeFound
+                    | ContractError::NoRewardsToClaim
+                    | ContractError::BelowMinimumClaim
+                    | ContractError::ClaimsFrozen
+                    | ContractError::RewardTransferFailed,
+                ) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(total_paid)
+    }
+
+    /// Admin or keeper: emit `expiring` for each listed staker whose unclaimed
+    /// rewards become sweepable within the next 7 days, so frontends can warn
+    /// them. Users without pending rewards are skipped. Returns the number
+    /// warned.
+    pub fn poke_deadlines(
+        env: Env,
+        caller: Address,
+        pool_index: u32,
+        users: Vec<Address>,
+    ) -> Result<u32, ContractError> {
+        Self::require_admin_or_keeper(&env, &caller)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        if users.len() > batch::MAX_BATCH_SIZE {
+            return Err(ContractError::BatchTooLarge);
+        }
+
+        let now = env.ledger().timestamp();
+        let mut warned = 0;
+        for user in users.iter() {
+            let Some(deadline) = Self::claim_deadline(&env, &user, pool_index) else {
+                continue;
+            };
+            if deadline <= now || deadline - now > EXPIRY_NOTICE {
+                continue;
+            }
+            let pending = Self::pending_reward(env.clone(), user.clone(), pool_index);
+            if pending > 0 {
+                events::expiring_soon(&env, &user, pool_index, deadline, pending);
+                warned += 1;
+            }
+        }
+        Ok(warned)
+    }
+
+    /// Admin-only: copy pools and stakers from the original deployment so
+    /// LPs don't have to re-prove after a redeploy. Only allowed before
+    /// `activate`. An empty `page` copies every pool (id, accumulator and
+    /// root) into an empty contract; otherwise each listed address's entry
+    /// in every copied pool is copied, and its pending rewards booked as
+    /// liability. The legacy contract keeps no staker registry, so the
+    /// addresses come from the snapshot tooling, up to `SEED_PAGE_SIZE` per
+    /// call. Addresses already copied or without an entry are skipped.
+    /// Returns the number of records copied.
+    ///
+    /// The legacy contract should be paused first so accrual and claims
+    /// there don't diverge from the copy.
+    pub fn seed_from_legacy(
+        env: Env,
+        admin: Address,
+        legacy: Address,
+        page: Vec<Address>,
+    ) -> Result<u32, ContractError> {
+        Self::require_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        Self::log_admin_action(
+            &env,
+            &admin,
+            symbol_short!("seed"),
+            (legacy.clone(), page.clone()),
+        );
+
+        if storage::is_activated(&env) {
+            return Err(ContractError::AlreadyActivated);
+        }
+        if page.len() > SEED_PAGE_SIZE {
+            return Err(ContractError::BatchTooLarge);
+        }
+
+        let legacy = legacy::LegacyClient::new(&env, &legacy);
+        if page.is_empty() {
+            if storage::get_pool_count(&env) != 0 {
+                return Err(ContractError::PoolAlreadyExists);
+            }
+            let pool_count = legacy.get_pool_count();
+            for i in 0..pool_count {
+                let pool_id = legacy.get_pool_id(&i);
+                storage::set_pool_id(&env, i, &pool_id);
+                storage::set_pool_id_index(&env, &pool_id, i);
+                storage::set_pool_state(&env, i, &legacy.get_pool_state(&i));
+                // The legacy getter traps on a pool that never had a root
+                if let Ok(Ok(root)) = legacy.try_get_merkle_root(&i) {
+                    storage::set_merkle_root(&env, i, &root.into());
+                }
+            }
+            storage::set_pool_count(&env, pool_count);
+            rewards::rebuild_emission_totals(&env);
+            return Ok(pool_count);
+        }
+
+        let mut copied = 0;
+        for i in 0..storage::get_pool_count(&env) {
+            let state = storage::get_pool_state(&env, i);
+            let precision = rewards::pool_precision(&env, i);
+            for user in page.iter() {
+                if storage::has_staker(&env, &user, i) {
+                    continue;
+                }
+                // The legacy getter traps on an address with no entry
+                let Ok(Ok(old)) = legacy.try_get_staker_info(&user, &i) else {
+                    continue;
+                };
+                let staker: StakerInfo = old.into();
+                let pending = if Self::is_current_epoch(&env, i, &staker) {
+                    rewards::calculate_pending(&state, &staker, precision)
+                } else {
+                    rewards::calculate_pending_stale(&state, &staker, precision)
+                };
+                storage::set_staker(&env, &user, i, &staker);
+                storage::register_staker(&env, &user, i);
+                let liability = storage::get_pool_liability(&env, i);
+                storage::set_pool_liability(&env, i, liability + pending.max(0));
+                copied += 1;
+            }
+        }
+        Ok(copied)
+    }
+
+    /// Admin-only: mark the deployment live, permanently closing the
+    /// `seed_from_legacy` window.
+    pub fn activate(env: Env, admin: Address) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        Self::log_admin_action(&env, &admin, symbol_short!("activate"), ());
+        storage::set_activated(&env);
+        Ok(())
+    }
+
+    /// Admin-only: withdraw LMNR from the contract to the primary admin.
+    /// Only the surplus above what stakers have already accrued can be
+    /// withdrawn; draining owed rewards requires the paused, timelocked
+    /// emergency path. Needs the admin quorum when one is set.
+    pub fn withdraw(
+        env: Env,
+        admin: Address,
+        amount: i128,
+    ) -> Result<(), ContractError> {
+        Self::require_co_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        if !Self::approve_sensitive(&env, &admin, symbol_short!("withdraw"), amount) {
+            return Ok(());
+        }
+        Self::log_admin_action(&env, &admin, symbol_short!("withdraw"), amount);
+
+        if amount <= 0 {
+            return Err(ContractError::InvalidAmount);
+        }
+
+        // Controller-scheduled rewards not yet emitted, carried idle
+        // rewards and earmarks beyond what their pools already owe stay
+        // reserved too
+        let liability = Self::update_all_pools(&env);
+        let totals = storage::get_emission_totals(&env);
+        let liability = liability + totals.scheduled + totals.carry + Self::unowed_earmarks(&env);
+
+        let lmnr_token = storage::get_lmnr_token(&env);
+        let token_client = token::Client::new(&env, &lmnr_token);
+
+        let contract_balance = token_client.balance(&env.current_contract_address());
+        if contract_balance - liability < amount {
+            return Err(ContractError::InsufficientRewardBalance);
+        }
+
+        let primary = storage::get_admin(&env);
+        token_client.transfer(&env.current_contract_address(), &primary, &amount);
+
+        let mut accounting = storage::get_accounting(&env);
+        accounting.total_withdrawn += amount;
+        storage::set_accounting(&env, &accounting);
+
+        Ok(())
+    }
+
+    /// Admin-only: halt staking and claiming. Unstaking stays open so users
+    /// can always exit.
+    pub fn pause(env: Env, admin: Address) -> Result<(), ContractError> {
+        Self::require_co_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        Self::log_admin_action(&env, &admin, symbol_short!("pause"), ());
+
+        storage::set_paused(&env, true);
+        events::paused(&env, true);
+        Ok(())
+    }
+
+    /// Admin-only: resume normal operation. Cancels any queued emergency
+    /// withdrawal or reward token migration, so neither can wait out its
+    /// timelock unpaused.
+    pub fn unpause(env: Env, admin: Address) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        Self::log_admin_action(&env, &admin, symbol_short!("unpause"), ());
+
+        storage::set_paused(&env, false);
+        storage::remove_pending_withdrawal(&env);
+        storage::remove_pending_token_migration(&env);
+        events::paused(&env, false);
+        Ok(())
+    }
+
+    /// Admin-only: halt staking and claiming in one pool, e.g. while its
+    /// snapshot is under review. Other pools and unstaking are unaffected.
+    pub fn pause_pool(env: Env, admin: Address, pool_index: u32) -> Result<(), ContractError> {
+        Self::set_pool_paused(&env, &admin, pool_index, true)
+    }
+
+    /// Admin-only: resume staking and claiming in a paused pool.
+    pub fn unpause_pool(env: Env, admin: Address, pool_index: u32) -> Result<(), ContractError> {
+        Self::set_pool_paused(&env, &admin, pool_index, false)
+    }
+
+    /// Admin-only: queue an emergency withdrawal of up to the full balance,
+    /// including rewards owed to stakers. Requires the contract to be paused
+    /// and becomes executable after `EMERGENCY_WITHDRAW_DELAY`. Needs the
+    /// admin quorum when one is set. Returns the unlock time, or 0 while
+    /// approvals are still collected.
+    pub fn queue_emergency_withdraw(
+        env: Env,
+        admin: Address,
+        amount: i128,
+    ) -> Result<u64, ContractError> {
+        Self::require_co_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        if !Self::approve_sensitive(&env, &admin, symbol_short!("emrg_q"), amount) {
+            return Ok(0);
+        }
+        Self::log_admin_action(&env, &admin, symbol_short!("emrg_q"), amount);
+
+        if !storage::is_paused(&env) {
+            return Err(ContractError::NotPaused);
+        }
+        if amount <= 0 {
+            return Err(ContractError::InvalidAmount);
+        }
+
+        let unlock_time = env.ledger().timestamp() + EMERGENCY_WITHDRAW_DELAY;
+        storage::set_pending_withdrawal(&env, &PendingWithdrawal { amount, unlock_time });
+        events::emergency_queued(&env, amount, unlock_time);
+
+        Ok(unlock_time)
+    }
+
+    /// Admin-only: pay a queued emergency withdrawal to the primary admin
+    /// once its timelock has elapsed. The contract must still be paused.
+    /// Needs the admin quorum when one is set. Returns the amount paid, or 0
+    /// while approvals are still collected.
+    pub fn execute_emergency_withdraw(env: Env, admin: Address) -> Result<i128, ContractError> {
+        Self::require_co_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        if !Self::approve_sensitive(&env, &admin, symbol_short!("emrg_exec"), ()) {
+            return Ok(0);
+        }
+        Self::log_admin_action(&env, &admin, symbol_short!("emrg_exec"), ());
+
+        if !storage::is_paused(&env) {
+            return Err(ContractError::NotPaused);
+        }
+        let pending = storage::get_pending_withdrawal(&env)
+            .ok_or(ContractError::NoPendingWithdrawal)?;
+        if env.ledger().timestamp() < pending.unlock_time {
+            return Err(ContractError::TimelockNotElapsed);
+        }
+
+        let lmnr_token = storage::get_lmnr_token(&env);
+        let token_client = token::Client::new(&env, &lmnr_token);
+        if token_client.balance(&env.current_contract_address()) < pending.amount {
+            return Err(ContractError::InsufficientRewardBalance);
+        }
+
+        let primary = storage::get_admin(&env);
+        token_client.transfer(&env.current_contract_address(), &primary, &pending.amount);
+        storage::remove_pending_withdrawal(&env);
+
+        let mut accounting = storage::get_accounting(&env);
+        accounting.total_withdrawn += pending.amount;
+        storage::set_accounting(&env, &accounting);
+
+        Ok(pending.amount)
+    }
+
+    /// Admin-only: when enabled, claims during a funding gap pay out the
+    /// contract's remaining balance and leave the shortfall pending instead
+    /// of failing with `InsufficientRewardBalance`.
+    pub fn set_partial_payouts(
+        env: Env,
+        admin: Address,
+        enabled: bool,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        Self::log_admin_action(&env, &admin, symbol_short!("partial"), enabled);
+        storage::set_partial_payouts(&env, enabled);
+        Ok(())
+    }
+
+    /// Admin-only: replace the storage TTL parameters, e.g. after network
+    /// changes to fees or maximum TTLs. Thresholds must not exceed their
+    /// extensions and no extension may exceed the network maximum.
+    pub fn set_ttl_config(
+        env: Env,
+        admin: Address,
+        config: TtlConfig,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+
+        let max_ttl = env.storage().max_ttl();
+        if config.instance_threshold > config.instance_extend
+            || config.persistent_threshold > config.persistent_extend
+            || config.persistent_threshold > config.staker_extend
+            || config.instance_extend > max_ttl
+            || config.persistent_extend > max_ttl
+            || config.staker_extend > max_ttl
+            || config.historical_root_ttl == 0
+            || config.historical_root_ttl > max_ttl
+        {
+            return Err(ContractError::InvalidConfig);
+        }
+
+        storage::set_ttl_config(&env, &config);
+        storage::extend_instance_ttl(&env);
+        Ok(())
+    }
+
+    /// Admin-only: LMNR paid to callers of `checkpoint`, `expire_staker` and
+    /// `sweep_expired` (0 disables each), at most `max_per_ledger` bounties
+    /// per ledger. Each bounty is capped at 10 LMNR, and one for a sweep at
+    /// the amount swept.
+    pub fn set_keeper_bounties(
+        env: Env,
+        admin: Address,
+        bounties: KeeperBounties,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+
+        for bounty in [bounties.checkpoint, bounties.expire, bounties.sweep] {
+            if !(0..=MAX_KEEPER_BOUNTY).contains(&bounty) {
+                return Err(ContractError::InvalidAmount);
+            }
+        }
+        storage::set_keeper_bounties(&env, &bounties);
+        Ok(())
+    }
+
+    /// Admin-only: LMNR paid to callers of `prune_staker` (0 disables).
+    pub fn set_prune_bounty(env: Env, admin: Address, bounty: i128) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+
+        if bounty < 0 {
+            return Err(ContractError::InvalidAmount);
+        }
+        storage::set_prune_bounty(&env, bounty);
+        Ok(())
+    }
+
+    /// Admin-only: let anyone sweep rewards a staker leaves unclaimed for
+    /// `seconds` after last staking or claiming (0 disables sweeping).
+    /// Stakers with no recorded activity count from when the deadline was
+    /// first set.
+    pub fn set_claim_deadline(env: Env, admin: Address, seconds: u64) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        Self::log_admin_action(&env, &admin, symbol_short!("deadline"), seconds);
+
+        if seconds == 0 {
+            storage::remove_claim_deadline(&env);
+            return Ok(());
+        }
+        let since = storage::get_claim_deadline(&env)
+            .map_or(env.ledger().timestamp(), |deadline| deadline.since);
+        storage::set_claim_deadline(&env, &ClaimDeadline { seconds, since });
+        Ok(())
+    }
+
+    /// Admin-only: block `user` from staking and from receiving claims.
+    /// With `freeze_claims` their rewards stay pending until unblocked;
+    /// otherwise any claim settled for them forfeits the rewards.
+    pub fn block_address(
+        env: Env,
+        admin: Address,
+        user: Address,
+        freeze_claims: bool,
+    ) -> Result<(), ContractError> {
+        Self::require_co_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        Self::log_admin_action(
+            &env,
+            &admin,
+            symbol_short!("block"),
+            (user.clone(), freeze_claims),
+        );
+        storage::set_blocked(&env, &user, freeze_claims);
+        events::address_blocked(&env, &user, freeze_claims);
+        Ok(())
+    }
+
+    /// Admin-only: lift a block. Frozen rewards become claimable again.
+    pub fn unblock_address(env: Env, admin: Address, user: Address) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        Self::log_admin_action(&env, &admin, symbol_short!("unblock"), user.clone());
+        storage::remove_blocked(&env, &user);
+        events::address_unblocked(&env, &user);
+        Ok(())
+    }
+
+    /// Admin-only: set or clear the compliance hook contract. When set, its
+    /// `check(user) -> bool` must return true for the staker (and for a
+    /// claim's destination) before any stake or claim goes through.
+    pub fn set_compliance_hook(
+        env: Env,
+        admin: Address,
+        hook: Option<Address>,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        Self::log_admin_action(&env, &admin, symbol_short!("compl_hk"), hook.clone());
+        storage::set_compliance_hook(&env, &hook);
+        Ok(())
+    }
+
+    /// Admin-only: set or clear the integrator hook notified with
+    /// `on_stake(user, pool_index, staked_amount)` whenever a position
+    /// changes and `on_claim(user, pool_index, amount)` after a payout.
+    /// Notification is best-effort.
+    pub fn set_stake_hook(
+        env: Env,
+        admin: Address,
+        hook: Option<Address>,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        Self::log_admin_action(&env, &admin, symbol_short!("stake_hk"), hook.clone());
+        storage::set_stake_hook(&env, &hook);
+        Ok(())
+    }
+
+    /// Admin-only: set or clear the emissions controller allowed to call
+    /// `notify_reward`.
+    pub fn set_emissions_controller(
+        env: Env,
+        admin: Address,
+        controller: Option<Address>,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        Self::log_admin_action(&env, &admin, symbol_short!("ctrl"), controller.clone());
+        storage::set_emissions_controller(&env, &controller);
+        Ok(())
+    }
+
+    /// Emissions controller only: transfer `amount` LMNR in and emit it to
+    /// the pool's stakers evenly over the next `duration` seconds, on top of
+    /// the global rate. Anything left of a running schedule is rolled into
+    /// the new one. For a hybrid deposit side the schedule lands on the
+    /// Merkle pool it shares. Partner-token pools use `fund_pool`.
+    pub fn notify_reward(
+        env: Env,
+        pool_index: u32,
+        amount: i128,
+        duration: u64,
+    ) -> Result<(), ContractError> {
+        let controller =
+            storage::get_emissions_controller(&env).ok_or(ContractError::Unauthorized)?;
+        controller.require_auth();
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        if amount <= 0 {
+            return Err(ContractError::InvalidAmount);
+        }
+        if duration == 0 {
+            return Err(ContractError::InvalidConfig);
+        }
+
+        // Book accrual under the old schedule before replacing it
+        let pool = Self::state_pool(&env, pool_index);
+        if !storage::pays_lmnr(&env, pool) {
+            return Err(ContractError::WrongPoolMode);
+        }
+        rewards::update_pool(&env, pool);
+
+        let token_client = token::Client::new(&env, &storage::get_lmnr_token(&env));
+        token_client.transfer(&controller, &env.current_contract_address(), &amount);
+        Self::extend_reward_schedule(&env, pool, amount, duration);
+
+        let mut accounting = storage::get_accounting(&env);
+        accounting.total_funded += amount;
+        storage::set_accounting(&env, &accounting);
+        Ok(())
+    }
+
+    /// Admin-only: emit the rewards a pool carried while nobody was staked
+    /// to its stakers evenly over the next `duration` seconds, rolled into
+    /// any running controller schedule. Returns the amount rolled over.
+    pub fn rollover_carry(
+        env: Env,
+        admin: Address,
+        pool_index: u32,
+        duration: u64,
+    ) -> Result<i128, ContractError> {
+        Self::require_admin(&env, &admin)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+        Self::log_admin_action(
+            &env,
+            &admin,
+            symbol_short!("rollover"),
+            (pool_index, duration),
+        );
+
+        if duration == 0 {
+            return Err(ContractError::InvalidConfig);
+        }
+
+        let pool = Self::state_pool(&env, pool_index);
+        rewards::update_pool(&env, pool);
+        let carry = storage::get_pool_carry(&env, pool);
+        if carry <= 0 {
+            return Err(ContractError::InvalidAmount);
+        }
+
+        storage::set_pool_carry(&env, pool, 0);
+        Self::extend_reward_schedule(&env, pool, carry, duration);
+        Ok(carry)
+    }
+
+    /// Admin-only: stop reserving a pool's carried idle rewards, leaving
+    /// them in the balance `withdraw` can release. Returns the amount.
+    pub fn reclaim_carry(env: Env, admin: Address, pool_index: u32) -> Result<i128, ContractError> {
+        Self::require_admin(&env, &admin)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+        Self::log_admin_action(&env, &admin, symbol_short!("rcl_carry"), pool_index);
+
+        let pool = Self::state_pool(&env, pool_index);
+        rewards::update_pool(&env, pool);
+        let carry = storage::get_pool_carry(&env, pool);
+        storage::set_pool_carry(&env, pool, 0);
+
+        events::carry_reclaimed(&env, pool, carry);
+        Ok(carry)
+    }
+
+    /// Admin-only: set or clear the rate oracle. While set, every root
+    /// rotation retargets the pool's oracle rate (earned on top of the global
+    /// rate) to pay `target_apr_bps` on its staked LP value, clamped to
+    /// `[min_rate, max_rate]`. Clearing it zeroes each pool's oracle rate at
+    /// that pool's next rotation.
+    pub fn set_rate_oracle(
+        env: Env,
+        admin: Address,
+        oracle: Option<RateOracle>,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        Self::log_admin_action(&env, &admin, symbol_short!("rate_orcl"), oracle.clone());
+
+        if let Some(config) = &oracle {
+            if config.min_rate < 0 || config.min_rate > config.max_rate {
+                return Err(ContractError::InvalidConfig);
+            }
+        }
+        storage::set_rate_oracle(&env, &oracle);
+        Ok(())
+    }
+
+    /// Admin-only: return tokens sent to the contract by mistake. Tokens the
+    /// contract holds on behalf of stakers (the LMNR reward token and LP
+    /// escrowed in deposit-mode pools) can never be moved through this path.
+    /// Needs the admin quorum when one is set.
+    pub fn rescue_tokens(
+        env: Env,
+        admin: Address,
+        token: Address,
+        to: Address,
+        amount: i128,
+    ) -> Result<(), ContractError> {
+        Self::require_co_admin(&env, &admin)?;
+        storage::extend_instance_ttl(&env);
+        let args = (token.clone(), to.clone(), amount);
+        if !Self::approve_sensitive(&env, &admin, symbol_short!("rescue"), args) {
+            return Ok(());
+        }
+        Self::log_admin_action(
+            &env,
+            &admin,
+            symbol_short!("rescue"),
+            (token.clone(), to.clone(), amount),
+        );
+
+        if amount <= 0 {
+            return Err(ContractError::InvalidAmount);
+        }
+        if Self::is_protected_token(&env, &token) {
+            return Err(ContractError::CannotRescueRewardToken);
+        }
+
+        // Partner reward tokens can only be rescued above what their pools owe
+        let token_client = token::Client::new(&env, &token);
+        let reserve = Self::partner_token_reserve(&env, &token);
+        if reserve > 0 && token_client.balance(&env.current_contract_address()) - reserve < amount {
+            return Err(ContractError::InsufficientRewardBalance);
+        }
+
+        token_client.transfer(&env.current_contract_address(), &to, &amount);
+        events::tokens_rescued(&env, &token, &to, amount);
+
+        Ok(())
+    }
+
+    /// Transfer LMNR into the contract for reward distribution.
+    pub fn fund(env: Env, funder: Address, amount: i128) -> Result<(), ContractError> {
+        if amount <= 0 {
+            return Err(ContractError::InvalidAmount);
+        }
+        funder.require_auth();
+
+        let lmnr_token = storage::get_lmnr_token(&env);
+        let token_client = token::Client::new(&env, &lmnr_token);
+        token_client.transfer(&funder, &env.current_contract_address(), &amount);
+        storage::extend_instance_ttl(&env);
+
+        let mut accounting = storage::get_accounting(&env);
+        accounting.total_funded += amount;
+        storage::set_accounting(&env, &accounting);
+
+        Ok(())
+    }
+
+    /// Transfer `amount` of a partner pool's reward token in and emit it to
+    /// the pool's stakers evenly over the next `duration` seconds, rolled
+    /// into any running schedule.
+    ///
+    /// For an LMNR pool `duration` must be 0: the LMNR is earmarked for the
+    /// pool's emissions at the global rate, and `withdraw` leaves it in
+    /// place until the pool's claims have drawn it down.
+    pub fn fund_pool(
+        env: Env,
+        funder: Address,
+        pool_index: u32,
+        amount: i128,
+        duration: u64,
+    ) -> Result<(), ContractError> {
+        funder.require_auth();
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        if amount <= 0 {
+            return Err(ContractError::InvalidAmount);
+        }
+        let Some(reward_token) = storage::get_pool_config(&env, pool_index).reward_token else {
+            return Self::earmark_funds(&env, &funder, pool_index, amount, duration);
+        };
+        if duration == 0 {
+            return Err(ContractError::InvalidConfig);
+        }
+
+        rewards::update_pool(&env, pool_index);
+        token::Client::new(&env, &reward_token).transfer(
+            &funder,
+            &env.current_contract_address(),
+            &amount,
+        );
+        Self::extend_reward_schedule(&env, pool_index, amount, duration);
+        Ok(())
+    }
+
+    /// Transfer `amount` of `token` in as fee revenue for a pool's stakers.
+    /// It is shared out by stake weight at the pool's next root rotation
+    /// and paid with each staker's next claim (or `claim_revenue`). The
+    /// deposit side of a hybrid pool shares its Merkle pool's pots. At most
+    /// `MAX_REVENUE_TOKENS` tokens per pool.
+    pub fn deposit_revenue(
+        env: Env,
+        depositor: Address,
+        token: Address,
+        pool_index: u32,
+        amount: i128,
+    ) -> Result<(), ContractError> {
+        depositor.require_auth();
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        if amount <= 0 {
+            return Err(ContractError::InvalidAmount);
+        }
+        let state_pool = Self::state_pool(&env, pool_index);
+        Self::require_merkle_pool(&env, state_pool)?;
+        Self::require_unaccounted_token(&env, &token)?;
+
+        let mut pots = storage::get_revenue_pots(&env, state_pool);
+        let mut pot = pots.get(token.clone()).unwrap_or_default();
+        if !pots.contains_key(token.clone()) && pots.len() >= MAX_REVENUE_TOKENS {
+            return Err(ContractError::InvalidConfig);
+        }
+        token::Client::new(&env, &token).transfer(
+            &depositor,
+            &env.current_contract_address(),
+            &amount,
+        );
+        pot.pending += amount;
+        pots.set(token.clone(), pot);
+        storage::set_revenue_pots(&env, state_pool, &pots);
+
+        events::revenue_deposited(&env, &token, state_pool, amount);
+        Ok(())
+    }
+
+    /// Open a sponsor campaign paying `amount` of `token` to a pool's
+    /// stakers by weight, released evenly from `start` to `end`. Anyone can
+    /// sponsor; stakers collect with `claim` or `claim_campaigns`. The
+    /// sponsor can take back whatever is still unpaid with
+    /// `reclaim_campaign` once the claim window after `end` has passed.
+    /// Returns the campaign id, numbered per pool.
+    pub fn create_campaign(
+        env: Env,
+        sponsor: Address,
+        pool_index: u32,
+        token: Address,
+        amount: i128,
+        start: u64,
+        end: u64,
+    ) -> Result<u32, ContractError> {
+        sponsor.require_auth();
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        if amount <= 0 {
+            return Err(ContractError::InvalidAmount);
+        }
+        if start < env.ledger().timestamp() || end <= start {
+            return Err(ContractError::InvalidConfig);
+        }
+        let state_pool = Self::state_pool(&env, pool_index);
+        if storage::get_pool_config(&env, state_pool).removed {
+            return Err(ContractError::PoolNotFound);
+        }
+        Self::require_unaccounted_token(&env, &token)?;
+        let mut active = storage::get_active_campaigns(&env, state_pool);
+        if active.len() >= campaigns::MAX_CAMPAIGNS {
+            return Err(ContractError::InvalidConfig);
+        }
+
+        token::Client::new(&env, &token).transfer(
+            &sponsor,
+            &env.current_contract_address(),
+            &amount,
+        );
+        let campaign = Campaign {
+            sponsor,
+            token,
+            amount,
+            start,
+            end,
+            acc_per_share: 0,
+            last_update: start,
+            unallocated: 0,
+            paid: 0,
+        };
+        let campaign_id = storage::next_campaign_id(&env, state_pool);
+        storage::set_campaign(&env, state_pool, campaign_id, &campaign);
+        active.push_back(campaign_id);
+        storage::set_active_campaigns(&env, state_pool, &active);
+
+        events::campaign_created(&env, state_pool, campaign_id, &campaign);
+        Ok(campaign_id)
+    }
+
+    /// Sponsor-only: close a campaign once `campaigns::CLAIM_WINDOW` has
+    /// passed since its end and take back everything it has not paid out,
+    /// including shares stakers left unclaimed. Returns the amount returned.
+    pub fn reclaim_campaign(
+        env: Env,
+        sponsor: Address,
+        pool_index: u32,
+        campaign_id: u32,
+    ) -> Result<i128, ContractError> {
+        sponsor.require_auth();
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        let state_pool = Self::state_pool(&env, pool_index);
+        let campaign = storage::get_campaign(&env, state_pool, campaign_id)
+            .ok_or(ContractError::CampaignNotFound)?;
+        if campaign.sponsor != sponsor {
+            return Err(ContractError::Unauthorized);
+        }
+        if env.ledger().timestamp() < campaign.end + campaigns::CLAIM_WINDOW {
+            return Err(ContractError::TimelockNotElapsed);
+        }
+
+        let unpaid = campaign.amount - campaign.paid;
+        storage::remove_campaign(&env, state_pool, campaign_id);
+        if unpaid > 0 {
+            token::Client::new(&env, &campaign.token).transfer(
+                &env.current_contract_address(),
+                &sponsor,
+                &unpaid,
+            );
+        }
+        events::campaign_reclaimed(&env, state_pool, campaign_id, unpaid);
+        Ok(unpaid)
+    }
+
+    // ========== User Functions ==========
+
+    /// Prove LP position via Merkle proof and start earning rewards.
+    pub fn stake(
+        env: Env,
+        user: Address,
+        pool_index: u32,
+        lp_balance: i128,
+        proof: Vec<BytesN<32>>,
+    ) -> Result<(), ContractError> {
+        user.require_auth();
+        Self::require_not_paused(&env)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        Self::verify_and_stake(&env, &user, pool_index, lp_balance, &proof, None)
+    }
+
+    /// `stake` with the proof's sibling hashes packed back to back into one
+    /// `Bytes` (32 bytes per level, leaf level first), which keeps deep
+    /// proofs smaller in the transaction. Sorted-pair pools only.
+    pub fn stake_packed(
+        env: Env,
+        user: Address,
+        pool_index: u32,
+        lp_balance: i128,
+        proof: Bytes,
+    ) -> Result<(), ContractError> {
+        user.require_auth();
+        Self::require_not_paused(&env)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        if !merkle::is_packed_proof(&proof) {
+            return Err(ContractError::InvalidProof);
+        }
+        Self::verify_and_stake(&env, &user, pool_index, lp_balance, &proof, None)
+    }
+
+    /// `stake` for pools with positional proofs enabled. Bit `i` of `path`
+    /// is 1 when `proof[i]` is the left sibling (see
+    /// `merkle::verify_positional_proof`).
+    pub fn stake_positional(
+        env: Env,
+        user: Address,
+        pool_index: u32,
+        lp_balance: i128,
+        proof: Vec<BytesN<32>>,
+        path: u32,
+    ) -> Result<(), ContractError> {
+        user.require_auth();
+        Self::require_not_paused(&env)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        Self::verify_and_stake(&env, &user, pool_index, lp_balance, &proof, Some(path))
+    }
+
+    /// Prove one of several positions the snapshot holds for `user` in this
+    /// pool (see `merkle::compute_indexed_leaf`). Each leaf index can be
+    /// proven once per epoch, and each adds `lp_balance` to the stake
+    /// already proven this epoch through other indexed leaves. Not for
+    /// positional pools.
+    pub fn stake_leaf(
+        env: Env,
+        user: Address,
+        pool_index: u32,
+        leaf_index: u32,
+        lp_balance: i128,
+        proof: Vec<BytesN<32>>,
+    ) -> Result<(), ContractError> {
+        user.require_auth();
+        Self::require_not_paused(&env)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        if lp_balance <= 0 {
+            return Err(ContractError::InvalidAmount);
+        }
+        if leaf_index >= merkle::MAX_LEAVES_PER_USER {
+            return Err(ContractError::InvalidProof);
+        }
+        Self::require_proof_depth(&proof)?;
+        if storage::get_pool_config(&env, pool_index).positional_proofs {
+            return Err(ContractError::WrongPoolMode);
+        }
+        let merkle_data = Self::provable_root(&env, pool_index)?;
+        // Identity and indexed leaves have no TWAB layout
+        if merkle_data.leaf_version != merkle::LEAF_VERSION as u32 {
+            return Err(ContractError::WrongPoolMode);
+        }
+
+        let contract = env.current_contract_address();
+        let verify = |pool: u32, root: &MerkleRootData| {
+            let leaf = merkle::compute_indexed_leaf(
+                &env,
+                &contract,
+                pool,
+                &user,
+                leaf_index,
+                lp_balance,
+                root.epoch_id,
+            );
+            merkle::verify_proof(&env, &leaf, &proof, &root.root)
+        };
+        if !verify(pool_index, &merkle_data) {
+            return Err(Self::diagnose_proof(
+                &env,
+                pool_index,
+                merkle_data.epoch_id,
+                verify,
+            ));
+        }
+
+        let epoch_id = merkle_data.epoch_id;
+        let current = storage::has_staker(&env, &user, pool_index)
+            .then(|| storage::get_staker(&env, &user, pool_index))
+            .filter(|staker| staker.epoch_id == epoch_id && staker.staked_amount > 0);
+        let leaves = storage::get_claimed_leaves(&env, &user, pool_index);
+        // Leaves only add to a stake built from indexed leaves; a position
+        // proven with a plain leaf this epoch stays as proven
+        let claimed = match current {
+            None => 0,
+            Some(_) if leaves.epoch_id == epoch_id => leaves.bitmap,
+            Some(_) => return Err(ContractError::AlreadyStakedThisEpoch),
+        };
+        let bit = 1u64 << leaf_index;
+        if claimed & bit != 0 {
+            return Err(ContractError::AlreadyStakedThisEpoch);
+        }
+        let proven = Self::check_declared_total(&env, pool_index, &merkle_data, lp_balance)?;
+
+        match current {
+            Some(staker) => Self::add_to_stake(&env, &user, pool_index, staker, lp_balance)?,
+            None => Self::apply_stake(&env, &user, pool_index, lp_balance, epoch_id)?,
+        }
+        storage::set_claimed_leaves(
+            &env,
+            &user,
+            pool_index,
+            &ClaimedLeaves {
+                epoch_id,
+                bitmap: claimed | bit,
+            },
+        );
+        if let Some(proven) = proven {
+            storage::set_proven_total(&env, pool_index, &proven);
+        }
+        Ok(())
+    }
+
+    /// Stake proven LP positions in several pools at once. See `batch::run`
+    /// for processing order, result codes and `atomic`.
+    pub fn stake_batch(
+        env: Env,
+        user: Address,
+        requests: Vec<StakeRequest>,
+        atomic: bool,
+    ) -> Result<Vec<u32>, ContractError> {
+        user.require_auth();
+        Self::require_not_paused(&env)?;
+        storage::extend_instance_ttl(&env);
+
+        batch::run(&env, &requests, atomic, |request| {
+            Self::verify_and_stake(
+                &env,
+                &user,
+                request.pool_index,
+                request.lp_balance,
+                &request.proof,
+                None,
+            )
+        })
+    }
+
+    /// Link the caller's current address to a stable identity so identity
+    /// leaves (see `merkle::compute_identity_leaf`) can be proven from it.
+    /// The identity key signs `merkle::identity_binding_payload`; re-binding
+    /// to a new address after a wallet rotation bumps the nonce.
+    /// Returns the identity hash.
+    pub fn bind_identity(
+        env: Env,
+        user: Address,
+        identity_proof: IdentityProof,
+    ) -> Result<BytesN<32>, ContractError> {
+        user.require_auth();
+        storage::extend_instance_ttl(&env);
+
+        let identity = merkle::identity_hash(&env, &identity_proof.public_key);
+        let nonce = if storage::has_identity_binding(&env, &identity) {
+            storage::get_identity_binding(&env, &identity).nonce
+        } else {
+            0
+        };
+
+        // Traps if the signature does not verify
+        let payload = merkle::identity_binding_payload(
+            &env,
+            &env.current_contract_address(),
+            &user,
+            nonce,
+        );
+        env.crypto()
+            .ed25519_verify(&identity_proof.public_key, &payload, &identity_proof.signature);
+
+        storage::set_identity_binding(
+            &env,
+            &identity,
+            &IdentityBinding {
+                owner: user,
+                nonce: nonce + 1,
+            },
+        );
+
+        Ok(identity)
+    }
+
+    /// Prove an identity-committed LP position from the identity's currently
+    /// bound address. Each identity can stake once per pool per epoch,
+    /// regardless of how many addresses it has been bound to.
+    pub fn stake_with_identity(
+        env: Env,
+        user: Address,
+        pool_index: u32,
+        identity: BytesN<32>,
+        lp_balance: i128,
+        proof: Vec<BytesN<32>>,
+    ) -> Result<(), ContractError> {
+        user.require_auth();
+        Self::require_not_paused(&env)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        if lp_balance <= 0 {
+            return Err(ContractError::InvalidAmount);
+        }
+
+        if !storage::has_identity_binding(&env, &identity)
+            || storage::get_identity_binding(&env, &identity).owner != user
+        {
+            return Err(ContractError::IdentityNotBound);
+        }
+
+        Self::require_proof_depth(&proof)?;
+        if storage::get_pool_config(&env, pool_index).positional_proofs {
+            return Err(ContractError::WrongPoolMode);
+        }
+        let merkle_data = Self::provable_root(&env, pool_index)?;
+        // Identity and indexed leaves have no TWAB layout
+        if merkle_data.leaf_version != merkle::LEAF_VERSION as u32 {
+            return Err(ContractError::WrongPoolMode);
+        }
+
+        let contract = env.current_contract_address();
+        let verify = |pool: u32, root: &MerkleRootData| {
+            let leaf = merkle::compute_identity_leaf(
+                &env,
+                &contract,
+                pool,
+                &identity,
+                lp_balance,
+                root.epoch_id,
+            );
+            merkle::verify_proof(&env, &leaf, &proof, &root.root)
+        };
+        if !verify(pool_index, &merkle_data) {
+            return Err(Self::diagnose_proof(
+                &env,
+                pool_index,
+                merkle_data.epoch_id,
+                verify,
+            ));
+        }
+
+        // A rotated wallet must not prove the same identity leaf twice
+        if storage::get_identity_epoch(&env, &identity, pool_index) == merkle_data.epoch_id {
+            return Err(ContractError::AlreadyStakedThisEpoch);
+        }
+        let proven = Self::check_declared_total(&env, pool_index, &merkle_data, lp_balance)?;
+
+        Self::apply_stake(&env, &user, pool_index, lp_balance, merkle_data.epoch_id)?;
+        storage::set_identity_epoch(&env, &identity, pool_index, merkle_data.epoch_id);
+        if let Some(proven) = proven {
+            storage::set_proven_total(&env, pool_index, &proven);
+        }
+
+        Ok(())
+    }
+
+    /// Claim accumulated LMNR rewards. Returns amount claimed, which is less
+    /// than pending if partial payouts are enabled and the contract is short.
+    pub fn claim(env: Env, user: Address, pool_index: u32) -> Result<i128, ContractError> {
+        user.require_auth();
+        Self::require_not_paused(&env)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        Self::settle_claim(&env, &user, pool_index, &user)
+    }
+
+    /// Settle every epoch in `epoch_ids` with one transfer, for wallets that
+    /// track which snapshots a user re-proved in without claiming. Re-staking
+    /// already carries earlier epochs' rewards forward into `pending_rewards`,
+    /// so this checks the list and settles the position once. Epochs must be
+    /// increasing and no later than the user's latest proof. Returns amount
+    /// claimed.
+    pub fn claim_epochs(
+        env: Env,
+        user: Address,
+        pool_index: u32,
+        epoch_ids: Vec<u64>,
+    ) -> Result<i128, ContractError> {
+        user.require_auth();
+        Self::require_not_paused(&env)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        Self::require_merkle_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        if epoch_ids.is_empty() || epoch_ids.len() > batch::MAX_BATCH_SIZE {
+            return Err(ContractError::BatchTooLarge);
+        }
+        if !storage::has_staker(&env, &user, pool_index) {
+            return Err(ContractError::NoStakeFound);
+        }
+        let latest = storage::get_staker(&env, &user, pool_index).epoch_id;
+        let mut previous = 0;
+        for epoch_id in epoch_ids.iter() {
+            if epoch_id <= previous || epoch_id > latest {
+                return Err(ContractError::StaleEpoch);
+            }
+            previous = epoch_id;
+        }
+
+        Self::settle_claim(&env, &user, pool_index, &user)
+    }
+
+    /// Claim accumulated LMNR rewards to a different destination, e.g. a
+    /// one-time address generated by the wallet. Returns amount claimed.
+    pub fn claim_to(
+        env: Env,
+        user: Address,
+        pool_index: u32,
+        to: Address,
+    ) -> Result<i128, ContractError> {
+        user.require_auth();
+        Self::require_not_paused(&env)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        Self::settle_claim(&env, &user, pool_index, &to)
+    }
+
+    /// Claim every reward stream `user` has in a pool at once: revenue
+    /// pots, sponsor campaigns and the pool's base reward (LMNR or its
+    /// partner token), which comes first in the result. Returns one payout
+    /// per stream that paid anything. Base rewards below the minimum claim
+    /// stay pending.
+    pub fn claim_all(
+        env: Env,
+        user: Address,
+        pool_index: u32,
+    ) -> Result<Vec<Payout>, ContractError> {
+        user.require_auth();
+        Self::prepare_share_claim(&env, &user, pool_index)?;
+
+        let state_pool = Self::state_pool(&env, pool_index);
+        let mut payouts = Vec::new(&env);
+        for (token, amount) in Self::pay_revenue(&env, &user, pool_index, &user).iter() {
+            payouts.push_back(Payout { token, amount });
+        }
+        for (id, amount) in campaigns::pay(&env, &user, pool_index, &user).iter() {
+            let token = storage::get_campaign(&env, state_pool, id).unwrap().token;
+            payouts.push_back(Payout { token, amount });
+        }
+
+        if storage::has_staker(&env, &user, pool_index) {
+            match Self::settle_claim(&env, &user, pool_index, &user) {
+                Ok(amount) if amount > 0 => {
+                    let token = Self::pool_reward_token(&env, state_pool);
+                    payouts.push_front(Payout { token, amount });
+                }
+                Ok(_)
+                | Err(ContractError::NoRewardsToClaim)
+                | Err(ContractError::BelowMinimumClaim) => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        if payouts.is_empty() {
+            return Err(ContractError::NoRewardsToClaim);
+        }
+        Ok(payouts)
+    }
+
+    /// Claim only the fee revenue owed to `user` in a pool, also after the
+    /// stake is gone. `claim` pays it alongside LMNR. Returns the amount
+    /// paid per token.
+    pub fn claim_revenue(
+        env: Env,
+        user: Address,
+        pool_index: u32,
+    ) -> Result<Map<Address, i128>, ContractError> {
+        user.require_auth();
+        Self::prepare_share_claim(&env, &user, pool_index)?;
+
+        let paid = Self::pay_revenue(&env, &user, pool_index, &user);
+        if paid.is_empty() {
+            return Err(ContractError::NoRewardsToClaim);
+        }
+        Ok(paid)
+    }
+
+    /// Claim only what sponsor campaigns owe `user` in a pool, also after
+    /// the stake is gone. `claim` pays it alongside LMNR. Returns the
+    /// amount paid per campaign id.
+    pub fn claim_campaigns(
+        env: Env,
+        user: Address,
+        pool_index: u32,
+    ) -> Result<Map<u32, i128>, ContractError> {
+        user.require_auth();
+        Self::prepare_share_claim(&env, &user, pool_index)?;
+
+        let paid = campaigns::pay(&env, &user, pool_index, &user);
+        if paid.is_empty() {
+            return Err(ContractError::NoRewardsToClaim);
+        }
+        Ok(paid)
+    }
+
+    /// Deposit-mode pools: escrow `amount` LP tokens from `user`, adding to
+    /// any existing deposit. Earns from the next second with no epoch
+    /// re-proving; `unstake` returns the full deposit.
+    pub fn stake_deposit(
+        env: Env,
+        user: Address,
+        pool_index: u32,
+        amount: i128,
+    ) -> Result<(), ContractError> {
+        user.require_auth();
+        Self::require_not_paused(&env)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        Self::require_pool_not_paused(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        let lp_token = match storage::get_pool_config(&env, pool_index).lp_token {
+            Some(lp_token) => lp_token,
+            None => return Err(ContractError::WrongPoolMode),
+        };
+        if amount <= 0 {
+            return Err(ContractError::InvalidAmount);
+        }
+
+        let state_pool = Self::state_pool(&env, pool_index);
+        let state = rewards::update_pool(&env, state_pool);
+        let precision = rewards::pool_precision(&env, state_pool);
+        let (old_amount, old_weight, pending) = if storage::has_staker(&env, &user, pool_index) {
+            let staker = storage::get_staker(&env, &user, pool_index);
+            Self::require_cooldown_over(&env, &staker)?;
+            (
+                staker.staked_amount,
+                staker.weight,
+                rewards::calculate_pending(&state, &staker, precision),
+            )
+        } else {
+            (0, 0, 0)
+        };
+        let new_amount = old_amount + amount;
+        let new_weight = rewards::stake_weight(&env, pool_index, new_amount, 0);
+        Self::require_can_stake(&env, &user, pool_index, new_amount)?;
+        Self::check_pool_cap(&env, state_pool, &state, old_weight, new_weight)?;
+
+        token::Client::new(&env, &lp_token).transfer(
+            &user,
+            &env.current_contract_address(),
+            &amount,
+        );
+
+        storage::set_staker(
+            &env,
+            &user,
+            pool_index,
+            &StakerInfo {
+                staked_amount: new_amount,
+                reward_debt: rewards::compute_reward_debt(
+                    new_weight,
+                    state.acc_reward_per_share,
+                    precision,
+                ),
+                pending_rewards: pending,
+                epoch_id: 0,
+                cooldown_until: 0,
+                weight: new_weight,
+                streak: 0,
+            },
+        );
+
+        let mut updated_state = storage::get_pool_state(&env, state_pool);
+        updated_state.total_staked += new_weight - old_weight;
+        storage::set_pool_state(&env, state_pool, &updated_state);
+        storage::register_staker(&env, &user, pool_index);
+        storage::set_last_active(&env, &user, pool_index);
+
+        hooks::notify_stake_changed(&env, &user, pool_index, new_amount);
+        Ok(())
+    }
+
+    /// Stop earning rewards. Pending rewards are preserved for later claiming.
+    /// In deposit-mode pools the escrowed LP tokens are returned.
+    pub fn unstake(env: Env, user: Address, pool_index: u32) -> Result<(), ContractError> {
+        user.require_auth();
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        if !storage::has_staker(&env, &user, pool_index) {
+            return Err(ContractError::NoStakeFound);
+        }
+        Self::exit_stake(&env, &user, pool_index);
+        Ok(())
+    }
+
+    /// Reduce a stake by `amount`, e.g. after part of the LP position was
+    /// withdrawn mid-epoch. Rewards are settled at the current accumulator
+    /// and the position keeps its epoch. In deposit-mode pools `amount` of
+    /// the escrowed LP is returned. Removing the whole stake is `unstake`.
+    pub fn unstake_partial(
+        env: Env,
+        user: Address,
+        pool_index: u32,
+        amount: i128,
+    ) -> Result<(), ContractError> {
+        user.require_auth();
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        if !storage::has_staker(&env, &user, pool_index) {
+            return Err(ContractError::NoStakeFound);
+        }
+        let staker = storage::get_staker(&env, &user, pool_index);
+        Self::reduce_stake_by(&env, &user, pool_index, staker, amount)
+    }
+
+    /// Self-report a lower LP balance: `unstake_partial` down to
+    /// `new_amount`, so a position shrunk mid-epoch stops earning on the
+    /// snapshot amount without waiting for `update_stake`. Emits a
+    /// `reduced` event either way for keepers to cross-check.
+    pub fn reduce_stake(
+        env: Env,
+        user: Address,
+        pool_index: u32,
+        new_amount: i128,
+    ) -> Result<(), ContractError> {
+        user.require_auth();
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        if !storage::has_staker(&env, &user, pool_index) {
+            return Err(ContractError::NoStakeFound);
+        }
+        let staker = storage::get_staker(&env, &user, pool_index);
+        if new_amount < 0 {
+            return Err(ContractError::InvalidAmount);
+        }
+        let amount = staker.staked_amount - new_amount;
+        Self::reduce_stake_by(&env, &user, pool_index, staker, amount)
+    }
+
+    /// Keep `user`'s position in a pool from expiring by topping up the TTL
+    /// of their staker entries. Anyone may pay for this; it changes no state
+    /// beyond entry lifetimes.
+    pub fn extend_my_storage(
+        env: Env,
+        user: Address,
+        pool_index: u32,
+    ) -> Result<(), ContractError> {
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        if !storage::extend_staker_entries(&env, &user, pool_index) {
+            return Err(ContractError::NoStakeFound);
+        }
+        Ok(())
+    }
+
+    /// Remove `user`'s staker entry in a pool once it holds no stake, no
+    /// pending rewards and no running unstake cooldown. Anyone may call; the
+    /// caller receives the prune bounty if one is set and the balance above
+    /// owed rewards covers it. Returns the bounty paid.
+    pub fn prune_staker(
+        env: Env,
+        caller: Address,
+        user: Address,
+        pool_index: u32,
+    ) -> Result<i128, ContractError> {
+        caller.require_auth();
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        if !storage::has_staker(&env, &user, pool_index) {
+            return Err(ContractError::NoStakeFound);
+        }
+        let staker = storage::get_staker(&env, &user, pool_index);
+        if staker.staked_amount != 0
+            || staker.pending_rewards != 0
+            || staker.cooldown_until > env.ledger().timestamp()
+        {
+            return Err(ContractError::StakerNotEmpty);
+        }
+
+        storage::remove_staker(&env, &user, pool_index);
+        storage::unregister_staker(&env, &user, pool_index);
+        storage::remove_last_claim_epoch(&env, &user, pool_index);
+        storage::remove_last_active(&env, &user, pool_index);
+
+        let mut bounty = storage::get_prune_bounty(&env);
+        if bounty > 0 && !Self::pay_bounty(&env, &caller, bounty) {
+            bounty = 0;
+        }
+
+        events::staker_pruned(&env, &user, pool_index, &caller, bounty);
+        Ok(bounty)
+    }
+
+    /// Drop the stake of a user who has not re-proven their position for
+    /// the pool's `expire_after_epochs`, so it stops diluting current
+    /// stakers. Rewards earned so far stay claimable. Anyone may call; the
+    /// caller earns the expiry bounty (see `set_keeper_bounties`). Returns
+    /// the bounty paid.
+    pub fn expire_staker(
+        env: Env,
+        caller: Address,
+        user: Address,
+        pool_index: u32,
+    ) -> Result<i128, ContractError> {
+        caller.require_auth();
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        if !storage::has_staker(&env, &user, pool_index) {
+            return Err(ContractError::NoStakeFound);
+        }
+        let staker = storage::get_staker(&env, &user, pool_index);
+        if staker.staked_amount == 0 {
+            return Err(ContractError::NoStakeFound);
+        }
+
+        // Deposit-mode pools and pools without a root have no epochs to miss
+        let limit = storage::get_pool_config(&env, pool_index).expire_after_epochs;
+        if limit == 0 || !storage::has_merkle_root(&env, pool_index) {
+            return Err(ContractError::StakerNotExpired);
+        }
+        let current_epoch = storage::get_merkle_root(&env, pool_index).epoch_id;
+        if current_epoch.saturating_sub(staker.epoch_id) < limit as u64 {
+            return Err(ContractError::StakerNotExpired);
+        }
+
+        let (_, pending) = Self::retire_stake(&env, &user, pool_index, 0);
+
+        events::staker_expired(&env, &user, pool_index, staker.staked_amount, pending);
+        hooks::notify_stake_changed(&env, &user, pool_index, 0);
+
+        let bounty = storage::get_keeper_bounties(&env).expire;
+        Ok(Self::pay_keeper_bounty(
+            &env,
+            &caller,
+            symbol_short!("expire"),
+            bounty,
+        ))
+    }
+
+    /// Release the rewards `user` has left unclaimed past the claim deadline
+    /// (see `set_claim_deadline`) back to the fund, so they stop counting as
+    /// owed. The stake itself keeps earning. Anyone may call; the caller
+    /// earns the sweep bounty (see `set_keeper_bounties`), paid out of the
+    /// swept rewards. Returns the amount swept.
+    pub fn sweep_expired(
+        env: Env,
+        caller: Address,
+        user: Address,
+        pool_index: u32,
+    ) -> Result<i128, ContractError> {
+        caller.require_auth();
+        Self::require_not_paused(&env)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+
+        if !storage::has_staker(&env, &user, pool_index) {
+            return Err(ContractError::NoStakeFound);
+        }
+        match Self::claim_deadline(&env, &user, pool_index) {
+            Some(deadline) if env.ledger().timestamp() >= deadline => {}
+            _ => return Err(ContractError::StakerNotExpired),
+        }
+
+        let state_pool = Self::state_pool(&env, pool_index);
+        let state = rewards::update_pool(&env, state_pool);
+        let precision = rewards::pool_precision(&env, state_pool);
+        let staker = storage::get_staker(&env, &user, pool_index);
+        let is_current_epoch = Self::is_current_epoch(&env, pool_index, &staker);
+        let pending = if is_current_epoch {
+            Self::settle_pending(&env, &user, pool_index, &state, &staker, precision)
+        } else {
+            rewards::calculate_pending_stale(&state, &staker, precision)
+        };
+        if pending <= 0 {
+            return Err(ContractError::NoRewardsToClaim);
+        }
+
+        Self::write_off_pending(
+            &env,
+            &user,
+            pool_index,
+            &state,
+            staker,
+            is_current_epoch,
+            pending,
+        );
+        events::rewards_swept(&env, &user, pool_index, pending);
+
+        let bounty = storage::get_keeper_bounties(&env).sweep.min(pending);
+        Self::pay_keeper_bounty(&env, &caller, symbol_short!("sweep"), bounty);
+        Ok(pending)
+    }
+
+    /// Admin-only: remove a fraudulent stake, e.g. one proven from a
+    /// flash-positioned account, and write off its pending rewards along
+    /// with its revenue and campaign shares. The action is logged with
+    /// `reason_code` (see `get_slash_log`). It does not stop the same proof
+    /// being staked again; block the address or post a corrected root as
+    /// well.
+    pub fn slash_staker(
+        env: Env,
+        admin: Address,
+        user: Address,
+        pool_index: u32,
+        reason_code: u32,
+    ) -> Result<(), ContractError> {
+        Self::require_admin(&env, &admin)?;
+        Self::require_valid_pool(&env, pool_index)?;
+        Self::require_merkle_pool(&env, pool_index)?;
+        storage::extend_instance_ttl(&env);
+        Self::log_admin_action(
+            &env,
+            &admin,
+            symbol_short!("slash"),
+            (user.clone(), pool_index, reason_code),
+        );
+
+        if !storage::has_staker(&env, &user, pool_index) {
+            return Err(ContractError::NoStakeFound);
+        }
+
+        let (staker, pending) = Self::retire_stake(&env, &user, pool_index, 0);
+        if pending > 0 {
+            // The retired entry has no weight left, so either accumulator
+            // gives it no reward debt
+            let state = storage::get_pool_state(&env, Self::state_pool(&env, pool_index));
+            let retired = storage::get_staker(&env, &user, pool_index);
+            Self::write_off_pending(&env, &user, pool_index, &state, retired, true, pending);
+        }
+        Self::forfeit_shares(&env, &user, pool_index);
+
+        let mut log = storage::get_slash_log(&env);
+        if log.len() >= MAX_SLASH_LOG {
+            log.pop_front();
+        }
+        log.push_back(SlashRecord {
+            user: user.clone(),
+            pool_index,
+            reason_code,
+            stake: staker.staked_amount,
+            forfeited: pending,
+            timestamp: env.ledger().timestamp(),
+        });
+        storage::set_slash_log(&env, &log);
+
+        events::staker_slashed(
+            &env,
+            &user,
+            pool_index,
+            reason_code,
+            staker.staked_amount,
+            pending,
+        );
+        hooks::notify_stake_changed(&env, &user, pool_index, 0);
+        Ok(())
+    }
+
+    // ========== View Functions ==========
+
+    /// Query unclaimed rewards for a user in a pool.
+    pub fn pending_reward(env: Env, user: Address, pool_index: u32) -> i128 {
+        if !storage::has_staker(&env, &user, pool_index) {
+            return 0;
+        }
+
+        let staker = storage::get_staker(&env, &user, pool_index);
+
+        let is_current_epoch = Self::is_current_epoch(&env, pool_index, &staker);
+
+        let state_pool = Self::state_pool(&env, pool_index);
+        let precision = rewards::pool_precision(&env, state_pool);
+        if !is_current_epoch {
+            let state = storage::get_pool_state(&env, state_pool);
+            return rewards::calculate_pending_stale(&state, &staker, precision);
+        }
+
+        let simulated_acc = rewards::simulate_acc_reward(&env, state_pool);
+        let now = env.ledger().timestamp();
+        rewards::calculate_pending_warm(
+            &env,
+            &user,
+            pool_index,
+            simulated_acc,
+            &staker,
+            precision,
+            now,
+        )
+    }
+
+    /// When `user`'s unclaimed rewards in a pool can be swept with
+    /// `sweep_expired`. `None` without a claim deadline or a position.
+    pub fn get_claim_deadline(env: Env, user: Address, pool_index: u32) -> Option<u64> {
+        if !storage::has_staker(&env, &user, pool_index) {
+            return None;
+        }
+        Self::claim_deadline(&env, &user, pool_index)
+    }
+
+    /// Projected `pending_reward` at `timestamp`, assuming the pool's current
+    /// rate and total stake hold until then. A stale staker's projection is
+    /// what they hold now; they earn nothing more until they re-prove.
+    pub fn pending_reward_at(env: Env, user: Address, pool_index: u32, timestamp: u64) -> i128 {
+        if !storage::has_staker(&env, &user, pool_index) {
+            return 0;
+        }
+
+        let staker = storage::get_staker(&env, &user, pool_index);
+        let state_pool = Self::state_pool(&env, pool_index);
+        let precision = rewards::pool_precision(&env, state_pool);
+        if !Self::is_current_epoch(&env, pool_index, &staker) {
+            let state = storage::get_pool_state(&env, state_pool);
+            return rewards::calculate_pending_stale(&state, &staker, precision);
+        }
+
+        let at = timestamp.max(env.ledger().timestamp());
+        let projected_acc = rewards::simulate_acc_reward_at(&env, state_pool, at);
+        rewards::calculate_pending_warm(
+            &env,
+            &user,
+            pool_index,
+            projected_acc,
+            &staker,
+            precision,
+            at,
+        )
+    }
+
+    /// `pending_reward` for up to `MAX_BATCH_SIZE` users in one call: the
+    /// pool accumulator is simulated once and every user is evaluated
+    /// against it. Amounts follow input order, 0 for users without a position.
+    pub fn pending_reward_batch(
+        env: Env,
+        pool_index: u32,
+        users: Vec<Address>,
+    ) -> Result<Vec<i128>, ContractError> {
+        if users.len() > batch::MAX_BATCH_SIZE {
+            return Err(ContractError::BatchTooLarge);
+        }
+
+        let state_pool = Self::state_pool(&env, pool_index);
+        let precision = rewards::pool_precision(&env, state_pool);
+        let state = storage::get_pool_state(&env, state_pool);
+        let simulated_acc = rewards::simulate_acc_reward(&env, state_pool);
+
+        let now = env.ledger().timestamp();
+        let mut amounts = Vec::new(&env);
+        for user in users.iter() {
+            let mut pending = 0;
+            if storage::has_staker(&env, &user, pool_index) {
+                let staker = storage::get_staker(&env, &user, pool_index);
+                pending = if Self::is_current_epoch(&env, pool_index, &staker) {
+                    rewards::calculate_pending_warm(
+                        &env,
+                        &user,
+                        pool_index,
+                        simulated_acc,
+                        &staker,
+                        precision,
+                        now,
+                    )
+                } else {
+                    rewards::calculate_pending_stale(&state, &staker, precision)
+                };
+            }
+            amounts.push_back(pending);
+        }
+        Ok(amounts)
+    }
+
+    /// Fee revenue `user` is owed in a pool, per token, including their
+    /// share of rotations since their stake was last written. Pots still
+    /// waiting for a rotation are not included.
+    pub fn pending_revenue(env: Env, user: Address, pool_index: u32) -> Map<Address, i128> {
+        let weight = if storage::has_staker(&env, &user, pool_index) {
+            storage::get_staker(&env, &user, pool_index).weight
+        } else {
+            0
+        };
+        let shares = rewards::revenue_shares(&env, &user, pool_index, weight, weight);
+        let mut owed = Map::new(&env);
+        for (token, share) in shares.iter() {
+            if share.owed > 0 {
+                owed.set(token, share.owed);
+            }
+        }
+        owed
+    }
+
+    /// What sponsor campaigns owe `user` in a pool, per campaign id, as of
+    /// now.
+    pub fn pending_campaign_rewards(env: Env, user: Address, pool_index: u32) -> Map<u32, i128> {
+        campaigns::pending(&env, &user, pool_index)
+    }
+
+    /// A sponsor campaign on a pool, if it has not been reclaimed.
+    pub fn get_campaign(env: Env, pool_index: u32, campaign_id: u32) -> Option<Campaign> {
+        storage::get_campaign(&env, Self::state_pool(&env, pool_index), campaign_id)
+    }
+
+    /// A pool's revenue pots, by token. The deposit side of a hybrid pool
+    /// shares its Merkle pool's.
+    pub fn get_revenue_pots(env: Env, pool_index: u32) -> Map<Address, RevenuePot> {
+        storage::get_revenue_pots(&env, Self::state_pool(&env, pool_index))
+    }
+
+    /// Whether `user`'s pending reward in a pool has reached the minimum
+    /// claim, see `set_min_claim`.
+    pub fn is_claimable(env: Env, user: Address, pool_index: u32) -> bool {
+        let pending = Self::pending_reward(env.clone(), user, pool_index);
+        pending > 0 && pending >= storage::get_min_claim(&env)
+    }
+
+    /// Query stake details for a user.
+    pub fn get_staker_info(env: Env, user: Address, pool_index: u32) -> StakerInfo {
+        storage::get_staker(&env, &user, pool_index)
+    }
+
+    /// Query pool accumulator state.
+    pub fn get_pool_state(env: Env, pool_index: u32) -> PoolState {
+        storage::get_pool_state(&env, pool_index)
+    }
+
+    /// `get_staker_info` that returns None instead of trapping when the user
+    /// has no position in the pool. (A `try_` name would clash with the
+    /// generated client's `try_get_staker_info`.)
+    pub fn find_staker_info(env: Env, user: Address, pool_index: u32) -> Option<StakerInfo> {
+        if !storage::has_staker(&env, &user, pool_index) {
+            return None;
+        }
+        Some(storage::get_staker(&env, &user, pool_index))
+    }
+
+    /// Indexed leaves `user` has proven in the pool's current epoch, bit `i`
+    /// set for leaf index `i`.
+    pub fn get_claimed_leaves(env: Env, user: Address, pool_index: u32) -> u64 {
+        if !storage::has_merkle_root(&env, pool_index) {
+            return 0;
+        }
+        let leaves = storage::get_claimed_leaves(&env, &user, pool_index);
+        if leaves.epoch_id != storage::get_merkle_root(&env, pool_index).epoch_id {
+            return 0;
+        }
+        leaves.bitmap
+    }
+
+    /// `get_pool_state` that returns None for an unregistered pool index
+    /// instead of an empty accumulator.
+    pub fn find_pool_state(env: Env, pool_index: u32) -> Option<PoolState> {
+        if pool_index >= storage::get_pool_count(&env) {
+            return None;
+        }
+        Some(storage::get_pool_state(&env, pool_index))
+    }
+
+    /// Contracts notified on every root post.
+    pub fn get_epoch_listeners(env: Env) -> Vec<Address> {
+        storage::get_epoch_listeners(&env)
+    }
+
+    /// Address an identity hash is currently bound to, if any.
+    pub fn get_identity_owner(env: Env, identity: BytesN<32>) -> Option<Address> {
+        if !storage::has_identity_binding(&env, &identity) {
+            return None;
+        }
+        Some(storage::get_identity_binding(&env, &identity).owner)
+    }
+
+    /// Query per-pool configuration.
+    pub fn get_pool_config(env: Env, pool_index: u32) -> PoolConfig {
+        storage::get_pool_config(&env, pool_index)
+    }
+
+    /// Decimals of the token a pool pays rewards in.
+    pub fn get_reward_decimals(env: Env, pool_index: u32) -> u32 {
+        storage::get_reward_decimals(&env, pool_index)
+    }
+
+    /// Query current epoch Merkle root for a pool.
+    pub fn get_merkle_root(env: Env, pool_index: u32) -> MerkleRootData {
+        storage::get_merkle_root(&env, pool_index)
+    }
+
+    /// Whether the pool's current root is older than its `max_root_age`,
+    /// i.e. `stake` will reject proofs until a new root is posted.
+    pub fn is_root_stale(env: Env, pool_index: u32) -> bool {
+        if !storage::has_merkle_root(&env, pool_index) {
+            return false;
+        }
+        let merkle_data = storage::get_merkle_root(&env, pool_index);
+        Self::root_expired(&env, pool_index, &merkle_data)
+    }
+
+    /// Number of registered pools.
+    pub fn get_pool_count(env: Env) -> u32 {
+        storage::get_pool_count(&env)
+    }
+
+    /// Pool hash at a given index.
+    pub fn get_pool_id(env: Env, pool_index: u32) -> BytesN<32> {
+        storage::get_pool_id(&env, pool_index)
+    }
+
+    /// Staking index registered for an SDEX pool hash, if any.
+    pub fn get_pool_index(env: Env, pool_id: BytesN<32>) -> Option<u32> {
+        storage::get_pool_id_index(&env, &pool_id)
+    }
+
+    /// Global reward rate in LMNR stroops per second.
+    pub fn get_reward_rate(env: Env) -> i128 {
+        storage::get_reward_rate(&env)
+    }
+
+    /// Contract's LMNR balance available for rewards.
+    pub fn reward_balance(env: Env) -> i128 {
+        let lmnr_token = storage::get_lmnr_token(&env);
+        let token_client = token::Client::new(&env, &lmnr_token);
+        token_client.balance(&env.current_contract_address())
+    }
+
+    /// Accrued-but-unclaimed rewards owed to a pool's stakers, as of now.
+    pub fn get_pool_liability(env: Env, pool_index: u32) -> i128 {
+        rewards::simulate_liability(&env, pool_index)
+    }
+
+    /// Rewards the pool accrued while nobody was staked, as of now. See
+    /// `rollover_carry` and `reclaim_carry`.
+    pub fn get_pool_carry(env: Env, pool_index: u32) -> i128 {
+        rewards::simulate_carry(&env, pool_index)
+    }
+
+    /// Seconds the pool had stakers but accrued nothing, by cause (see
+    /// `UnaccruedTime`). Booked as pools are updated.
+    pub fn get_unaccrued_time(env: Env, pool_index: u32) -> UnaccruedTime {
+        storage::get_unaccrued_time(&env, pool_index)
+    }
+
+    /// Earmarked LMNR not yet paid out of a pool (see `fund_pool`).
+    pub fn get_pool_earmark(env: Env, pool_index: u32) -> i128 {
+        storage::get_pool_earmark(&env, pool_index)
+    }
+
+    /// Map a `ContractError` code to a short stable symbol (e.g.
+    /// `PROOF_WRONG_EPOCH`) that wallets can show from a failed simulation.
+    /// Unknown codes return `UNKNOWN`.
+    pub fn explain(env: Env, error_code: u32) -> Symbol {
+        match ContractError::from_code(error_code) {
+            Some(err) => Symbol::new(&env, err.explain()),
+            None => Symbol::new(&env, "UNKNOWN"),
+        }
+    }
+
+    /// Whether `activate` has been called.
+    pub fn is_activated(env: Env) -> bool {
+        storage::is_activated(&env)
+    }
+
+    /// Whether staking and claiming are halted.
+    pub fn is_paused(env: Env) -> bool {
+        storage::is_paused(&env)
+    }
+
+    /// The queued emergency withdrawal, if any.
+    pub fn get_pending_withdrawal(env: Env) -> Option<PendingWithdrawal> {
+        storage::get_pending_withdrawal(&env)
+    }
+
+    /// Reward token switch waiting for `migrate_reward_token`, if any.
+    pub fn get_pending_token_migration(env: Env) -> Option<PendingTokenMigration> {
+        storage::get_pending_token_migration(&env)
+    }
+
+    /// Outstanding liability across all LMNR pools versus the contract's
+    /// LMNR balance: `(liability, balance, surplus)`. A negative surplus
+    /// means the contract cannot cover everything stakers have accrued.
+    /// Carried idle rewards count as liability until reclaimed. See
+    /// `get_token_solvency` for partner tokens.
+    pub fn get_solvency(env: Env) -> (i128, i128, i128) {
+        let pool_count = storage::get_pool_count(&env);
+        let mut liability: i128 = 0;
+        for i in 0..pool_count {
+            if storage::pays_lmnr(&env, i) {
+                liability +=
+                    rewards::simulate_liability(&env, i) + rewards::simulate_carry(&env, i);
+            }
+        }
+
+        let balance = Self::reward_balance(env);
+        (liability, balance, balance - liability)
+    }
+
+    /// Seconds of emissions the LMNR balance not already owed to stakers can
+    /// sustain at the global rate across staked pools earning it, plus
+    /// oracle rates. Controller schedules are already reserved in the
+    /// liability. Returns `u64::MAX` when nothing is emitting and 0 for a
+    /// rate too large to represent.
+    pub fn get_runway(env: Env) -> u64 {
+        let totals = storage::get_emission_totals(&env);
+        let rate = storage::get_reward_rate(&env)
+            .checked_mul(totals.emitting_pools as i128)
+            .and_then(|rate| rate.checked_add(totals.oracle_rate_sum));
+        // A rate too large to represent drains any balance at once
+        let Some(rate) = rate else {
+            return 0;
+        };
+        if rate <= 0 {
+            return u64::MAX;
+        }
+
+        let unowed = Self::reward_balance(env.clone()) - rewards::outstanding_liability(&env);
+        (unowed.max(0) / rate).min(u64::MAX as i128) as u64
+    }
+
+    /// `get_solvency` for a partner reward token: what its pools owe or have
+    /// scheduled versus the contract's balance of it,
+    /// `(liability, balance, surplus)`.
+    pub fn get_token_solvency(env: Env, token: Address) -> (i128, i128, i128) {
+        let liability = Self::partner_token_reserve(&env, &token);
+        let balance = token::Client::new(&env, &token).balance(&env.current_contract_address());
+        (liability, balance, balance - liability)
+    }
+
+    /// Rewards claimed and distinct claimants for a pool epoch, attributed
+    /// to the epoch each claimant's stake was proven in.
+    pub fn get_claim_stats(env: Env, pool_index: u32, epoch_id: u64) -> ClaimStats {
+        storage::get_claim_stats(&env, pool_index, epoch_id)
+    }
+
+    /// Storage schema version the contract's data is at. Lower than the
+    /// wasm's `migration::SCHEMA_VERSION` means `migrate` is still due.
+    pub fn get_schema_version(env: Env) -> u32 {
+        storage::get_schema_version(&env)
+    }
+
+    /// Crate version baked into this wasm, plus the git commit it was built
+    /// from when known (see build.rs). Also in the wasm's contract metadata.
+    pub fn get_version(env: Env) -> VersionInfo {
+        VersionInfo {
+            version: String::from_str(&env, VERSION),
+            git_hash: GIT_HASH.map(|hash| String::from_str(&env, hash)),
+        }
+    }
+
+    /// All global settings and roles in one call.
+    pub fn get_config(env: Env) -> ContractConfig {
+        let admin_set = storage::get_admin_set(&env);
+        ContractConfig {
+            admin: storage::get_admin(&env),
+            lmnr_token: storage::get_lmnr_token(&env),
+            reward_rate_per_sec: storage::get_reward_rate(&env),
+            paused: storage::is_paused(&env),
+            activated: storage::is_activated(&env),
+            keeper: storage::get_keeper(&env),
+            epoch_listeners: storage::get_epoch_listeners(&env),
+            min_runway_days: storage::get_min_runway_days(&env),
+            min_stake: storage::get_min_stake(&env),
+            min_claim: storage::get_min_claim(&env),
+            compliance_hook: storage::get_compliance_hook(&env),
+            stake_hook: storage::get_stake_hook(&env),
+            emissions_controller: storage::get_emissions_controller(&env),
+            partial_payouts: storage::get_partial_payouts(&env),
+            hide_claim_destination: storage::get_hide_claim_destination(&env),
+            ttl: storage::get_ttl_config(&env),
+            prune_bounty: storage::get_prune_bounty(&env),
+            rate_bounds: storage::get_rate_bounds(&env),
+            co_admins: admin_set
+                .as_ref()
+                .map_or(Vec::new(&env), |set| set.members.clone()),
+            admin_threshold: admin_set.map_or(1, |set| set.threshold),
+        }
+    }
+
+    /// A superseded root for `epoch_id`, while it is still retained in
+    /// temporary storage. The active root is only served by `get_merkle_root`.
+    pub fn get_historical_root(env: Env, pool_index: u32, epoch_id: u64) -> Option<MerkleRootData> {
+        storage::get_historical_root(&env, pool_index, epoch_id)
+    }
+
+    /// Storage TTL parameters currently in effect.
+    pub fn get_ttl_config(env: Env) -> TtlConfig {
+        storage::get_ttl_config(&env)
+    }
+
+    /// Pool count, staker positions, LP staked and LMNR distributed across
+    /// all pools, plus the current emission rate. An address staked in two
+    /// pools counts twice toward `total_stakers`.
+    pub fn get_global_stats(env: Env) -> GlobalStats {
+        let total_pools = storage::get_pool_count(&env);
+        let mut total_stakers: u32 = 0;
+        let mut total_staked: i128 = 0;
+        for i in 0..total_pools {
+            total_stakers += storage::get_staker_count(&env, i);
+            total_staked += storage::get_pool_state(&env, i).total_staked;
+        }
+
+        GlobalStats {
+            total_pools,
+            total_stakers,
+            total_staked,
+            total_distributed: storage::get_accounting(&env).total_claimed,
+            reward_rate_per_sec: storage::get_reward_rate(&env),
+        }
+    }
+
+    /// Lifetime funded / claimed / withdrawn LMNR totals.
+    pub fn get_accounting(env: Env) -> Accounting {
+        storage::get_accounting(&env)
+    }
+
+    /// Lifetime rewards `user` has claimed from a pool.
+    pub fn get_user_claimed(env: Env, user: Address, pool_index: u32) -> i128 {
+        storage::get_user_claimed(&env, &user, pool_index)
+    }
+
+    /// Lifetime LMNR `user` has claimed across all pools.
+    pub fn get_user_total_claimed(env: Env, user: Address) -> i128 {
+        storage::get_user_claimed_total(&env, &user)
+    }
+
+    /// Number of addresses with a non-zero stake in a pool.
+    pub fn get_staker_count(env: Env, pool_index: u32) -> u32 {
+        storage::get_staker_count(&env, pool_index)
+    }
+
+    /// Number of addresses with a non-zero stake proven in the pool's current
+    /// epoch; stakers who have not re-proven since the last root are not
+    /// counted. Counting starts from the first root posted after upgrading.
+    pub fn get_active_staker_count(env: Env, pool_index: u32) -> u32 {
+        storage::get_active_staker_count(&env, pool_index)
+    }
+
+    /// Page through a pool's stakers: up to `limit` addresses starting at
+    /// `start`. Unstaking moves the last entry into the vacated slot, so the
+    /// order is not stable across writes.
+    pub fn get_stakers(env: Env, pool_index: u32, start: u32, limit: u32) -> Vec<Address> {
+        let count = storage::get_staker_count(&env, pool_index);
+        let end = start.saturating_add(limit).min(count);
+        let mut stakers = Vec::new(&env);
+        for slot in start..end {
+            stakers.push_back(storage::get_staker_at(&env, pool_index, slot));
+        }
+        stakers
+    }
+
+    /// Page through the global rate's history, oldest first: up to `limit`
+    /// checkpoints from `start`. The first is the rate in force before the
+    /// first recorded change (timestamp 0 if that is the initial rate); each
+    /// later one is a `set_reward_rate`.
+    pub fn get_rate_history(env: Env, start: u32, limit: u32) -> Vec<RateCheckpoint> {
+        let mut history = Vec::new(&env);
+        let count = storage::get_rate_history_count(&env);
+        if count == 0 {
+            if start == 0 && limit > 0 {
+                history.push_back(storage::get_rate_checkpoint(&env));
+            }
+            return history;
+        }
+
+        let end = start.saturating_add(limit).min(count);
+        for index in start..end {
+            history.push_back(storage::get_rate_history(&env, index));
+        }
+        history
+    }
+
+    /// Blocklist status: `None` if not blocked, otherwise whether claims
+    /// are frozen (`true`) or forfeited (`false`).
+    pub fn get_blocked(env: Env, user: Address) -> Option<bool> {
+        storage::get_blocked(&env, &user)
+    }
+
+    /// Oracle keys and threshold `set_merkle_root_signed` checks, if set.
+    pub fn get_root_oracles(env: Env) -> Option<RootOracles> {
+        storage::get_root_oracles(&env)
+    }
+
+    /// Current rate oracle configuration, if any.
+    pub fn get_rate_oracle(env: Env) -> Option<RateOracle> {
+        storage::get_rate_oracle(&env)
+    }
+
+    /// Oracle-driven rate a pool earns on top of the global rate.
+    pub fn get_oracle_rate(env: Env, pool_index: u32) -> i128 {
+        storage::get_oracle_rate(&env, pool_index)
+    }
+
+    /// Controller reward schedule for a pool, if one was ever notified.
+    pub fn get_reward_schedule(env: Env, pool_index: u32) -> Option<RewardSchedule> {
+        storage::get_reward_schedule(&env, pool_index)
+    }
+
+    /// Current keeper, if one is set.
+    pub fn get_keeper(env: Env) -> Option<Address> {
+        storage::get_keeper(&env)
+    }
+
+    /// Bounties paid for maintenance calls; see `set_keeper_bounties`.
+    pub fn get_keeper_bounties(env: Env) -> KeeperBounties {
+        storage::get_keeper_bounties(&env)
+    }
+
+    /// Accumulator checkpoints for a pool, oldest first (at most
+    /// `rewards::MAX_CHECKPOINTS`).
+    pub fn get_checkpoints(env: Env, pool_index: u32) -> Vec<Checkpoint> {
+        storage::get_checkpoints(&env, pool_index)
+    }
+
+    /// Recent admin actions, oldest first (at most 50). Covers every admin
+    /// call that moves funds or changes who can stake or claim: root posts,
+    /// rate, budget and carry changes, stake edits, slashes and seeding,
+    /// pauses, blocks, hooks, pool removal and rebinding, keeper, controller,
+    /// payout mode and claim deadline changes, withdrawals (queued, executed
+    /// and surplus) and rescues, admin and token changes, and upgrades.
+    /// Parameter tweaks such as minimums, cooldowns and TTLs are not logged.
+    pub fn get_admin_log(env: Env) -> Vec<AdminAction> {
+        storage::get_admin_log(&env)
+    }
+
+    /// Recent `slash_staker` actions, oldest first (at most 50).
+    pub fn get_slash_log(env: Env) -> Vec<SlashRecord> {
+        storage::get_slash_log(&env)
+    }
+
+    // ========== Internal Helpers ==========
+
+    /// Recompute a pool's oracle rate from its freshly updated `state`. An
+    /// oracle that fails to answer leaves the rate unchanged so it can never
+    /// block root posting.
+    fn retarget_oracle_rate(env: &Env, pool_index: u32, state: &PoolState) {
+        // Oracle rates are paid in LMNR
+        let oracle = storage::get_rate_oracle(env).filter(|_| storage::pays_lmnr(env, pool_index));
+        let config = match oracle {
+            Some(config) => config,
+            None => {
+                if storage::get_oracle_rate(env, pool_index) != 0 {
+                    storage::set_oracle_rate(env, pool_index, 0);
+                }
+                return;
+            }
+        };
+
+        let pool_id = storage::get_pool_id(env, pool_index);
+        let price = match hooks::oracle_lp_price(env, &config.oracle, &pool_id) {
+            Some(price) if price >= 0 => price,
+            _ => return,
+        };
+
+        let lp_decimals = storage::get_pool_config(env, pool_index).lp_decimals;
+        let staked_value = state.total_staked * price / 10i128.pow(lp_decimals);
+        let rate =
+            (staked_value * config.target_apr_bps as i128 / BPS_DENOMINATOR / SECONDS_PER_YEAR)
+                .clamp(config.min_rate, config.max_rate);
+        storage::set_oracle_rate(env, pool_index, rate);
+    }
+
+    /// Append a pool with a fresh accumulator under `pool_id`.
+    fn register_pool(env: &Env, pool_id: &BytesN<32>) -> Result<u32, ContractError> {
+        if storage::has_pool_id_index(env, pool_id) {
+            return Err(ContractError::PoolAlreadyExists);
+        }
+
+        let index = storage::get_pool_count(env);
+        storage::set_pool_id(env, index, pool_id);
+        storage::set_pool_id_index(env, pool_id, index);
+        storage::set_pool_state(
+            env,
+            index,
+            &PoolState {
+                acc_reward_per_share: 0,
+                total_staked: 0,
+                last_reward_time: env.ledger().timestamp(),
+                prev_acc_reward_per_share: 0,
+            },
+        );
+        storage::set_pool_count(env, index + 1);
+
+        Ok(index)
+    }
+
+    /// Register a deposit-mode pool escrowing `lp_token`, taking the pool's
+    /// LP decimals from the token itself.
+    fn register_deposit_pool(
+        env: &Env,
+        lp_token: &Address,
+        amm_pool: Option<Address>,
+    ) -> Result<u32, ContractError> {
+        let lp_decimals = match token::Client::new(env, lp_token).try_decimals() {
+            Ok(Ok(decimals)) => decimals,
+            _ => return Err(ContractError::InvalidAmmPool),
+        };
+        if lp_decimals > MAX_LP_DECIMALS {
+            return Err(ContractError::InvalidConfig);
+        }
+
+        let index = Self::register_pool(env, &merkle::deposit_pool_id(env, lp_token))?;
+        let mut config = storage::get_pool_config(env, index);
+        config.lp_decimals = lp_decimals;
+        config.lp_token = Some(lp_token.clone());
+        config.amm_pool = amm_pool;
+        storage::set_pool_config(env, index, &config);
+        Ok(index)
+    }
+
+    /// Pool whose accumulator and total_staked a pool's stakers accrue on:
+    /// the Merkle pool for the deposit side of a hybrid pool, else itself.
+    fn state_pool(env: &Env, pool_index: u32) -> u32 {
+        storage::get_pool_config(env, pool_index)
+            .shared_with
+            .unwrap_or(pool_index)
+    }
+
+    /// Take `amount` off `user`'s stake (entry `staker`), unstaking fully
+    /// when nothing would remain.
+    fn reduce_stake_by(
+        env: &Env,
+        user: &Address,
+        pool_index: u32,
+        staker: StakerInfo,
+        amount: i128,
+    ) -> Result<(), ContractError> {
+        if amount <= 0 || amount > staker.staked_amount {
+            return Err(ContractError::InvalidAmount);
+        }
+        if amount == staker.staked_amount {
+            Self::exit_stake(env, user, pool_index);
+            events::stake_reduced(env, user, pool_index, amount, 0);
+            return Ok(());
+        }
+        let remaining = staker.staked_amount - amount;
+        if remaining < Self::min_stake(env, pool_index) {
+            return Err(ContractError::BelowMinimumStake);
+        }
+
+        let state_pool = Self::state_pool(env, pool_index);
+        let state = rewards::update_pool(env, state_pool);
+        let precision = rewards::pool_precision(env, state_pool);
+        // A stale position keeps earning nothing: settle it against the
+        // accumulator its epoch closed at
+        let (pending, acc) = if Self::is_current_epoch(env, pool_index, &staker) {
+            let pending = Self::settle_pending(env, user, pool_index, &state, &staker, precision);
+            (pending, state.acc_reward_per_share)
+        } else {
+            let pending = rewards::calculate_pending_stale(&state, &staker, precision);
+            (pending, state.prev_acc_reward_per_share)
+        };
+        let weight = rewards::stake_weight(env, pool_index, remaining, staker.streak);
+        storage::set_staker(
+            env,
+            user,
+            pool_index,
+            &StakerInfo {
+                staked_amount: remaining,
+                reward_debt: rewards::compute_reward_debt(weight, acc, precision),
+                pending_rewards: pending,
+                epoch_id: staker.epoch_id,
+                cooldown_until: staker.cooldown_until,
+                weight,
+                streak: staker.streak,
+            },
+        );
+
+        let mut updated_state = storage::get_pool_state(env, state_pool);
+        updated_state.total_staked -= staker.weight - weight;
+        storage::set_pool_state(env, state_pool, &updated_state);
+
+        if let Some(lp_token) = storage::get_pool_config(env, pool_index).lp_token {
+            token::Client::new(env, &lp_token).transfer(
+                &env.current_contract_address(),
+                user,
+                &amount,
+            );
+        }
+
+        events::stake_reduced(env, user, pool_index, amount, remaining);
+        hooks::notify_stake_changed(env, user, pool_index, remaining);
+        Ok(())
+    }
+
+    /// Unstake `user`'s whole position, returning escrowed LP in deposit-mode
+    /// pools.
+    fn exit_stake(env: &Env, user: &Address, pool_index: u32) {
+        let cooldown = storage::get_pool_config(env, pool_index).unstake_cooldown;
+        let cooldown_until = if cooldown > 0 {
+            env.ledger().timestamp() + cooldown
+        } else {
+            0
+        };
+        let (staker, _) = Self::retire_stake(env, user, pool_index, cooldown_until);
+
+        if let Some(lp_token) = storage::get_pool_config(env, pool_index).lp_token {
+            if staker.staked_amount > 0 {
+                token::Client::new(env, &lp_token).transfer(
+                    &env.current_contract_address(),
+                    user,
+                    &staker.staked_amount,
+                );
+            }
+        }
+
+        hooks::notify_stake_changed(env, user, pool_index, 0);
+    }
+
+    /// Take `user`'s stake out of the pool, keeping any pending rewards (and
+    /// a `cooldown_until` still to run) in a claim-only entry. Returns the
+    /// entry as it was before, and the rewards left pending.
+    fn retire_stake(
+        env: &Env,
+        user: &Address,
+        pool_index: u32,
+        cooldown_until: u64,
+    ) -> (StakerInfo, i128) {
+        let state_pool = Self::state_pool(env, pool_index);
+        let state = rewards::update_pool(env, state_pool);
+        let precision = rewards::pool_precision(env, state_pool);
+        let staker = storage::get_staker(env, user, pool_index);
+
+        // Check if staker's epoch is current for reward calculation
+        let is_current_epoch = Self::is_current_epoch(env, pool_index, &staker);
+
+        let pending = if is_current_epoch {
+            Self::settle_pending(env, user, pool_index, &state, &staker, precision)
+        } else {
+            rewards::calculate_pending_stale(&state, &staker, precision)
+        };
+
+        // Remove from pool total (stakes now carry over, so always subtract)
+        if staker.weight > 0 {
+            let mut updated_state = storage::get_pool_state(env, state_pool);
+            updated_state.total_staked -= staker.weight;
+            storage::set_pool_state(env, state_pool, &updated_state);
+        }
+        storage::unregister_staker(env, user, pool_index);
+
+        if pending > 0 || cooldown_until > env.ledger().timestamp() {
+            // Keep staker record with zero stake but pending rewards
+            storage::set_staker(
+                env,
+                user,
+                pool_index,
+                &StakerInfo {
+                    staked_amount: 0,
+                    reward_debt: 0,
+                    pending_rewards: pending,
+                    epoch_id: staker.epoch_id,
+                    cooldown_until,
+                    weight: 0,
+                    streak: staker.streak,
+                },
+            );
+        } else {
+            storage::remove_staker(env, user, pool_index);
+        }
+
+        (staker, pending)
+    }
+
+    /// Emit `amount` over the next `duration` seconds on `pool`'s reward
+    /// schedule, rolling in whatever the running schedule has left.
+    fn extend_reward_schedule(env: &Env, pool: u32, amount: i128, duration: u64) {
+        let now = env.ledger().timestamp();
+        let leftover = rewards::scheduled_rewards(env, pool, now, u64::MAX);
+        let rate = (amount + leftover) / duration as i128;
+        let period_finish = now + duration;
+        storage::set_reward_schedule(
+            env,
+            pool,
+            &RewardSchedule {
+                rate,
+                period_finish,
+            },
+        );
+
+        // Rounding dust beyond rate * duration stays unreserved surplus
+        if storage::pays_lmnr(env, pool) {
+            let mut totals = storage::get_emission_totals(env);
+            totals.scheduled += rate * duration as i128 - leftover;
+            storage::set_emission_totals(env, &totals);
+        }
+
+        events::reward_notified(env, pool, amount, rate, period_finish);
+    }
+
+    /// Pending rewards of a current-epoch staker settled against `state`
+    /// now, net of any warm-up (see `set_warmup_period`).
+    fn settle_pending(
+        env: &Env,
+        user: &Address,
+        pool_index: u32,
+        state: &PoolState,
+        staker: &StakerInfo,
+        precision: i128,
+    ) -> i128 {
+        rewards::calculate_pending_warm(
+            env,
+            user,
+            pool_index,
+            state.acc_reward_per_share,
+            staker,
+            precision,
+            env.ledger().timestamp(),
+        )
+    }
+
+    /// Whether `staker` earns at the pool's live accumulator. Deposit-mode
+    /// pools have no epochs; in Merkle pools the stake must have been proven
+    /// against the current root.
+    fn is_current_epoch(env: &Env, pool_index: u32, staker: &StakerInfo) -> bool {
+        if storage::get_pool_config(env, pool_index).lp_token.is_some() {
+            return true;
+        }
+        storage::has_merkle_root(env, pool_index)
+            && staker.epoch_id == storage::get_merkle_root(env, pool_index).epoch_id
+    }
+
+    /// Append an admin call to the log behind `get_admin_log`. A failed call
+    /// rolls its entry back with everything else.
+    fn log_admin_action(env: &Env, caller: &Address, action: Symbol, args: impl IntoVal<Env, Val>) {
+        let args: Val = args.into_val(env);
+        let mut log = storage::get_admin_log(env);
+        if log.len() >= MAX_ADMIN_LOG {
+            log.pop_front();
+        }
+        log.push_back(AdminAction {
+            action,
+            args_hash: env.crypto().sha256(&args.to_xdr(env)).into(),
+            caller: caller.clone(),
+            timestamp: env.ledger().timestamp(),
+        });
+        storage::set_admin_log(env, &log);
+    }
+
+    fn require_merkle_pool(env: &Env, pool_index: u32) -> Result<(), ContractError> {
+        if storage::get_pool_config(env, pool_index).lp_token.is_some() {
+            return Err(ContractError::WrongPoolMode);
+        }
+        Ok(())
+    }
+
+    /// Bring every pool's accumulator up to now. Returns the total LMNR
+    /// liability.
+    fn update_all_pools(env: &Env) -> i128 {
+        let pool_count = storage::get_pool_count(env);
+        let mut liability: i128 = 0;
+        for i in 0..pool_count {
+            rewards::update_pool(env, i);
+            if storage::pays_lmnr(env, i) {
+                liability += storage::get_pool_liability(env, i);
+            }
+        }
+        liability
+    }
+
+    /// `fund_pool` for an LMNR pool: take `amount` of LMNR from `funder`
+    /// and earmark it for the pool.
+    fn earmark_funds(
+        env: &Env,
+        funder: &Address,
+        pool_index: u32,
+        amount: i128,
+        duration: u64,
+    ) -> Result<(), ContractError> {
+        if duration != 0 {
+            return Err(ContractError::InvalidConfig);
+        }
+        if storage::get_pool_config(env, pool_index).removed {
+            return Err(ContractError::PoolNotFound);
+        }
+
+        let lmnr_token = storage::get_lmnr_token(env);
+        token::Client::new(env, &lmnr_token).transfer(
+            funder,
+            &env.current_contract_address(),
+            &amount,
+        );
+        let mut accounting = storage::get_accounting(env);
+        accounting.total_funded += amount;
+        storage::set_accounting(env, &accounting);
+
+        let earmark = storage::get_pool_earmark(env, pool_index) + amount;
+        storage::set_pool_earmark(env, pool_index, earmark);
+        events::pool_earmarked(env, pool_index, amount, earmark);
+        Ok(())
+    }
+
+    /// Earmarked LMNR beyond what each LMNR pool already owes its stakers,
+    /// which the liability does not cover yet.
+    fn unowed_earmarks(env: &Env) -> i128 {
+        (0..storage::get_pool_count(env))
+            .filter(|&i| storage::pays_lmnr(env, i))
+            .map(|i| {
+                let earmark = storage::get_pool_earmark(env, i);
+                (earmark - storage::get_pool_liability(env, i)).max(0)
+            })
+            .sum()
+    }
+
+    /// Token the pool's stakers are paid in: its partner token, or LMNR.
+    fn pool_reward_token(env: &Env, pool_index: u32) -> Address {
+        storage::get_pool_config(env, pool_index)
+            .reward_token
+            .unwrap_or_else(|| storage::get_lmnr_token(env))
+    }
+
+    /// What pools paying partner `token` still owe or have scheduled, as of now.
+    fn partner_token_reserve(env: &Env, token: &Address) -> i128 {
+        let now = env.ledger().timestamp();
+        (0..storage::get_pool_count(env))
+            .filter(|&i| storage::get_pool_config(env, i).reward_token.as_ref() == Some(token))
+            .map(|i| {
+                rewards::simulate_liability(env, i)
+                    + rewards::simulate_carry(env, i)
+                    + rewards::scheduled_rewards(env, i, now, u64::MAX)
+            })
+            .sum()
+    }
+
+    /// Pay `user` the revenue they are owed in a pool to `to`, once their
+    /// shares are settled. Returns the amount paid per token.
+    fn pay_revenue(env: &Env, user: &Address, pool_index: u32, to: &Address) -> Map<Address, i128> {
+        let mut shares = storage::get_revenue_shares(env, user, pool_index);
+        let mut paid = Map::new(env);
+        for (token, mut share) in shares.iter() {
+            if share.owed <= 0 {
+                continue;
+            }
+            // A token that rejects the transfer (frozen trustline, clawback)
+            // keeps its share owed rather than blocking the rest of the claim
+            let transfer = token::Client::new(env, &token).try_transfer(
+                &env.current_contract_address(),
+                to,
+                &share.owed,
+            );
+            if !matches!(transfer, Ok(Ok(()))) {
+                continue;
+            }
+            events::revenue_claimed(env, user, pool_index, &token, share.owed);
+            paid.set(token.clone(), share.owed);
+            share.owed = 0;
+            shares.set(token, share);
+        }
+        if !paid.is_empty() {
+            storage::set_revenue_shares(env, user, pool_index, &shares);
+        }
+        paid
+    }
+
+    /// Checks shared by `claim_revenue` and `claim_campaigns`, then settle
+    /// `user`'s shares up to now.
+    fn prepare_share_claim(
+        env: &Env,
+        user: &Address,
+        pool_index: u32,
+    ) -> Result<(), ContractError> {
+        Self::require_not_paused(env)?;
+        Self::require_valid_pool(env, pool_index)?;
+        Self::require_pool_not_paused(env, pool_index)?;
+        storage::extend_instance_ttl(env);
+
+        if storage::get_blocked(env, user).is_some() {
+            return Err(ContractError::ClaimsFrozen);
+        }
+        if !hooks::is_compliant(env, user) {
+            return Err(ContractError::NotCompliant);
+        }
+        rewards::update_pool(env, Self::state_pool(env, pool_index));
+        if storage::has_staker(env, user, pool_index) {
+            let weight = storage::get_staker(env, user, pool_index).weight;
+            rewards::settle_shares(env, user, pool_index, weight, weight);
+        }
+        Ok(())
+    }
+
+    /// Revenue and campaign tokens must not be tokens whose balance the
+    /// contract already accounts for: LMNR, escrowed LP or a partner
+    /// reward token.
+    fn require_unaccounted_token(env: &Env, token: &Address) -> Result<(), ContractError> {
+        let accounted = *token == storage::get_lmnr_token(env)
+            || (0..storage::get_pool_count(env)).any(|i| {
+                let config = storage::get_pool_config(env, i);
+                config.lp_token.as_ref() == Some(token)
+                    || config.reward_token.as_ref() == Some(token)
+            });
+        if accounted {
+            return Err(ContractError::InvalidConfig);
+        }
+        Ok(())
+    }
+
+    /// Settle and pay out `user`'s pending rewards in a pool to `to`.
+    fn settle_claim(
+        env: &Env,
+        user: &Address,
+        pool_index: u32,
+        to: &Address,
+    ) -> Result<i128, ContractError> {
+        Self::require_pool_not_paused(env, pool_index)?;
+        if !storage::has_staker(env, user, pool_index) {
+            return Err(ContractError::NoStakeFound);
+        }
+
+        let state_pool = Self::state_pool(env, pool_index);
+        let state = rewards::update_pool(env, state_pool);
+        let precision = rewards::pool_precision(env, state_pool);
+        let mut staker = storage::get_staker(env, user, pool_index);
+
+        // Check if staker's epoch is current
+        let is_current_epoch = Self::is_current_epoch(env, pool_index, &staker);
+
+        let pending = if is_current_epoch {
+            Self::settle_pending(env, user, pool_index, &state, &staker, precision)
+        } else {
+            rewards::calculate_pending_stale(&state, &staker, precision)
+        };
+
+        if pending <= 0 {
+            return Err(ContractError::NoRewardsToClaim);
+        }
+
+        match storage::get_blocked(env, user) {
+            Some(true) => return Err(ContractError::ClaimsFrozen),
+            Some(false) => {
+                Self::write_off_pending(
+                    env,
+                    user,
+                    pool_index,
+                    &state,
+                    staker,
+                    is_current_epoch,
+                    pending,
+                );
+                events::rewards_forfeited(env, user, pool_index, pending);
+                return Ok(0);
+            }
+            None => {}
+        }
+        if storage::get_blocked(env, to).is_some() {
+            return Err(ContractError::AddressBlocked);
+        }
+        if !hooks::is_compliant(env, user) || (to != user && !hooks::is_compliant(env, to)) {
+            return Err(ContractError::NotCompliant);
+        }
+        if pending < storage::get_min_claim(env) {
+            return Err(ContractError::BelowMinimumClaim);
+        }
+
+        // Transfer the pool's reward token to the destination
+        let reward_token = Self::pool_reward_token(env, state_pool);
+        let token_client = token::Client::new(env, &reward_token);
+
+        let contract_balance = token_client.balance(&env.current_contract_address());
+        // In partial payout mode a funding gap pays what is available and
+        // leaves the shortfall pending instead of blocking the claim
+        let paid = if contract_balance >= pending {
+            pending
+        } else if storage::get_partial_payouts(env) && contract_balance > 0 {
+            contract_balance
+        } else {
+            return Err(ContractError::InsufficientRewardBalance);
+        };
+        let shortfall = pending - paid;
+
+        // A clawback or a frozen trustline on `to` makes the token reject the
+        // transfer; fail before the staker is touched so rewards stay pending
+        let transfer = token_client.try_transfer(&env.current_contract_address(), to, &paid);
+        if !matches!(transfer, Ok(Ok(()))) {
+            return Err(ContractError::RewardTransferFailed);
+        }
+
+        // Lifetime totals are in LMNR; partner payouts show per pool only
+        let pays_lmnr = storage::pays_lmnr(env, state_pool);
+        if pays_lmnr {
+            let mut accounting = storage::get_accounting(env);
+            accounting.total_claimed += paid;
+            storage::set_accounting(env, &accounting);
+
+            let earmark = storage::get_pool_earmark(env, state_pool);
+            if earmark > 0 {
+                storage::set_pool_earmark(env, state_pool, (earmark - paid).max(0));
+            }
+        }
+
+        // Per-staker rounding can pay a stroop or two more than was booked
+        let liability = storage::get_pool_liability(env, state_pool);
+        storage::set_pool_liability(env, state_pool, (liability - paid).max(0));
+
+        // Update staker state
+        if is_current_epoch {
+            staker.reward_debt =
+                rewards::compute_reward_debt(staker.weight, state.acc_reward_per_share, precision);
+            staker.pending_rewards = shortfall;
+        } else {
+            staker.reward_debt = rewards::compute_reward_debt(
+                staker.weight,
+                state.prev_acc_reward_per_share,
+                precision,
+            );
+            staker.pending_rewards = shortfall;
+        }
+
+        storage::set_staker(env, user, pool_index, &staker);
+        storage::set_last_active(env, user, pool_index);
+        Self::record_claim_stats(env, user, pool_index, staker.epoch_id, paid);
+
+        let claimed = storage::get_user_claimed(env, user, pool_index);
+        storage::set_user_claimed(env, user, pool_index, claimed + paid);
+        if pays_lmnr {
+            let claimed_total = storage::get_user_claimed_total(env, user);
+            storage::set_user_claimed_total(env, user, claimed_total + paid);
+        }
+
+        let destination = if storage::get_hide_claim_destination(env) {
+            None
+        } else {
+            Some(to.clone())
+        };
+        events::claimed(env, user, pool_index, paid, destination);
+        if shortfall > 0 {
+            events::claim_shortfall(env, user, pool_index, shortfall);
+        }
+        hooks::notify_claimed(env, user, pool_index, paid);
+        Self::pay_revenue(env, user, pool_index, to);
+        campaigns::pay(env, user, pool_index, to);
+
+        Ok(paid)
+    }
+
+    /// Epoch the next root posted to a pool opens.
+    fn next_epoch_id(env: &Env, pool_index: u32) -> u64 {
+        if storage::has_merkle_root(env, pool_index) {
+            storage::get_merkle_root(env, pool_index).epoch_id + 1
+        } else {
+            1
+        }
+    }
+
+    /// Pay `caller` a keeper bounty for `action` unless this ledger's
+    /// bounties are used up. Returns the bounty paid.
+    fn pay_keeper_bounty(env: &Env, caller: &Address, action: Symbol, bounty: i128) -> i128 {
+        if bounty <= 0 {
+            return 0;
+        }
+        let sequence = env.ledger().sequence();
+        let mut usage = storage::get_bounty_usage(env);
+        if usage.sequence != sequence {
+            usage = BountyUsage { sequence, count: 0 };
+        }
+        if usage.count >= storage::get_keeper_bounties(env).max_per_ledger
+            || !Self::pay_bounty(env, caller, bounty)
+        {
+            return 0;
+        }
+        usage.count += 1;
+        storage::set_bounty_usage(env, &usage);
+        events::bounty_paid(env, caller, action, bounty);
+        bounty
+    }
+
+    /// Transfer an LMNR `bounty` to `caller` if the balance above owed
+    /// rewards covers it. Returns whether it was paid.
+    fn pay_bounty(env: &Env, caller: &Address, bounty: i128) -> bool {
+        let token_client = token::Client::new(env, &storage::get_lmnr_token(env));
+        let balance = token_client.balance(&env.current_contract_address());
+        if balance - rewards::outstanding_liability(env) < bounty {
+            return false;
+        }
+        token_client.transfer(&env.current_contract_address(), caller, &bounty);
+        let mut accounting = storage::get_accounting(env);
+        accounting.total_bounties += bounty;
+        storage::set_accounting(env, &accounting);
+        true
+    }
+
+    /// Drop the `pending` rewards of `user`'s entry `staker`, settled against
+    /// `state`, releasing them from the pool's liability and LMNR earmark.
+    fn write_off_pending(
+        env: &Env,
+        user: &Address,
+        pool_index: u32,
+        state: &PoolState,
+        mut staker: StakerInfo,
+        is_current_epoch: bool,
+        pending: i128,
+    ) {
+        let state_pool = Self::state_pool(env, pool_index);
+        let liability = storage::get_pool_liability(env, state_pool);
+        storage::set_pool_liability(env, state_pool, (liability - pending).max(0));
+        let earmark = storage::get_pool_earmark(env, state_pool);
+        if earmark > 0 {
+            storage::set_pool_earmark(env, state_pool, (earmark - pending).max(0));
+        }
+
+        let acc = if is_current_epoch {
+            state.acc_reward_per_share
+        } else {
+            state.prev_acc_reward_per_share
+        };
+        let precision = rewards::pool_precision(env, state_pool);
+        staker.reward_debt = rewards::compute_reward_debt(staker.weight, acc, precision);
+        staker.pending_rewards = 0;
+        storage::set_staker(env, user, pool_index, &staker);
+    }
+
+    /// Drop `user`'s revenue and campaign shares in a pool. Forfeited
+    /// revenue goes back to its pot to be shared at the next rotation;
+    /// forfeited campaign rewards stay unpaid for the sponsor to reclaim.
+    fn forfeit_shares(env: &Env, user: &Address, pool_index: u32) {
+        let state_pool = Self::state_pool(env, pool_index);
+        let shares = storage::get_revenue_shares(env, user, pool_index);
+        if !shares.is_empty() {
+            let mut pots = storage::get_revenue_pots(env, state_pool);
+            for (token, share) in shares.iter() {
+                if let Some(mut pot) = pots.get(token.clone()) {
+                    pot.pending += share.owed;
+                    pots.set(token, pot);
+                }
+            }
+            storage::set_revenue_pots(env, state_pool, &pots);
+            storage::set_revenue_shares(env, user, pool_index, &Map::new(env));
+        }
+        storage::set_campaign_shares(env, user, pool_index, &Map::new(env));
+    }
+
+    /// When `user`'s unclaimed rewards in a pool become sweepable, if a
+    /// claim deadline is set.
+    fn claim_deadline(env: &Env, user: &Address, pool_index: u32) -> Option<u64> {
+        let deadline = storage::get_claim_deadline(env)?;
+        let last_active = storage::get_last_active(env, user, pool_index).unwrap_or(0);
+        Some(last_active.max(deadline.since) + deadline.seconds)
+    }
+
+    /// Attribute a payout to the epoch the claimant's stake was proven in.
+    fn record_claim_stats(
+        env: &Env,
+        user: &Address,
+        pool_index: u32,
+        epoch_id: u64,
+        amount: i128,
+    ) {
+        let mut stats = storage::get_claim_stats(env, pool_index, epoch_id);
+        stats.total_claimed += amount;
+        if storage::get_last_claim_epoch(env, user, pool_index) != epoch_id {
+            stats.claimant_count += 1;
+            storage::set_last_claim_epoch(env, user, pool_index, epoch_id);
+        }
+        storage::set_claim_stats(env, pool_index, epoch_id, &stats);
+    }
+
+    /// Set `user`'s stake in a pool to `new_amount`, settling pending rewards.
+    fn apply_stake_update(
+        env: &Env,
+        user: &Address,
+        pool_index: u32,
+        new_amount: i128,
+    ) -> Result<(), ContractError> {
+        if new_amount < 0 {
+            return Err(ContractError::InvalidAmount);
+        }
+        Self::require_merkle_pool(env, pool_index)?;
+
+        // Update pool accumulator
+        let state = rewards::update_pool(env, pool_index);
+        let precision = rewards::pool_precision(env, pool_index);
+
+        let (old_weight, streak) = if storage::has_staker(env, user, pool_index) {
+            let staker = storage::get_staker(env, user, pool_index);
+            (staker.weight, staker.streak)
+        } else {
+            (0, 0)
+        };
+        let new_weight = rewards::stake_weight(env, pool_index, new_amount, streak);
+        Self::check_pool_cap(env, pool_index, &state, old_weight, new_weight)?;
+
+        // Get current epoch_id (needed for new staker records)
+        let current_epoch_id = if storage::has_merkle_root(env, pool_index) {
+            storage::get_merkle_root(env, pool_index).epoch_id
+        } else {
+            0
+        };
+
+        if storage::has_staker(env, user, pool_index) {
+            let staker = storage::get_staker(env, user, pool_index);
+
+            // Check if staker's epoch is current
+            let is_current_epoch = current_epoch_id > 0 && staker.epoch_id == current_epoch_id;
+
+            // Settle pending rewards
+            let pending = if is_current_epoch {
+                Self::settle_pending(env, user, pool_index, &state, &staker, precision)
+            } else {
+                rewards::calculate_pending_stale(&state, &staker, precision)
+            };
+
+            // Update staker record
+            let new_debt =
+                rewards::compute_reward_debt(new_weight, state.acc_reward_per_share, precision);
+            storage::set_staker(
+                env,
+                user,
+                pool_index,
+                &StakerInfo {
+                    staked_amount: new_amount,
+                    reward_debt: new_debt,
+                    pending_rewards: pending,
+                    epoch_id: current_epoch_id,
+                    cooldown_until: staker.cooldown_until,
+                    weight: new_weight,
+                    streak,
+                },
+            );
 
-        let lmnr_token = storage::get_lmnr_token(&env);
-        let token_client = token::Client::new(&env, &lmnr_token);
-        token_client.transfer(&funder, &env.current_contract_address(), &amount);
-        storage::extend_instance_ttl(&env);
+            // Adjust total_staked by the delta
+            let mut updated_state = storage::get_pool_state(env, pool_index);
+            updated_state.total_staked = updated_state.total_staked - old_weight + new_weight;
+            storage::set_pool_state(env, pool_index, &updated_state);
+
+            if new_amount > 0 {
+                storage::register_staker(env, user, pool_index);
+            } else {
+                storage::unregister_staker(env, user, pool_index);
+            }
+        } else if new_amount > 0 {
+            // Create new staker entry
+            let new_debt =
+                rewards::compute_reward_debt(new_weight, state.acc_reward_per_share, precision);
+            storage::set_staker(
+                env,
+                user,
+                pool_index,
+                &StakerInfo {
+                    staked_amount: new_amount,
+                    reward_debt: new_debt,
+                    pending_rewards: 0,
+                    epoch_id: current_epoch_id,
+                    cooldown_until: 0,
+                    weight: new_weight,
+                    streak: 0,
+                },
+            );
 
+            let mut updated_state = storage::get_pool_state(env, pool_index);
+            updated_state.total_staked += new_weight;
+            storage::set_pool_state(env, pool_index, &updated_state);
+            storage::register_staker(env, user, pool_index);
+        }
+        // If new_amount == 0 and staker doesn't exist, no-op
+
+        hooks::notify_stake_changed(env, user, pool_index, new_amount);
         Ok(())
     }
 
-    // ========== User Functions ==========
-
-    /// Prove LP position via Merkle proof and start earning rewards.
-    pub fn stake(
-        env: Env,
-        user: Address,
+    /// Verify `user`'s LP position against the pool's current root and stake it.
+    /// `path` carries the direction bits for positional pools and must be
+    /// `None` for sorted-pair pools.
+    fn verify_and_stake(
+        env: &Env,
+        user: &Address,
         pool_index: u32,
         lp_balance: i128,
-        proof: Vec<BytesN<32>>,
+        proof: &impl merkle::Proof,
+        path: Option<u32>,
     ) -> Result<(), ContractError> {
-        user.require_auth();
-        Self::require_valid_pool(&env, pool_index)?;
-        storage::extend_instance_ttl(&env);
-
         if lp_balance <= 0 {
             return Err(ContractError::InvalidAmount);
         }
 
-        // Get current Merkle root
-        if !storage::has_merkle_root(&env, pool_index) {
-            return Err(ContractError::NoMerkleRoot);
+        Self::require_proof_depth(proof)?;
+        if storage::get_pool_config(env, pool_index).positional_proofs != path.is_some() {
+            return Err(ContractError::WrongPoolMode);
         }
-        let merkle_data = storage::get_merkle_root(&env, pool_index);
+        let merkle_data = Self::provable_root(env, pool_index)?;
 
         // Verify Merkle proof
-        let leaf = merkle::compute_leaf(&env, pool_index, &user, lp_balance, merkle_data.epoch_id);
-        if !merkle::verify_proof(&env, &leaf, &proof, &merkle_data.root) {
-            return Err(ContractError::InvalidProof);
+        let contract = env.current_contract_address();
+        let verify = |pool: u32, root: &MerkleRootData| {
+            let leaf = merkle::compute_root_leaf(env, &contract, pool, user, lp_balance, root);
+            match path {
+                Some(path) => merkle::verify_positional_proof(env, &leaf, proof, path, &root.root),
+                None => merkle::verify_proof(env, &leaf, proof, &root.root),
+            }
+        };
+        if !verify(pool_index, &merkle_data) {
+            return Err(Self::diagnose_proof(
+                env,
+                pool_index,
+                merkle_data.epoch_id,
+                verify,
+            ));
+        }
+
+        let proven = Self::check_declared_total(env, pool_index, &merkle_data, lp_balance)?;
+        Self::apply_stake(env, user, pool_index, lp_balance, merkle_data.epoch_id)?;
+        if let Some(proven) = proven {
+            storage::set_proven_total(env, pool_index, &proven);
+        }
+        Ok(())
+    }
+
+    /// Add `lp_balance` to what has been proven against `root` so far and
+    /// reject it if that would exceed the root's declared total. Returns
+    /// the running total for the caller to store once the stake is
+    /// applied, or `None` if the root declared no total.
+    fn check_declared_total(
+        env: &Env,
+        pool_index: u32,
+        root: &MerkleRootData,
+        lp_balance: i128,
+    ) -> Result<Option<ProvenTotal>, ContractError> {
+        if root.declared_total == 0 {
+            return Ok(None);
+        }
+        let proven = storage::get_proven_total(env, pool_index);
+        let so_far = if proven.epoch_id == root.epoch_id {
+            proven.amount
+        } else {
+            0
+        };
+        let amount = so_far
+            .checked_add(lp_balance)
+            .filter(|amount| *amount <= root.declared_total)
+            .ok_or(ContractError::DeclaredTotalExceeded)?;
+        Ok(Some(ProvenTotal {
+            epoch_id: root.epoch_id,
+            amount,
+        }))
+    }
+
+    /// Work out why a proof failed against the pool's current root so wallets
+    /// can say what to fix: `StaleEpoch` if it was built from the previous
+    /// snapshot, `PoolMismatch` if it belongs to a neighbouring pool's
+    /// snapshot, otherwise `InvalidProof` (balance or address not in the
+    /// snapshot). Only the `MAX_DIAGNOSED_POOLS` pools nearest by index are
+    /// tried, so the cost stays bounded however many pools are registered.
+    /// `verify(pool, root)` re-runs the caller's proof against another root.
+    /// Only runs once verification has already failed.
+    fn diagnose_proof(
+        env: &Env,
+        pool_index: u32,
+        epoch_id: u64,
+        verify: impl Fn(u32, &MerkleRootData) -> bool,
+    ) -> ContractError {
+        let previous = epoch_id
+            .checked_sub(1)
+            .and_then(|epoch| storage::get_historical_root(env, pool_index, epoch));
+        if let Some(previous) = previous {
+            if verify(pool_index, &previous) {
+                return ContractError::StaleEpoch;
+            }
+        }
+
+        let pool_count = storage::get_pool_count(env);
+        let nearest = (1..=MAX_DIAGNOSED_POOLS)
+            .flat_map(|distance| {
+                [
+                    pool_index.checked_sub(distance),
+                    pool_index
+                        .checked_add(distance)
+                        .filter(|other| *other < pool_count),
+                ]
+            })
+            .flatten()
+            .take(MAX_DIAGNOSED_POOLS as usize);
+        for other in nearest {
+            if storage::has_merkle_root(env, other)
+                && verify(other, &storage::get_merkle_root(env, other))
+            {
+                return ContractError::PoolMismatch;
+            }
+        }
+
+        ContractError::InvalidProof
+    }
+
+    fn require_unverified_updates(env: &Env, pool_index: u32) -> Result<(), ContractError> {
+        if storage::get_pool_config(env, pool_index).verified_updates {
+            return Err(ContractError::WrongPoolMode);
         }
+        Ok(())
+    }
+
+    /// Reject oversized proofs before any hashing is spent on them.
+    fn require_proof_depth(proof: &impl merkle::Proof) -> Result<(), ContractError> {
+        if proof.depth() > merkle::MAX_PROOF_DEPTH {
+            return Err(ContractError::ProofTooLong);
+        }
+        Ok(())
+    }
+
+    /// The pool's current root, if it is still young enough to prove against.
+    fn provable_root(env: &Env, pool_index: u32) -> Result<MerkleRootData, ContractError> {
+        if !storage::has_merkle_root(env, pool_index) {
+            return Err(ContractError::NoMerkleRoot);
+        }
+        let merkle_data = storage::get_merkle_root(env, pool_index);
+        if Self::root_expired(env, pool_index, &merkle_data) {
+            return Err(ContractError::RootExpired);
+        }
+        Ok(merkle_data)
+    }
+
+    fn root_expired(env: &Env, pool_index: u32, merkle_data: &MerkleRootData) -> bool {
+        let max_age = storage::get_pool_config(env, pool_index).max_root_age;
+        max_age > 0 && env.ledger().timestamp() - merkle_data.posted_at > max_age
+    }
+
+    /// Record a verified LP position for `user` in the given epoch.
+    fn apply_stake(
+        env: &Env,
+        user: &Address,
+        pool_index: u32,
+        lp_balance: i128,
+        epoch_id: u64,
+    ) -> Result<(), ContractError> {
+        Self::require_pool_not_paused(env, pool_index)?;
+        Self::require_can_stake(env, user, pool_index, lp_balance)?;
 
         // Update pool accumulator
-        let state = rewards::update_pool(&env, pool_index);
+        let state = rewards::update_pool(env, pool_index);
+        let precision = rewards::pool_precision(env, pool_index);
 
         // Handle existing staker
-        let old_staked_amount = if storage::has_staker(&env, &user, pool_index) {
-            let staker = storage::get_staker(&env, &user, pool_index);
+        let (old_amount, old_weight, new_weight) = if storage::has_staker(env, user, pool_index) {
+            let staker = storage::get_staker(env, user, pool_index);
 
-            if staker.epoch_id == merkle_data.epoch_id && staker.staked_amount > 0 {
+            if staker.epoch_id == epoch_id && staker.staked_amount > 0 {
                 return Err(ContractError::AlreadyStakedThisEpoch);
             }
+            Self::require_cooldown_over(env, &staker)?;
+
+            // The streak grows only when the previous epoch's stake is
+            // re-proven in the very next one
+            let streak = if staker.epoch_id == epoch_id {
+                staker.streak
+            } else if staker.epoch_id + 1 == epoch_id && staker.staked_amount > 0 {
+                staker.streak + 1
+            } else {
+                0
+            };
+            let weight = rewards::stake_weight(env, pool_index, lp_balance, streak);
+            Self::check_pool_cap(env, pool_index, &state, staker.weight, weight)?;
 
             // Stale epoch — preserve pending rewards, re-stake with new proof
-            let pending = if staker.epoch_id == merkle_data.epoch_id {
-                rewards::calculate_pending(&state, &staker)
+            let pending = if staker.epoch_id == epoch_id {
+                Self::settle_pending(env, user, pool_index, &state, &staker, precision)
             } else {
-                rewards::calculate_pending_stale(&state, &staker)
+                rewards::calculate_pending_stale(&state, &staker, precision)
             };
 
-            let new_debt = rewards::compute_reward_debt(lp_balance, state.acc_reward_per_share);
+            let new_debt =
+                rewards::compute_reward_debt(weight, state.acc_reward_per_share, precision);
             storage::set_staker(
-                &env,
-                &user,
+                env,
+                user,
                 pool_index,
                 &StakerInfo {
                     staked_amount: lp_balance,
                     reward_debt: new_debt,
                     pending_rewards: pending,
-                    epoch_id: merkle_data.epoch_id,
+                    epoch_id,
+                    cooldown_until: 0,
+                    weight,
+                    streak,
                 },
             );
 
-            staker.staked_amount // Return old amount for total_staked adjustment
+            // Return old amounts for total_staked adjustment
+            (staker.staked_amount, staker.weight, weight)
         } else {
-            let new_debt = rewards::compute_reward_debt(lp_balance, state.acc_reward_per_share);
+            let weight = rewards::stake_weight(env, pool_index, lp_balance, 0);
+            Self::check_pool_cap(env, pool_index, &state, 0, weight)?;
+            let new_debt =
+                rewards::compute_reward_debt(weight, state.acc_reward_per_share, precision);
             storage::set_staker(
-                &env,
-                &user,
+                env,
+                user,
                 pool_index,
                 &StakerInfo {
                     staked_amount: lp_balance,
                     reward_debt: new_debt,
                     pending_rewards: 0,
-                    epoch_id: merkle_data.epoch_id,
+                    epoch_id,
+                    cooldown_until: 0,
+                    weight,
+                    streak: 0,
                 },
             );
 
-            0 // No old amount for new stakers
+            (0, 0, weight) // No old amounts for new stakers
         };
 
-        // Update pool total: subtract old stake (if re-staking), add new stake
-        let mut updated_state = storage::get_pool_state(&env, pool_index);
-        updated_state.total_staked = updated_state.total_staked - old_staked_amount + lp_balance;
-        storage::set_pool_state(&env, pool_index, &updated_state);
+        // Update pool total: subtract old weight (if re-staking), add new weight
+        let mut updated_state = storage::get_pool_state(env, pool_index);
+        updated_state.total_staked = updated_state.total_staked - old_weight + new_weight;
+        storage::set_pool_state(env, pool_index, &updated_state);
+        storage::register_staker(env, user, pool_index);
+        storage::set_last_active(env, user, pool_index);
+        // Re-proving no more than was already staked needs no warm-up
+        if lp_balance > old_amount {
+            Self::start_warmup(env, user, pool_index);
+        }
 
+        hooks::notify_stake_changed(env, user, pool_index, lp_balance);
         Ok(())
     }
 
-    /// Claim accumulated LMNR rewards. Returns amount claimed.
-    pub fn claim(env: Env, user: Address, pool_index: u32) -> Result<i128, ContractError> {
-        user.require_auth();
-        Self::require_valid_pool(&env, pool_index)?;
-        storage::extend_instance_ttl(&env);
+    /// Grow `user`'s current-epoch stake (entry `staker`) by `amount`,
+    /// settling what it earned so far.
+    fn add_to_stake(
+        env: &Env,
+        user: &Address,
+        pool_index: u32,
+        staker: StakerInfo,
+        amount: i128,
+    ) -> Result<(), ContractError> {
+        Self::require_pool_not_paused(env, pool_index)?;
+        let total = staker.staked_amount + amount;
+        Self::require_can_stake(env, user, pool_index, total)?;
 
-        if !storage::has_staker(&env, &user, pool_index) {
-            return Err(ContractError::NoStakeFound);
-        }
+        let state = rewards::update_pool(env, pool_index);
+        let precision = rewards::pool_precision(env, pool_index);
+        let weight = rewards::stake_weight(env, pool_index, total, staker.streak);
+        Self::check_pool_cap(env, pool_index, &state, staker.weight, weight)?;
 
-        let state = rewards::update_pool(&env, pool_index);
-        let mut staker = storage::get_staker(&env, &user, pool_index);
+        storage::set_staker(
+            env,
+            user,
+            pool_index,
+            &StakerInfo {
+                staked_amount: total,
+                reward_debt: rewards::compute_reward_debt(
+                    weight,
+                    state.acc_reward_per_share,
+                    precision,
+                ),
+                pending_rewards: Self::settle_pending(
+                    env, user, pool_index, &state, &staker, precision,
+                ),
+                epoch_id: staker.epoch_id,
+                cooldown_until: staker.cooldown_until,
+                weight,
+                streak: staker.streak,
+            },
+        );
 
-        // Check if staker's epoch is current
-        let is_current_epoch = storage::has_merkle_root(&env, pool_index) && {
-            let merkle_data = storage::get_merkle_root(&env, pool_index);
-            staker.epoch_id == merkle_data.epoch_id
-        };
+        let mut updated_state = storage::get_pool_state(env, pool_index);
+        updated_state.total_staked += weight - staker.weight;
+        storage::set_pool_state(env, pool_index, &updated_state);
+        storage::set_last_active(env, user, pool_index);
+        Self::start_warmup(env, user, pool_index);
 
-        let pending = if is_current_epoch {
-            rewards::calculate_pending(&state, &staker)
-        } else {
-            rewards::calculate_pending_stale(&state, &staker)
-        };
+        hooks::notify_stake_changed(env, user, pool_index, total);
+        Ok(())
+    }
 
-        if pending <= 0 {
-            return Err(ContractError::NoRewardsToClaim);
+    /// Start (or restart) `user`'s warm-up if the pool has one.
+    fn start_warmup(env: &Env, user: &Address, pool_index: u32) {
+        let period = storage::get_pool_config(env, pool_index).warmup_period;
+        if period == 0 {
+            return;
         }
+        let now = env.ledger().timestamp();
+        storage::set_warmup(
+            env,
+            user,
+            pool_index,
+            &Warmup {
+                from: now,
+                until: now + period,
+            },
+        );
+    }
 
-        // Transfer LMNR to user
-        let lmnr_token = storage::get_lmnr_token(&env);
-        let token_client = token::Client::new(&env, &lmnr_token);
-
-        let contract_balance = token_client.balance(&env.current_contract_address());
-        if contract_balance < pending {
-            return Err(ContractError::InsufficientRewardBalance);
+    /// Checks every user-initiated stake must pass for a resulting position
+    /// of `amount`: blocklist, compliance hook and minimum stake.
+    fn require_can_stake(
+        env: &Env,
+        user: &Address,
+        pool_index: u32,
+        amount: i128,
+    ) -> Result<(), ContractError> {
+        if storage::get_blocked(env, user).is_some() {
+            return Err(ContractError::AddressBlocked);
         }
-
-        token_client.transfer(&env.current_contract_address(), &user, &pending);
-
-        // Update staker state
-        if is_current_epoch {
-            staker.reward_debt =
-                rewards::compute_reward_debt(staker.staked_amount, state.acc_reward_per_share);
-            staker.pending_rewards = 0;
-        } else {
-            staker.reward_debt = rewards::compute_reward_debt(
-                staker.staked_amount,
-                state.prev_acc_reward_per_share,
-            );
-            staker.pending_rewards = 0;
+        if !hooks::is_compliant(env, user) {
+            return Err(ContractError::NotCompliant);
         }
 
-        storage::set_staker(&env, &user, pool_index, &staker);
-
-        Ok(pending)
+        if amount < Self::min_stake(env, pool_index) {
+            return Err(ContractError::BelowMinimumStake);
+        }
+        Ok(())
     }
 
-    /// Stop earning rewards. Pending rewards are preserved for later claiming.
-    pub fn unstake(env: Env, user: Address, pool_index: u32) -> Result<(), ContractError> {
-        user.require_auth();
-        Self::require_valid_pool(&env, pool_index)?;
-        storage::extend_instance_ttl(&env);
-
-        if !storage::has_staker(&env, &user, pool_index) {
-            return Err(ContractError::NoStakeFound);
+    /// Reject a new stake while `staker`'s unstake cooldown is still running.
+    fn require_cooldown_over(env: &Env, staker: &StakerInfo) -> Result<(), ContractError> {
+        if staker.cooldown_until > env.ledger().timestamp() {
+            return Err(ContractError::CooldownActive);
         }
+        Ok(())
+    }
 
-        let state = rewards::update_pool(&env, pool_index);
-        let staker = storage::get_staker(&env, &user, pool_index);
-
-        // Check if staker's epoch is current for reward calculation
-        let is_current_epoch = storage::has_merkle_root(&env, pool_index) && {
-            let merkle_data = storage::get_merkle_root(&env, pool_index);
-            staker.epoch_id == merkle_data.epoch_id
-        };
-
-        let pending = if is_current_epoch {
-            rewards::calculate_pending(&state, &staker)
-        } else {
-            rewards::calculate_pending_stale(&state, &staker)
-        };
+    /// Smallest position the pool accepts: its own minimum or the global one.
+    fn min_stake(env: &Env, pool_index: u32) -> i128 {
+        storage::get_pool_config(env, pool_index)
+            .min_stake
+            .unwrap_or_else(|| storage::get_min_stake(env))
+    }
 
-        // Remove from pool total (stakes now carry over, so always subtract)
-        if staker.staked_amount > 0 {
-            let mut updated_state = storage::get_pool_state(&env, pool_index);
-            updated_state.total_staked -= staker.staked_amount;
-            storage::set_pool_state(&env, pool_index, &updated_state);
+    /// Reject growing a position's weight from `old_weight` to `new_weight`
+    /// when it would take the pool past its `max_total_staked`.
+    fn check_pool_cap(
+        env: &Env,
+        pool_index: u32,
+        state: &PoolState,
+        old_weight: i128,
+        new_weight: i128,
+    ) -> Result<(), ContractError> {
+        let cap = storage::get_pool_config(env, pool_index).max_total_staked;
+        if cap > 0 && new_weight > old_weight && state.total_staked - old_weight + new_weight > cap
+        {
+            return Err(ContractError::PoolCapExceeded);
         }
+        Ok(())
+    }
 
-        if pending > 0 {
-            // Keep staker record with zero stake but pending rewards
-            storage::set_staker(
-                &env,
-                &user,
-                pool_index,
-                &StakerInfo {
-                    staked_amount: 0,
-                    reward_debt: 0,
-                    pending_rewards: pending,
-                    epoch_id: staker.epoch_id,
-                },
-            );
-        } else {
-            storage::remove_staker(&env, &user, pool_index);
+    /// Reject a root posted outside the pool's configured cadence window,
+    /// measured from the previous root. The first root is always accepted.
+    fn check_epoch_cadence(env: &Env, pool_index: u32) -> Result<(), ContractError> {
+        if !storage::has_merkle_root(env, pool_index) {
+            return Ok(());
+        }
+        let config = storage::get_pool_config(env, pool_index);
+        let elapsed =
+            env.ledger().timestamp() - storage::get_merkle_root(env, pool_index).posted_at;
+        if elapsed < config.min_epoch_interval {
+            return Err(ContractError::RootTooSoon);
+        }
+        if config.max_epoch_interval > 0 && elapsed > config.max_epoch_interval {
+            return Err(ContractError::RootTooLate);
         }
-
         Ok(())
     }
 
-    // ========== View Functions ==========
-
-    /// Query unclaimed rewards for a user in a pool.
-    pub fn pending_reward(env: Env, user: Address, pool_index: u32) -> i128 {
-        if !storage::has_staker(&env, &user, pool_index) {
-            return 0;
+    /// A new root must snapshot a later ledger than the previous one, and
+    /// not one that has yet to close.
+    fn check_snapshot_ledger(
+        env: &Env,
+        pool_index: u32,
+        snapshot_ledger: u32,
+    ) -> Result<(), ContractError> {
+        if snapshot_ledger > env.ledger().sequence() {
+            return Err(ContractError::SnapshotInFuture);
         }
-
-        let staker = storage::get_staker(&env, &user, pool_index);
-
-        let is_current_epoch = storage::has_merkle_root(&env, pool_index) && {
-            let merkle_data = storage::get_merkle_root(&env, pool_index);
-            staker.epoch_id == merkle_data.epoch_id
-        };
-
-        if !is_current_epoch {
-            let state = storage::get_pool_state(&env, pool_index);
-            return rewards::calculate_pending_stale(&state, &staker);
+        if storage::has_merkle_root(env, pool_index)
+            && snapshot_ledger <= storage::get_merkle_root(env, pool_index).snapshot_ledger
+        {
+            return Err(ContractError::SnapshotNotIncreasing);
         }
-
-        let simulated_acc = rewards::simulate_acc_reward(&env, pool_index);
-        let accumulated = (staker.staked_amount * simulated_acc) / 1_000_000_000_000_000_000i128;
-        let pending = accumulated - staker.reward_debt;
-        staker.pending_rewards + pending
+        Ok(())
     }
 
-    /// Query stake details for a user.
-    pub fn get_staker_info(env: Env, user: Address, pool_index: u32) -> StakerInfo {
-        storage::get_staker(&env, &user, pool_index)
+    /// Tokens the contract owes to stakers and must never release via rescue.
+    fn is_protected_token(env: &Env, token: &Address) -> bool {
+        if *token == storage::get_lmnr_token(env) {
+            return true;
+        }
+        (0..storage::get_pool_count(env)).any(|i| {
+            storage::get_pool_config(env, i).lp_token.as_ref() == Some(token)
+                || storage::get_revenue_pots(env, i).contains_key(token.clone())
+                || storage::get_active_campaigns(env, i)
+                    .iter()
+                    .any(|id| storage::get_campaign(env, i, id).is_some_and(|c| c.token == *token))
+        })
     }
 
-    /// Query pool accumulator state.
-    pub fn get_pool_state(env: Env, pool_index: u32) -> PoolState {
-        storage::get_pool_state(&env, pool_index)
+    fn require_admin_or_keeper(env: &Env, caller: &Address) -> Result<(), ContractError> {
+        caller.require_auth();
+        if !Self::is_admin(env, caller) && Some(caller.clone()) != storage::get_keeper(env) {
+            return Err(ContractError::Unauthorized);
+        }
+        Ok(())
     }
 
-    /// Query current epoch Merkle root for a pool.
-    pub fn get_merkle_root(env: Env, pool_index: u32) -> MerkleRootData {
-        storage::get_merkle_root(&env, pool_index)
+    /// The primary admin only. Co-admins reach just the calls that use
+    /// `require_co_admin` (see `AdminSet`).
+    fn require_admin(env: &Env, caller: &Address) -> Result<(), ContractError> {
+        caller.require_auth();
+        if *caller != storage::get_admin(env) {
+            return Err(ContractError::Unauthorized);
+        }
+        Ok(())
     }
 
-    /// Number of registered pools.
-    pub fn get_pool_count(env: Env) -> u32 {
-        storage::get_pool_count(&env)
+    /// The primary admin or a co-admin. Only for the routine calls listed on
+    /// `AdminSet` and for sensitive calls that then go through
+    /// `approve_sensitive`.
+    fn require_co_admin(env: &Env, caller: &Address) -> Result<(), ContractError> {
+        caller.require_auth();
+        if !Self::is_admin(env, caller) {
+            return Err(ContractError::Unauthorized);
+        }
+        Ok(())
     }
 
-    /// Pool hash at a given index.
-    pub fn get_pool_id(env: Env, pool_index: u32) -> BytesN<32> {
-        storage::get_pool_id(&env, pool_index)
+    /// The primary admin or a co-admin from `set_admins`.
+    fn is_admin(env: &Env, address: &Address) -> bool {
+        *address == storage::get_admin(env)
+            || storage::get_admin_set(env).is_some_and(|set| set.members.contains(address))
     }
 
-    /// Global reward rate in LMNR stroops per second.
-    pub fn get_reward_rate(env: Env) -> i128 {
-        storage::get_reward_rate(&env)
+    /// Count `caller`'s approval of a sensitive call and report whether the
+    /// call should run now. Without an admin quorum it always runs. Otherwise
+    /// approvals are collected per `(action, args)` and run the call, and
+    /// are cleared, once `threshold` current admins have made it. Until then
+    /// the caller must return Ok so the approval is kept.
+    fn approve_sensitive(
+        env: &Env,
+        caller: &Address,
+        action: Symbol,
+        args: impl IntoVal<Env, Val>,
+    ) -> bool {
+        let Some(admin_set) = storage::get_admin_set(env) else {
+            return true;
+        };
+        if admin_set.threshold <= 1 {
+            return true;
+        }
+
+        let args: Val = args.into_val(env);
+        let call: Val = (action, args).into_val(env);
+        let hash: BytesN<32> = env.crypto().sha256(&call.to_xdr(env)).into();
+        let mut approvals = Vec::new(env);
+        for approver in storage::get_approvals(env, &hash).iter() {
+            if approver != *caller && Self::is_admin(env, &approver) {
+                approvals.push_back(approver);
+            }
+        }
+        approvals.push_back(caller.clone());
+
+        if approvals.len() >= admin_set.threshold {
+            storage::remove_approvals(env, &hash);
+            return true;
+        }
+        storage::set_approvals(env, &hash, &approvals);
+        events::approval_recorded(env, &hash, caller, approvals.len(), admin_set.threshold);
+        false
     }
 
-    /// Contract's LMNR balance available for rewards.
-    pub fn reward_balance(env: Env) -> i128 {
-        let lmnr_token = storage::get_lmnr_token(&env);
-        let token_client = token::Client::new(&env, &lmnr_token);
-        token_client.balance(&env.current_contract_address())
+    fn require_not_paused(env: &Env) -> Result<(), ContractError> {
+        if storage::is_paused(env) {
+            return Err(ContractError::ContractPaused);
+        }
+        Ok(())
     }
 
-    // ========== Internal Helpers ==========
+    fn require_pool_not_paused(env: &Env, pool_index: u32) -> Result<(), ContractError> {
+        if storage::get_pool_config(env, pool_index).paused {
+            return Err(ContractError::PoolPaused);
+        }
+        Ok(())
+    }
 
-    fn require_admin(env: &Env, caller: &Address) -> Result<(), ContractError> {
-        caller.require_auth();
-        let admin = storage::get_admin(env);
-        if *caller != admin {
-            return Err(ContractError::Unauthorized);
+    fn set_pool_paused(
+        env: &Env,
+        admin: &Address,
+        pool_index: u32,
+        paused: bool,
+    ) -> Result<(), ContractError> {
+        if paused {
+            Self::require_co_admin(env, admin)?;
+        } else {
+            Self::require_admin(env, admin)?;
         }
+        Self::require_valid_pool(env, pool_index)?;
+        storage::extend_instance_ttl(env);
+        Self::log_admin_action(env, admin, symbol_short!("pool_paus"), (pool_index, paused));
+
+        let mut config = storage::get_pool_config(env, pool_index);
+        config.paused = paused;
+        storage::set_pool_config(env, pool_index, &config);
+        events::pool_paused(env, pool_index, paused);
         Ok(())
     }
 
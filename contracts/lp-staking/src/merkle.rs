@@ -1,29 +1,46 @@
 use soroban_sdk::xdr::ToXdr;
 use soroban_sdk::{Address, Bytes, BytesN, Env, Vec};
 
+use crate::storage::MerkleRootData;
+
 const LEAF_PREFIX: u8 = 0x00;
 const NODE_PREFIX: u8 = 0x01;
+const IDENTITY_LEAF_PREFIX: u8 = 0x02;
+const INDEXED_LEAF_PREFIX: u8 = 0x03;
+
+/// Deepest proof accepted: 2^32 leaves is far beyond any snapshot, and the
+/// cap stops a caller burning budget on an arbitrarily long proof.
+pub const MAX_PROOF_DEPTH: u32 = 32;
+
+/// Version of the leaf layouts below, committed to every leaf so snapshot
+/// tooling and the contract can't silently disagree on the format.
+pub const LEAF_VERSION: u8 = 1;
+
+/// Version of the time-weighted balance layout (`compute_twab_leaf`). Each
+/// root records which version its tree was built with.
+pub const TWAB_LEAF_VERSION: u8 = 2;
+
+/// Leaves one address may hold per pool and epoch via indexed leaves (one
+/// bit each in the staker's claimed-leaf bitmap).
+pub const MAX_LEAVES_PER_USER: u32 = 64;
 
 /// Compute a Merkle leaf hash for an LP position.
 ///
-/// leaf = SHA-256(0x00 || pool_index_u32_be || user_address_xdr || lp_balance_i128_be || epoch_id_u64_be)
+/// leaf = SHA-256(0x00 || version || network_id || contract_address_xdr || pool_index_u32_be
+///                || user_address_xdr || lp_balance_i128_be || epoch_id_u64_be)
+///
+/// The network id (SHA-256 of the passphrase) and contract address keep a
+/// proof built for testnet or another deployment from verifying here.
 pub fn compute_leaf(
     env: &Env,
+    contract: &Address,
     pool_index: u32,
     user: &Address,
     lp_balance: i128,
     epoch_id: u64,
 ) -> BytesN<32> {
-    let mut data = Bytes::new(env);
-
     // Domain separator for leaf
-    data.push_back(LEAF_PREFIX);
-
-    // Pool index (4 bytes big-endian)
-    let pool_bytes = pool_index.to_be_bytes();
-    for b in pool_bytes {
-        data.push_back(b);
-    }
+    let mut data = leaf_header(env, LEAF_PREFIX, LEAF_VERSION, contract, pool_index);
 
     // User address as XDR
     let user_bytes = user.to_xdr(env);
@@ -44,36 +61,315 @@ pub fn compute_leaf(
     env.crypto().sha256(&data).into()
 }
 
+/// Compute a v2 leaf: the user's time-weighted average LP balance over an
+/// epoch of `epoch_duration` seconds, in place of a single-ledger balance.
+///
+/// leaf = SHA-256(0x00 || 2 || network_id || contract_address_xdr || pool_index_u32_be
+///                || user_address_xdr || twab_i128_be || epoch_duration_u64_be || epoch_id_u64_be)
+pub fn compute_twab_leaf(
+    env: &Env,
+    contract: &Address,
+    pool_index: u32,
+    user: &Address,
+    twab_balance: i128,
+    epoch_duration: u64,
+    epoch_id: u64,
+) -> BytesN<32> {
+    let mut data = leaf_header(env, LEAF_PREFIX, TWAB_LEAF_VERSION, contract, pool_index);
+    data.append(&user.to_xdr(env));
+
+    let balance_bytes = twab_balance.to_be_bytes();
+    for b in balance_bytes {
+        data.push_back(b);
+    }
+
+    let duration_bytes = epoch_duration.to_be_bytes();
+    for b in duration_bytes {
+        data.push_back(b);
+    }
+
+    let epoch_bytes = epoch_id.to_be_bytes();
+    for b in epoch_bytes {
+        data.push_back(b);
+    }
+
+    env.crypto().sha256(&data).into()
+}
+
+/// `user`'s leaf for `lp_balance` in the layout `root` was built with.
+pub fn compute_root_leaf(
+    env: &Env,
+    contract: &Address,
+    pool_index: u32,
+    user: &Address,
+    lp_balance: i128,
+    root: &MerkleRootData,
+) -> BytesN<32> {
+    if root.leaf_version == TWAB_LEAF_VERSION as u32 {
+        return compute_twab_leaf(
+            env,
+            contract,
+            pool_index,
+            user,
+            lp_balance,
+            root.epoch_duration,
+            root.epoch_id,
+        );
+    }
+    compute_leaf(env, contract, pool_index, user, lp_balance, root.epoch_id)
+}
+
+/// Compute a Merkle leaf hash for an LP position committed to a stable identity
+/// rather than a Stellar address (see `identity_hash`).
+///
+/// leaf = SHA-256(0x02 || version || network_id || contract_address_xdr || pool_index_u32_be
+///                || identity_hash || lp_balance_i128_be || epoch_id_u64_be)
+pub fn compute_identity_leaf(
+    env: &Env,
+    contract: &Address,
+    pool_index: u32,
+    identity: &BytesN<32>,
+    lp_balance: i128,
+    epoch_id: u64,
+) -> BytesN<32> {
+    // Domain separator for identity leaves (distinct from address leaves and nodes)
+    let mut data = leaf_header(
+        env,
+        IDENTITY_LEAF_PREFIX,
+        LEAF_VERSION,
+        contract,
+        pool_index,
+    );
+
+    let identity_bytes: Bytes = identity.clone().into();
+    data.append(&identity_bytes);
+
+    let balance_bytes = lp_balance.to_be_bytes();
+    for b in balance_bytes {
+        data.push_back(b);
+    }
+
+    let epoch_bytes = epoch_id.to_be_bytes();
+    for b in epoch_bytes {
+        data.push_back(b);
+    }
+
+    env.crypto().sha256(&data).into()
+}
+
+/// Compute a Merkle leaf hash for one of several LP positions the snapshot
+/// holds for the same address (e.g. balances of two sub-accounts), told
+/// apart by `leaf_index`.
+///
+/// leaf = SHA-256(0x03 || version || network_id || contract_address_xdr || pool_index_u32_be
+///                || user_address_xdr || leaf_index_u32_be || lp_balance_i128_be || epoch_id_u64_be)
+pub fn compute_indexed_leaf(
+    env: &Env,
+    contract: &Address,
+    pool_index: u32,
+    user: &Address,
+    leaf_index: u32,
+    lp_balance: i128,
+    epoch_id: u64,
+) -> BytesN<32> {
+    let mut data = leaf_header(env, INDEXED_LEAF_PREFIX, LEAF_VERSION, contract, pool_index);
+    data.append(&user.to_xdr(env));
+
+    let index_bytes = leaf_index.to_be_bytes();
+    for b in index_bytes {
+        data.push_back(b);
+    }
+
+    let balance_bytes = lp_balance.to_be_bytes();
+    for b in balance_bytes {
+        data.push_back(b);
+    }
+
+    let epoch_bytes = epoch_id.to_be_bytes();
+    for b in epoch_bytes {
+        data.push_back(b);
+    }
+
+    env.crypto().sha256(&data).into()
+}
+
+/// Fields shared by every leaf kind: prefix || version || network_id ||
+/// contract_address_xdr || pool_index_u32_be
+fn leaf_header(env: &Env, prefix: u8, version: u8, contract: &Address, pool_index: u32) -> Bytes {
+    let mut data = Bytes::new(env);
+    data.push_back(prefix);
+    data.push_back(version);
+
+    let network_id: Bytes = env.ledger().network_id().into();
+    data.append(&network_id);
+    data.append(&contract.to_xdr(env));
+
+    // Pool index (4 bytes big-endian)
+    let pool_bytes = pool_index.to_be_bytes();
+    for b in pool_bytes {
+        data.push_back(b);
+    }
+
+    data
+}
+
+/// Identity committed to by identity leaves: SHA-256(ed25519_public_key).
+pub fn identity_hash(env: &Env, public_key: &BytesN<32>) -> BytesN<32> {
+    let key_bytes: Bytes = public_key.clone().into();
+    env.crypto().sha256(&key_bytes).into()
+}
+
+/// Pool id for a deposit-mode pool, which has no SDEX pool id of its own:
+/// SHA-256(lp_token_address_xdr).
+pub fn deposit_pool_id(env: &Env, lp_token: &Address) -> BytesN<32> {
+    env.crypto().sha256(&lp_token.to_xdr(env)).into()
+}
+
+/// Message the identity key signs to bind itself to `user`.
+///
+/// payload = contract_address_xdr || user_address_xdr || nonce_u64_be
+///
+/// The contract address scopes the signature to this deployment and the nonce
+/// stops an old binding signature from being replayed after a rotation.
+pub fn identity_binding_payload(env: &Env, contract: &Address, user: &Address, nonce: u64) -> Bytes {
+    let mut data = Bytes::new(env);
+    data.append(&contract.to_xdr(env));
+    data.append(&user.to_xdr(env));
+
+    let nonce_bytes = nonce.to_be_bytes();
+    for b in nonce_bytes {
+        data.push_back(b);
+    }
+
+    data
+}
+
+/// Message the root oracle signs to post `root` as epoch `epoch_id` of a
+/// pool through `set_merkle_root_signed`.
+///
+/// payload = contract_address_xdr || pool_index_u32_be || epoch_id_u64_be
+///           || root || snapshot_ledger_u32_be
+///
+/// Naming the epoch the root opens makes each signature good for one post.
+pub fn root_attestation_payload(
+    env: &Env,
+    contract: &Address,
+    pool_index: u32,
+    epoch_id: u64,
+    root: &BytesN<32>,
+    snapshot_ledger: u32,
+) -> Bytes {
+    let mut data = Bytes::new(env);
+    data.append(&contract.to_xdr(env));
+    for b in pool_index.to_be_bytes() {
+        data.push_back(b);
+    }
+    for b in epoch_id.to_be_bytes() {
+        data.push_back(b);
+    }
+
+    let root_bytes: Bytes = root.clone().into();
+    data.append(&root_bytes);
+    for b in snapshot_ledger.to_be_bytes() {
+        data.push_back(b);
+    }
+
+    data
+}
+
+/// Sibling hashes of a proof, leaf level first: either one `BytesN<32>`
+/// per level or the same hashes packed back to back in a single `Bytes`,
+/// which is smaller in a transaction and one host object instead of one
+/// per level.
+pub trait Proof {
+    fn depth(&self) -> u32;
+    fn sibling(&self, level: u32) -> BytesN<32>;
+}
+
+impl Proof for Vec<BytesN<32>> {
+    fn depth(&self) -> u32 {
+        self.len()
+    }
+
+    fn sibling(&self, level: u32) -> BytesN<32> {
+        self.get(level).unwrap()
+    }
+}
+
+impl Proof for Bytes {
+    /// Whole 32-byte siblings only; check `is_packed_proof` first.
+    fn depth(&self) -> u32 {
+        self.len() / 32
+    }
+
+    fn sibling(&self, level: u32) -> BytesN<32> {
+        self.slice(level * 32..(level + 1) * 32).try_into().unwrap()
+    }
+}
+
+/// Whether `proof` is a whole number of 32-byte siblings.
+pub fn is_packed_proof(proof: &Bytes) -> bool {
+    proof.len().is_multiple_of(32)
+}
+
 /// Verify a Merkle proof against a known root.
 ///
 /// Uses canonical ordering: internal node = SHA-256(0x01 || min(left, right) || max(left, right))
-pub fn verify_proof(env: &Env, leaf: &BytesN<32>, proof: &Vec<BytesN<32>>, root: &BytesN<32>) -> bool {
+pub fn verify_proof(env: &Env, leaf: &BytesN<32>, proof: &impl Proof, root: &BytesN<32>) -> bool {
     let mut current = leaf.clone();
 
-    for i in 0..proof.len() {
-        let sibling = proof.get(i).unwrap();
-        current = hash_pair(env, &current, &sibling);
+    for i in 0..proof.depth() {
+        current = hash_pair(env, &current, &proof.sibling(i));
     }
 
     current == *root
 }
 
-/// Hash two nodes together with canonical ordering (smaller first).
-fn hash_pair(env: &Env, a: &BytesN<32>, b: &BytesN<32>) -> BytesN<32> {
-    let mut data = Bytes::new(env);
-    data.push_back(NODE_PREFIX);
+/// Verify a positional Merkle proof, for pools whose snapshot tooling keeps
+/// leaf order instead of sorting pairs.
+///
+/// Bit `i` of `path` gives the side of `proof[i]`: 1 if the sibling is the
+/// left child, 0 if it is the right. Internal node = SHA-256(0x01 || left || right)
+pub fn verify_positional_proof(
+    env: &Env,
+    leaf: &BytesN<32>,
+    proof: &impl Proof,
+    path: u32,
+    root: &BytesN<32>,
+) -> bool {
+    let mut current = leaf.clone();
 
-    // Canonical ordering: smaller hash first
-    let a_bytes: Bytes = a.clone().into();
-    let b_bytes: Bytes = b.clone().into();
+    for i in 0..proof.depth() {
+        let sibling = proof.sibling(i);
+        current = if (path >> i) & 1 == 1 {
+            hash_ordered(env, &sibling, &current)
+        } else {
+            hash_ordered(env, &current, &sibling)
+        };
+    }
 
-    if a_bytes <= b_bytes {
-        data.append(&a_bytes);
-        data.append(&b_bytes);
+    current == *root
+}
+
+/// Hash two nodes together with canonical ordering (smaller first).
+pub(crate) fn hash_pair(env: &Env, a: &BytesN<32>, b: &BytesN<32>) -> BytesN<32> {
+    // Canonical ordering: smaller hash first
+    if a.to_array() <= b.to_array() {
+        hash_ordered(env, a, b)
     } else {
-        data.append(&b_bytes);
-        data.append(&a_bytes);
+        hash_ordered(env, b, a)
     }
+}
+
+/// Hash two nodes together in the order given.
+pub(crate) fn hash_ordered(env: &Env, left: &BytesN<32>, right: &BytesN<32>) -> BytesN<32> {
+    let mut data = Bytes::new(env);
+    data.push_back(NODE_PREFIX);
+
+    let left_bytes: Bytes = left.clone().into();
+    let right_bytes: Bytes = right.clone().into();
+    data.append(&left_bytes);
+    data.append(&right_bytes);
 
     env.crypto().sha256(&data).into()
 }
@@ -0,0 +1,50 @@
+use soroban_sdk::Env;
+
+use crate::{rewards, storage};
+
+/// Storage layout this wasm reads and writes. Bump it together with a new
+/// step in `migrate` whenever a stored struct's layout changes.
+pub const SCHEMA_VERSION: u32 = 6;
+
+/// Run every migration step between the stored schema version and
+/// `SCHEMA_VERSION`, in order. Returns the version now stored.
+///
+/// Steps that must rewrite per-staker entries should do so lazily (decode
+/// the old layout in the storage getter and write back the new one) rather
+/// than here, since a single call cannot visit every persistent entry.
+pub fn migrate(env: &Env) -> u32 {
+    let mut version = storage::get_schema_version(env);
+
+    while version < SCHEMA_VERSION {
+        match version {
+            // v0 -> v1: versioning introduced; layouts unchanged
+            0 => {}
+            // v1 -> v2: lazy rate checkpoints; pools gain an emission index
+            // and the cross-pool totals are built once here
+            1 => rewards::rebuild_emission_totals(env),
+            // v2 -> v3: staker entries gain cooldown_until, weight and
+            // streak; `get_staker` decodes the old layout until each entry
+            // is next written
+            2 => {}
+            // v3 -> v4: roots gain leaf_version, epoch_duration and
+            // declared_total; the root getters decode the old layout as
+            // undeclared v1 roots
+            3 => {}
+            // v4 -> v5: each pool's state and current root move into one
+            // `PoolEntry`, halving the entries a stake or claim touches
+            4 => {
+                for i in 0..storage::get_pool_count(env) {
+                    storage::combine_pool_entry(env, i);
+                }
+            }
+            // v5 -> v6: emitting pools leave out epoch-budget and partner
+            // token pools, which never earn the global rate
+            5 => rewards::rebuild_emission_totals(env),
+            _ => unreachable!(),
+        }
+        version += 1;
+    }
+
+    storage::set_schema_version(env, version);
+    version
+}
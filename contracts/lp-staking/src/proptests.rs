@@ -0,0 +1,163 @@
+#![cfg(test)]
+#![allow(clippy::inconsistent_digit_grouping)]
+// Randomised operation sequences checked against the reward invariants.
+// Each case runs against a fresh `setup_env` contract with up to four
+// stakers proving fixed balances in one pool.
+extern crate std;
+
+use proptest::prelude::*;
+use soroban_sdk::testutils::Address as _;
+use soroban_sdk::{token, Address, BytesN, Vec};
+use std::vec::Vec as StdVec;
+
+use crate::merkle;
+use crate::test::{make_pool_id, set_ledger, setup_env, TestEnv};
+use crate::testutils::build_merkle_tree;
+use crate::LpStakingContractClient;
+
+const USERS: usize = 4;
+const FUNDED: i128 = 50_000_0000000; // setup_env's contract funding
+
+#[derive(Clone, Debug)]
+enum Op {
+    Stake(usize),
+    Claim(usize),
+    Unstake(usize),
+    Rotate,
+    SetRate(i128),
+    Advance(u64),
+}
+
+fn op_strategy() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        3 => (0..USERS).prop_map(Op::Stake),
+        2 => (0..USERS).prop_map(Op::Claim),
+        1 => (0..USERS).prop_map(Op::Unstake),
+        1 => Just(Op::Rotate),
+        1 => (0i128..10_000_000).prop_map(Op::SetRate),
+        3 => (1u64..600).prop_map(Op::Advance),
+    ]
+}
+
+struct Harness {
+    t: TestEnv,
+    users: StdVec<Address>,
+    balances: StdVec<i128>,
+    epoch_id: u64,
+    proofs: Vec<Vec<BytesN<32>>>,
+    claimed: i128,
+}
+
+impl Harness {
+    fn new(balances: StdVec<i128>) -> Self {
+        let t = setup_env();
+        let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+        client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+        let users = (0..USERS).map(|_| Address::generate(&t.env)).collect();
+        let proofs = Vec::new(&t.env);
+        let mut harness = Harness {
+            t,
+            users,
+            balances,
+            epoch_id: 0,
+            proofs,
+            claimed: 0,
+        };
+        harness.rotate();
+        harness
+    }
+
+    fn client(&self) -> LpStakingContractClient<'_> {
+        LpStakingContractClient::new(&self.t.env, &self.t.contract_id)
+    }
+
+    // Post the next epoch's root, snapshotting the current ledger.
+    fn rotate(&mut self) {
+        self.epoch_id += 1;
+        let leaves: StdVec<BytesN<32>> = (0..USERS)
+            .map(|i| {
+                merkle::compute_leaf(
+                    &self.t.env,
+                    &self.t.contract_id,
+                    0,
+                    &self.users[i],
+                    self.balances[i],
+                    self.epoch_id,
+                )
+            })
+            .collect();
+        let (root, proofs) = build_merkle_tree(&self.t.env, &leaves);
+        let sequence = self.t.env.ledger().sequence();
+        self.client()
+            .set_merkle_root(&self.t.admin, &0, &root, &sequence);
+        self.proofs = proofs;
+    }
+
+    fn apply(&mut self, op: &Op) {
+        let client = self.client();
+        match *op {
+            Op::Stake(i) => {
+                let proof = self.proofs.get(i as u32).unwrap();
+                let _ = client.try_stake(&self.users[i], &0, &self.balances[i], &proof);
+            }
+            Op::Claim(i) => {
+                if let Ok(Ok(paid)) = client.try_claim(&self.users[i], &0) {
+                    self.claimed += paid;
+                }
+            }
+            Op::Unstake(i) => {
+                let _ = client.try_unstake(&self.users[i], &0);
+            }
+            Op::Rotate => {
+                self.advance(1);
+                self.rotate();
+            }
+            Op::SetRate(rate) => client.set_reward_rate(&self.t.admin, &rate, &true),
+            Op::Advance(seconds) => self.advance(seconds),
+        }
+    }
+
+    fn advance(&self, seconds: u64) {
+        let ledger = self.t.env.ledger();
+        set_ledger(
+            &self.t.env,
+            ledger.timestamp() + seconds,
+            ledger.sequence() + 1,
+        );
+    }
+
+    fn check_invariants(&self) {
+        let client = self.client();
+        let mut active_stake = 0;
+        for user in self.users.iter() {
+            assert!(client.pending_reward(user, &0) >= 0);
+            if let Ok(Ok(staker)) = client.try_get_staker_info(user, &0) {
+                assert!(staker.staked_amount >= 0);
+                active_stake += staker.staked_amount;
+            }
+        }
+        assert_eq!(client.get_pool_state(&0).total_staked, active_stake);
+
+        assert!(self.claimed <= FUNDED);
+        assert_eq!(client.get_accounting().total_claimed, self.claimed);
+        let balance =
+            token::Client::new(&self.t.env, &self.t.lmnr_token).balance(&self.t.contract_id);
+        assert_eq!(balance, FUNDED - self.claimed);
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(24))]
+
+    #[test]
+    fn reward_invariants_hold(
+        balances in prop::collection::vec(1i128..100_000_0000000, USERS),
+        ops in prop::collection::vec(op_strategy(), 1..40),
+    ) {
+        let mut harness = Harness::new(balances);
+        for op in ops.iter() {
+            harness.apply(op);
+            harness.check_invariants();
+        }
+    }
+}
@@ -1,70 +1,431 @@
-use soroban_sdk::Env;
+use soroban_sdk::{Address, Env, Map};
 
-use crate::storage::{self, PoolState, StakerInfo};
+use crate::campaigns;
+use crate::events;
+use crate::storage::{self, Checkpoint, EmissionTotals, PoolState, RevenueShare, StakerInfo};
+use crate::BPS_DENOMINATOR;
 
-/// Precision multiplier for accumulated reward per share (1e18).
-const PRECISION: i128 = 1_000_000_000_000_000_000;
+/// Precision multiplier for a pool whose LP amounts carry `lp_decimals`
+/// decimals and whose rewards carry `reward_decimals`: 1e18 for 7-decimal
+/// SDEX LP paid in 7-decimal LMNR, one order of magnitude less per extra
+/// decimal on either side, floored at 1e9 for 18-decimal tokens so
+/// `staked_amount * acc_reward_per_share` and `rewards * precision` stay
+/// within i128.
+pub fn precision_for_decimals(lp_decimals: u32, reward_decimals: u32) -> i128 {
+    let exponent = (25 + storage::LMNR_DECIMALS)
+        .saturating_sub(lp_decimals + reward_decimals)
+        .clamp(9, 18);
+    10i128.pow(exponent)
+}
+
+/// Precision multiplier for accumulated reward per share in a pool.
+pub fn pool_precision(env: &Env, pool_index: u32) -> i128 {
+    precision_for_decimals(
+        storage::get_pool_config(env, pool_index).lp_decimals,
+        storage::get_reward_decimals(env, pool_index),
+    )
+}
+
+/// Reward credited to each emitting pool since genesis, as of now.
+pub fn emission_index(env: &Env) -> i128 {
+    emission_index_at(env, env.ledger().timestamp())
+}
+
+/// Emission index at `at`, assuming the current global rate holds until then.
+fn emission_index_at(env: &Env, at: u64) -> i128 {
+    let checkpoint = storage::get_rate_checkpoint(env);
+    let elapsed = at.saturating_sub(checkpoint.timestamp) as i128;
+    checkpoint.cumulative + elapsed * checkpoint.rate
+}
 
 /// Update the pool's accumulated reward per share to the current time.
 /// Returns the updated PoolState.
 pub fn update_pool(env: &Env, pool_index: u32) -> PoolState {
     let mut state = storage::get_pool_state(env, pool_index);
     let now = env.ledger().timestamp();
-    let reward_rate = storage::get_reward_rate(env);
+    campaigns::update(env, pool_index, state.total_staked, now);
+    let emission_index = emission_index(env);
+    let scheduled = scheduled_rewards(env, pool_index, state.last_reward_time, now);
+    let oracle_rate = storage::get_oracle_rate(env, pool_index);
+    let oracle_rewards = oracle_rate * (now - state.last_reward_time) as i128;
+    let new_rewards = global_rewards(env, pool_index, emission_index) + scheduled + oracle_rewards;
+    let elapsed = now.saturating_sub(state.last_reward_time);
+    if state.total_staked > 0 && new_rewards == 0 && elapsed > 0 {
+        record_unaccrued(env, pool_index, elapsed);
+    }
 
-    if now > state.last_reward_time && state.total_staked > 0 && reward_rate > 0 {
-        let elapsed = (now - state.last_reward_time) as i128;
-        let new_rewards = elapsed * reward_rate;
-        state.acc_reward_per_share += (new_rewards * PRECISION) / state.total_staked;
+    // Scheduled rewards are spent whether or not anyone is staked
+    if (scheduled > 0 || oracle_rewards != 0) && storage::pays_lmnr(env, pool_index) {
+        let mut totals = storage::get_emission_totals(env);
+        totals.scheduled -= scheduled;
+        totals.oracle_rate_time_sum += oracle_rewards;
+        storage::set_emission_totals(env, &totals);
     }
 
+    if state.total_staked > 0 && new_rewards > 0 {
+        // What doesn't divide evenly is folded into the next update, so the
+        // accumulator credits exactly `new_rewards` over time
+        let precision = pool_precision(env, pool_index);
+        let scaled = new_rewards * precision + storage::get_pool_remainder(env, pool_index);
+        state.acc_reward_per_share += scaled / state.total_staked;
+        storage::set_pool_remainder(env, pool_index, scaled % state.total_staked);
+
+        let liability = storage::get_pool_liability(env, pool_index);
+        storage::set_pool_liability(env, pool_index, liability + new_rewards);
+    } else if new_rewards > 0 && !storage::get_pool_config(env, pool_index).removed {
+        // Nobody to credit: hold the rewards rather than let them lapse
+        let carry = storage::get_pool_carry(env, pool_index);
+        storage::set_pool_carry(env, pool_index, carry + new_rewards);
+    }
+
+    storage::set_pool_emission_index(env, pool_index, emission_index);
     state.last_reward_time = now;
     storage::set_pool_state(env, pool_index, &state);
     state
 }
 
+/// Book `seconds` the pool's stakers went unrewarded (see `UnaccruedTime`)
+/// rather than let `update_pool` pass over them silently. Emissions count
+/// as paused when the pool holds earmarked LMNR it does not yet owe.
+fn record_unaccrued(env: &Env, pool_index: u32, seconds: u64) {
+    let earmark = storage::get_pool_earmark(env, pool_index);
+    let paused = earmark > storage::get_pool_liability(env, pool_index);
+    let mut time = storage::get_unaccrued_time(env, pool_index);
+    if paused {
+        time.paused += seconds;
+    } else {
+        time.unbudgeted += seconds;
+    }
+    storage::set_unaccrued_time(env, pool_index, &time);
+    events::time_unaccrued(env, pool_index, seconds, paused);
+}
+
+/// Global-rate reward the pool has earned since its emission index was last
+/// moved to `emission_index`. Pools on an epoch budget or paying a partner
+/// token earn none and are left out of `EmissionTotals::emitting_pools`;
+/// their index still advances so switching mode starts from now.
+fn global_rewards(env: &Env, pool_index: u32, emission_index: i128) -> i128 {
+    if !storage::earns_global_rate(&storage::get_pool_config(env, pool_index)) {
+        return 0;
+    }
+    emission_index - storage::get_pool_emission_index(env, pool_index)
+}
+
+/// Reward from the pool's controller schedule between `from` and `to`.
+pub fn scheduled_rewards(env: &Env, pool_index: u32, from: u64, to: u64) -> i128 {
+    match storage::get_reward_schedule(env, pool_index) {
+        Some(schedule) if schedule.period_finish.min(to) > from => {
+            schedule.rate * (schedule.period_finish.min(to) - from) as i128
+        }
+        _ => 0,
+    }
+}
+
+/// Upper bound on what all pools owe right now, including accrual not yet
+/// booked by `update_pool` (oracle rates included), controller rewards
+/// still to be emitted and carried idle rewards,
+/// computed without visiting the pools.
+pub fn outstanding_liability(env: &Env) -> i128 {
+    let totals = storage::get_emission_totals(env);
+    let now = env.ledger().timestamp() as i128;
+    totals.liability + totals.emitting_pools as i128 * emission_index(env) - totals.index_sum
+        + totals.scheduled
+        + totals.carry
+        + totals.oracle_rate_sum * now
+        - totals.oracle_rate_time_sum
+}
+
+/// Recompute `EmissionTotals` from every pool, stamping an emission index on
+/// pools that predate them. Only for migration and seeding.
+pub fn rebuild_emission_totals(env: &Env) {
+    let mut totals = EmissionTotals::default();
+    for i in 0..storage::get_pool_count(env) {
+        let emission_index = storage::get_pool_emission_index(env, i);
+        storage::set_pool_emission_index(env, i, emission_index);
+        let state = storage::get_pool_state(env, i);
+        if storage::pays_lmnr(env, i) {
+            totals.liability += storage::get_pool_liability(env, i);
+            totals.carry += storage::get_pool_carry(env, i);
+            totals.scheduled += scheduled_rewards(env, i, state.last_reward_time, u64::MAX);
+        }
+        let oracle_rate = storage::get_oracle_rate(env, i);
+        totals.oracle_rate_sum += oracle_rate;
+        totals.oracle_rate_time_sum += oracle_rate * state.last_reward_time as i128;
+        if storage::is_emitting(env, i) {
+            totals.emitting_pools += 1;
+            totals.index_sum += emission_index;
+        }
+    }
+    storage::set_emission_totals(env, &totals);
+}
+
+/// Checkpoints kept per pool; the oldest is dropped beyond this.
+pub const MAX_CHECKPOINTS: u32 = 30;
+
+/// Append `state` to the pool's checkpoint history. A second checkpoint in
+/// the same ledger second replaces the first, so repeated calls are
+/// idempotent.
+pub fn record_checkpoint(env: &Env, pool_index: u32, state: &PoolState) {
+    let mut checkpoints = storage::get_checkpoints(env, pool_index);
+    let checkpoint = Checkpoint {
+        timestamp: state.last_reward_time,
+        acc_reward_per_share: state.acc_reward_per_share,
+        total_staked: state.total_staked,
+    };
+
+    match checkpoints.last() {
+        Some(last) if last.timestamp == checkpoint.timestamp => {
+            checkpoints.set(checkpoints.len() - 1, checkpoint);
+        }
+        _ => {
+            if checkpoints.len() >= MAX_CHECKPOINTS {
+                checkpoints.pop_front();
+            }
+            checkpoints.push_back(checkpoint);
+        }
+    }
+
+    storage::set_checkpoints(env, pool_index, &checkpoints);
+}
+
 /// Calculate pending rewards for a staker based on the current pool state.
 /// Does NOT update pool state — caller must call update_pool first.
-pub fn calculate_pending(pool_state: &PoolState, staker: &StakerInfo) -> i128 {
-    if staker.staked_amount == 0 {
+pub fn calculate_pending(pool_state: &PoolState, staker: &StakerInfo, precision: i128) -> i128 {
+    calculate_pending_at(pool_state.acc_reward_per_share, staker, precision)
+}
+
+/// Calculate pending rewards for a current-epoch staker against an arbitrary
+/// accumulator value (e.g. one simulated to the current time).
+pub fn calculate_pending_at(acc_reward_per_share: i128, staker: &StakerInfo, precision: i128) -> i128 {
+    if staker.weight == 0 {
         return staker.pending_rewards;
     }
 
-    let accumulated = (staker.staked_amount * pool_state.acc_reward_per_share) / PRECISION;
+    let accumulated = (staker.weight * acc_reward_per_share) / precision;
     let pending = accumulated - staker.reward_debt;
     staker.pending_rewards + pending
 }
 
+/// `calculate_pending_at` for `user`'s entry in `pool_index` settled at
+/// `at`, less what a warm-up forfeits: accrual since the reward debt was
+/// set counts only for the time after the warm-up ends, pro rata.
+pub fn calculate_pending_warm(
+    env: &Env,
+    user: &Address,
+    pool_index: u32,
+    acc_reward_per_share: i128,
+    staker: &StakerInfo,
+    precision: i128,
+    at: u64,
+) -> i128 {
+    let pending = calculate_pending_at(acc_reward_per_share, staker, precision);
+    let Some(warmup) = storage::get_warmup(env, user, pool_index) else {
+        return pending;
+    };
+    if at <= warmup.until {
+        return staker.pending_rewards;
+    }
+    if warmup.from >= warmup.until {
+        return pending;
+    }
+    let accrued = pending - staker.pending_rewards;
+    let warm = (at - warmup.until) as i128;
+    staker.pending_rewards + accrued * warm / (at - warmup.from) as i128
+}
+
 /// View-only: simulate the accumulated reward per share at the current time
 /// without writing to storage. Used for pending_reward queries.
 pub fn simulate_acc_reward(env: &Env, pool_index: u32) -> i128 {
+    simulate_acc_reward_at(env, pool_index, env.ledger().timestamp())
+}
+
+/// View-only: project the accumulated reward per share to `at` (not before
+/// the last update), assuming today's rates and total stake hold until then.
+pub fn simulate_acc_reward_at(env: &Env, pool_index: u32, at: u64) -> i128 {
     let state = storage::get_pool_state(env, pool_index);
-    let now = env.ledger().timestamp();
-    let reward_rate = storage::get_reward_rate(env);
+    let new_rewards = unbooked_rewards_at(env, pool_index, &state, at);
 
     let mut acc = state.acc_reward_per_share;
-    if now > state.last_reward_time && state.total_staked > 0 && reward_rate > 0 {
-        let elapsed = (now - state.last_reward_time) as i128;
-        let new_rewards = elapsed * reward_rate;
-        acc += (new_rewards * PRECISION) / state.total_staked;
+    if state.total_staked > 0 && new_rewards > 0 {
+        let scaled = new_rewards * pool_precision(env, pool_index)
+            + storage::get_pool_remainder(env, pool_index);
+        acc += scaled / state.total_staked;
     }
     acc
 }
 
+/// View-only: the pool's carried idle rewards including accrual since the
+/// last update_pool, matching what `update_pool` would carry.
+pub fn simulate_carry(env: &Env, pool_index: u32) -> i128 {
+    let state = storage::get_pool_state(env, pool_index);
+    let carry = storage::get_pool_carry(env, pool_index);
+    let new_rewards = unbooked_rewards(env, pool_index, &state);
+    if state.total_staked > 0
+        || new_rewards <= 0
+        || storage::get_pool_config(env, pool_index).removed
+    {
+        return carry;
+    }
+    carry + new_rewards
+}
+
+/// Reward the pool has earned since its last update_pool.
+fn unbooked_rewards(env: &Env, pool_index: u32, state: &PoolState) -> i128 {
+    unbooked_rewards_at(env, pool_index, state, env.ledger().timestamp())
+}
+
+/// Reward the pool will have earned between its last update_pool and `at`.
+fn unbooked_rewards_at(env: &Env, pool_index: u32, state: &PoolState, at: u64) -> i128 {
+    let at = at.max(state.last_reward_time);
+    global_rewards(env, pool_index, emission_index_at(env, at))
+        + scheduled_rewards(env, pool_index, state.last_reward_time, at)
+        + storage::get_oracle_rate(env, pool_index) * (at - state.last_reward_time) as i128
+}
+
+/// View-only: the pool's outstanding liability including accrual since the
+/// last update_pool, matching what `update_pool` would book.
+/// This is an upper bound on claimable rewards: per-staker rounding, stale
+/// stakers' forfeited accrual and accrual forfeited to warm-ups are never
+/// paid out.
+pub fn simulate_liability(env: &Env, pool_index: u32) -> i128 {
+    let state = storage::get_pool_state(env, pool_index);
+    let liability = storage::get_pool_liability(env, pool_index);
+    let new_rewards = unbooked_rewards(env, pool_index, &state);
+    if state.total_staked > 0 && new_rewards > 0 {
+        return liability + new_rewards;
+    }
+    liability
+}
+
 /// Calculate pending rewards for a stale staker using the previous epoch's accumulator snapshot.
 /// Stale stakers earned rewards up to the epoch change but not after.
-pub fn calculate_pending_stale(pool_state: &PoolState, staker: &StakerInfo) -> i128 {
-    if staker.staked_amount == 0 {
+pub fn calculate_pending_stale(pool_state: &PoolState, staker: &StakerInfo, precision: i128) -> i128 {
+    if staker.weight == 0 {
         return staker.pending_rewards;
     }
 
-    let accumulated =
-        (staker.staked_amount * pool_state.prev_acc_reward_per_share) / PRECISION;
+    let accumulated = (staker.weight * pool_state.prev_acc_reward_per_share) / precision;
     let pending = accumulated - staker.reward_debt;
     staker.pending_rewards + pending
 }
 
-/// Compute the reward_debt for a staker given their staked amount and current accumulator.
-pub fn compute_reward_debt(staked_amount: i128, acc_reward_per_share: i128) -> i128 {
-    (staked_amount * acc_reward_per_share) / PRECISION
+/// Compute the reward_debt for a staker given their stake weight and current accumulator.
+pub fn compute_reward_debt(weight: i128, acc_reward_per_share: i128, precision: i128) -> i128 {
+    (weight * acc_reward_per_share) / precision
+}
+
+/// Weight a staker holding `amount` LP carries in the pool's accumulator,
+/// having re-proven `streak` consecutive epochs: `amount` up to the pool's
+/// `stake_cap`, or its integer square root in a pool with `sqrt_weighting`
+/// (the deposit side of a hybrid pool follows its Merkle pool), plus any
+/// loyalty bonus.
+pub fn stake_weight(env: &Env, pool_index: u32, amount: i128, streak: u32) -> i128 {
+    let config = storage::get_pool_config(env, pool_index);
+    let amount = if config.stake_cap > 0 {
+        amount.min(config.stake_cap)
+    } else {
+        amount
+    };
+    let sqrt_weighting = match config.shared_with {
+        Some(merkle_pool) => storage::get_pool_config(env, merkle_pool).sqrt_weighting,
+        None => config.sqrt_weighting,
+    };
+    let base = if sqrt_weighting {
+        isqrt(amount)
+    } else {
+        amount
+    };
+
+    let bonus_bps = streak
+        .saturating_mul(config.loyalty_step_bps)
+        .min(config.loyalty_max_bps);
+    base * (BPS_DENOMINATOR + bonus_bps as i128) / BPS_DENOMINATOR
+}
+
+/// Largest integer whose square is at most `n` (0 for n <= 0).
+pub fn isqrt(n: i128) -> i128 {
+    if n <= 0 {
+        return 0;
+    }
+    // Newton's method from an overestimate converges downwards
+    let mut x = n;
+    let mut y = n / 2 + 1;
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    x
+}
+
+/// Share each revenue pot's pending amount across the pool's stake weight,
+/// at a root rotation. What doesn't divide evenly stays pending, as does
+/// the whole pot while nobody is staked.
+pub fn distribute_revenue(env: &Env, pool_index: u32, total_staked: i128) {
+    let mut pots = storage::get_revenue_pots(env, pool_index);
+    if pots.is_empty() || total_staked <= 0 {
+        return;
+    }
+    let precision = pool_precision(env, pool_index);
+    for (token, mut pot) in pots.iter() {
+        let per_share = pot.pending * precision / total_staked;
+        pot.acc_per_share += per_share;
+        pot.pending -= per_share * total_staked / precision;
+        pots.set(token, pot);
+    }
+    storage::set_revenue_pots(env, pool_index, &pots);
+}
+
+/// `user`'s revenue shares in a pool with everything distributed while
+/// they held `old_weight` credited to `owed`, rebased on `new_weight`.
+pub fn revenue_shares(
+    env: &Env,
+    user: &Address,
+    pool_index: u32,
+    old_weight: i128,
+    new_weight: i128,
+) -> Map<Address, RevenueShare> {
+    let state_pool = storage::get_pool_config(env, pool_index)
+        .shared_with
+        .unwrap_or(pool_index);
+    let mut shares = storage::get_revenue_shares(env, user, pool_index);
+    let pots = storage::get_revenue_pots(env, state_pool);
+    if pots.is_empty() {
+        return shares;
+    }
+    let precision = pool_precision(env, state_pool);
+    for (token, pot) in pots.iter() {
+        let mut share = shares.get(token.clone()).unwrap_or_default();
+        share.owed += compute_reward_debt(old_weight, pot.acc_per_share, precision) - share.debt;
+        share.debt = compute_reward_debt(new_weight, pot.acc_per_share, precision);
+        shares.set(token, share);
+    }
+    shares
+}
+
+/// Settle `user`'s revenue and campaign shares on a change of stake
+/// weight. Runs on every staker write, so weights never change between
+/// settlements.
+pub fn settle_shares(
+    env: &Env,
+    user: &Address,
+    pool_index: u32,
+    old_weight: i128,
+    new_weight: i128,
+) {
+    settle_revenue(env, user, pool_index, old_weight, new_weight);
+    campaigns::settle(env, user, pool_index, old_weight, new_weight);
+}
+
+/// Revenue half of `settle_shares`.
+pub fn settle_revenue(
+    env: &Env,
+    user: &Address,
+    pool_index: u32,
+    old_weight: i128,
+    new_weight: i128,
+) {
+    let shares = revenue_shares(env, user, pool_index, old_weight, new_weight);
+    if !shares.is_empty() {
+        storage::set_revenue_shares(env, user, pool_index, &shares);
+    }
 }
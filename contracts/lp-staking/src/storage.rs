@@ -1,10 +1,16 @@
-use soroban_sdk::{contracttype, Address, BytesN, Env};
+use soroban_sdk::{
+    contracttype, Address, BytesN, Env, FromVal, IntoVal, Map, String, Symbol, Val, Vec,
+};
 
-// Storage TTL constants (in ledgers, ~5 seconds each)
+// Default storage TTLs (in ledgers, ~5 seconds each); see TtlConfig
 const INSTANCE_TTL_THRESHOLD: u32 = 17_280; // ~1 day
 const INSTANCE_TTL_EXTEND: u32 = 518_400; // ~30 days
 const PERSISTENT_TTL_THRESHOLD: u32 = 17_280; // ~1 day
 const PERSISTENT_TTL_EXTEND: u32 = 518_400; // ~30 days
+const STAKER_TTL_EXTEND: u32 = 2_073_600; // ~120 days
+const HISTORICAL_ROOT_TTL: u32 = 518_400; // ~30 days
+const APPROVAL_TTL: u32 = 120_960; // ~7 days for a quorum to gather
+const LEDGER_SECONDS: u64 = 5;
 
 #[contracttype]
 #[derive(Clone)]
@@ -18,10 +24,87 @@ pub enum DataKey {
     PoolState(u32),
     MerkleRoot(u32),
     Staker(Address, u32),
+    HideClaimDestination,
+    EpochListeners,
+    MinRunwayDays,
+    PoolLiability(u32),
+    PoolConfig(u32),
+    IdentityBinding(BytesN<32>),
+    IdentityEpoch(BytesN<32>, u32),
+    Paused,
+    PendingWithdrawal,
+    ClaimStats(u32, u64),
+    LastClaimEpoch(Address, u32),
+    Accounting,
+    Keeper,
+    Checkpoints(u32),
+    PartialPayouts,
+    StakerCount(u32),
+    StakerAt(u32, u32),
+    StakerSlot(Address, u32),
+    UserClaimed(Address, u32),
+    UserClaimedTotal(Address),
+    Activated,
+    SchemaVersion,
+    TtlConfig,
+    HistoricalRoot(u32, u64),
+    PruneBounty,
+    RateCheckpoint,
+    PoolEmissionIndex(u32),
+    EmissionTotals,
+    MinStake,
+    Blocked(Address),
+    ComplianceHook,
+    StakeHook,
+    EmissionsController,
+    RewardSchedule(u32),
+    RateOracle,
+    OracleRate(u32),
+    MinClaim,
+    PoolCarry(u32),
+    PoolRemainder(u32),
+    SlashLog,
 }
 
+/// Keys added after `DataKey` reached the 50-variant limit on contract
+/// enums. Variant names must not repeat any in `DataKey`, since a key's
+/// encoding is its variant name and fields.
 #[contracttype]
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone)]
+pub enum DataKeyExt {
+    CorrectionRoot(u32),
+    RateBounds,
+    AdminLog,
+    RateHistoryCount,
+    RateHistory(u32),
+    ActiveStakers(u32),
+    AdminSet,
+    Approvals(BytesN<32>),
+    ClaimedLeaves(Address, u32),
+    Warmup(Address, u32),
+    ProvenTotal(u32),
+    RevenuePots(u32),
+    RevenueShares(Address, u32),
+    PoolEarmark(u32),
+    Campaign(u32, u32),
+    CampaignCount(u32),
+    ActiveCampaigns(u32),
+    CampaignShares(Address, u32),
+    ClaimDeadline,
+    LastActive(Address, u32),
+    KeeperBounties,
+    BountyUsage,
+    RootOracles,
+    RewardDecimals(u32),
+    TokenMigrations,
+    PendingTokenMigration,
+    StakerEra(Address, u32),
+    PoolEntry(u32),
+    UnaccruedTime(u32),
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct PoolState {
     pub acc_reward_per_share: i128,
     pub total_staked: i128,
@@ -29,6 +112,146 @@ pub struct PoolState {
     pub prev_acc_reward_per_share: i128, // Accumulator snapshot at last epoch change
 }
 
+/// The reward rate in force since `timestamp`, and the reward each emitting
+/// pool had been credited in total by then. A pool accrues the difference
+/// between `rewards::emission_index` and its own stored index, so changing
+/// the rate never has to visit the pools.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RateCheckpoint {
+    pub timestamp: u64,
+    pub rate: i128,
+    pub cumulative: i128,
+}
+
+/// Running totals over all pools, kept in step by the pool setters below.
+/// Liability, scheduled and carry cover LMNR-paying pools only; emitting
+/// pools and their index sum cover staked pools earning the global rate.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EmissionTotals {
+    pub emitting_pools: u32,
+    pub index_sum: i128,            // sum of emitting pools' emission indices
+    pub liability: i128,            // sum of booked pool liabilities
+    pub scheduled: i128,            // controller-funded rewards not yet accrued
+    pub oracle_rate_sum: i128,      // sum of pools' oracle-driven rates
+    pub oracle_rate_time_sum: i128, // sum of oracle rate * last_reward_time
+    pub carry: i128,                // sum of pools' carried idle rewards
+}
+
+/// Seconds a pool had stakers but accrued nothing, split by why: `paused`
+/// while LMNR earmarked for the pool sat unemitted (the rate was zeroed
+/// with a budget in place), `unbudgeted` while nothing was funded for it.
+/// The earmark itself is untouched; only the time is gone.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct UnaccruedTime {
+    pub paused: u64,
+    pub unbudgeted: u64,
+}
+
+/// Price oracle used to retarget each pool's rate at epoch rotation so the
+/// pool pays `target_apr_bps` on its staked LP value, within rate bounds.
+/// The oracle answers `lp_price(pool_id) -> i128`: the value of one whole
+/// LP share in reward-token stroops.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RateOracle {
+    pub oracle: Address,
+    pub target_apr_bps: u32,
+    pub min_rate: i128,
+    pub max_rate: i128,
+}
+
+/// Ed25519 keys attesting roots for `set_merkle_root_signed`, at least
+/// `threshold` of which must sign each one.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RootOracles {
+    pub keys: Vec<BytesN<32>>,
+    pub threshold: u32,
+}
+
+/// One root oracle's signature over `merkle::root_attestation_payload`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OracleSignature {
+    pub public_key: BytesN<32>,
+    pub signature: BytesN<64>,
+}
+
+/// Rewards an emissions controller scheduled for a pool: `rate` per second
+/// on top of the global rate until `period_finish`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RewardSchedule {
+    pub rate: i128,
+    pub period_finish: u64,
+}
+
+/// Per-pool settings. Pools without a stored config use `PoolConfig::default`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PoolConfig {
+    pub lp_decimals: u32,
+    pub max_total_staked: i128,    // 0 = uncapped
+    pub min_stake: Option<i128>,   // overrides the global minimum when set
+    pub lp_token: Option<Address>, // set for deposit-mode pools
+    pub amm_pool: Option<Address>, // AMM pool the share token belongs to, if known
+    pub shared_with: Option<u32>,  // hybrid deposit side: Merkle pool it accrues on
+    pub min_epoch_interval: u64,   // seconds between roots, 0 = no minimum
+    pub max_epoch_interval: u64,   // seconds between roots, 0 = no maximum
+    pub max_root_age: u64,         // seconds a root can be proven against, 0 = forever
+    pub expire_after_epochs: u32,  // missed epochs before `expire_staker`, 0 = never
+    pub paused: bool,              // stake and claim halted for this pool only
+    pub removed: bool,             // set by `remove_pool`; stops carrying idle rewards
+    pub positional_proofs: bool,   // proofs keep leaf order; see `stake_positional`
+    pub epoch_budget: i128,        // LMNR per epoch instead of the global rate, 0 = off
+    pub budget_duration: u64,      // seconds each epoch's budget is streamed over
+    pub verified_updates: bool,    // stake edits need a correction-root proof
+    pub unstake_cooldown: u64,     // seconds after unstaking before re-staking, 0 = off
+    pub warmup_period: u64,        // seconds a new proof accrues nothing, 0 = off
+    pub loyalty_step_bps: u32,     // weight bonus per consecutive epoch re-proven
+    pub loyalty_max_bps: u32,      // cap on the loyalty bonus
+    pub sqrt_weighting: bool,      // weight stakes by sqrt(lp_balance)
+    pub stake_cap: i128,           // LP counted per address for weight, 0 = uncapped
+    // Partner token paid instead of LMNR, if any
+    pub reward_token: Option<Address>,
+}
+
+const DEFAULT_LP_DECIMALS: u32 = 7; // SDEX pool shares
+pub const LMNR_DECIMALS: u32 = 7;
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        PoolConfig {
+            lp_decimals: DEFAULT_LP_DECIMALS,
+            max_total_staked: 0,
+            min_stake: None,
+            lp_token: None,
+            amm_pool: None,
+            shared_with: None,
+            min_epoch_interval: 0,
+            max_epoch_interval: 0,
+            max_root_age: 0,
+            expire_after_epochs: 0,
+            paused: false,
+            removed: false,
+            positional_proofs: false,
+            epoch_budget: 0,
+            budget_duration: 0,
+            verified_updates: false,
+            unstake_cooldown: 0,
+            warmup_period: 0,
+            loyalty_step_bps: 0,
+            loyalty_max_bps: 0,
+            sqrt_weighting: false,
+            stake_cap: 0,
+            reward_token: None,
+        }
+    }
+}
+
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct MerkleRootData {
@@ -36,6 +259,56 @@ pub struct MerkleRootData {
     pub epoch_id: u64,
     pub snapshot_ledger: u32,
     pub posted_at: u64,
+    pub leaf_version: u32, // leaf layout the tree was built with; see merkle.rs
+    pub epoch_duration: u64, // seconds averaged over by TWAB (v2) leaves, else 0
+    pub declared_total: i128, // sum of all leaves if declared when posted, else 0
+}
+
+/// A pool's accumulator and current root, stored as one entry since nearly
+/// every stake and claim reads both. Kept as a `(state, root)` tuple, as
+/// contract types can't hold an optional struct. Pools written before
+/// schema v5 keep them under `DataKey::PoolState` and `DataKey::MerkleRoot`
+/// until `migrate` combines them.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PoolEntry {
+    pub state: PoolState,
+    pub root: Option<MerkleRootData>,
+}
+
+/// `MerkleRootData` as stored before schema v4, decoded by the root getters
+/// until the entry is next written. Also the layout the legacy deployment
+/// returns from `get_merkle_root`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MerkleRootDataV3 {
+    pub root: BytesN<32>,
+    pub epoch_id: u64,
+    pub snapshot_ledger: u32,
+    pub posted_at: u64,
+}
+
+const MERKLE_ROOT_V3_FIELDS: u32 = 4;
+
+/// Decode a stored root in either the current or the v3 layout.
+fn decode_root(env: &Env, raw: Val) -> MerkleRootData {
+    if Map::<Symbol, Val>::from_val(env, &raw).len() != MERKLE_ROOT_V3_FIELDS {
+        return MerkleRootData::from_val(env, &raw);
+    }
+    MerkleRootDataV3::from_val(env, &raw).into()
+}
+
+impl From<MerkleRootDataV3> for MerkleRootData {
+    fn from(old: MerkleRootDataV3) -> Self {
+        MerkleRootData {
+            root: old.root,
+            epoch_id: old.epoch_id,
+            snapshot_ledger: old.snapshot_ledger,
+            posted_at: old.posted_at,
+            leaf_version: crate::merkle::LEAF_VERSION as u32,
+            epoch_duration: 0,
+            declared_total: 0,
+        }
+    }
 }
 
 #[contracttype]
@@ -45,9 +318,350 @@ pub struct StakerInfo {
     pub reward_debt: i128,
     pub pending_rewards: i128,
     pub epoch_id: u64,
+    pub cooldown_until: u64, // no re-stake before this timestamp after unstaking
+    pub weight: i128,        // share of the pool's total_staked; see `rewards::stake_weight`
+    pub streak: u32,         // consecutive epochs re-proven before `epoch_id`
+}
+
+/// `StakerInfo` as stored before schema v3, decoded by `get_staker` until
+/// the entry is next written. Also the layout the legacy deployment returns
+/// from `get_staker_info`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StakerInfoV2 {
+    pub staked_amount: i128,
+    pub reward_debt: i128,
+    pub pending_rewards: i128,
+    pub epoch_id: u64,
+}
+
+const STAKER_INFO_V2_FIELDS: u32 = 4;
+
+impl From<StakerInfoV2> for StakerInfo {
+    fn from(old: StakerInfoV2) -> Self {
+        StakerInfo {
+            staked_amount: old.staked_amount,
+            reward_debt: old.reward_debt,
+            pending_rewards: old.pending_rewards,
+            epoch_id: old.epoch_id,
+            cooldown_until: 0,
+            weight: old.staked_amount,
+            streak: 0,
+        }
+    }
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IdentityBinding {
+    pub owner: Address,
+    pub nonce: u64, // Next nonce the identity key must sign
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IdentityProof {
+    pub public_key: BytesN<32>,
+    pub signature: BytesN<64>,
+}
+
+/// One item of `stake_batch`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StakeRequest {
+    pub pool_index: u32,
+    pub lp_balance: i128,
+    pub proof: Vec<BytesN<32>>,
+}
+
+/// One item of `update_stakes`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StakeUpdate {
+    pub user: Address,
+    pub new_amount: i128,
+}
+
+/// Snapshot of a pool's accumulator for historical views and off-chain replay.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Checkpoint {
+    pub timestamp: u64,
+    pub acc_reward_per_share: i128,
+    pub total_staked: i128,
+}
+
+/// Stakers in a pool with a non-zero stake proven in epoch `epoch_id`. A
+/// count tagged with an older epoch is stale and reads as 0.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ActiveStakers {
+    pub epoch_id: u64,
+    pub count: u32,
+}
+
+/// Indexed leaves a staker has proven in a pool, one bit per leaf index.
+/// Bits recorded for an older epoch no longer count.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ClaimedLeaves {
+    pub epoch_id: u64,
+    pub bitmap: u64,
+}
+
+/// LP balance proven against a pool's root so far, checked against the
+/// root's declared total. Amounts recorded for an older epoch no longer
+/// count.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ProvenTotal {
+    pub epoch_id: u64,
+    pub amount: i128,
+}
+
+/// Fee revenue deposited to a pool in one token. `pending` is shared out
+/// at the pool's next root rotation; `acc_per_share` is what each unit of
+/// stake weight has been credited by past rotations, scaled by the pool's
+/// precision.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RevenuePot {
+    pub pending: i128,
+    pub acc_per_share: i128,
+}
+
+/// A staker's position in one revenue token or sponsor campaign: `owed` is
+/// credited and not yet paid, `debt` is `weight * acc_per_share` as of the
+/// last settlement.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RevenueShare {
+    pub debt: i128,
+    pub owed: i128,
+}
+
+/// Sponsor campaign paying `amount` of `token` to a pool's stakers by
+/// weight, released evenly from `start` to `end`. `unallocated` collects
+/// what was released while nobody was staked, plus rounding dust.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Campaign {
+    pub sponsor: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub start: u64,
+    pub end: u64,
+    pub acc_per_share: i128,
+    pub last_update: u64,
+    pub unallocated: i128,
+    pub paid: i128,
+}
+
+/// Rewards left unclaimed `seconds` after a staker last staked or claimed
+/// may be swept. Stakers with no recorded activity count from `since`, when
+/// the deadline was set.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ClaimDeadline {
+    pub seconds: u64,
+    pub since: u64,
+}
+
+/// LMNR paid to callers of the permissionless maintenance functions, at
+/// most `max_per_ledger` bounties in one ledger across all of them.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct KeeperBounties {
+    pub checkpoint: i128,
+    pub expire: i128,
+    pub sweep: i128,
+    pub max_per_ledger: u32,
+}
+
+/// Keeper bounties paid so far in ledger `sequence`.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BountyUsage {
+    pub sequence: u32,
+    pub count: u32,
+}
+
+/// What one reward stream paid out in a `claim_all`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Payout {
+    pub token: Address,
+    pub amount: i128,
+}
+
+/// Warm-up of a staker's newly proven position: accrual between `from`
+/// (when its reward debt was last set) and `until` is forfeited.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Warmup {
+    pub from: u64,
+    pub until: u64,
+}
+
+/// One `slash_staker` action, kept in the slash log for audit.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SlashRecord {
+    pub user: Address,
+    pub pool_index: u32,
+    pub reason_code: u32,
+    pub stake: i128,     // stake removed
+    pub forfeited: i128, // pending rewards written off
+    pub timestamp: u64,
+}
+
+/// One logged admin call, see `get_admin_log`. `args_hash` is the SHA-256
+/// of the call's XDR-encoded arguments, so an auditor can match it against
+/// the transaction that made it.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AdminAction {
+    pub action: Symbol,
+    pub args_hash: BytesN<32>,
+    pub caller: Address,
+    pub timestamp: u64,
+}
+
+/// Rewards paid out against one pool epoch.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ClaimStats {
+    pub total_claimed: i128,
+    pub claimant_count: u32, // Distinct addresses that claimed
+}
+
+/// Lifetime LMNR flows through the contract, for on-chain reconciliation.
+/// Tokens transferred in directly (not through `fund`) are not counted.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Accounting {
+    pub total_funded: i128,
+    pub total_claimed: i128,
+    pub total_withdrawn: i128,
+    pub total_bounties: i128,
+}
+
+/// Storage TTL parameters in ledgers. Entries are re-extended to `*_extend`
+/// once their remaining TTL drops below `*_threshold`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TtlConfig {
+    pub instance_threshold: u32,
+    pub instance_extend: u32,
+    pub persistent_threshold: u32,
+    pub persistent_extend: u32,
+    pub staker_extend: u32,       // Staker entries, see extend_staker_entries
+    pub historical_root_ttl: u32, // Least time superseded roots stay readable
+}
+
+impl Default for TtlConfig {
+    fn default() -> Self {
+        TtlConfig {
+            instance_threshold: INSTANCE_TTL_THRESHOLD,
+            instance_extend: INSTANCE_TTL_EXTEND,
+            persistent_threshold: PERSISTENT_TTL_THRESHOLD,
+            persistent_extend: PERSISTENT_TTL_EXTEND,
+            staker_extend: STAKER_TTL_EXTEND,
+            historical_root_ttl: HISTORICAL_ROOT_TTL,
+        }
+    }
+}
+
+/// Build identification returned by `get_version`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VersionInfo {
+    pub version: String,          // Crate semver
+    pub git_hash: Option<String>, // Set when the build knew its commit
+}
+
+/// Co-admins alongside the primary admin. A co-admin alone may only make
+/// routine calls: posting roots (`set_merkle_root`, `set_merkle_roots`,
+/// `set_merkle_root_with_total`, `set_twab_root`, `set_correction_root`),
+/// updating stakes (`update_stake`, `update_stake_verified`,
+/// `update_stakes`), `pause`, `pause_pool`, `block_address` and the keeper
+/// calls. Sensitive calls (`withdraw`, the emergency withdrawal and reward
+/// token migration, `rescue_tokens`, `set_lmnr_token`, `upgrade`,
+/// `set_admin`, `set_admins`) run only once `threshold` of them, the primary
+/// admin included, have made the same call; funds they release go to the
+/// primary admin. Everything else stays with the primary admin.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AdminSet {
+    pub members: Vec<Address>,
+    pub threshold: u32,
+}
+
+/// Ceilings `set_reward_rate` enforces even when forced, guarding against a
+/// mistyped rate. 0 leaves a bound unchecked.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RateBounds {
+    pub max_rate: i128,           // LMNR stroops per second per pool
+    pub max_daily_emission: i128, // across all emitting pools
+}
+
+/// Every global setting and role, see `get_config`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ContractConfig {
+    pub admin: Address,
+    pub lmnr_token: Address,
+    pub reward_rate_per_sec: i128,
+    pub paused: bool,
+    pub activated: bool,
+    pub keeper: Option<Address>,
+    pub epoch_listeners: Vec<Address>,
+    pub min_runway_days: u32,
+    pub min_stake: i128,
+    pub min_claim: i128,
+    pub compliance_hook: Option<Address>,
+    pub stake_hook: Option<Address>,
+    pub emissions_controller: Option<Address>,
+    pub partial_payouts: bool,
+    pub hide_claim_destination: bool,
+    pub ttl: TtlConfig,
+    pub prune_bounty: i128,
+    pub rate_bounds: RateBounds,
+    pub co_admins: Vec<Address>, // see `set_admins`; empty under a single admin
+    pub admin_threshold: u32,    // approvals sensitive calls need, 1 = none
+}
+
+/// Protocol-wide figures for dashboards, see `get_global_stats`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GlobalStats {
+    pub total_pools: u32,
+    pub total_stakers: u32, // Staker positions summed over pools
+    pub total_staked: i128, // stake weight summed over pools
+    pub total_distributed: i128,
+    pub reward_rate_per_sec: i128,
+}
+
+/// Emergency withdrawal queued while the contract is paused.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PendingWithdrawal {
+    pub amount: i128,
+    pub unlock_time: u64,
+}
+
+/// Reward token switch queued by `queue_reward_token_migration`, executable
+/// from `unlock_time` while the contract stays paused.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PendingTokenMigration {
+    pub new_token: Address,
+    pub exchange_ratio: i128,
+    pub unlock_time: u64,
 }
 
-// --- Instance storage helpers (Admin, LmnrToken, RewardRate, PoolCount) ---
+// --- Instance storage helpers (Admin, LmnrToken, RewardRate, PoolCount, settings) ---
 
 pub fn has_admin(env: &Env) -> bool {
     env.storage().instance().has(&DataKey::Admin)
@@ -61,6 +675,43 @@ pub fn set_admin(env: &Env, admin: &Address) {
     env.storage().instance().set(&DataKey::Admin, admin);
 }
 
+pub fn get_admin_set(env: &Env) -> Option<AdminSet> {
+    env.storage().instance().get(&DataKeyExt::AdminSet)
+}
+
+pub fn set_admin_set(env: &Env, admin_set: &Option<AdminSet>) {
+    match admin_set {
+        Some(admin_set) => env
+            .storage()
+            .instance()
+            .set(&DataKeyExt::AdminSet, admin_set),
+        None => env.storage().instance().remove(&DataKeyExt::AdminSet),
+    }
+}
+
+/// Admins who have made the sensitive call hashing to `action`, while the
+/// approvals have not lapsed.
+pub fn get_approvals(env: &Env, action: &BytesN<32>) -> Vec<Address> {
+    env.storage()
+        .temporary()
+        .get(&DataKeyExt::Approvals(action.clone()))
+        .unwrap_or(Vec::new(env))
+}
+
+pub fn set_approvals(env: &Env, action: &BytesN<32>, approvals: &Vec<Address>) {
+    let key = DataKeyExt::Approvals(action.clone());
+    env.storage().temporary().set(&key, approvals);
+    env.storage()
+        .temporary()
+        .extend_ttl(&key, APPROVAL_TTL, APPROVAL_TTL);
+}
+
+pub fn remove_approvals(env: &Env, action: &BytesN<32>) {
+    env.storage()
+        .temporary()
+        .remove(&DataKeyExt::Approvals(action.clone()));
+}
+
 pub fn get_lmnr_token(env: &Env) -> Address {
     env.storage().instance().get(&DataKey::LmnrToken).unwrap()
 }
@@ -93,105 +744,1347 @@ pub fn set_pool_count(env: &Env, count: u32) {
     env.storage().instance().set(&DataKey::PoolCount, &count);
 }
 
-pub fn extend_instance_ttl(env: &Env) {
+pub fn get_hide_claim_destination(env: &Env) -> bool {
     env.storage()
         .instance()
-        .extend_ttl(INSTANCE_TTL_THRESHOLD, INSTANCE_TTL_EXTEND);
+        .get(&DataKey::HideClaimDestination)
+        .unwrap_or(false)
 }
 
-// --- Persistent storage helpers (PoolId, PoolState, MerkleRoot, Staker) ---
+pub fn set_hide_claim_destination(env: &Env, hide: bool) {
+    env.storage()
+        .instance()
+        .set(&DataKey::HideClaimDestination, &hide);
+}
 
-pub fn get_pool_id(env: &Env, index: u32) -> BytesN<32> {
-    let key = DataKey::PoolId(index);
-    env.storage().persistent().get(&key).unwrap()
+pub fn get_partial_payouts(env: &Env) -> bool {
+    env.storage()
+        .instance()
+        .get(&DataKey::PartialPayouts)
+        .unwrap_or(false)
 }
 
-pub fn set_pool_id(env: &Env, index: u32, pool_id: &BytesN<32>) {
-    let key = DataKey::PoolId(index);
-    env.storage().persistent().set(&key, pool_id);
-    extend_persistent(env, &key);
+pub fn set_partial_payouts(env: &Env, enabled: bool) {
+    env.storage()
+        .instance()
+        .set(&DataKey::PartialPayouts, &enabled);
 }
 
-pub fn has_pool_id_index(env: &Env, pool_id: &BytesN<32>) -> bool {
+/// LMNR paid to whoever prunes an empty staker entry (0 disables).
+pub fn get_prune_bounty(env: &Env) -> i128 {
     env.storage()
-        .persistent()
-        .has(&DataKey::PoolIdIndex(pool_id.clone()))
+        .instance()
+        .get(&DataKey::PruneBounty)
+        .unwrap_or(0)
 }
 
-pub fn get_pool_id_index(env: &Env, pool_id: &BytesN<32>) -> u32 {
-    let key = DataKey::PoolIdIndex(pool_id.clone());
-    env.storage().persistent().get(&key).unwrap()
+pub fn set_prune_bounty(env: &Env, bounty: i128) {
+    env.storage().instance().set(&DataKey::PruneBounty, &bounty);
 }
 
-pub fn set_pool_id_index(env: &Env, pool_id: &BytesN<32>, index: u32) {
-    let key = DataKey::PoolIdIndex(pool_id.clone());
-    env.storage().persistent().set(&key, &index);
-    extend_persistent(env, &key);
+pub fn get_epoch_listeners(env: &Env) -> Vec<Address> {
+    env.storage()
+        .instance()
+        .get(&DataKey::EpochListeners)
+        .unwrap_or(Vec::new(env))
 }
 
-pub fn get_pool_state(env: &Env, index: u32) -> PoolState {
-    let key = DataKey::PoolState(index);
-    let state: PoolState = env.storage().persistent().get(&key).unwrap_or(PoolState {
-        acc_reward_per_share: 0,
-        total_staked: 0,
-        last_reward_time: 0,
-        prev_acc_reward_per_share: 0,
-    });
-    extend_persistent(env, &key);
-    state
+pub fn set_epoch_listeners(env: &Env, listeners: &Vec<Address>) {
+    env.storage()
+        .instance()
+        .set(&DataKey::EpochListeners, listeners);
 }
 
-pub fn set_pool_state(env: &Env, index: u32, state: &PoolState) {
-    let key = DataKey::PoolState(index);
-    env.storage().persistent().set(&key, state);
-    extend_persistent(env, &key);
+pub fn get_min_runway_days(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&DataKey::MinRunwayDays)
+        .unwrap_or(0)
 }
 
-pub fn has_merkle_root(env: &Env, pool_index: u32) -> bool {
+pub fn set_min_runway_days(env: &Env, days: u32) {
+    env.storage().instance().set(&DataKey::MinRunwayDays, &days);
+}
+
+pub fn get_rate_bounds(env: &Env) -> RateBounds {
     env.storage()
-        .persistent()
-        .has(&DataKey::MerkleRoot(pool_index))
+        .instance()
+        .get(&DataKeyExt::RateBounds)
+        .unwrap_or_default()
 }
 
-pub fn get_merkle_root(env: &Env, pool_index: u32) -> MerkleRootData {
-    let key = DataKey::MerkleRoot(pool_index);
-    let data: MerkleRootData = env.storage().persistent().get(&key).unwrap();
-    extend_persistent(env, &key);
-    data
+pub fn set_rate_bounds(env: &Env, bounds: &RateBounds) {
+    env.storage()
+        .instance()
+        .set(&DataKeyExt::RateBounds, bounds);
 }
 
-pub fn set_merkle_root(env: &Env, pool_index: u32, data: &MerkleRootData) {
-    let key = DataKey::MerkleRoot(pool_index);
-    env.storage().persistent().set(&key, data);
-    extend_persistent(env, &key);
+/// Smallest LP balance `stake` accepts unless a pool overrides it (0 disables).
+pub fn get_min_stake(env: &Env) -> i128 {
+    env.storage()
+        .instance()
+        .get(&DataKey::MinStake)
+        .unwrap_or(0)
 }
 
-pub fn has_staker(env: &Env, user: &Address, pool_index: u32) -> bool {
+pub fn set_min_stake(env: &Env, amount: i128) {
+    env.storage().instance().set(&DataKey::MinStake, &amount);
+}
+
+/// Smallest pending reward a claim pays out (0 disables).
+pub fn get_min_claim(env: &Env) -> i128 {
     env.storage()
-        .persistent()
-        .has(&DataKey::Staker(user.clone(), pool_index))
+        .instance()
+        .get(&DataKey::MinClaim)
+        .unwrap_or(0)
 }
 
-pub fn get_staker(env: &Env, user: &Address, pool_index: u32) -> StakerInfo {
-    let key = DataKey::Staker(user.clone(), pool_index);
-    let info: StakerInfo = env.storage().persistent().get(&key).unwrap();
-    extend_persistent(env, &key);
-    info
+pub fn set_min_claim(env: &Env, amount: i128) {
+    env.storage().instance().set(&DataKey::MinClaim, &amount);
 }
 
-pub fn set_staker(env: &Env, user: &Address, pool_index: u32, info: &StakerInfo) {
-    let key = DataKey::Staker(user.clone(), pool_index);
-    env.storage().persistent().set(&key, info);
-    extend_persistent(env, &key);
+/// Blocklist entry for `user`: `None` if not blocked, otherwise whether
+/// their claims are frozen (`true`) or forfeited (`false`).
+pub fn get_blocked(env: &Env, user: &Address) -> Option<bool> {
+    env.storage()
+        .persistent()
+        .get(&DataKey::Blocked(user.clone()))
+}
+
+pub fn set_blocked(env: &Env, user: &Address, freeze_claims: bool) {
+    let key = DataKey::Blocked(user.clone());
+    env.storage().persistent().set(&key, &freeze_claims);
+    extend_persistent(env, &key);
+}
+
+pub fn remove_blocked(env: &Env, user: &Address) {
+    env.storage()
+        .persistent()
+        .remove(&DataKey::Blocked(user.clone()));
+}
+
+pub fn is_paused(env: &Env) -> bool {
+    env.storage()
+        .instance()
+        .get(&DataKey::Paused)
+        .unwrap_or(false)
+}
+
+pub fn set_paused(env: &Env, paused: bool) {
+    env.storage().instance().set(&DataKey::Paused, &paused);
+}
+
+pub fn get_pending_withdrawal(env: &Env) -> Option<PendingWithdrawal> {
+    env.storage().instance().get(&DataKey::PendingWithdrawal)
+}
+
+pub fn set_pending_withdrawal(env: &Env, pending: &PendingWithdrawal) {
+    env.storage()
+        .instance()
+        .set(&DataKey::PendingWithdrawal, pending);
+}
+
+pub fn remove_pending_withdrawal(env: &Env) {
+    env.storage().instance().remove(&DataKey::PendingWithdrawal);
+}
+
+pub fn get_pending_token_migration(env: &Env) -> Option<PendingTokenMigration> {
+    env.storage()
+        .instance()
+        .get(&DataKeyExt::PendingTokenMigration)
+}
+
+pub fn set_pending_token_migration(env: &Env, pending: &PendingTokenMigration) {
+    env.storage()
+        .instance()
+        .set(&DataKeyExt::PendingTokenMigration, pending);
+}
+
+pub fn remove_pending_token_migration(env: &Env) {
+    env.storage()
+        .instance()
+        .remove(&DataKeyExt::PendingTokenMigration);
+}
+
+/// Exchange ratio of every reward token migration so far, oldest first.
+/// A staker entry last written before migration `n` is still in the units
+/// of the token migration `n` replaced.
+pub fn get_token_migrations(env: &Env) -> Vec<i128> {
+    env.storage()
+        .instance()
+        .get(&DataKeyExt::TokenMigrations)
+        .unwrap_or(Vec::new(env))
+}
+
+pub fn set_token_migrations(env: &Env, ratios: &Vec<i128>) {
+    env.storage()
+        .instance()
+        .set(&DataKeyExt::TokenMigrations, ratios);
+}
+
+/// Number of reward token migrations the staker entry was written after.
+pub fn get_staker_era(env: &Env, user: &Address, pool_index: u32) -> u32 {
+    env.storage()
+        .persistent()
+        .get(&DataKeyExt::StakerEra(user.clone(), pool_index))
+        .unwrap_or(0)
+}
+
+pub fn get_accounting(env: &Env) -> Accounting {
+    env.storage()
+        .instance()
+        .get(&DataKey::Accounting)
+        .unwrap_or_default()
+}
+
+pub fn set_accounting(env: &Env, accounting: &Accounting) {
+    env.storage().instance().set(&DataKey::Accounting, accounting);
+}
+
+/// Address allowed to call `claim_batch` and `poke_deadlines` alongside
+/// the admin.
+pub fn get_keeper(env: &Env) -> Option<Address> {
+    env.storage().instance().get(&DataKey::Keeper)
+}
+
+pub fn set_keeper(env: &Env, keeper: &Option<Address>) {
+    match keeper {
+        Some(keeper) => env.storage().instance().set(&DataKey::Keeper, keeper),
+        None => env.storage().instance().remove(&DataKey::Keeper),
+    }
+}
+
+/// Contract consulted with `check(user) -> bool` before stakes and claims.
+pub fn get_compliance_hook(env: &Env) -> Option<Address> {
+    env.storage().instance().get(&DataKey::ComplianceHook)
+}
+
+pub fn set_compliance_hook(env: &Env, hook: &Option<Address>) {
+    match hook {
+        Some(hook) => env.storage().instance().set(&DataKey::ComplianceHook, hook),
+        None => env.storage().instance().remove(&DataKey::ComplianceHook),
+    }
+}
+
+/// Integrator contract told about stake changes and claims.
+pub fn get_stake_hook(env: &Env) -> Option<Address> {
+    env.storage().instance().get(&DataKey::StakeHook)
+}
+
+pub fn set_stake_hook(env: &Env, hook: &Option<Address>) {
+    match hook {
+        Some(hook) => env.storage().instance().set(&DataKey::StakeHook, hook),
+        None => env.storage().instance().remove(&DataKey::StakeHook),
+    }
+}
+
+/// Contract allowed to fund and schedule per-pool rewards via `notify_reward`.
+pub fn get_emissions_controller(env: &Env) -> Option<Address> {
+    env.storage().instance().get(&DataKey::EmissionsController)
+}
+
+pub fn set_emissions_controller(env: &Env, controller: &Option<Address>) {
+    match controller {
+        Some(controller) => env
+            .storage()
+            .instance()
+            .set(&DataKey::EmissionsController, controller),
+        None => env
+            .storage()
+            .instance()
+            .remove(&DataKey::EmissionsController),
+    }
+}
+
+pub fn get_reward_schedule(env: &Env, pool_index: u32) -> Option<RewardSchedule> {
+    env.storage()
+        .persistent()
+        .get(&DataKey::RewardSchedule(pool_index))
+}
+
+pub fn set_reward_schedule(env: &Env, pool_index: u32, schedule: &RewardSchedule) {
+    let key = DataKey::RewardSchedule(pool_index);
+    env.storage().persistent().set(&key, schedule);
+    extend_persistent(env, &key);
+}
+
+pub fn get_root_oracles(env: &Env) -> Option<RootOracles> {
+    env.storage().instance().get(&DataKeyExt::RootOracles)
+}
+
+pub fn set_root_oracles(env: &Env, oracles: &Option<RootOracles>) {
+    match oracles {
+        Some(oracles) => env
+            .storage()
+            .instance()
+            .set(&DataKeyExt::RootOracles, oracles),
+        None => env.storage().instance().remove(&DataKeyExt::RootOracles),
+    }
+}
+
+pub fn get_rate_oracle(env: &Env) -> Option<RateOracle> {
+    env.storage().instance().get(&DataKey::RateOracle)
+}
+
+pub fn set_rate_oracle(env: &Env, oracle: &Option<RateOracle>) {
+    match oracle {
+        Some(oracle) => env.storage().instance().set(&DataKey::RateOracle, oracle),
+        None => env.storage().instance().remove(&DataKey::RateOracle),
+    }
+}
+
+/// Per-second rate a pool earns on top of the global rate, last set by the
+/// rate oracle at epoch rotation.
+pub fn get_oracle_rate(env: &Env, pool_index: u32) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&DataKey::OracleRate(pool_index))
+        .unwrap_or(0)
+}
+
+/// Only call right after `update_pool`, so the old rate has been accrued up
+/// to the pool's `last_reward_time`.
+pub fn set_oracle_rate(env: &Env, pool_index: u32, rate: i128) {
+    let delta = rate - get_oracle_rate(env, pool_index);
+    let mut totals = get_emission_totals(env);
+    totals.oracle_rate_sum += delta;
+    totals.oracle_rate_time_sum += delta * get_pool_state(env, pool_index).last_reward_time as i128;
+    set_emission_totals(env, &totals);
+
+    let key = DataKey::OracleRate(pool_index);
+    env.storage().persistent().set(&key, &rate);
+    extend_persistent(env, &key);
+}
+
+/// Set once by `activate`; closes the `seed_from_legacy` window.
+pub fn is_activated(env: &Env) -> bool {
+    env.storage()
+        .instance()
+        .get(&DataKey::Activated)
+        .unwrap_or(false)
+}
+
+pub fn set_activated(env: &Env) {
+    env.storage().instance().set(&DataKey::Activated, &true);
+}
+
+/// Storage layout version the data was last migrated to. Deployments from
+/// before versioning have no entry and read as 0.
+pub fn get_schema_version(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&DataKey::SchemaVersion)
+        .unwrap_or(0)
+}
+
+pub fn set_schema_version(env: &Env, version: u32) {
+    env.storage()
+        .instance()
+        .set(&DataKey::SchemaVersion, &version);
+}
+
+pub fn get_ttl_config(env: &Env) -> TtlConfig {
+    env.storage()
+        .instance()
+        .get(&DataKey::TtlConfig)
+        .unwrap_or_default()
+}
+
+pub fn set_ttl_config(env: &Env, config: &TtlConfig) {
+    env.storage().instance().set(&DataKey::TtlConfig, config);
+}
+
+pub fn extend_instance_ttl(env: &Env) {
+    let ttl = get_ttl_config(env);
+    env.storage()
+        .instance()
+        .extend_ttl(ttl.instance_threshold, ttl.instance_extend);
+}
+
+// --- Persistent storage helpers (pool, staker, identity and history records) ---
+
+pub fn get_pool_id(env: &Env, index: u32) -> BytesN<32> {
+    let key = DataKey::PoolId(index);
+    env.storage().persistent().get(&key).unwrap()
+}
+
+pub fn set_pool_id(env: &Env, index: u32, pool_id: &BytesN<32>) {
+    let key = DataKey::PoolId(index);
+    env.storage().persistent().set(&key, pool_id);
+    extend_persistent(env, &key);
+}
+
+pub fn has_pool_id_index(env: &Env, pool_id: &BytesN<32>) -> bool {
+    env.storage()
+        .persistent()
+        .has(&DataKey::PoolIdIndex(pool_id.clone()))
+}
+
+pub fn get_pool_id_index(env: &Env, pool_id: &BytesN<32>) -> Option<u32> {
+    let key = DataKey::PoolIdIndex(pool_id.clone());
+    env.storage().persistent().get(&key)
+}
+
+pub fn set_pool_id_index(env: &Env, pool_id: &BytesN<32>, index: u32) {
+    let key = DataKey::PoolIdIndex(pool_id.clone());
+    env.storage().persistent().set(&key, &index);
+    extend_persistent(env, &key);
+}
+
+pub fn remove_pool_id_index(env: &Env, pool_id: &BytesN<32>) {
+    env.storage()
+        .persistent()
+        .remove(&DataKey::PoolIdIndex(pool_id.clone()));
+}
+
+pub fn get_pool_state(env: &Env, index: u32) -> PoolState {
+    read_pool_entry(env, index).unwrap_or_default().state
+}
+
+pub fn set_pool_state(env: &Env, index: u32, state: &PoolState) {
+    let index_key = DataKey::PoolEmissionIndex(index);
+    if !env.storage().persistent().has(&index_key) {
+        let emission_index = legacy_emission_index(env, state.last_reward_time);
+        env.storage().persistent().set(&index_key, &emission_index);
+        extend_persistent(env, &index_key);
+    }
+
+    let mut entry = read_pool_entry(env, index).unwrap_or_default();
+    let global = earns_global_rate(&get_pool_config(env, index));
+    let was_emitting = global && entry.state.total_staked > 0;
+    let emitting = global && state.total_staked > 0;
+    if was_emitting != emitting {
+        let emission_index = get_pool_emission_index(env, index);
+        let mut totals = get_emission_totals(env);
+        if emitting {
+            totals.emitting_pools += 1;
+            totals.index_sum += emission_index;
+        } else {
+            totals.emitting_pools -= 1;
+            totals.index_sum -= emission_index;
+        }
+        set_emission_totals(env, &totals);
+    }
+
+    entry.state = state.clone();
+    write_pool_entry(env, index, &entry);
+}
+
+/// The pool's combined entry, falling back to the separate pre-v5 entries
+/// for a pool `migrate` has not combined yet.
+fn read_pool_entry(env: &Env, index: u32) -> Option<PoolEntry> {
+    let key = DataKeyExt::PoolEntry(index);
+    if let Some((state, root)) = env.storage().persistent().get(&key) {
+        extend_persistent(env, &key);
+        return Some(PoolEntry { state, root });
+    }
+
+    let state: Option<PoolState> = env.storage().persistent().get(&DataKey::PoolState(index));
+    let root = env
+        .storage()
+        .persistent()
+        .get(&DataKey::MerkleRoot(index))
+        .map(|raw| decode_root(env, raw));
+    if state.is_none() && root.is_none() {
+        return None;
+    }
+    Some(PoolEntry {
+        state: state.unwrap_or_default(),
+        root,
+    })
+}
+
+fn write_pool_entry(env: &Env, index: u32, entry: &PoolEntry) {
+    let key = DataKeyExt::PoolEntry(index);
+    let value = (entry.state.clone(), entry.root.clone());
+    env.storage().persistent().set(&key, &value);
+    extend_persistent(env, &key);
+}
+
+/// Move a pool's pre-v5 state and root entries into its combined entry.
+pub fn combine_pool_entry(env: &Env, index: u32) {
+    let storage = env.storage().persistent();
+    if storage.has(&DataKeyExt::PoolEntry(index)) {
+        return;
+    }
+    let Some(entry) = read_pool_entry(env, index) else {
+        return;
+    };
+    write_pool_entry(env, index, &entry);
+    storage.remove(&DataKey::PoolState(index));
+    storage.remove(&DataKey::MerkleRoot(index));
+}
+
+/// Emission index a pool was last brought up to (see `RateCheckpoint`).
+pub fn get_pool_emission_index(env: &Env, index: u32) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&DataKey::PoolEmissionIndex(index))
+        .unwrap_or_else(|| legacy_emission_index(env, get_pool_state(env, index).last_reward_time))
+}
+
+pub fn set_pool_emission_index(env: &Env, index: u32, emission_index: i128) {
+    if is_emitting(env, index) {
+        let mut totals = get_emission_totals(env);
+        totals.index_sum += emission_index - get_pool_emission_index(env, index);
+        set_emission_totals(env, &totals);
+    }
+
+    let key = DataKey::PoolEmissionIndex(index);
+    env.storage().persistent().set(&key, &emission_index);
+    extend_persistent(env, &key);
+}
+
+/// Index for a pool written before emission indices existed. Exact as long
+/// as the rate has not changed since the pool's last update, which held
+/// while every rate change updated every pool.
+fn legacy_emission_index(env: &Env, last_reward_time: u64) -> i128 {
+    let checkpoint = get_rate_checkpoint(env);
+    checkpoint.cumulative
+        + checkpoint.rate * (last_reward_time as i128 - checkpoint.timestamp as i128)
+}
+
+pub fn get_rate_checkpoint(env: &Env) -> RateCheckpoint {
+    env.storage()
+        .instance()
+        .get(&DataKey::RateCheckpoint)
+        .unwrap_or_else(|| RateCheckpoint {
+            timestamp: 0,
+            rate: get_reward_rate(env),
+            cumulative: 0,
+        })
+}
+
+pub fn set_rate_checkpoint(env: &Env, checkpoint: &RateCheckpoint) {
+    env.storage()
+        .instance()
+        .set(&DataKey::RateCheckpoint, checkpoint);
+}
+
+/// Number of checkpoints in the rate history.
+pub fn get_rate_history_count(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&DataKeyExt::RateHistoryCount)
+        .unwrap_or(0)
+}
+
+pub fn get_rate_history(env: &Env, index: u32) -> RateCheckpoint {
+    let key = DataKeyExt::RateHistory(index);
+    env.storage().persistent().get(&key).unwrap()
+}
+
+/// Append a rate checkpoint to the history. The first append also records
+/// the checkpoint it replaces, so the history starts from the rate in force
+/// before any recorded change.
+pub fn push_rate_history(env: &Env, checkpoint: &RateCheckpoint) {
+    let mut count = get_rate_history_count(env);
+    if count == 0 {
+        let key = DataKeyExt::RateHistory(0);
+        let replaced = get_rate_checkpoint(env);
+        env.storage().persistent().set(&key, &replaced);
+        extend_persistent(env, &key);
+        count = 1;
+    }
+    let key = DataKeyExt::RateHistory(count);
+    env.storage().persistent().set(&key, checkpoint);
+    extend_persistent(env, &key);
+    env.storage()
+        .instance()
+        .set(&DataKeyExt::RateHistoryCount, &(count + 1));
+}
+
+pub fn get_emission_totals(env: &Env) -> EmissionTotals {
+    env.storage()
+        .instance()
+        .get(&DataKey::EmissionTotals)
+        .unwrap_or_default()
+}
+
+pub fn set_emission_totals(env: &Env, totals: &EmissionTotals) {
+    env.storage()
+        .instance()
+        .set(&DataKey::EmissionTotals, totals);
+}
+
+/// Whether the pool pays LMNR. Partner-token pools are kept out of
+/// `EmissionTotals`, which only tracks what the LMNR balance owes.
+pub fn pays_lmnr(env: &Env, index: u32) -> bool {
+    get_pool_config(env, index).reward_token.is_none()
+}
+
+/// Whether a pool on `config` earns the global rate: it pays LMNR and has
+/// no epoch budget of its own.
+pub fn earns_global_rate(config: &PoolConfig) -> bool {
+    config.reward_token.is_none() && config.epoch_budget == 0
+}
+
+/// Whether the pool counts towards `EmissionTotals::emitting_pools`: it
+/// earns the global rate and has stake to credit it to.
+pub fn is_emitting(env: &Env, index: u32) -> bool {
+    earns_global_rate(&get_pool_config(env, index)) && get_pool_state(env, index).total_staked > 0
+}
+
+pub fn get_pool_config(env: &Env, index: u32) -> PoolConfig {
+    env.storage()
+        .persistent()
+        .get(&DataKey::PoolConfig(index))
+        .unwrap_or_default()
+}
+
+pub fn set_pool_config(env: &Env, index: u32, config: &PoolConfig) {
+    // A pool moving on or off the global rate joins or leaves the totals
+    let was_global = earns_global_rate(&get_pool_config(env, index));
+    if was_global != earns_global_rate(config) && get_pool_state(env, index).total_staked > 0 {
+        let emission_index = get_pool_emission_index(env, index);
+        let mut totals = get_emission_totals(env);
+        if was_global {
+            totals.emitting_pools -= 1;
+            totals.index_sum -= emission_index;
+        } else {
+            totals.emitting_pools += 1;
+            totals.index_sum += emission_index;
+        }
+        set_emission_totals(env, &totals);
+    }
+
+    let key = DataKey::PoolConfig(index);
+    env.storage().persistent().set(&key, config);
+    extend_persistent(env, &key);
+}
+
+/// Accrued-but-unclaimed rewards owed to a pool's stakers.
+pub fn get_pool_liability(env: &Env, index: u32) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&DataKey::PoolLiability(index))
+        .unwrap_or(0)
+}
+
+pub fn set_pool_liability(env: &Env, index: u32, liability: i128) {
+    if pays_lmnr(env, index) {
+        let mut totals = get_emission_totals(env);
+        totals.liability += liability - get_pool_liability(env, index);
+        set_emission_totals(env, &totals);
+    }
+
+    let key = DataKey::PoolLiability(index);
+    env.storage().persistent().set(&key, &liability);
+    extend_persistent(env, &key);
+}
+
+/// Rewards a pool accrued while nobody was staked, held until the admin
+/// streams them to stakers or returns them to the unreserved balance.
+pub fn get_pool_carry(env: &Env, index: u32) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&DataKey::PoolCarry(index))
+        .unwrap_or(0)
+}
+
+pub fn get_unaccrued_time(env: &Env, index: u32) -> UnaccruedTime {
+    env.storage()
+        .persistent()
+        .get(&DataKeyExt::UnaccruedTime(index))
+        .unwrap_or_default()
+}
+
+pub fn set_unaccrued_time(env: &Env, index: u32, time: &UnaccruedTime) {
+    let key = DataKeyExt::UnaccruedTime(index);
+    env.storage().persistent().set(&key, time);
+    extend_persistent(env, &key);
+}
+
+/// LMNR funded through `fund_pool` for a pool and not yet paid out of it.
+pub fn get_pool_earmark(env: &Env, index: u32) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&DataKeyExt::PoolEarmark(index))
+        .unwrap_or(0)
+}
+
+pub fn set_pool_earmark(env: &Env, index: u32, earmark: i128) {
+    let key = DataKeyExt::PoolEarmark(index);
+    env.storage().persistent().set(&key, &earmark);
+    extend_persistent(env, &key);
+}
+
+/// Decimals of the token a pool pays, recorded when a partner reward token
+/// is configured; LMNR's otherwise.
+pub fn get_reward_decimals(env: &Env, index: u32) -> u32 {
+    env.storage()
+        .persistent()
+        .get(&DataKeyExt::RewardDecimals(index))
+        .unwrap_or(LMNR_DECIMALS)
+}
+
+pub fn set_reward_decimals(env: &Env, index: u32, decimals: u32) {
+    let key = DataKeyExt::RewardDecimals(index);
+    env.storage().persistent().set(&key, &decimals);
+    extend_persistent(env, &key);
+}
+
+pub fn set_pool_carry(env: &Env, index: u32, carry: i128) {
+    if pays_lmnr(env, index) {
+        let mut totals = get_emission_totals(env);
+        totals.carry += carry - get_pool_carry(env, index);
+        set_emission_totals(env, &totals);
+    }
+
+    let key = DataKey::PoolCarry(index);
+    env.storage().persistent().set(&key, &carry);
+    extend_persistent(env, &key);
+}
+
+/// Reward not yet credited to the pool's accumulator because it did not
+/// divide evenly over `total_staked`, scaled by the pool's precision.
+pub fn get_pool_remainder(env: &Env, index: u32) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&DataKey::PoolRemainder(index))
+        .unwrap_or(0)
+}
+
+pub fn set_pool_remainder(env: &Env, index: u32, remainder: i128) {
+    let key = DataKey::PoolRemainder(index);
+    env.storage().persistent().set(&key, &remainder);
+    extend_persistent(env, &key);
+}
+
+pub fn has_merkle_root(env: &Env, pool_index: u32) -> bool {
+    read_pool_entry(env, pool_index).is_some_and(|entry| entry.root.is_some())
+}
+
+pub fn get_merkle_root(env: &Env, pool_index: u32) -> MerkleRootData {
+    read_pool_entry(env, pool_index)
+        .and_then(|entry| entry.root)
+        .unwrap()
+}
+
+pub fn set_merkle_root(env: &Env, pool_index: u32, data: &MerkleRootData) {
+    let mut entry = read_pool_entry(env, pool_index).unwrap_or_default();
+    entry.root = Some(data.clone());
+    write_pool_entry(env, pool_index, &entry);
+}
+
+/// Correction root posted for the pool's current epoch, if any.
+pub fn get_correction_root(env: &Env, pool_index: u32) -> Option<MerkleRootData> {
+    env.storage()
+        .persistent()
+        .get(&DataKeyExt::CorrectionRoot(pool_index))
+        .map(|raw| decode_root(env, raw))
+}
+
+pub fn set_correction_root(env: &Env, pool_index: u32, data: &MerkleRootData) {
+    let key = DataKeyExt::CorrectionRoot(pool_index);
+    env.storage().persistent().set(&key, data);
+    extend_persistent(env, &key);
+}
+
+pub fn get_claimed_leaves(env: &Env, user: &Address, pool_index: u32) -> ClaimedLeaves {
+    env.storage()
+        .persistent()
+        .get(&DataKeyExt::ClaimedLeaves(user.clone(), pool_index))
+        .unwrap_or_default()
+}
+
+pub fn set_claimed_leaves(env: &Env, user: &Address, pool_index: u32, leaves: &ClaimedLeaves) {
+    let key = DataKeyExt::ClaimedLeaves(user.clone(), pool_index);
+    env.storage().persistent().set(&key, leaves);
+    extend_persistent(env, &key);
+}
+
+pub fn get_proven_total(env: &Env, pool_index: u32) -> ProvenTotal {
+    env.storage()
+        .persistent()
+        .get(&DataKeyExt::ProvenTotal(pool_index))
+        .unwrap_or_default()
+}
+
+pub fn set_proven_total(env: &Env, pool_index: u32, proven: &ProvenTotal) {
+    let key = DataKeyExt::ProvenTotal(pool_index);
+    env.storage().persistent().set(&key, proven);
+    extend_persistent(env, &key);
+}
+
+/// Revenue pots of a pool, by token.
+pub fn get_revenue_pots(env: &Env, pool_index: u32) -> Map<Address, RevenuePot> {
+    env.storage()
+        .persistent()
+        .get(&DataKeyExt::RevenuePots(pool_index))
+        .unwrap_or(Map::new(env))
+}
+
+pub fn set_revenue_pots(env: &Env, pool_index: u32, pots: &Map<Address, RevenuePot>) {
+    let key = DataKeyExt::RevenuePots(pool_index);
+    env.storage().persistent().set(&key, pots);
+    extend_persistent(env, &key);
+}
+
+/// A staker's revenue shares in a pool, by token.
+pub fn get_revenue_shares(
+    env: &Env,
+    user: &Address,
+    pool_index: u32,
+) -> Map<Address, RevenueShare> {
+    env.storage()
+        .persistent()
+        .get(&DataKeyExt::RevenueShares(user.clone(), pool_index))
+        .unwrap_or(Map::new(env))
+}
+
+/// Store a staker's revenue shares, dropping the entry once nothing is
+/// owed or held against it.
+pub fn set_revenue_shares(
+    env: &Env,
+    user: &Address,
+    pool_index: u32,
+    shares: &Map<Address, RevenueShare>,
+) {
+    let key = DataKeyExt::RevenueShares(user.clone(), pool_index);
+    let empty = RevenueShare::default();
+    if shares.values().iter().all(|share| share == empty) {
+        env.storage().persistent().remove(&key);
+        return;
+    }
+    env.storage().persistent().set(&key, shares);
+    extend_persistent(env, &key);
+}
+
+pub fn get_campaign(env: &Env, pool_index: u32, campaign_id: u32) -> Option<Campaign> {
+    env.storage()
+        .persistent()
+        .get(&DataKeyExt::Campaign(pool_index, campaign_id))
+}
+
+pub fn set_campaign(env: &Env, pool_index: u32, campaign_id: u32, campaign: &Campaign) {
+    let key = DataKeyExt::Campaign(pool_index, campaign_id);
+    env.storage().persistent().set(&key, campaign);
+    extend_persistent(env, &key);
+}
+
+/// Close a campaign: drop its entry and take it off the active list.
+pub fn remove_campaign(env: &Env, pool_index: u32, campaign_id: u32) {
+    env.storage()
+        .persistent()
+        .remove(&DataKeyExt::Campaign(pool_index, campaign_id));
+    let mut active = get_active_campaigns(env, pool_index);
+    if let Some(position) = active.first_index_of(campaign_id) {
+        active.remove(position);
+        set_active_campaigns(env, pool_index, &active);
+    }
+}
+
+/// Allocate the next campaign id for a pool.
+pub fn next_campaign_id(env: &Env, pool_index: u32) -> u32 {
+    let key = DataKeyExt::CampaignCount(pool_index);
+    let id: u32 = env.storage().persistent().get(&key).unwrap_or(0);
+    env.storage().persistent().set(&key, &(id + 1));
+    extend_persistent(env, &key);
+    id
+}
+
+/// Ids of a pool's campaigns that have not been reclaimed.
+pub fn get_active_campaigns(env: &Env, pool_index: u32) -> Vec<u32> {
+    env.storage()
+        .persistent()
+        .get(&DataKeyExt::ActiveCampaigns(pool_index))
+        .unwrap_or(Vec::new(env))
+}
+
+pub fn set_active_campaigns(env: &Env, pool_index: u32, active: &Vec<u32>) {
+    let key = DataKeyExt::ActiveCampaigns(pool_index);
+    env.storage().persistent().set(&key, active);
+    extend_persistent(env, &key);
+}
+
+/// A staker's campaign shares in a pool, by campaign id.
+pub fn get_campaign_shares(env: &Env, user: &Address, pool_index: u32) -> Map<u32, RevenueShare> {
+    env.storage()
+        .persistent()
+        .get(&DataKeyExt::CampaignShares(user.clone(), pool_index))
+        .unwrap_or(Map::new(env))
+}
+
+/// Store a staker's campaign shares, dropping the entry once empty.
+pub fn set_campaign_shares(
+    env: &Env,
+    user: &Address,
+    pool_index: u32,
+    shares: &Map<u32, RevenueShare>,
+) {
+    let key = DataKeyExt::CampaignShares(user.clone(), pool_index);
+    if shares.is_empty() {
+        env.storage().persistent().remove(&key);
+        return;
+    }
+    env.storage().persistent().set(&key, shares);
+    extend_persistent(env, &key);
+}
+
+pub fn get_claim_deadline(env: &Env) -> Option<ClaimDeadline> {
+    env.storage().instance().get(&DataKeyExt::ClaimDeadline)
+}
+
+pub fn set_claim_deadline(env: &Env, deadline: &ClaimDeadline) {
+    env.storage()
+        .instance()
+        .set(&DataKeyExt::ClaimDeadline, deadline);
+}
+
+pub fn remove_claim_deadline(env: &Env) {
+    env.storage().instance().remove(&DataKeyExt::ClaimDeadline);
+}
+
+pub fn get_keeper_bounties(env: &Env) -> KeeperBounties {
+    env.storage()
+        .instance()
+        .get(&DataKeyExt::KeeperBounties)
+        .unwrap_or_default()
+}
+
+pub fn set_keeper_bounties(env: &Env, bounties: &KeeperBounties) {
+    env.storage()
+        .instance()
+        .set(&DataKeyExt::KeeperBounties, bounties);
+}
+
+pub fn get_bounty_usage(env: &Env) -> BountyUsage {
+    env.storage()
+        .instance()
+        .get(&DataKeyExt::BountyUsage)
+        .unwrap_or_default()
+}
+
+pub fn set_bounty_usage(env: &Env, usage: &BountyUsage) {
+    env.storage()
+        .instance()
+        .set(&DataKeyExt::BountyUsage, usage);
+}
+
+/// When `user` last staked or claimed in a pool, if recorded.
+pub fn get_last_active(env: &Env, user: &Address, pool_index: u32) -> Option<u64> {
+    env.storage()
+        .persistent()
+        .get(&DataKeyExt::LastActive(user.clone(), pool_index))
+}
+
+pub fn set_last_active(env: &Env, user: &Address, pool_index: u32) {
+    let key = DataKeyExt::LastActive(user.clone(), pool_index);
+    env.storage()
+        .persistent()
+        .set(&key, &env.ledger().timestamp());
+    extend_persistent(env, &key);
+}
+
+pub fn remove_last_active(env: &Env, user: &Address, pool_index: u32) {
+    env.storage()
+        .persistent()
+        .remove(&DataKeyExt::LastActive(user.clone(), pool_index));
+}
+
+// --- Temporary storage helpers (superseded Merkle roots) ---
+
+/// Keep a superseded root readable while its stakers can still claim: the
+/// claim deadline's worth of ledgers when one is set, never less than
+/// `TtlConfig::historical_root_ttl` and never past the network maximum.
+/// Temporary entries are not archived, they simply expire.
+pub fn set_historical_root(env: &Env, pool_index: u32, data: &MerkleRootData) {
+    let key = DataKey::HistoricalRoot(pool_index, data.epoch_id);
+    let deadline = get_claim_deadline(env).map_or(0, |deadline| {
+        u32::try_from(deadline.seconds.div_ceil(LEDGER_SECONDS)).unwrap_or(u32::MAX)
+    });
+    let ttl = deadline
+        .max(get_ttl_config(env).historical_root_ttl)
+        .min(env.storage().max_ttl());
+    env.storage().temporary().set(&key, data);
+    env.storage().temporary().extend_ttl(&key, ttl, ttl);
+}
+
+pub fn get_historical_root(env: &Env, pool_index: u32, epoch_id: u64) -> Option<MerkleRootData> {
+    env.storage()
+        .temporary()
+        .get(&DataKey::HistoricalRoot(pool_index, epoch_id))
+        .map(|raw| decode_root(env, raw))
+}
+
+pub fn has_staker(env: &Env, user: &Address, pool_index: u32) -> bool {
+    env.storage()
+        .persistent()
+        .has(&DataKey::Staker(user.clone(), pool_index))
+}
+
+pub fn get_staker(env: &Env, user: &Address, pool_index: u32) -> StakerInfo {
+    let key = DataKey::Staker(user.clone(), pool_index);
+    let mut info = read_staker(env, &key).unwrap();
+    extend_staker_key(env, &key, get_ttl_config(env).persistent_threshold);
+    crate::token_migration::convert_staker(env, user, pool_index, &mut info);
+    info
+}
+
+/// Decode the staker entry at `key` in either the current or the v2 layout.
+fn read_staker(env: &Env, key: &DataKey) -> Option<StakerInfo> {
+    let raw: Val = env.storage().persistent().get(key)?;
+    if Map::<Symbol, Val>::from_val(env, &raw).len() != STAKER_INFO_V2_FIELDS {
+        return Some(StakerInfo::from_val(env, &raw));
+    }
+    Some(StakerInfoV2::from_val(env, &raw).into())
+}
+
+pub fn set_staker(env: &Env, user: &Address, pool_index: u32, info: &StakerInfo) {
+    let key = DataKey::Staker(user.clone(), pool_index);
+    let old = read_staker(env, &key);
+    track_active_staker(env, pool_index, old.as_ref(), Some(info));
+    let old_weight = old.map_or(0, |staker| staker.weight);
+    crate::rewards::settle_shares(env, user, pool_index, old_weight, info.weight);
+    roll_warmup(env, user, pool_index);
+    env.storage().persistent().set(&key, info);
+    extend_staker_key(env, &key, get_ttl_config(env).persistent_threshold);
+
+    // Entries are written in the current token's units
+    let era = get_token_migrations(env).len();
+    if era > 0 {
+        let era_key = DataKeyExt::StakerEra(user.clone(), pool_index);
+        env.storage().persistent().set(&era_key, &era);
+        extend_persistent(env, &era_key);
+    }
+}
+
+/// Top up every persistent entry holding `user`'s position in a pool to the
+/// full staker TTL. Returns false if the user has no staker entry.
+pub fn extend_staker_entries(env: &Env, user: &Address, pool_index: u32) -> bool {
+    let staker_key = DataKey::Staker(user.clone(), pool_index);
+    if !env.storage().persistent().has(&staker_key) {
+        return false;
+    }
+
+    let keys = [
+        staker_key,
+        DataKey::StakerSlot(user.clone(), pool_index),
+        DataKey::UserClaimed(user.clone(), pool_index),
+        DataKey::LastClaimEpoch(user.clone(), pool_index),
+        DataKey::UserClaimedTotal(user.clone()),
+    ];
+    let staker_extend = get_ttl_config(env).staker_extend;
+    for key in keys.iter() {
+        if env.storage().persistent().has(key) {
+            extend_staker_key(env, key, staker_extend);
+        }
+    }
+    true
+}
+
+// Staker entries get a longer extension than pool data: pools are touched by
+// every interaction, a staker's entry only by their own.
+fn extend_staker_key(env: &Env, key: &DataKey, threshold: u32) {
+    let extend_to = get_ttl_config(env).staker_extend;
+    env.storage()
+        .persistent()
+        .extend_ttl(key, threshold.min(extend_to), extend_to);
 }
 
 pub fn remove_staker(env: &Env, user: &Address, pool_index: u32) {
     let key = DataKey::Staker(user.clone(), pool_index);
+    let old = read_staker(env, &key);
+    track_active_staker(env, pool_index, old.as_ref(), None);
+    let old_weight = old.map_or(0, |staker| staker.weight);
+    crate::rewards::settle_shares(env, user, pool_index, old_weight, 0);
     env.storage().persistent().remove(&key);
+    env.storage()
+        .persistent()
+        .remove(&DataKeyExt::Warmup(user.clone(), pool_index));
+    env.storage()
+        .persistent()
+        .remove(&DataKeyExt::StakerEra(user.clone(), pool_index));
+}
+
+pub fn get_warmup(env: &Env, user: &Address, pool_index: u32) -> Option<Warmup> {
+    env.storage()
+        .persistent()
+        .get(&DataKeyExt::Warmup(user.clone(), pool_index))
+}
+
+pub fn set_warmup(env: &Env, user: &Address, pool_index: u32, warmup: &Warmup) {
+    let key = DataKeyExt::Warmup(user.clone(), pool_index);
+    env.storage().persistent().set(&key, warmup);
+    extend_persistent(env, &key);
+}
+
+/// A staker entry is only written once its rewards are settled to now, so
+/// any warm-up left restarts its forfeit window from now, and one that has
+/// run out is dropped.
+fn roll_warmup(env: &Env, user: &Address, pool_index: u32) {
+    let Some(mut warmup) = get_warmup(env, user, pool_index) else {
+        return;
+    };
+    let now = env.ledger().timestamp();
+    if now >= warmup.until {
+        env.storage()
+            .persistent()
+            .remove(&DataKeyExt::Warmup(user.clone(), pool_index));
+    } else {
+        warmup.from = now;
+        set_warmup(env, user, pool_index, &warmup);
+    }
+}
+
+/// Stakers with a non-zero stake in the pool's current epoch. Deposit-mode
+/// pools have no epochs, so every non-zero stake counts there.
+pub fn get_active_staker_count(env: &Env, pool_index: u32) -> u32 {
+    let active: ActiveStakers = env
+        .storage()
+        .persistent()
+        .get(&DataKeyExt::ActiveStakers(pool_index))
+        .unwrap_or_default();
+    if active.epoch_id != current_epoch_id(env, pool_index) {
+        return 0;
+    }
+    active.count
+}
+
+/// Adjust the active staker count for the staker entry at `key` changing to
+/// `info` (None when it is removed). Rotating the root resets the count
+/// implicitly, since every stake then belongs to an older epoch.
+fn track_active_staker(
+    env: &Env,
+    pool_index: u32,
+    old: Option<&StakerInfo>,
+    info: Option<&StakerInfo>,
+) {
+    let epoch_id = current_epoch_id(env, pool_index);
+    let is_active = |staker: &StakerInfo| staker.staked_amount > 0 && staker.epoch_id == epoch_id;
+    let was_active = old.is_some_and(is_active);
+    if was_active == info.is_some_and(is_active) {
+        return;
+    }
+
+    let active_key = DataKeyExt::ActiveStakers(pool_index);
+    let mut active: ActiveStakers = env
+        .storage()
+        .persistent()
+        .get(&active_key)
+        .unwrap_or_default();
+    if active.epoch_id != epoch_id {
+        active = ActiveStakers { epoch_id, count: 0 };
+    }
+    if was_active {
+        active.count = active.count.saturating_sub(1);
+    } else {
+        active.count += 1;
+    }
+    env.storage().persistent().set(&active_key, &active);
+    extend_persistent(env, &active_key);
+}
+
+fn current_epoch_id(env: &Env, pool_index: u32) -> u64 {
+    read_pool_entry(env, pool_index)
+        .and_then(|entry| entry.root)
+        .map_or(0, |root| root.epoch_id)
+}
+
+pub fn has_identity_binding(env: &Env, identity: &BytesN<32>) -> bool {
+    env.storage()
+        .persistent()
+        .has(&DataKey::IdentityBinding(identity.clone()))
+}
+
+pub fn get_identity_binding(env: &Env, identity: &BytesN<32>) -> IdentityBinding {
+    let key = DataKey::IdentityBinding(identity.clone());
+    let binding: IdentityBinding = env.storage().persistent().get(&key).unwrap();
+    extend_persistent(env, &key);
+    binding
+}
+
+pub fn set_identity_binding(env: &Env, identity: &BytesN<32>, binding: &IdentityBinding) {
+    let key = DataKey::IdentityBinding(identity.clone());
+    env.storage().persistent().set(&key, binding);
+    extend_persistent(env, &key);
+}
+
+/// Last epoch in which `identity` was used to stake in a pool (0 if never).
+pub fn get_identity_epoch(env: &Env, identity: &BytesN<32>, pool_index: u32) -> u64 {
+    env.storage()
+        .persistent()
+        .get(&DataKey::IdentityEpoch(identity.clone(), pool_index))
+        .unwrap_or(0)
+}
+
+pub fn set_identity_epoch(env: &Env, identity: &BytesN<32>, pool_index: u32, epoch_id: u64) {
+    let key = DataKey::IdentityEpoch(identity.clone(), pool_index);
+    env.storage().persistent().set(&key, &epoch_id);
+    extend_persistent(env, &key);
+}
+
+/// Checkpoint history for a pool, oldest first.
+pub fn get_checkpoints(env: &Env, pool_index: u32) -> Vec<Checkpoint> {
+    env.storage()
+        .persistent()
+        .get(&DataKey::Checkpoints(pool_index))
+        .unwrap_or(Vec::new(env))
+}
+
+pub fn set_checkpoints(env: &Env, pool_index: u32, checkpoints: &Vec<Checkpoint>) {
+    let key = DataKey::Checkpoints(pool_index);
+    env.storage().persistent().set(&key, checkpoints);
+    extend_persistent(env, &key);
+}
+
+/// Recent slashes, oldest first.
+pub fn get_slash_log(env: &Env) -> Vec<SlashRecord> {
+    env.storage()
+        .persistent()
+        .get(&DataKey::SlashLog)
+        .unwrap_or(Vec::new(env))
+}
+
+pub fn set_slash_log(env: &Env, log: &Vec<SlashRecord>) {
+    let key = DataKey::SlashLog;
+    env.storage().persistent().set(&key, log);
+    extend_persistent(env, &key);
+}
+
+pub fn get_admin_log(env: &Env) -> Vec<AdminAction> {
+    env.storage()
+        .persistent()
+        .get(&DataKeyExt::AdminLog)
+        .unwrap_or(Vec::new(env))
+}
+
+pub fn set_admin_log(env: &Env, log: &Vec<AdminAction>) {
+    let key = DataKeyExt::AdminLog;
+    env.storage().persistent().set(&key, log);
+    extend_persistent(env, &key);
+}
+
+pub fn get_claim_stats(env: &Env, pool_index: u32, epoch_id: u64) -> ClaimStats {
+    env.storage()
+        .persistent()
+        .get(&DataKey::ClaimStats(pool_index, epoch_id))
+        .unwrap_or_default()
+}
+
+pub fn set_claim_stats(env: &Env, pool_index: u32, epoch_id: u64, stats: &ClaimStats) {
+    let key = DataKey::ClaimStats(pool_index, epoch_id);
+    env.storage().persistent().set(&key, stats);
+    extend_persistent(env, &key);
+}
+
+/// Last epoch `user` claimed against in a pool (0 if never).
+pub fn get_last_claim_epoch(env: &Env, user: &Address, pool_index: u32) -> u64 {
+    env.storage()
+        .persistent()
+        .get(&DataKey::LastClaimEpoch(user.clone(), pool_index))
+        .unwrap_or(0)
+}
+
+pub fn remove_last_claim_epoch(env: &Env, user: &Address, pool_index: u32) {
+    env.storage()
+        .persistent()
+        .remove(&DataKey::LastClaimEpoch(user.clone(), pool_index));
+}
+
+pub fn set_last_claim_epoch(env: &Env, user: &Address, pool_index: u32, epoch_id: u64) {
+    let key = DataKey::LastClaimEpoch(user.clone(), pool_index);
+    env.storage().persistent().set(&key, &epoch_id);
+    extend_persistent(env, &key);
+}
+
+/// Lifetime rewards `user` has claimed from a pool.
+pub fn get_user_claimed(env: &Env, user: &Address, pool_index: u32) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&DataKey::UserClaimed(user.clone(), pool_index))
+        .unwrap_or(0)
+}
+
+pub fn set_user_claimed(env: &Env, user: &Address, pool_index: u32, amount: i128) {
+    let key = DataKey::UserClaimed(user.clone(), pool_index);
+    env.storage().persistent().set(&key, &amount);
+    extend_persistent(env, &key);
+}
+
+/// Lifetime rewards `user` has claimed across all pools.
+pub fn get_user_claimed_total(env: &Env, user: &Address) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&DataKey::UserClaimedTotal(user.clone()))
+        .unwrap_or(0)
+}
+
+pub fn set_user_claimed_total(env: &Env, user: &Address, amount: i128) {
+    let key = DataKey::UserClaimedTotal(user.clone());
+    env.storage().persistent().set(&key, &amount);
+    extend_persistent(env, &key);
+}
+
+// --- Staker registry: per-pool index of addresses with a non-zero stake ---
+
+pub fn get_staker_count(env: &Env, pool_index: u32) -> u32 {
+    env.storage()
+        .persistent()
+        .get(&DataKey::StakerCount(pool_index))
+        .unwrap_or(0)
+}
+
+fn set_staker_count(env: &Env, pool_index: u32, count: u32) {
+    let key = DataKey::StakerCount(pool_index);
+    env.storage().persistent().set(&key, &count);
+    extend_persistent(env, &key);
+}
+
+pub fn get_staker_at(env: &Env, pool_index: u32, slot: u32) -> Address {
+    let key = DataKey::StakerAt(pool_index, slot);
+    env.storage().persistent().get(&key).unwrap()
+}
+
+fn set_staker_at(env: &Env, pool_index: u32, slot: u32, user: &Address) {
+    let key = DataKey::StakerAt(pool_index, slot);
+    env.storage().persistent().set(&key, user);
+    extend_persistent(env, &key);
+}
+
+/// Add `user` to the pool's registry. No-op if already registered.
+pub fn register_staker(env: &Env, user: &Address, pool_index: u32) {
+    let slot_key = DataKey::StakerSlot(user.clone(), pool_index);
+    if env.storage().persistent().has(&slot_key) {
+        return;
+    }
+
+    let slot = get_staker_count(env, pool_index);
+    set_staker_at(env, pool_index, slot, user);
+    env.storage().persistent().set(&slot_key, &slot);
+    extend_persistent(env, &slot_key);
+    set_staker_count(env, pool_index, slot + 1);
+}
+
+/// Remove `user` from the pool's registry by moving the last entry into
+/// its slot. No-op if not registered.
+pub fn unregister_staker(env: &Env, user: &Address, pool_index: u32) {
+    let slot_key = DataKey::StakerSlot(user.clone(), pool_index);
+    let slot: u32 = match env.storage().persistent().get(&slot_key) {
+        Some(slot) => slot,
+        None => return,
+    };
+
+    let last = get_staker_count(env, pool_index) - 1;
+    if slot != last {
+        let moved = get_staker_at(env, pool_index, last);
+        set_staker_at(env, pool_index, slot, &moved);
+        let moved_key = DataKey::StakerSlot(moved, pool_index);
+        env.storage().persistent().set(&moved_key, &slot);
+        extend_persistent(env, &moved_key);
+    }
+
+    env.storage()
+        .persistent()
+        .remove(&DataKey::StakerAt(pool_index, last));
+    env.storage().persistent().remove(&slot_key);
+    set_staker_count(env, pool_index, last);
 }
 
-fn extend_persistent(env: &Env, key: &DataKey) {
+fn extend_persistent<K: IntoVal<Env, Val>>(env: &Env, key: &K) {
+    let ttl = get_ttl_config(env);
     env.storage()
         .persistent()
-        .extend_ttl(key, PERSISTENT_TTL_THRESHOLD, PERSISTENT_TTL_EXTEND);
+        .extend_ttl(key, ttl.persistent_threshold, ttl.persistent_extend);
 }
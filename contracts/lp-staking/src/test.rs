@@ -1,121 +1,214 @@
 #![cfg(test)]
+// Amounts are written as whole-LMNR + 7-digit stroop groups (e.g. 1_000_0000000).
+#![allow(clippy::inconsistent_digit_grouping)]
 extern crate alloc;
 
+use crate::batch::{ITEM_OK, ITEM_SKIPPED, MAX_BATCH_SIZE};
+use crate::errors::ContractError;
 use crate::merkle;
+use crate::migration::SCHEMA_VERSION;
+use crate::rewards;
+use crate::storage::{
+    Accounting, ClaimStats, IdentityProof, KeeperBounties, OracleSignature, Payout, RateOracle,
+    RootOracles, StakeRequest, StakeUpdate, TtlConfig,
+};
+use crate::testutils::{build_merkle_tree, build_positional_merkle_tree, pack_proof};
 use crate::{LpStakingContract, LpStakingContractClient};
-use soroban_sdk::testutils::{Address as _, Ledger, LedgerInfo};
-use soroban_sdk::{token, Address, BytesN, Env, Vec};
-
-// Helper: build a minimal Merkle tree from leaves and return (root, proofs).
-// Supports 1-4 leaves for testing.
-fn build_merkle_tree(
-    env: &Env,
-    leaves: &[BytesN<32>],
-) -> (BytesN<32>, soroban_sdk::Vec<soroban_sdk::Vec<BytesN<32>>>) {
-    use soroban_sdk::Bytes;
-
-    let hash_pair = |a: &BytesN<32>, b: &BytesN<32>| -> BytesN<32> {
-        let mut data = Bytes::new(env);
-        data.push_back(0x01); // NODE_PREFIX
-
-        let a_bytes: Bytes = a.clone().into();
-        let b_bytes: Bytes = b.clone().into();
-
-        if a_bytes <= b_bytes {
-            data.append(&a_bytes);
-            data.append(&b_bytes);
-        } else {
-            data.append(&b_bytes);
-            data.append(&a_bytes);
+use aqua_pool::{MockAquaPool, MockAquaPoolClient};
+use denylist_hook::{DenylistHook, DenylistHookClient};
+use ed25519_dalek::{Signer, SigningKey};
+use failing_listener::FailingListener;
+use lp_staking_interface::events::{
+    self, Claimed, EpochRotated, ExpiringSoon, StakeReduced, TimeUnaccrued,
+};
+use price_oracle::{MockPriceOracle, MockPriceOracleClient};
+use recording_listener::{RecordingListener, RecordingListenerClient};
+use recording_stake_hook::{RecordingStakeHook, RecordingStakeHookClient};
+use soroban_sdk::testutils::storage::{Persistent as _, Temporary as _};
+use soroban_sdk::testutils::{Address as _, Events, IssuerFlags, Ledger, LedgerInfo};
+use soroban_sdk::xdr::ToXdr;
+use soroban_sdk::{token, Address, BytesN, Env, IntoVal, Symbol, Val, Vec};
+use wide_token::{WideToken, WideTokenClient};
+
+// The original deployment, to seed from.
+#[allow(clippy::all, dead_code)]
+#[path = "../fixtures/legacy/lib.rs"]
+mod legacy_contract;
+
+// Listener contract that records the last epoch notification it received.
+mod recording_listener {
+    use soroban_sdk::{contract, contractimpl, symbol_short, BytesN, Env};
+
+    #[contract]
+    pub struct RecordingListener;
+
+    #[contractimpl]
+    impl RecordingListener {
+        pub fn on_epoch(env: Env, pool_index: u32, epoch_id: u64, root: BytesN<32>) {
+            env.storage()
+                .instance()
+                .set(&symbol_short!("last"), &(pool_index, epoch_id, root));
         }
 
-        env.crypto().sha256(&data).into()
-    };
+        pub fn last(env: Env) -> Option<(u32, u64, BytesN<32>)> {
+            env.storage().instance().get(&symbol_short!("last"))
+        }
+    }
+}
+
+// Listener contract that always traps.
+mod failing_listener {
+    use soroban_sdk::{contract, contractimpl, BytesN, Env};
+
+    #[contract]
+    pub struct FailingListener;
+
+    #[contractimpl]
+    impl FailingListener {
+        pub fn on_epoch(_env: Env, _pool_index: u32, _epoch_id: u64, _root: BytesN<32>) {
+            panic!("listener failure");
+        }
+    }
+}
+
+// Stake hook that records the last notification of each kind.
+mod recording_stake_hook {
+    use soroban_sdk::{contract, contractimpl, symbol_short, Address, Env};
+
+    #[contract]
+    pub struct RecordingStakeHook;
+
+    #[contractimpl]
+    impl RecordingStakeHook {
+        pub fn on_stake(env: Env, user: Address, pool_index: u32, staked_amount: i128) {
+            env.storage()
+                .instance()
+                .set(&symbol_short!("stake"), &(user, pool_index, staked_amount));
+        }
+
+        pub fn on_claim(env: Env, user: Address, pool_index: u32, amount: i128) {
+            env.storage()
+                .instance()
+                .set(&symbol_short!("claim"), &(user, pool_index, amount));
+        }
+
+        pub fn last_stake(env: Env) -> Option<(Address, u32, i128)> {
+            env.storage().instance().get(&symbol_short!("stake"))
+        }
+
+        pub fn last_claim(env: Env) -> Option<(Address, u32, i128)> {
+            env.storage().instance().get(&symbol_short!("claim"))
+        }
+    }
+}
+
+// Minimal Aquarius AMM pool exposing only its share token.
+mod aqua_pool {
+    use soroban_sdk::{contract, contractimpl, symbol_short, Address, Env};
+
+    #[contract]
+    pub struct MockAquaPool;
+
+    #[contractimpl]
+    impl MockAquaPool {
+        pub fn init(env: Env, share_token: Address) {
+            env.storage()
+                .instance()
+                .set(&symbol_short!("share"), &share_token);
+        }
+
+        pub fn share_id(env: Env) -> Address {
+            env.storage()
+                .instance()
+                .get(&symbol_short!("share"))
+                .unwrap()
+        }
+    }
+}
+
+// Rate oracle quoting one fixed LP price for every pool.
+mod price_oracle {
+    use soroban_sdk::{contract, contractimpl, symbol_short, BytesN, Env};
 
-    match leaves.len() {
-        1 => {
-            let root = leaves[0].clone();
-            let mut proofs = soroban_sdk::Vec::new(env);
-            proofs.push_back(soroban_sdk::Vec::new(env));
-            (root, proofs)
+    #[contract]
+    pub struct MockPriceOracle;
+
+    #[contractimpl]
+    impl MockPriceOracle {
+        pub fn set_price(env: Env, price: i128) {
+            env.storage()
+                .instance()
+                .set(&symbol_short!("price"), &price);
+        }
+
+        pub fn lp_price(env: Env, _pool_id: BytesN<32>) -> i128 {
+            env.storage()
+                .instance()
+                .get(&symbol_short!("price"))
+                .unwrap()
         }
-        2 => {
-            let root = hash_pair(&leaves[0], &leaves[1]);
-            let mut proofs = soroban_sdk::Vec::new(env);
+    }
+}
+
+// Token with 18 decimals, like bridged EVM assets, for partner pools.
+mod wide_token {
+    use soroban_sdk::{contract, contractimpl, Address, Env};
 
-            let mut proof0 = soroban_sdk::Vec::new(env);
-            proof0.push_back(leaves[1].clone());
-            proofs.push_back(proof0);
+    #[contract]
+    pub struct WideToken;
 
-            let mut proof1 = soroban_sdk::Vec::new(env);
-            proof1.push_back(leaves[0].clone());
-            proofs.push_back(proof1);
+    #[contractimpl]
+    impl WideToken {
+        pub fn decimals(_env: Env) -> u32 {
+            18
+        }
 
-            (root, proofs)
+        pub fn mint(env: Env, to: Address, amount: i128) {
+            let balance = Self::balance(env.clone(), to.clone());
+            env.storage().instance().set(&to, &(balance + amount));
         }
-        3 => {
-            let n01 = hash_pair(&leaves[0], &leaves[1]);
-            let root = hash_pair(&n01, &leaves[2]);
 
-            let mut proofs = soroban_sdk::Vec::new(env);
+        pub fn balance(env: Env, id: Address) -> i128 {
+            env.storage().instance().get(&id).unwrap_or(0)
+        }
 
-            let mut proof0 = soroban_sdk::Vec::new(env);
-            proof0.push_back(leaves[1].clone());
-            proof0.push_back(leaves[2].clone());
-            proofs.push_back(proof0);
+        pub fn transfer(env: Env, from: Address, to: Address, amount: i128) {
+            from.require_auth();
+            let remaining = Self::balance(env.clone(), from.clone()) - amount;
+            assert!(remaining >= 0, "insufficient balance");
+            env.storage().instance().set(&from, &remaining);
+            Self::mint(env, to, amount);
+        }
+    }
+}
 
-            let mut proof1 = soroban_sdk::Vec::new(env);
-            proof1.push_back(leaves[0].clone());
-            proof1.push_back(leaves[2].clone());
-            proofs.push_back(proof1);
+// Compliance hook that passes everyone not explicitly denied.
+mod denylist_hook {
+    use soroban_sdk::{contract, contractimpl, Address, Env};
 
-            let mut proof2 = soroban_sdk::Vec::new(env);
-            proof2.push_back(n01);
-            proofs.push_back(proof2);
+    #[contract]
+    pub struct DenylistHook;
 
-            (root, proofs)
+    #[contractimpl]
+    impl DenylistHook {
+        pub fn deny(env: Env, user: Address) {
+            env.storage().instance().set(&user, &true);
         }
-        4 => {
-            let n01 = hash_pair(&leaves[0], &leaves[1]);
-            let n23 = hash_pair(&leaves[2], &leaves[3]);
-            let root = hash_pair(&n01, &n23);
-
-            let mut proofs = soroban_sdk::Vec::new(env);
-
-            let mut proof0 = soroban_sdk::Vec::new(env);
-            proof0.push_back(leaves[1].clone());
-            proof0.push_back(n23.clone());
-            proofs.push_back(proof0);
-
-            let mut proof1 = soroban_sdk::Vec::new(env);
-            proof1.push_back(leaves[0].clone());
-            proof1.push_back(n23.clone());
-            proofs.push_back(proof1);
-
-            let mut proof2 = soroban_sdk::Vec::new(env);
-            proof2.push_back(leaves[3].clone());
-            proof2.push_back(n01.clone());
-            proofs.push_back(proof2);
-
-            let mut proof3 = soroban_sdk::Vec::new(env);
-            proof3.push_back(leaves[2].clone());
-            proof3.push_back(n01);
-            proofs.push_back(proof3);
-
-            (root, proofs)
+
+        pub fn check(env: Env, user: Address) -> bool {
+            !env.storage().instance().has(&user)
         }
-        _ => panic!("build_merkle_tree only supports 1-4 leaves in tests"),
     }
 }
 
-struct TestEnv {
-    env: Env,
-    admin: Address,
-    lmnr_token: Address,
-    contract_id: Address,
+pub(crate) struct TestEnv {
+    pub(crate) env: Env,
+    pub(crate) admin: Address,
+    pub(crate) lmnr_token: Address,
+    pub(crate) contract_id: Address,
 }
 
-fn setup_env() -> TestEnv {
+pub(crate) fn setup_env() -> TestEnv {
     let env = Env::default();
     env.mock_all_auths();
 
@@ -136,6 +229,8 @@ fn setup_env() -> TestEnv {
     let lmnr_admin = Address::generate(&env);
     let lmnr_token_id = env.register_stellar_asset_contract_v2(lmnr_admin.clone());
     let lmnr_token = lmnr_token_id.address();
+    // Revocable, so tests can freeze trustlines
+    lmnr_token_id.issuer().set_flag(IssuerFlags::RevocableFlag);
 
     let client = LpStakingContractClient::new(&env, &contract_id);
     client.initialize(&admin, &lmnr_token, &462_962_963_i128);
@@ -154,10 +249,74 @@ fn setup_env() -> TestEnv {
     }
 }
 
-fn make_pool_id(env: &Env, seed: u8) -> BytesN<32> {
+// Helper: move the ledger clock, keeping the rest of setup_env's LedgerInfo.
+pub(crate) fn set_ledger(env: &Env, timestamp: u64, sequence_number: u32) {
+    env.ledger().set(LedgerInfo {
+        timestamp,
+        protocol_version: 22,
+        sequence_number,
+        network_id: [0u8; 32],
+        base_reserve: 10,
+        min_temp_entry_ttl: 100,
+        min_persistent_entry_ttl: 100,
+        max_entry_ttl: 10_000_000,
+    });
+}
+
+// Helper: add pool 0, post a single-leaf root for `user` and stake it.
+fn stake_single(t: &TestEnv, user: &Address, lp_balance: i128) {
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, user, lp_balance, 1);
+    let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+    client.stake(user, &0, &lp_balance, &proofs.get(0).unwrap());
+}
+
+pub(crate) fn make_pool_id(env: &Env, seed: u8) -> BytesN<32> {
     BytesN::from_array(env, &[seed; 32])
 }
 
+// Helper: sign the identity binding payload for `user` with `key`.
+fn make_identity_proof(
+    t: &TestEnv,
+    key: &SigningKey,
+    user: &Address,
+    nonce: u64,
+) -> IdentityProof {
+    let payload = merkle::identity_binding_payload(&t.env, &t.contract_id, user, nonce);
+    let message: alloc::vec::Vec<u8> = payload.iter().collect();
+    IdentityProof {
+        public_key: BytesN::from_array(&t.env, &key.verifying_key().to_bytes()),
+        signature: BytesN::from_array(&t.env, &key.sign(&message).to_bytes()),
+    }
+}
+
+// Helper: attest `root` as epoch `epoch_id` of `pool_index` with the root
+// oracle `key`.
+fn sign_root(
+    t: &TestEnv,
+    key: &SigningKey,
+    pool_index: u32,
+    epoch_id: u64,
+    root: &BytesN<32>,
+    snapshot_ledger: u32,
+) -> OracleSignature {
+    let payload = merkle::root_attestation_payload(
+        &t.env,
+        &t.contract_id,
+        pool_index,
+        epoch_id,
+        root,
+        snapshot_ledger,
+    );
+    let message: alloc::vec::Vec<u8> = payload.iter().collect();
+    OracleSignature {
+        public_key: BytesN::from_array(&t.env, &key.verifying_key().to_bytes()),
+        signature: BytesN::from_array(&t.env, &key.sign(&message).to_bytes()),
+    }
+}
+
 // ========== Tests ==========
 
 #[test]
@@ -185,6 +344,10 @@ fn test_add_pool() {
     assert_eq!(index, 0);
     assert_eq!(client.get_pool_count(), 1);
     assert_eq!(client.get_pool_id(&0), pool_id);
+    assert_eq!(client.get_pool_index(&pool_id), Some(0));
+    assert_eq!(client.get_pool_index(&make_pool_id(&t.env, 2)), None);
+    assert_eq!(client.find_pool_state(&0), Some(client.get_pool_state(&0)));
+    assert_eq!(client.find_pool_state(&1), None);
 }
 
 #[test]
@@ -235,8 +398,8 @@ fn test_merkle_proof_single_leaf() {
     let lp_balance: i128 = 1_000_0000000;
     let epoch_id: u64 = 1;
 
-    let leaf = merkle::compute_leaf(&t.env, 0, &user, lp_balance, epoch_id);
-    let (root, proofs) = build_merkle_tree(&t.env, &[leaf.clone()]);
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, lp_balance, epoch_id);
+    let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
 
     client.set_merkle_root(&t.admin, &0, &root, &100);
 
@@ -267,9 +430,9 @@ fn test_merkle_proof_multiple_leaves() {
     let bal3: i128 = 500_0000000;
     let epoch_id: u64 = 1;
 
-    let leaf1 = merkle::compute_leaf(&t.env, 0, &user1, bal1, epoch_id);
-    let leaf2 = merkle::compute_leaf(&t.env, 0, &user2, bal2, epoch_id);
-    let leaf3 = merkle::compute_leaf(&t.env, 0, &user3, bal3, epoch_id);
+    let leaf1 = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user1, bal1, epoch_id);
+    let leaf2 = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user2, bal2, epoch_id);
+    let leaf3 = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user3, bal3, epoch_id);
 
     let (root, proofs) = build_merkle_tree(&t.env, &[leaf1, leaf2, leaf3]);
     client.set_merkle_root(&t.admin, &0, &root, &100);
@@ -282,6 +445,41 @@ fn test_merkle_proof_multiple_leaves() {
     assert_eq!(state.total_staked, bal1 + bal2 + bal3);
 }
 
+#[test]
+fn test_merkle_tree_builder_any_leaf_count() {
+    let env = Env::default();
+    for count in 1..=9u8 {
+        let leaves: alloc::vec::Vec<BytesN<32>> = (0..count)
+            .map(|i| BytesN::from_array(&env, &[i + 1; 32]))
+            .collect();
+        let (root, proofs) = build_merkle_tree(&env, &leaves);
+        assert_eq!(proofs.len(), count as u32);
+        for (i, leaf) in leaves.iter().enumerate() {
+            let proof = proofs.get(i as u32).unwrap();
+            assert!(merkle::verify_proof(&env, leaf, &proof, &root));
+        }
+    }
+}
+
+#[test]
+fn test_positional_tree_builder_any_leaf_count() {
+    let env = Env::default();
+    for count in 1..=9u8 {
+        let leaves: alloc::vec::Vec<BytesN<32>> = (0..count)
+            .map(|i| BytesN::from_array(&env, &[i + 1; 32]))
+            .collect();
+        let (root, proofs, paths) = build_positional_merkle_tree(&env, &leaves);
+        for (i, leaf) in leaves.iter().enumerate() {
+            let proof = proofs.get(i as u32).unwrap();
+            let path = paths.get(i as u32).unwrap();
+            let verifies = |path| merkle::verify_positional_proof(&env, leaf, &proof, path, &root);
+            assert!(verifies(path));
+            // Flipping a direction bit breaks the proof
+            assert!(proof.is_empty() || !verifies(path ^ 1));
+        }
+    }
+}
+
 #[test]
 fn test_invalid_proof_rejected() {
     let t = setup_env();
@@ -293,7 +491,7 @@ fn test_invalid_proof_rejected() {
     let lp_balance: i128 = 1_000_0000000;
     let epoch_id: u64 = 1;
 
-    let leaf = merkle::compute_leaf(&t.env, 0, &user, lp_balance, epoch_id);
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, lp_balance, epoch_id);
     let (root, _proofs) = build_merkle_tree(&t.env, &[leaf]);
     client.set_merkle_root(&t.admin, &0, &root, &100);
 
@@ -314,7 +512,7 @@ fn test_stake_claim_flow() {
     let lp_balance: i128 = 10_000_0000000;
     let epoch_id: u64 = 1;
 
-    let leaf = merkle::compute_leaf(&t.env, 0, &user, lp_balance, epoch_id);
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, lp_balance, epoch_id);
     let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
     client.set_merkle_root(&t.admin, &0, &root, &100);
 
@@ -359,8 +557,8 @@ fn test_multiple_stakers_share_rewards() {
     let bal2: i128 = 3_000_0000000;
     let epoch_id: u64 = 1;
 
-    let leaf1 = merkle::compute_leaf(&t.env, 0, &user1, bal1, epoch_id);
-    let leaf2 = merkle::compute_leaf(&t.env, 0, &user2, bal2, epoch_id);
+    let leaf1 = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user1, bal1, epoch_id);
+    let leaf2 = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user2, bal2, epoch_id);
 
     let (root, proofs) = build_merkle_tree(&t.env, &[leaf1, leaf2]);
     client.set_merkle_root(&t.admin, &0, &root, &100);
@@ -401,7 +599,7 @@ fn test_epoch_transition() {
     let lp_balance: i128 = 10_000_0000000;
 
     // Epoch 1
-    let leaf1 = merkle::compute_leaf(&t.env, 0, &user, lp_balance, 1);
+    let leaf1 = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, lp_balance, 1);
     let (root1, proofs1) = build_merkle_tree(&t.env, &[leaf1]);
     client.set_merkle_root(&t.admin, &0, &root1, &100);
     client.stake(&user, &0, &lp_balance, &proofs1.get(0).unwrap());
@@ -423,7 +621,7 @@ fn test_epoch_transition() {
 
     // Post new epoch root (epoch 2) — resets total_staked
     let new_balance: i128 = 12_000_0000000;
-    let leaf2 = merkle::compute_leaf(&t.env, 0, &user, new_balance, 2);
+    let leaf2 = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, new_balance, 2);
     let (root2, proofs2) = build_merkle_tree(&t.env, &[leaf2]);
     client.set_merkle_root(&t.admin, &0, &root2, &150);
 
@@ -448,7 +646,7 @@ fn test_stale_staker_can_claim_pending() {
     let lp_balance: i128 = 10_000_0000000;
 
     // Epoch 1: stake
-    let leaf1 = merkle::compute_leaf(&t.env, 0, &user, lp_balance, 1);
+    let leaf1 = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, lp_balance, 1);
     let (root1, proofs1) = build_merkle_tree(&t.env, &[leaf1]);
     client.set_merkle_root(&t.admin, &0, &root1, &100);
     client.stake(&user, &0, &lp_balance, &proofs1.get(0).unwrap());
@@ -467,7 +665,7 @@ fn test_stale_staker_can_claim_pending() {
 
     // Post epoch 2 without user re-staking
     let another_user = Address::generate(&t.env);
-    let leaf2 = merkle::compute_leaf(&t.env, 0, &another_user, lp_balance, 2);
+    let leaf2 = merkle::compute_leaf(&t.env, &t.contract_id, 0, &another_user, lp_balance, 2);
     let (root2, _) = build_merkle_tree(&t.env, &[leaf2]);
     client.set_merkle_root(&t.admin, &0, &root2, &200);
 
@@ -504,7 +702,7 @@ fn test_double_stake_same_epoch_rejected() {
     let lp_balance: i128 = 1_000_0000000;
     let epoch_id: u64 = 1;
 
-    let leaf = merkle::compute_leaf(&t.env, 0, &user, lp_balance, epoch_id);
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, lp_balance, epoch_id);
     let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
     client.set_merkle_root(&t.admin, &0, &root, &100);
 
@@ -527,7 +725,7 @@ fn test_unstake() {
     let lp_balance: i128 = 10_000_0000000;
     let epoch_id: u64 = 1;
 
-    let leaf = merkle::compute_leaf(&t.env, 0, &user, lp_balance, epoch_id);
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, lp_balance, epoch_id);
     let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
     client.set_merkle_root(&t.admin, &0, &root, &100);
     client.stake(&user, &0, &lp_balance, &proofs.get(0).unwrap());
@@ -561,6 +759,412 @@ fn test_unstake() {
     assert_eq!(claimed, 462_962_963_000_i128);
 }
 
+#[test]
+fn test_unstake_partial_settles_and_reduces() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    let other = Address::generate(&t.env);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let leaves = [
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, 4_000_0000000, 1),
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &other, 4_000_0000000, 1),
+    ];
+    let (root, proofs) = build_merkle_tree(&t.env, &leaves);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+    client.stake(&user, &0, &4_000_0000000, &proofs.get(0).unwrap());
+    client.stake(&other, &0, &4_000_0000000, &proofs.get(1).unwrap());
+
+    set_ledger(&t.env, 1100, 110);
+    let earned = client.pending_reward(&user, &0);
+    client.unstake_partial(&user, &0, &3_000_0000000);
+    let staker = client.get_staker_info(&user, &0);
+    assert_eq!(staker.staked_amount, 1_000_0000000);
+    assert_eq!(staker.pending_rewards, earned);
+    assert_eq!(client.get_pool_state(&0).total_staked, 5_000_0000000);
+
+    // The smaller stake earns a fifth of the pool from here
+    set_ledger(&t.env, 1200, 120);
+    let share = 100 * 462_962_963 / 5;
+    assert_eq!(client.pending_reward(&user, &0), earned + share);
+
+    let result = client.try_unstake_partial(&user, &0, &2_000_0000000);
+    assert_eq!(result, Err(Ok(ContractError::InvalidAmount)));
+    client.unstake_partial(&user, &0, &1_000_0000000);
+    assert_eq!(client.get_staker_info(&user, &0).staked_amount, 0);
+    assert_eq!(client.get_staker_count(&0), 1);
+}
+
+#[test]
+fn test_reduce_stake_self_reports_new_balance() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 4_000_0000000);
+    set_ledger(&t.env, 1100, 110);
+
+    client.reduce_stake(&user, &0, &1_500_0000000);
+    let event = t.env.events().all().last().unwrap();
+    let data: StakeReduced = event.2.into_val(&t.env);
+    assert_eq!(
+        data,
+        StakeReduced {
+            amount: 2_500_0000000,
+            remaining: 1_500_0000000,
+        }
+    );
+    assert_eq!(client.get_pool_state(&0).total_staked, 1_500_0000000);
+
+    // Only reductions are self-reported
+    let result = client.try_reduce_stake(&user, &0, &2_000_0000000);
+    assert_eq!(result, Err(Ok(ContractError::InvalidAmount)));
+    client.reduce_stake(&user, &0, &0);
+    assert_eq!(client.get_staker_count(&0), 0);
+    assert!(client.pending_reward(&user, &0) > 0);
+}
+
+#[test]
+fn test_unstake_cooldown_blocks_restake() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    client.set_unstake_cooldown(&t.admin, &0, &600);
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, 1_000_0000000, 1);
+    let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+    let proof = proofs.get(0).unwrap();
+
+    client.stake(&user, &0, &1_000_0000000, &proof);
+    client.unstake(&user, &0);
+    // Nothing was pending, but the entry stays to hold the cooldown
+    assert_eq!(client.get_staker_info(&user, &0).cooldown_until, 1600);
+    let result = client.try_prune_staker(&t.admin, &user, &0);
+    assert_eq!(result, Err(Ok(ContractError::StakerNotEmpty)));
+
+    set_ledger(&t.env, 1599, 110);
+    let result = client.try_stake(&user, &0, &1_000_0000000, &proof);
+    assert_eq!(result, Err(Ok(ContractError::CooldownActive)));
+    set_ledger(&t.env, 1600, 111);
+    client.stake(&user, &0, &1_000_0000000, &proof);
+    assert_eq!(client.get_staker_info(&user, &0).cooldown_until, 0);
+}
+
+#[test]
+fn test_warmup_forfeits_early_accrual_pro_rata() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    client.set_warmup_period(&t.admin, &0, &100);
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, 1_000_0000000, 1);
+    let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+    client.stake(&user, &0, &1_000_0000000, &proofs.get(0).unwrap());
+
+    set_ledger(&t.env, 1050, 105);
+    assert_eq!(client.pending_reward(&user, &0), 0);
+    let result = client.try_claim(&user, &0);
+    assert_eq!(result, Err(Ok(ContractError::NoRewardsToClaim)));
+
+    // Half of the 200s since staking fell inside the warm-up
+    set_ledger(&t.env, 1200, 110);
+    assert_eq!(client.claim(&user, &0), 100 * 462_962_963);
+    set_ledger(&t.env, 1300, 120);
+    assert_eq!(client.claim(&user, &0), 100 * 462_962_963);
+
+    // Re-proving the same balance next epoch starts no new warm-up
+    rotate_and_restake(&t, &user, 1_000_0000000, 2);
+    set_ledger(&t.env, 1400, 130);
+    assert_eq!(client.claim(&user, &0), 100 * 462_962_963);
+}
+
+#[test]
+fn test_loyalty_streak_boosts_weight_until_an_epoch_is_missed() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    let other = Address::generate(&t.env);
+    stake_single(&t, &user, 1_000_0000000);
+    client.set_loyalty_bonus(&t.admin, &0, &100, &150);
+    assert_eq!(client.get_staker_info(&user, &0).weight, 1_000_0000000);
+
+    rotate_and_restake(&t, &user, 1_000_0000000, 2);
+    let staker = client.get_staker_info(&user, &0);
+    assert_eq!((staker.streak, staker.weight), (1, 1_010_0000000));
+    rotate_and_restake(&t, &user, 1_000_0000000, 3);
+    rotate_and_restake(&t, &user, 1_000_0000000, 4);
+    // Capped at 1.5%
+    let staker = client.get_staker_info(&user, &0);
+    assert_eq!((staker.streak, staker.weight), (3, 1_015_0000000));
+    assert_eq!(client.get_pool_state(&0).total_staked, 1_015_0000000);
+
+    // Epoch 5 passes without the user re-proving
+    rotate_and_restake(&t, &other, 1_000_0000000, 5);
+    rotate_and_restake(&t, &user, 1_000_0000000, 6);
+    let staker = client.get_staker_info(&user, &0);
+    assert_eq!((staker.streak, staker.weight), (0, 1_000_0000000));
+    assert_eq!(client.get_pool_state(&0).total_staked, 2_000_0000000);
+}
+
+#[test]
+fn test_sqrt_weighting_shares_by_root_of_balance() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let small = Address::generate(&t.env);
+    let large = Address::generate(&t.env);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    client.set_sqrt_weighting(&t.admin, &0, &true);
+    let leaves = [
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &small, 90_0000000, 1),
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &large, 360_0000000, 1),
+    ];
+    let (root, proofs) = build_merkle_tree(&t.env, &leaves);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+    client.stake(&small, &0, &90_0000000, &proofs.get(0).unwrap());
+    client.stake(&large, &0, &360_0000000, &proofs.get(1).unwrap());
+
+    // Four times the LP earns twice the rewards
+    assert_eq!(client.get_staker_info(&small, &0).weight, 30_000);
+    assert_eq!(client.get_pool_state(&0).total_staked, 90_000);
+    set_ledger(&t.env, 1300, 130);
+    let small_reward = client.pending_reward(&small, &0);
+    let large_reward = client.pending_reward(&large, &0);
+    assert!((large_reward - 2 * small_reward).abs() <= 2);
+
+    let result = client.try_set_sqrt_weighting(&t.admin, &0, &false);
+    assert_eq!(result, Err(Ok(ContractError::PoolInUse)));
+}
+
+#[test]
+fn test_stake_cap_limits_weight_not_balance() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let whale = Address::generate(&t.env);
+    let minnow = Address::generate(&t.env);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    client.set_pool_stake_cap(&t.admin, &0, &1_000_0000000);
+    let leaves = [
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &whale, 9_000_0000000, 1),
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &minnow, 1_000_0000000, 1),
+    ];
+    let (root, proofs) = build_merkle_tree(&t.env, &leaves);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+    client.stake(&whale, &0, &9_000_0000000, &proofs.get(0).unwrap());
+    client.stake(&minnow, &0, &1_000_0000000, &proofs.get(1).unwrap());
+
+    let staker = client.get_staker_info(&whale, &0);
+    assert_eq!(staker.staked_amount, 9_000_0000000);
+    assert_eq!(staker.weight, 1_000_0000000);
+    assert_eq!(client.get_pool_state(&0).total_staked, 2_000_0000000);
+
+    set_ledger(&t.env, 1100, 110);
+    assert_eq!(
+        client.pending_reward(&whale, &0),
+        client.pending_reward(&minnow, &0)
+    );
+}
+
+#[test]
+fn test_isqrt() {
+    assert_eq!(rewards::isqrt(0), 0);
+    assert_eq!(rewards::isqrt(1), 1);
+    assert_eq!(rewards::isqrt(15), 3);
+    assert_eq!(rewards::isqrt(16), 4);
+    assert_eq!(rewards::isqrt(i128::MAX), 13_043_817_825_332_782_212);
+}
+
+#[test]
+fn test_twab_root_accepts_only_twab_leaves() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    let other = Address::generate(&t.env);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let leaves = [
+        merkle::compute_twab_leaf(&t.env, &t.contract_id, 0, &user, 750_0000000, 86_400, 1),
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &other, 750_0000000, 1),
+    ];
+    let (root, proofs) = build_merkle_tree(&t.env, &leaves);
+
+    let result = client.try_set_twab_root(&t.admin, &0, &root, &100, &0, &0);
+    assert_eq!(result, Err(Ok(ContractError::InvalidConfig)));
+    client.set_twab_root(&t.admin, &0, &root, &100, &86_400, &0);
+    let posted = client.get_merkle_root(&0);
+    assert_eq!(posted.leaf_version, merkle::TWAB_LEAF_VERSION as u32);
+    assert_eq!(posted.epoch_duration, 86_400);
+
+    client.stake(&user, &0, &750_0000000, &proofs.get(0).unwrap());
+    assert_eq!(client.get_staker_info(&user, &0).staked_amount, 750_0000000);
+
+    // A v1 leaf in the same tree does not verify against a TWAB root
+    let result = client.try_stake(&other, &0, &750_0000000, &proofs.get(1).unwrap());
+    assert_eq!(result, Err(Ok(ContractError::InvalidProof)));
+}
+
+#[test]
+fn test_declared_total_bounds_proven_stake() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let alice = Address::generate(&t.env);
+    let bob = Address::generate(&t.env);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    // A corrupted tree whose leaves sum past the snapshot's supply
+    let leaves = [
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &alice, 600_0000000, 1),
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &bob, 600_0000000, 1),
+    ];
+    let (root, proofs) = build_merkle_tree(&t.env, &leaves);
+
+    let result = client.try_set_merkle_root_with_total(&t.admin, &0, &root, &100, &0);
+    assert_eq!(result, Err(Ok(ContractError::InvalidAmount)));
+    client.set_merkle_root_with_total(&t.admin, &0, &root, &100, &1_000_0000000);
+    assert_eq!(client.get_merkle_root(&0).declared_total, 1_000_0000000);
+
+    client.stake(&alice, &0, &600_0000000, &proofs.get(0).unwrap());
+    let result = client.try_stake(&bob, &0, &600_0000000, &proofs.get(1).unwrap());
+    assert_eq!(result, Err(Ok(ContractError::DeclaredTotalExceeded)));
+    assert_eq!(client.get_pool_state(&0).total_staked, 600_0000000);
+
+    // The next root starts its own count
+    set_ledger(&t.env, 1100, 110);
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, &bob, 600_0000000, 2);
+    let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
+    client.set_merkle_root_with_total(&t.admin, &0, &root, &110, &600_0000000);
+    client.stake(&bob, &0, &600_0000000, &proofs.get(0).unwrap());
+    assert_eq!(client.get_staker_info(&bob, &0).staked_amount, 600_0000000);
+}
+
+#[test]
+fn test_v3_roots_still_decode() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, 1_000_0000000, 1);
+    let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
+
+    // Store the root in the layout written before leaf versions, which
+    // predates combined pool entries too
+    t.env.as_contract(&t.contract_id, || {
+        let old = crate::storage::MerkleRootDataV3 {
+            root: root.clone(),
+            epoch_id: 1,
+            snapshot_ledger: 100,
+            posted_at: 1000,
+        };
+        let storage = t.env.storage().persistent();
+        storage.remove(&crate::storage::DataKeyExt::PoolEntry(0));
+        storage.set(&crate::storage::DataKey::MerkleRoot(0), &old);
+    });
+    let decoded = client.get_merkle_root(&0);
+    assert_eq!(decoded.leaf_version, merkle::LEAF_VERSION as u32);
+    assert_eq!(decoded.epoch_duration, 0);
+    assert_eq!(decoded.declared_total, 0);
+
+    client.stake(&user, &0, &1_000_0000000, &proofs.get(0).unwrap());
+    assert_eq!(client.get_pool_state(&0).total_staked, 1_000_0000000);
+}
+
+#[test]
+fn test_stake_leaf_adds_each_leaf_once() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let leaf = |index: u32, balance: i128| {
+        merkle::compute_indexed_leaf(&t.env, &t.contract_id, 0, &user, index, balance, 1)
+    };
+    let leaves = [leaf(0, 1_000_0000000), leaf(1, 3_000_0000000)];
+    let (root, proofs) = build_merkle_tree(&t.env, &leaves);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+
+    client.stake_leaf(&user, &0, &0, &1_000_0000000, &proofs.get(0).unwrap());
+    set_ledger(&t.env, 1100, 110);
+    client.stake_leaf(&user, &0, &1, &3_000_0000000, &proofs.get(1).unwrap());
+    let staker = client.get_staker_info(&user, &0);
+    assert_eq!(staker.staked_amount, 4_000_0000000);
+    assert_eq!(client.get_pool_state(&0).total_staked, 4_000_0000000);
+    assert_eq!(client.get_claimed_leaves(&user, &0), 0b11);
+    // The first 100s were earned on the first leaf alone
+    assert_eq!(client.pending_reward(&user, &0), 100 * 462_962_963);
+
+    let result = client.try_stake_leaf(&user, &0, &1, &3_000_0000000, &proofs.get(1).unwrap());
+    assert_eq!(result, Err(Ok(ContractError::AlreadyStakedThisEpoch)));
+    // A plain leaf for the same balance is not in this snapshot
+    let result = client.try_stake(&user, &0, &1_000_0000000, &proofs.get(0).unwrap());
+    assert_eq!(result, Err(Ok(ContractError::InvalidProof)));
+}
+
+#[test]
+fn test_find_staker_info_without_position() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    assert_eq!(client.find_staker_info(&user, &0), None);
+
+    stake_single(&t, &user, 1_000_0000000);
+    let staker = client.find_staker_info(&user, &0).unwrap();
+    assert_eq!(staker, client.get_staker_info(&user, &0));
+    assert_eq!(client.find_staker_info(&t.admin, &0), None);
+}
+
+#[test]
+fn test_pending_reward_batch_matches_single_queries() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let stale = Address::generate(&t.env);
+    let current = Address::generate(&t.env);
+    let outsider = Address::generate(&t.env);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let leaves = [
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &stale, 1_000_0000000, 1),
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &current, 3_000_0000000, 1),
+    ];
+    let (root, proofs) = build_merkle_tree(&t.env, &leaves);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+    client.stake(&stale, &0, &1_000_0000000, &proofs.get(0).unwrap());
+    client.stake(&current, &0, &3_000_0000000, &proofs.get(1).unwrap());
+
+    set_ledger(&t.env, 1100, 110);
+    rotate_and_restake(&t, &current, 3_000_0000000, 2);
+    set_ledger(&t.env, 1200, 120);
+
+    let users = Vec::from_array(&t.env, [stale.clone(), current.clone(), outsider.clone()]);
+    let amounts = client.pending_reward_batch(&0, &users);
+    for (user, amount) in users.iter().zip(amounts.iter()) {
+        assert_eq!(amount, client.pending_reward(&user, &0));
+    }
+    assert!(amounts.get(0).unwrap() > 0);
+    assert!(amounts.get(1).unwrap() > amounts.get(0).unwrap());
+    assert_eq!(amounts.get(2).unwrap(), 0);
+
+    let mut too_many = Vec::new(&t.env);
+    for _ in 0..=MAX_BATCH_SIZE {
+        too_many.push_back(outsider.clone());
+    }
+    let result = client.try_pending_reward_batch(&0, &too_many);
+    assert_eq!(result, Err(Ok(ContractError::BatchTooLarge)));
+}
+
+#[test]
+fn test_pending_reward_at_projects_current_rate() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 1_000_0000000);
+    set_ledger(&t.env, 1100, 110);
+
+    let now = client.pending_reward(&user, &0);
+    assert_eq!(client.pending_reward_at(&user, &0, &1100), now);
+    assert_eq!(client.pending_reward_at(&user, &0, &500), now);
+    let projected = client.pending_reward_at(&user, &0, &1500);
+    assert_eq!(projected, now + 400 * 462_962_963);
+
+    set_ledger(&t.env, 1500, 150);
+    assert_eq!(client.pending_reward(&user, &0), projected);
+}
+
 #[test]
 fn test_set_reward_rate() {
     let t = setup_env();
@@ -572,7 +1176,7 @@ fn test_set_reward_rate() {
     let lp_balance: i128 = 10_000_0000000;
     let epoch_id: u64 = 1;
 
-    let leaf = merkle::compute_leaf(&t.env, 0, &user, lp_balance, epoch_id);
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, lp_balance, epoch_id);
     let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
     client.set_merkle_root(&t.admin, &0, &root, &100);
     client.stake(&user, &0, &lp_balance, &proofs.get(0).unwrap());
@@ -591,7 +1195,7 @@ fn test_set_reward_rate() {
 
     // Double the rate
     let new_rate = 462_962_963_i128 * 2;
-    client.set_reward_rate(&t.admin, &new_rate);
+    client.set_reward_rate(&t.admin, &new_rate, &false);
 
     // Advance another 500 seconds at double rate
     t.env.ledger().set(LedgerInfo {
@@ -685,10 +1289,10 @@ fn test_four_leaf_merkle_tree() {
     let epoch_id: u64 = 1;
 
     let leaves: [BytesN<32>; 4] = [
-        merkle::compute_leaf(&t.env, 0, &users[0], balances[0], epoch_id),
-        merkle::compute_leaf(&t.env, 0, &users[1], balances[1], epoch_id),
-        merkle::compute_leaf(&t.env, 0, &users[2], balances[2], epoch_id),
-        merkle::compute_leaf(&t.env, 0, &users[3], balances[3], epoch_id),
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &users[0], balances[0], epoch_id),
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &users[1], balances[1], epoch_id),
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &users[2], balances[2], epoch_id),
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &users[3], balances[3], epoch_id),
     ];
 
     let (root, proofs) = build_merkle_tree(&t.env, &leaves);
@@ -804,7 +1408,7 @@ fn test_update_stake_increase() {
     let epoch_id: u64 = 1;
 
     // Stake via merkle proof first
-    let leaf = merkle::compute_leaf(&t.env, 0, &user, lp_balance, epoch_id);
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, lp_balance, epoch_id);
     let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
     client.set_merkle_root(&t.admin, &0, &root, &100);
     client.stake(&user, &0, &lp_balance, &proofs.get(0).unwrap());
@@ -848,7 +1452,7 @@ fn test_update_stake_decrease() {
     let lp_balance: i128 = 10_000_0000000;
     let epoch_id: u64 = 1;
 
-    let leaf = merkle::compute_leaf(&t.env, 0, &user, lp_balance, epoch_id);
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, lp_balance, epoch_id);
     let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
     client.set_merkle_root(&t.admin, &0, &root, &100);
     client.stake(&user, &0, &lp_balance, &proofs.get(0).unwrap());
@@ -890,7 +1494,7 @@ fn test_update_stake_to_zero() {
     let lp_balance: i128 = 10_000_0000000;
     let epoch_id: u64 = 1;
 
-    let leaf = merkle::compute_leaf(&t.env, 0, &user, lp_balance, epoch_id);
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, lp_balance, epoch_id);
     let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
     client.set_merkle_root(&t.admin, &0, &root, &100);
     client.stake(&user, &0, &lp_balance, &proofs.get(0).unwrap());
@@ -935,7 +1539,7 @@ fn test_update_stake_new_user() {
 
     // Post merkle root so there's a current epoch
     let dummy_user = Address::generate(&t.env);
-    let leaf = merkle::compute_leaf(&t.env, 0, &dummy_user, 1_000_0000000, 1);
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, &dummy_user, 1_000_0000000, 1);
     let (root, _) = build_merkle_tree(&t.env, &[leaf]);
     client.set_merkle_root(&t.admin, &0, &root, &100);
 
@@ -977,7 +1581,7 @@ fn test_update_stake_stale_staker() {
     let lp_balance: i128 = 10_000_0000000;
 
     // Epoch 1: stake
-    let leaf1 = merkle::compute_leaf(&t.env, 0, &user, lp_balance, 1);
+    let leaf1 = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, lp_balance, 1);
     let (root1, proofs1) = build_merkle_tree(&t.env, &[leaf1]);
     client.set_merkle_root(&t.admin, &0, &root1, &100);
     client.stake(&user, &0, &lp_balance, &proofs1.get(0).unwrap());
@@ -996,7 +1600,7 @@ fn test_update_stake_stale_staker() {
 
     // Post epoch 2 (user is now stale)
     let other = Address::generate(&t.env);
-    let leaf2 = merkle::compute_leaf(&t.env, 0, &other, lp_balance, 2);
+    let leaf2 = merkle::compute_leaf(&t.env, &t.contract_id, 0, &other, lp_balance, 2);
     let (root2, _) = build_merkle_tree(&t.env, &[leaf2]);
     client.set_merkle_root(&t.admin, &0, &root2, &200);
 
@@ -1067,3 +1671,3779 @@ fn test_withdraw_exceeds_balance_fails() {
     let result = client.try_withdraw(&t.admin, &100_000_0000000_i128);
     assert!(result.is_err());
 }
+
+#[test]
+fn test_withdraw_limited_to_surplus() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+
+    // 1000s accrues ~463 LMNR owed to the staker
+    set_ledger(&t.env, 2000, 200);
+    let accrued = 462_962_963_000_i128;
+    let surplus = 50_000_0000000 - accrued;
+
+    let result = client.try_withdraw(&t.admin, &(surplus + 1));
+    assert_eq!(result, Err(Ok(ContractError::InsufficientRewardBalance)));
+
+    client.withdraw(&t.admin, &surplus);
+    assert_eq!(client.reward_balance(), accrued);
+
+    // Owed rewards remain claimable
+    assert_eq!(client.claim(&user, &0), accrued);
+}
+
+#[test]
+fn test_migrate_reward_token_converts_what_is_owed() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.set_reward_rate(&t.admin, &1_000, &false);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+
+    let new_token = t
+        .env
+        .register_stellar_asset_contract_v2(Address::generate(&t.env))
+        .address();
+    let ratio = 2_0000000_i128;
+    let result = client.try_queue_reward_token_migration(&t.admin, &new_token, &ratio);
+    assert_eq!(result, Err(Ok(ContractError::NotPaused)));
+
+    client.pause(&t.admin);
+    let result = client.try_migrate_reward_token(&t.admin, &new_token, &ratio);
+    assert_eq!(result, Err(Ok(ContractError::NoPendingMigration)));
+    client.queue_reward_token_migration(&t.admin, &new_token, &ratio);
+
+    // Unpausing cancels the queued switch, so it can't wait out the timelock
+    // unpaused
+    client.unpause(&t.admin);
+    assert_eq!(client.get_pending_token_migration(), None);
+    client.pause(&t.admin);
+    let unlock = client.queue_reward_token_migration(&t.admin, &new_token, &ratio);
+    let result = client.try_migrate_reward_token(&t.admin, &new_token, &ratio);
+    assert_eq!(result, Err(Ok(ContractError::TimelockNotElapsed)));
+
+    // The new token has to be in place before the old one leaves
+    set_ledger(&t.env, unlock, 200);
+    let owed = client.pending_reward(&user, &0);
+    let result = client.try_migrate_reward_token(&t.admin, &new_token, &ratio);
+    assert_eq!(result, Err(Ok(ContractError::InsufficientRewardBalance)));
+    let new_sac = token::StellarAssetClient::new(&t.env, &new_token);
+    new_sac.mint(&t.contract_id, &100_000_0000000);
+
+    let returned = client.migrate_reward_token(&t.admin, &new_token, &ratio);
+    assert_eq!(returned, 50_000_0000000);
+    let old_client = token::Client::new(&t.env, &t.lmnr_token);
+    assert_eq!(old_client.balance(&t.admin), 100_000_0000000);
+    assert_eq!(client.get_pending_token_migration(), None);
+    assert_eq!(client.get_reward_rate(), 2_000);
+    assert_eq!(client.pending_reward(&user, &0), owed * 2);
+
+    client.unpause(&t.admin);
+    set_ledger(&t.env, unlock + 100, 210);
+    let claimed = client.claim(&user, &0);
+    assert_eq!(claimed, owed * 2 + 200_000);
+    let new_client = token::Client::new(&t.env, &new_token);
+    assert_eq!(new_client.balance(&user), claimed);
+}
+
+// ========== admin quorum tests ==========
+
+#[test]
+fn test_admin_quorum_gates_sensitive_calls() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let first = Address::generate(&t.env);
+    let second = Address::generate(&t.env);
+    let members = Vec::from_array(&t.env, [first.clone(), second.clone()]);
+    let result = client.try_set_admins(&t.admin, &members, &4);
+    assert_eq!(result, Err(Ok(ContractError::InvalidConfig)));
+    client.set_admins(&t.admin, &members, &2);
+
+    // Co-admins make routine calls alone; anything off that list stays with
+    // the primary admin, and outsiders still cannot call either
+    let result = client.try_add_pool(&first, &make_pool_id(&t.env, 1));
+    assert_eq!(result, Err(Ok(ContractError::Unauthorized)));
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let root = BytesN::from_array(&t.env, &[7u8; 32]);
+    client.set_merkle_root(&first, &0, &root, &100);
+    let outsider = Address::generate(&t.env);
+    let result = client.try_set_merkle_root(&outsider, &0, &root, &100);
+    assert_eq!(result, Err(Ok(ContractError::Unauthorized)));
+    let result = client.try_set_root_oracles(&first, &None);
+    assert_eq!(result, Err(Ok(ContractError::Unauthorized)));
+
+    // A withdrawal waits for a second admin to make the same call
+    client.withdraw(&first, &1_000_0000000);
+    client.withdraw(&first, &1_000_0000000);
+    client.withdraw(&second, &2_000_0000000);
+    assert_eq!(client.reward_balance(), 50_000_0000000);
+    // Only the call that runs is logged, not each approval
+    let log = client.get_admin_log();
+    assert_eq!(log.last().unwrap().action, Symbol::new(&t.env, "root"));
+    client.withdraw(&second, &1_000_0000000);
+    assert_eq!(client.reward_balance(), 49_000_0000000);
+    let log = client.get_admin_log();
+    let entry = log.last().unwrap();
+    assert_eq!(entry.action, Symbol::new(&t.env, "withdraw"));
+    assert_eq!(entry.caller, second);
+    assert_eq!(log.len(), 3);
+    let token_client = token::Client::new(&t.env, &t.lmnr_token);
+    assert_eq!(token_client.balance(&t.admin), 51_000_0000000);
+
+    // Approvals are spent once the call runs
+    client.withdraw(&first, &1_000_0000000);
+    assert_eq!(client.reward_balance(), 49_000_0000000);
+
+    // Leaving the quorum needs the quorum too
+    let none = Vec::new(&t.env);
+    client.set_admins(&first, &none, &0);
+    assert_eq!(client.get_config().admin_threshold, 2);
+    client.set_admins(&t.admin, &none, &0);
+    assert_eq!(client.get_config().admin_threshold, 1);
+    assert!(client.get_config().co_admins.is_empty());
+    client.withdraw(&t.admin, &1_000_0000000);
+    assert_eq!(client.reward_balance(), 48_000_0000000);
+}
+
+#[test]
+fn test_co_admin_cannot_drain_alone() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let co_admin = Address::generate(&t.env);
+    let members = Vec::from_array(&t.env, [co_admin.clone()]);
+    client.set_admins(&t.admin, &members, &2);
+
+    // A co-admin can pause, but the emergency path waits for the quorum
+    client.pause(&co_admin);
+    let unlock = client.queue_emergency_withdraw(&co_admin, &50_000_0000000);
+    assert_eq!(unlock, 0);
+    assert_eq!(client.get_pending_withdrawal(), None);
+    let unlock = client.queue_emergency_withdraw(&t.admin, &50_000_0000000);
+    assert!(unlock > 0);
+
+    set_ledger(&t.env, unlock, 200);
+    assert_eq!(client.execute_emergency_withdraw(&co_admin), 0);
+    assert_eq!(client.execute_emergency_withdraw(&t.admin), 50_000_0000000);
+
+    // Paid to the primary admin, whoever made the last approval
+    let token_client = token::Client::new(&t.env, &t.lmnr_token);
+    assert_eq!(token_client.balance(&co_admin), 0);
+    assert_eq!(token_client.balance(&t.admin), 100_000_0000000);
+
+    let issuer = Address::generate(&t.env);
+    let stray_token = t.env.register_stellar_asset_contract_v2(issuer).address();
+    token::StellarAssetClient::new(&t.env, &stray_token).mint(&t.contract_id, &500_0000000);
+    client.rescue_tokens(&co_admin, &stray_token, &co_admin, &500_0000000);
+    let stray_client = token::Client::new(&t.env, &stray_token);
+    assert_eq!(stray_client.balance(&co_admin), 0);
+    let result = client.try_unpause(&co_admin);
+    assert_eq!(result, Err(Ok(ContractError::Unauthorized)));
+}
+
+// ========== pause / emergency withdraw tests ==========
+
+#[test]
+fn test_pause_blocks_stake_and_claim() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    set_ledger(&t.env, 2000, 200);
+
+    client.pause(&t.admin);
+    assert!(client.is_paused());
+
+    let result = client.try_claim(&user, &0);
+    assert_eq!(result, Err(Ok(ContractError::ContractPaused)));
+    let proof: Vec<BytesN<32>> = Vec::new(&t.env);
+    let result = client.try_stake(&user, &0, &10_000_0000000, &proof);
+    assert_eq!(result, Err(Ok(ContractError::ContractPaused)));
+
+    // Users can still exit, and rewards stay owed
+    client.unstake(&user, &0);
+    client.unpause(&t.admin);
+    assert_eq!(client.claim(&user, &0), 462_962_963_000);
+}
+
+#[test]
+fn test_emergency_withdraw_requires_pause_and_timelock() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+
+    let result = client.try_queue_emergency_withdraw(&t.admin, &50_000_0000000);
+    assert_eq!(result, Err(Ok(ContractError::NotPaused)));
+
+    client.pause(&t.admin);
+    let result = client.try_execute_emergency_withdraw(&t.admin);
+    assert_eq!(result, Err(Ok(ContractError::NoPendingWithdrawal)));
+
+    let unlock_time = client.queue_emergency_withdraw(&t.admin, &50_000_0000000);
+    assert_eq!(unlock_time, 1000 + 3 * 86_400);
+
+    set_ledger(&t.env, unlock_time - 1, 200);
+    let result = client.try_execute_emergency_withdraw(&t.admin);
+    assert_eq!(result, Err(Ok(ContractError::TimelockNotElapsed)));
+
+    // Emergency path may take rewards already owed to stakers
+    set_ledger(&t.env, unlock_time, 300);
+    assert_eq!(client.execute_emergency_withdraw(&t.admin), 50_000_0000000);
+    assert_eq!(client.reward_balance(), 0);
+    assert_eq!(client.get_pending_withdrawal(), None);
+}
+
+#[test]
+fn test_unpause_cancels_emergency_withdraw() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+
+    client.pause(&t.admin);
+    client.queue_emergency_withdraw(&t.admin, &1_0000000);
+    client.unpause(&t.admin);
+    assert_eq!(client.get_pending_withdrawal(), None);
+
+    client.pause(&t.admin);
+    set_ledger(&t.env, 1000 + 3 * 86_400, 200);
+    let result = client.try_execute_emergency_withdraw(&t.admin);
+    assert_eq!(result, Err(Ok(ContractError::NoPendingWithdrawal)));
+}
+
+#[test]
+fn test_pause_non_admin_fails() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+
+    let rando = Address::generate(&t.env);
+    assert!(client.try_pause(&rando).is_err());
+    assert!(!client.is_paused());
+}
+
+#[test]
+fn test_pause_pool_leaves_other_pools_running() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 2));
+
+    let user = Address::generate(&t.env);
+    let mut proofs = Vec::new(&t.env);
+    for pool_index in 0..2 {
+        let leaf =
+            merkle::compute_leaf(&t.env, &t.contract_id, pool_index, &user, 10_000_0000000, 1);
+        let (root, pool_proofs) = build_merkle_tree(&t.env, &[leaf]);
+        client.set_merkle_root(&t.admin, &pool_index, &root, &100);
+        proofs.push_back(pool_proofs.get(0).unwrap());
+    }
+    client.stake(&user, &0, &10_000_0000000, &proofs.get(0).unwrap());
+    client.stake(&user, &1, &10_000_0000000, &proofs.get(1).unwrap());
+
+    client.pause_pool(&t.admin, &0);
+    assert!(client.get_pool_config(&0).paused);
+    assert!(!client.is_paused());
+
+    set_ledger(&t.env, 1100, 110);
+    let result = client.try_claim(&user, &0);
+    assert_eq!(result, Err(Ok(ContractError::PoolPaused)));
+    assert!(client.claim(&user, &1) > 0);
+
+    // Unstaking stays open; the rewards wait for the pool to resume
+    let pending = client.pending_reward(&user, &0);
+    client.unstake(&user, &0);
+    let result = client.try_stake(&user, &0, &10_000_0000000, &proofs.get(0).unwrap());
+    assert_eq!(result, Err(Ok(ContractError::PoolPaused)));
+    client.unpause_pool(&t.admin, &0);
+    assert_eq!(client.claim(&user, &0), pending);
+
+    let rando = Address::generate(&t.env);
+    assert!(client.try_pause_pool(&rando, &1).is_err());
+}
+
+// ========== explain tests ==========
+
+#[test]
+fn test_explain_error_codes() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+
+    assert_eq!(
+        client.explain(&(ContractError::StaleEpoch as u32)),
+        Symbol::new(&t.env, "PROOF_WRONG_EPOCH")
+    );
+    assert_eq!(client.explain(&0), Symbol::new(&t.env, "UNKNOWN"));
+    assert_eq!(client.explain(&9999), Symbol::new(&t.env, "UNKNOWN"));
+}
+
+#[test]
+fn test_explain_covers_every_error() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let unknown = Symbol::new(&t.env, "UNKNOWN");
+
+    // Codes are contiguous from 1; every one must round-trip
+    let mut code = 1;
+    while let Some(err) = ContractError::from_code(code) {
+        assert_eq!(err as u32, code);
+        assert_ne!(client.explain(&code), unknown);
+        code += 1;
+    }
+    assert_eq!(code - 1, ContractError::NoPendingMigration as u32);
+}
+
+#[test]
+fn test_interface_errors_match_contract() {
+    // One past the last code checks the interface has no extra variants
+    for code in 1..=ContractError::NoPendingMigration as u32 + 1 {
+        let error = soroban_sdk::Error::from_contract_error(code);
+        let mirrored = lp_staking_interface::ContractError::try_from(error).ok();
+        assert_eq!(
+            mirrored.map(|err| err as u32),
+            ContractError::from_code(code).map(|err| err as u32)
+        );
+    }
+}
+
+#[test]
+fn test_interface_client_calls_contract() {
+    let t = setup_env();
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    set_ledger(&t.env, 1100, 110);
+
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let staking = lp_staking_interface::LpStakingClient::new(&t.env, &t.contract_id);
+    let staker = staking.get_staker_info(&user, &0);
+    let expected = client.get_staker_info(&user, &0);
+    assert_eq!(
+        (staker.staked_amount, staker.weight, staker.epoch_id),
+        (expected.staked_amount, expected.weight, expected.epoch_id)
+    );
+    let root = client.get_merkle_root(&0).root;
+    assert_eq!(staking.get_merkle_root(&0).root, root);
+    assert_eq!(staking.get_pool_state(&0).total_staked, 10_000_0000000);
+    assert_eq!(staking.get_pool_count(), 1);
+    assert_eq!(staking.get_active_staker_count(&0), 1);
+
+    let pending = staking.pending_reward(&user, &0);
+    assert_eq!(staking.claim(&user, &0), pending);
+    assert_eq!(staking.get_user_claimed(&user, &0), pending);
+
+    let outsider = Address::generate(&t.env);
+    assert_eq!(staking.find_staker_info(&outsider, &0), None);
+    let result = staking.try_claim_to(&outsider, &0, &user);
+    assert_eq!(
+        result,
+        Err(Ok(lp_staking_interface::ContractError::NoStakeFound))
+    );
+}
+
+// ========== identity binding tests ==========
+
+#[test]
+fn test_stake_with_bound_identity() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let pool_id = make_pool_id(&t.env, 1);
+    client.add_pool(&t.admin, &pool_id);
+
+    let key = SigningKey::from_bytes(&[7u8; 32]);
+    let user = Address::generate(&t.env);
+    let identity = client.bind_identity(&user, &make_identity_proof(&t, &key, &user, 0));
+    assert_eq!(client.get_identity_owner(&identity), Some(user.clone()));
+
+    let lp_balance: i128 = 1_000_0000000;
+    let leaf = merkle::compute_identity_leaf(&t.env, &t.contract_id, 0, &identity, lp_balance, 1);
+    let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+
+    client.stake_with_identity(&user, &0, &identity, &lp_balance, &proofs.get(0).unwrap());
+
+    let staker = client.get_staker_info(&user, &0);
+    assert_eq!(staker.staked_amount, lp_balance);
+    assert_eq!(staker.epoch_id, 1);
+    assert_eq!(client.get_pool_state(&0).total_staked, lp_balance);
+
+    // The identity leaf is not valid as an address leaf
+    let result = client.try_stake(&user, &0, &lp_balance, &proofs.get(0).unwrap());
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_identity_rotation() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let pool_id = make_pool_id(&t.env, 1);
+    client.add_pool(&t.admin, &pool_id);
+
+    let key = SigningKey::from_bytes(&[9u8; 32]);
+    let old_wallet = Address::generate(&t.env);
+    let new_wallet = Address::generate(&t.env);
+    let identity = client.bind_identity(&old_wallet, &make_identity_proof(&t, &key, &old_wallet, 0));
+
+    let lp_balance: i128 = 1_000_0000000;
+    let leaf = merkle::compute_identity_leaf(&t.env, &t.contract_id, 0, &identity, lp_balance, 1);
+    let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+    client.stake_with_identity(&old_wallet, &0, &identity, &lp_balance, &proofs.get(0).unwrap());
+
+    // Replaying the nonce-0 signature is rejected; nonce 1 rebinds
+    let replay = client.try_bind_identity(&new_wallet, &make_identity_proof(&t, &key, &new_wallet, 0));
+    assert!(replay.is_err());
+    client.bind_identity(&new_wallet, &make_identity_proof(&t, &key, &new_wallet, 1));
+    assert_eq!(client.get_identity_owner(&identity), Some(new_wallet.clone()));
+
+    // Same leaf cannot be proven twice in one epoch from the new wallet
+    let result =
+        client.try_stake_with_identity(&new_wallet, &0, &identity, &lp_balance, &proofs.get(0).unwrap());
+    assert!(result.is_err());
+
+    // Next epoch: the new wallet proves, the old wallet no longer can
+    let leaf2 = merkle::compute_identity_leaf(&t.env, &t.contract_id, 0, &identity, lp_balance, 2);
+    let (root2, proofs2) = build_merkle_tree(&t.env, &[leaf2]);
+    set_ledger(&t.env, 1000, 200);
+    client.set_merkle_root(&t.admin, &0, &root2, &200);
+
+    let result =
+        client.try_stake_with_identity(&old_wallet, &0, &identity, &lp_balance, &proofs2.get(0).unwrap());
+    assert!(result.is_err());
+    client.stake_with_identity(&new_wallet, &0, &identity, &lp_balance, &proofs2.get(0).unwrap());
+    assert_eq!(client.get_staker_info(&new_wallet, &0).epoch_id, 2);
+}
+
+#[test]
+fn test_stake_with_unbound_identity_fails() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let pool_id = make_pool_id(&t.env, 1);
+    client.add_pool(&t.admin, &pool_id);
+
+    let user = Address::generate(&t.env);
+    let identity = BytesN::from_array(&t.env, &[5u8; 32]);
+    let lp_balance: i128 = 1_000_0000000;
+    let leaf = merkle::compute_identity_leaf(&t.env, &t.contract_id, 0, &identity, lp_balance, 1);
+    let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+
+    let result = client.try_stake_with_identity(&user, &0, &identity, &lp_balance, &proofs.get(0).unwrap());
+    assert!(result.is_err());
+}
+
+// ========== claim_to tests ==========
+
+#[test]
+fn test_claim_to_destination() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    set_ledger(&t.env, 2000, 200);
+
+    let stealth = Address::generate(&t.env);
+    let claimed = client.claim_to(&user, &0, &stealth);
+    assert_eq!(claimed, 462_962_963_000_i128);
+
+    let event = t.env.events().all().last().unwrap();
+    assert_eq!(
+        event.1,
+        (events::CLAIMED, user.clone(), 0u32).into_val(&t.env)
+    );
+    let data: Claimed = event.2.into_val(&t.env);
+    assert_eq!(data.amount, claimed);
+    assert_eq!(data.destination, Some(stealth.clone()));
+
+    let token_client = token::Client::new(&t.env, &t.lmnr_token);
+    assert_eq!(token_client.balance(&stealth), claimed);
+    assert_eq!(token_client.balance(&user), 0);
+    assert_eq!(client.pending_reward(&user, &0), 0);
+}
+
+#[test]
+fn test_claim_epochs_settles_missed_epochs_once() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let user = Address::generate(&t.env);
+    let lp_balance: i128 = 1_000_0000000;
+
+    rotate_and_restake(&t, &user, lp_balance, 1);
+    set_ledger(&t.env, 1100, t.env.ledger().sequence());
+    rotate_and_restake(&t, &user, lp_balance, 2);
+    set_ledger(&t.env, 1200, t.env.ledger().sequence());
+    rotate_and_restake(&t, &user, lp_balance, 3);
+    set_ledger(&t.env, 1300, t.env.ledger().sequence());
+
+    let pending = client.pending_reward(&user, &0);
+    assert_eq!(pending, 300 * 462_962_963);
+
+    // Epochs must be increasing and already proven
+    let epochs = Vec::from_array(&t.env, [2u64, 1]);
+    let result = client.try_claim_epochs(&user, &0, &epochs);
+    assert_eq!(result, Err(Ok(ContractError::StaleEpoch)));
+    let epochs = Vec::from_array(&t.env, [1u64, 4]);
+    let result = client.try_claim_epochs(&user, &0, &epochs);
+    assert_eq!(result, Err(Ok(ContractError::StaleEpoch)));
+
+    let before = token::Client::new(&t.env, &t.lmnr_token).balance(&user);
+    let epochs = Vec::from_array(&t.env, [1u64, 2, 3]);
+    assert_eq!(client.claim_epochs(&user, &0, &epochs), pending);
+    let after = token::Client::new(&t.env, &t.lmnr_token).balance(&user);
+    assert_eq!(after - before, pending);
+    assert_eq!(client.pending_reward(&user, &0), 0);
+}
+
+#[test]
+fn test_claim_event_hides_destination() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    set_ledger(&t.env, 2000, 200);
+
+    client.set_hide_claim_destination(&t.admin, &true);
+
+    let stealth = Address::generate(&t.env);
+    let claimed = client.claim_to(&user, &0, &stealth);
+
+    let event = t.env.events().all().last().unwrap();
+    let data: Claimed = event.2.into_val(&t.env);
+    assert_eq!(data.amount, claimed);
+    assert_eq!(data.destination, None);
+}
+
+#[test]
+fn test_claim_to_frozen_trustline_keeps_rewards_pending() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    set_ledger(&t.env, 2000, 200);
+
+    let frozen = Address::generate(&t.env);
+    let sac_admin = token::StellarAssetClient::new(&t.env, &t.lmnr_token);
+    sac_admin.set_authorized(&frozen, &false);
+
+    let pending = client.pending_reward(&user, &0);
+    let result = client.try_claim_to(&user, &0, &frozen);
+    assert_eq!(result, Err(Ok(ContractError::RewardTransferFailed)));
+    assert_eq!(client.pending_reward(&user, &0), pending);
+
+    // Batch flows record the failure instead of trapping
+    let users = Vec::from_array(&t.env, [user.clone()]);
+    sac_admin.set_authorized(&user, &false);
+    let results = client.distribute(&t.admin, &0, &users, &false);
+    let failed = ContractError::RewardTransferFailed as u32;
+    assert_eq!(results.get(0), Some(failed));
+    assert_eq!(client.pending_reward(&user, &0), pending);
+
+    sac_admin.set_authorized(&user, &true);
+    assert_eq!(client.claim(&user, &0), pending);
+}
+
+#[test]
+fn test_set_hide_claim_destination_non_admin_fails() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let rando = Address::generate(&t.env);
+    let result = client.try_set_hide_claim_destination(&rando, &true);
+    assert!(result.is_err());
+}
+
+// ========== liability / solvency tests ==========
+
+#[test]
+fn test_solvency_tracks_accrual_and_claims() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+
+    assert_eq!(client.get_solvency(), (0, 50_000_0000000, 50_000_0000000));
+
+    set_ledger(&t.env, 2000, 200);
+    let accrued = 462_962_963_000_i128;
+    assert_eq!(client.get_pool_liability(&0), accrued);
+    assert_eq!(
+        client.get_solvency(),
+        (accrued, 50_000_0000000, 50_000_0000000 - accrued)
+    );
+
+    client.claim(&user, &0);
+    let balance = 50_000_0000000 - accrued;
+    assert_eq!(client.get_solvency(), (0, balance, balance));
+}
+
+#[test]
+fn test_solvency_reports_shortfall() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+
+    // 2000 seconds at 462_962_963/sec exceeds the 50k LMNR funded
+    set_ledger(&t.env, 3000, 300);
+    let (liability, balance, surplus) = client.get_solvency();
+    assert_eq!(liability, 2000 * 462_962_963_i128);
+    assert_eq!(balance, 50_000_0000000);
+    assert!(surplus < 0);
+}
+
+// ========== claim stats tests ==========
+
+#[test]
+fn test_claim_stats_per_epoch() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+
+    set_ledger(&t.env, 1100, 110);
+    let first = client.claim(&user, &0);
+    set_ledger(&t.env, 1200, 120);
+    let second = client.claim(&user, &0);
+
+    // Repeat claims add to the total but count the claimant once
+    let stats = client.get_claim_stats(&0, &1);
+    assert_eq!(stats.total_claimed, first + second);
+    assert_eq!(stats.claimant_count, 1);
+    assert_eq!(client.get_claim_stats(&0, &2), ClaimStats::default());
+}
+
+// ========== lifetime claimed tests ==========
+
+#[test]
+fn test_user_lifetime_claimed() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+
+    // Second pool with its own root for the same user
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 2));
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 1, &user, 10_000_0000000, 1);
+    let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
+    client.set_merkle_root(&t.admin, &1, &root, &100);
+    client.stake(&user, &1, &10_000_0000000, &proofs.get(0).unwrap());
+
+    set_ledger(&t.env, 1100, 110);
+    let first = client.claim(&user, &0);
+    let other = client.claim(&user, &1);
+    set_ledger(&t.env, 1200, 120);
+    let second = client.claim(&user, &0);
+
+    assert_eq!(client.get_user_claimed(&user, &0), first + second);
+    assert_eq!(client.get_user_claimed(&user, &1), other);
+    assert_eq!(client.get_user_total_claimed(&user), first + second + other);
+    assert_eq!(client.get_user_total_claimed(&Address::generate(&t.env)), 0);
+}
+
+// ========== accounting tests ==========
+
+#[test]
+fn test_accounting_counters() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    // setup_env transfers directly, bypassing fund
+    assert_eq!(client.get_accounting(), Accounting::default());
+
+    client.fund(&t.admin, &1_000_0000000);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    set_ledger(&t.env, 1100, 110);
+    let claimed = client.claim(&user, &0);
+    client.withdraw(&t.admin, &500_0000000);
+
+    assert_eq!(
+        client.get_accounting(),
+        Accounting {
+            total_funded: 1_000_0000000,
+            total_claimed: claimed,
+            total_withdrawn: 500_0000000,
+            total_bounties: 0,
+        }
+    );
+}
+
+// ========== partial payout tests ==========
+
+#[test]
+fn test_claim_underfunded_rejected_by_default() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+
+    set_ledger(&t.env, 3000, 300);
+    let result = client.try_claim(&user, &0);
+    assert_eq!(result, Err(Ok(ContractError::InsufficientRewardBalance)));
+}
+
+#[test]
+fn test_partial_payout_leaves_shortfall_pending() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    client.set_partial_payouts(&t.admin, &true);
+
+    // 2000s accrues more than the 50k LMNR funded
+    set_ledger(&t.env, 3000, 300);
+    let owed = 2000 * 462_962_963_i128;
+    let shortfall = owed - 50_000_0000000;
+
+    assert_eq!(client.claim(&user, &0), 50_000_0000000);
+    let event = t.env.events().all().last().unwrap();
+    let data: i128 = event.2.into_val(&t.env);
+    assert_eq!(data, shortfall);
+
+    assert_eq!(client.reward_balance(), 0);
+    assert_eq!(client.pending_reward(&user, &0), shortfall);
+    assert_eq!(client.get_pool_liability(&0), shortfall);
+
+    // Nothing to pay out at all still fails
+    let result = client.try_claim(&user, &0);
+    assert_eq!(result, Err(Ok(ContractError::InsufficientRewardBalance)));
+
+    client.fund(&t.admin, &shortfall);
+    assert_eq!(client.claim(&user, &0), shortfall);
+}
+
+// ========== staker registry tests ==========
+
+#[test]
+fn test_staker_registry_pagination_and_unstake() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+
+    let users = [
+        Address::generate(&t.env),
+        Address::generate(&t.env),
+        Address::generate(&t.env),
+    ];
+    let leaves: alloc::vec::Vec<BytesN<32>> = users
+        .iter()
+        .map(|u| merkle::compute_leaf(&t.env, &t.contract_id, 0, u, 1_000_0000000, 1))
+        .collect();
+    let (root, proofs) = build_merkle_tree(&t.env, &leaves);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+    for (i, user) in users.iter().enumerate() {
+        client.stake(user, &0, &1_000_0000000, &proofs.get(i as u32).unwrap());
+    }
+
+    assert_eq!(client.get_staker_count(&0), 3);
+    let page = client.get_stakers(&0, &0, &2);
+    assert_eq!(
+        page,
+        Vec::from_array(&t.env, [users[0].clone(), users[1].clone()])
+    );
+    assert_eq!(client.get_stakers(&0, &2, &10).len(), 1);
+    assert_eq!(client.get_stakers(&0, &5, &10).len(), 0);
+
+    // Unstaking the first staker moves the last into its slot
+    client.unstake(&users[0], &0);
+    assert_eq!(
+        client.get_stakers(&0, &0, &10),
+        Vec::from_array(&t.env, [users[2].clone(), users[1].clone()])
+    );
+
+    // Admin reductions to zero also drop out of the registry
+    client.update_stake(&t.admin, &users[1], &0, &0);
+    assert_eq!(
+        client.get_stakers(&0, &0, &10),
+        Vec::from_array(&t.env, [users[2].clone()])
+    );
+
+    client.update_stake(&t.admin, &users[0], &0, &500_0000000);
+    assert_eq!(client.get_staker_count(&0), 2);
+}
+
+#[test]
+fn test_active_staker_count_follows_current_epoch() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let users = [Address::generate(&t.env), Address::generate(&t.env)];
+    let leaves = [
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &users[0], 1_000_0000000, 1),
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &users[1], 1_000_0000000, 1),
+    ];
+    let (root, proofs) = build_merkle_tree(&t.env, &leaves);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+    assert_eq!(client.get_active_staker_count(&0), 0);
+    client.stake(&users[0], &0, &1_000_0000000, &proofs.get(0).unwrap());
+    client.stake(&users[1], &0, &1_000_0000000, &proofs.get(1).unwrap());
+    assert_eq!(client.get_active_staker_count(&0), 2);
+
+    // Only re-proven stakers count in the new epoch
+    set_ledger(&t.env, 1100, 110);
+    rotate_and_restake(&t, &users[0], 1_000_0000000, 2);
+    assert_eq!(client.get_active_staker_count(&0), 1);
+    assert_eq!(client.get_staker_count(&0), 2);
+
+    client.unstake(&users[1], &0);
+    assert_eq!(client.get_active_staker_count(&0), 1);
+    client.unstake(&users[0], &0);
+    assert_eq!(client.get_active_staker_count(&0), 0);
+}
+
+// ========== batch tests ==========
+
+// Helper: three pools with a single-leaf root for `user` at `lp_balance`.
+// The request for pool 1 carries a proof for the wrong balance.
+fn setup_batch_pools(t: &TestEnv, user: &Address, lp_balance: i128) -> Vec<StakeRequest> {
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let mut requests = Vec::new(&t.env);
+    for pool in 0..3u32 {
+        client.add_pool(&t.admin, &make_pool_id(&t.env, pool as u8 + 1));
+        let leaf = merkle::compute_leaf(&t.env, &t.contract_id, pool, user, lp_balance, 1);
+        let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
+        client.set_merkle_root(&t.admin, &pool, &root, &100);
+        let claimed = if pool == 1 {
+            lp_balance + 1
+        } else {
+            lp_balance
+        };
+        requests.push_back(StakeRequest {
+            pool_index: pool,
+            lp_balance: claimed,
+            proof: proofs.get(0).unwrap(),
+        });
+    }
+    requests
+}
+
+#[test]
+fn test_stake_batch_non_atomic_stops_at_failure() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    let requests = setup_batch_pools(&t, &user, 1_000_0000000);
+
+    let results = client.stake_batch(&user, &requests, &false);
+    assert_eq!(
+        results,
+        Vec::from_array(
+            &t.env,
+            [ITEM_OK, ContractError::InvalidProof as u32, ITEM_SKIPPED]
+        )
+    );
+
+    // Prior item committed, later item untouched
+    assert_eq!(client.get_pool_state(&0).total_staked, 1_000_0000000);
+    assert_eq!(client.get_pool_state(&1).total_staked, 0);
+    assert_eq!(client.get_pool_state(&2).total_staked, 0);
+    assert_eq!(client.get_staker_count(&2), 0);
+}
+
+#[test]
+fn test_stake_batch_atomic_rolls_back() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    let requests = setup_batch_pools(&t, &user, 1_000_0000000);
+
+    let result = client.try_stake_batch(&user, &requests, &true);
+    assert_eq!(result, Err(Ok(ContractError::InvalidProof)));
+    assert_eq!(client.get_pool_state(&0).total_staked, 0);
+    assert_eq!(client.get_staker_count(&0), 0);
+}
+
+#[test]
+fn test_update_stakes_in_input_order() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+
+    let a = Address::generate(&t.env);
+    let b = Address::generate(&t.env);
+    let updates = Vec::from_array(
+        &t.env,
+        [
+            StakeUpdate {
+                user: a.clone(),
+                new_amount: 100_0000000,
+            },
+            StakeUpdate {
+                user: b.clone(),
+                new_amount: -1,
+            },
+            StakeUpdate {
+                user: a.clone(),
+                new_amount: 300_0000000,
+            },
+        ],
+    );
+
+    let results = client.update_stakes(&t.admin, &0, &updates, &false);
+    assert_eq!(
+        results,
+        Vec::from_array(
+            &t.env,
+            [ITEM_OK, ContractError::InvalidAmount as u32, ITEM_SKIPPED]
+        )
+    );
+    assert_eq!(client.get_staker_info(&a, &0).staked_amount, 100_0000000);
+    assert_eq!(client.get_pool_state(&0).total_staked, 100_0000000);
+
+    // Without the failing item both writes to `a` apply, last one wins
+    let updates = Vec::from_array(
+        &t.env,
+        [
+            StakeUpdate {
+                user: a.clone(),
+                new_amount: 200_0000000,
+            },
+            StakeUpdate {
+                user: a.clone(),
+                new_amount: 300_0000000,
+            },
+        ],
+    );
+    let results = client.update_stakes(&t.admin, &0, &updates, &true);
+    assert_eq!(results, Vec::from_array(&t.env, [ITEM_OK, ITEM_OK]));
+    assert_eq!(client.get_staker_info(&a, &0).staked_amount, 300_0000000);
+}
+
+#[test]
+fn test_distribute_pays_stakers() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    set_ledger(&t.env, 1100, 110);
+
+    let stranger = Address::generate(&t.env);
+    let users = Vec::from_array(&t.env, [user.clone(), stranger.clone(), user.clone()]);
+    let results = client.distribute(&t.admin, &0, &users, &false);
+    assert_eq!(
+        results,
+        Vec::from_array(
+            &t.env,
+            [ITEM_OK, ContractError::NoStakeFound as u32, ITEM_SKIPPED]
+        )
+    );
+
+    let token_client = token::Client::new(&t.env, &t.lmnr_token);
+    assert_eq!(token_client.balance(&user), 100 * 462_962_963);
+}
+
+#[test]
+fn test_claim_batch_by_keeper_skips_empty() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    set_ledger(&t.env, 1100, 110);
+
+    let keeper = Address::generate(&t.env);
+    let stranger = Address::generate(&t.env);
+    let users = Vec::from_array(&t.env, [user.clone(), stranger.clone(), user.clone()]);
+    let result = client.try_claim_batch(&keeper, &0, &users);
+    assert_eq!(result, Err(Ok(ContractError::Unauthorized)));
+
+    client.set_keeper(&t.admin, &Some(keeper.clone()));
+    assert_eq!(client.claim_batch(&keeper, &0, &users), 100 * 462_962_963);
+    let token_client = token::Client::new(&t.env, &t.lmnr_token);
+    assert_eq!(token_client.balance(&user), 100 * 462_962_963);
+
+    // Anything else fails the whole batch
+    set_ledger(&t.env, 1200, 120);
+    client.pause_pool(&t.admin, &0);
+    let result = client.try_claim_batch(&keeper, &0, &users);
+    assert_eq!(result, Err(Ok(ContractError::PoolPaused)));
+}
+
+#[test]
+fn test_claim_batch_skips_dust_and_frozen_users() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let first = Address::generate(&t.env);
+    let dust = Address::generate(&t.env);
+    let frozen = Address::generate(&t.env);
+    let last = Address::generate(&t.env);
+    let positions = [
+        (&first, 10_000_0000000),
+        (&dust, 1_000),
+        (&frozen, 10_000_0000000),
+        (&last, 10_000_0000000),
+    ];
+    let leaves: alloc::vec::Vec<BytesN<32>> = positions
+        .iter()
+        .map(|(user, balance)| merkle::compute_leaf(&t.env, &t.contract_id, 0, user, *balance, 1))
+        .collect();
+    let (root, proofs) = build_merkle_tree(&t.env, &leaves);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+    for (i, (user, balance)) in positions.iter().enumerate() {
+        client.stake(user, &0, balance, &proofs.get(i as u32).unwrap());
+    }
+    client.set_min_claim(&t.admin, &1_0000000);
+    client.block_address(&t.admin, &frozen, &true);
+    set_ledger(&t.env, 1100, 110);
+
+    let keeper = Address::generate(&t.env);
+    client.set_keeper(&t.admin, &Some(keeper.clone()));
+    let users = Vec::from_array(
+        &t.env,
+        [first.clone(), dust.clone(), frozen.clone(), last.clone()],
+    );
+    let paid = client.claim_batch(&keeper, &0, &users);
+
+    let token_client = token::Client::new(&t.env, &t.lmnr_token);
+    assert!(token_client.balance(&first) > 0);
+    assert!(token_client.balance(&last) > 0);
+    assert_eq!(
+        paid,
+        token_client.balance(&first) + token_client.balance(&last)
+    );
+    assert_eq!(token_client.balance(&dust), 0);
+    assert!(client.pending_reward(&dust, &0) > 0);
+    assert_eq!(token_client.balance(&frozen), 0);
+    assert!(client.pending_reward(&frozen, &0) > 0);
+}
+
+#[test]
+fn test_batch_too_large() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+
+    let mut users = Vec::new(&t.env);
+    for _ in 0..=MAX_BATCH_SIZE {
+        users.push_back(Address::generate(&t.env));
+    }
+    let result = client.try_distribute(&t.admin, &0, &users, &false);
+    assert_eq!(result, Err(Ok(ContractError::BatchTooLarge)));
+}
+
+#[test]
+fn test_set_merkle_roots_rotates_pools_together() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    for i in 1..=3 {
+        client.add_pool(&t.admin, &make_pool_id(&t.env, i));
+    }
+    let root = |seed: u8| BytesN::from_array(&t.env, &[seed; 32]);
+
+    let roots = Vec::from_array(
+        &t.env,
+        [
+            (0u32, root(1), 100u32),
+            (1, root(2), 100),
+            (2, root(3), 100),
+        ],
+    );
+    client.set_merkle_roots(&t.admin, &roots);
+    for i in 0..3 {
+        assert_eq!(client.get_merkle_root(&i).epoch_id, 1);
+        assert_eq!(client.get_merkle_root(&i).root, root(i as u8 + 1));
+    }
+
+    // One bad entry rolls back the whole batch
+    set_ledger(&t.env, 2000, 110);
+    let roots = Vec::from_array(&t.env, [(0u32, root(4), 110u32), (1, root(5), 111)]);
+    let result = client.try_set_merkle_roots(&t.admin, &roots);
+    assert_eq!(result, Err(Ok(ContractError::SnapshotInFuture)));
+    assert_eq!(client.get_merkle_root(&0).epoch_id, 1);
+
+    let roots = Vec::from_array(&t.env, [(0u32, root(4), 110u32), (0, root(5), 110)]);
+    let result = client.try_set_merkle_roots(&t.admin, &roots);
+    assert_eq!(result, Err(Ok(ContractError::InvalidConfig)));
+
+    let outsider = Address::generate(&t.env);
+    let roots = Vec::from_array(&t.env, [(0u32, root(4), 110u32)]);
+    let result = client.try_set_merkle_roots(&outsider, &roots);
+    assert_eq!(result, Err(Ok(ContractError::Unauthorized)));
+}
+
+// ========== seed_from_legacy tests ==========
+
+#[test]
+fn test_seed_from_legacy() {
+    let t = setup_env();
+    let legacy_id = t.env.register(legacy_contract::LpStakingContract, ());
+    let legacy = legacy_contract::LpStakingContractClient::new(&t.env, &legacy_id);
+    legacy.initialize(&t.admin, &t.lmnr_token, &462_962_963_i128);
+
+    // Legacy deployment: one pool with 30 stakers (two seed pages), one empty pool
+    legacy.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    legacy.add_pool(&t.admin, &make_pool_id(&t.env, 2));
+    let users: alloc::vec::Vec<Address> = (0..30).map(|_| Address::generate(&t.env)).collect();
+    let balance = |i: usize| 100_0000000 + i as i128;
+    let leaves: alloc::vec::Vec<BytesN<32>> = users
+        .iter()
+        .enumerate()
+        .map(|(i, user)| legacy_contract::merkle::compute_leaf(&t.env, 0, user, balance(i), 1))
+        .collect();
+    let (root, proofs) = build_merkle_tree(&t.env, &leaves);
+    legacy.set_merkle_root(&t.admin, &0, &root, &100);
+    for (i, user) in users.iter().enumerate() {
+        legacy.stake(user, &0, &balance(i), &proofs.get(i as u32).unwrap());
+    }
+
+    // Epoch 2: one staker re-proves, one leaves, the rest go stale
+    set_ledger(&t.env, 1100, 110);
+    let leaf = legacy_contract::merkle::compute_leaf(&t.env, 0, &users[0], balance(0), 2);
+    let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
+    legacy.set_merkle_root(&t.admin, &0, &root, &110);
+    legacy.stake(&users[0], &0, &balance(0), &proofs.get(0).unwrap());
+    legacy.unstake(&users[1], &0);
+
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    assert_eq!(client.seed_from_legacy(&t.admin, &legacy_id, &Vec::new(&t.env)), 2);
+    let page = |range: core::ops::Range<usize>| {
+        let mut page = Vec::new(&t.env);
+        for user in &users[range] {
+            page.push_back(user.clone());
+        }
+        page
+    };
+    assert_eq!(client.seed_from_legacy(&t.admin, &legacy_id, &page(0..25)), 25);
+    assert_eq!(client.seed_from_legacy(&t.admin, &legacy_id, &page(25..30)), 5);
+
+    // Re-running a page or pool seeding copies nothing twice
+    assert_eq!(client.seed_from_legacy(&t.admin, &legacy_id, &page(25..30)), 0);
+    let result = client.try_seed_from_legacy(&t.admin, &legacy_id, &Vec::new(&t.env));
+    assert_eq!(result, Err(Ok(ContractError::PoolAlreadyExists)));
+    let mut oversized = page(0..25);
+    oversized.push_back(Address::generate(&t.env));
+    let result = client.try_seed_from_legacy(&t.admin, &legacy_id, &oversized);
+    assert_eq!(result, Err(Ok(ContractError::BatchTooLarge)));
+
+    assert_eq!(client.get_pool_count(), 2);
+    assert_eq!(client.get_pool_id(&1), make_pool_id(&t.env, 2));
+    let state = client.get_pool_state(&0);
+    let legacy_state = legacy.get_pool_state(&0);
+    assert_eq!(state.acc_reward_per_share, legacy_state.acc_reward_per_share);
+    assert_eq!(state.total_staked, legacy_state.total_staked);
+    assert_eq!(client.get_merkle_root(&0).root, root);
+    assert_eq!(client.get_merkle_root(&0).epoch_id, 2);
+    assert_eq!(client.get_staker_count(&0), 30);
+
+    // Rewards the legacy stakers had earned are owed here
+    let pending: i128 = users.iter().map(|user| legacy.pending_reward(user, &0)).sum();
+    assert!(pending > 0);
+    assert_eq!(client.get_pool_liability(&0), pending);
+    set_ledger(&t.env, 1200, 120);
+    for user in users.iter() {
+        let info = client.get_staker_info(user, &0);
+        assert_eq!(info.staked_amount, legacy.get_staker_info(user, &0).staked_amount);
+        assert_eq!(client.pending_reward(user, &0), legacy.pending_reward(user, &0));
+    }
+
+    client.activate(&t.admin);
+    let result = client.try_seed_from_legacy(&t.admin, &legacy_id, &page(0..1));
+    assert_eq!(result, Err(Ok(ContractError::AlreadyActivated)));
+}
+
+// ========== checkpoint tests ==========
+
+#[test]
+fn test_checkpoint_by_anyone_and_epoch_rollover() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+
+    // set_merkle_root checkpointed epoch 1 before anyone staked
+    let checkpoints = client.get_checkpoints(&0);
+    assert_eq!(checkpoints.len(), 1);
+    assert_eq!(checkpoints.get(0).unwrap().total_staked, 0);
+
+    set_ledger(&t.env, 1100, 110);
+    client.checkpoint(&t.admin, &0);
+    client.checkpoint(&t.admin, &0); // same second: replaces, not appends
+
+    let checkpoints = client.get_checkpoints(&0);
+    assert_eq!(checkpoints.len(), 2);
+    let last = checkpoints.get(1).unwrap();
+    assert_eq!(last.timestamp, 1100);
+    assert_eq!(last.total_staked, 10_000_0000000);
+    assert_eq!(last.acc_reward_per_share, client.get_pool_state(&0).acc_reward_per_share);
+}
+
+#[test]
+fn test_checkpoint_history_is_bounded() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+
+    for i in 0..(rewards::MAX_CHECKPOINTS as u64 + 5) {
+        set_ledger(&t.env, 1000 + i, 100);
+        client.checkpoint(&t.admin, &0);
+    }
+
+    let checkpoints = client.get_checkpoints(&0);
+    assert_eq!(checkpoints.len(), rewards::MAX_CHECKPOINTS);
+    assert_eq!(checkpoints.get(0).unwrap().timestamp, 1005);
+}
+
+#[test]
+fn test_checkpoint_all_pages_live_pools() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 2));
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 3));
+    client.remove_pool(&t.admin, &1);
+
+    set_ledger(&t.env, 1100, 110);
+    assert_eq!(client.checkpoint_all(&0, &10), 2);
+    assert_eq!(client.get_pool_state(&0).last_reward_time, 1100);
+    let pending = client.pending_reward(&user, &0);
+    assert_eq!(client.get_pool_liability(&0), pending);
+    assert_eq!(client.get_checkpoints(&0).len(), 2);
+    assert_eq!(client.get_checkpoints(&1).len(), 0);
+    assert_eq!(client.get_checkpoints(&2).len(), 1);
+
+    assert_eq!(client.checkpoint_all(&2, &10), 1);
+    assert_eq!(client.checkpoint_all(&3, &10), 0);
+    let result = client.try_checkpoint_all(&0, &(MAX_BATCH_SIZE + 1));
+    assert_eq!(result, Err(Ok(ContractError::BatchTooLarge)));
+}
+
+#[test]
+fn test_keeper_bounties_limited_per_ledger() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 2));
+
+    let mut bounties = KeeperBounties {
+        checkpoint: 1_0000000,
+        expire: 2_0000000,
+        sweep: 5_0000000,
+        max_per_ledger: 2,
+    };
+    client.set_keeper_bounties(&t.admin, &bounties);
+    assert_eq!(client.get_keeper_bounties(), bounties);
+
+    let keeper = Address::generate(&t.env);
+    set_ledger(&t.env, 1100, 110);
+    assert_eq!(client.checkpoint(&keeper, &0), 1_0000000);
+    // Nothing left to update this second
+    assert_eq!(client.checkpoint(&keeper, &0), 0);
+    assert_eq!(client.checkpoint(&keeper, &1), 1_0000000);
+
+    // Two bounties already paid in ledger 110
+    set_ledger(&t.env, 1101, 110);
+    assert_eq!(client.checkpoint(&keeper, &0), 0);
+    set_ledger(&t.env, 1102, 111);
+    assert_eq!(client.checkpoint(&keeper, &0), 1_0000000);
+
+    let token_client = token::Client::new(&t.env, &t.lmnr_token);
+    assert_eq!(token_client.balance(&keeper), 3_0000000);
+    assert_eq!(client.get_accounting().total_bounties, 3_0000000);
+
+    bounties.checkpoint = 10_0000001;
+    let result = client.try_set_keeper_bounties(&t.admin, &bounties);
+    assert_eq!(result, Err(Ok(ContractError::InvalidAmount)));
+}
+
+// ========== storage TTL tests ==========
+
+#[test]
+fn test_extend_my_storage() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+
+    let staker_ttl = || {
+        t.env.as_contract(&t.contract_id, || {
+            t.env
+                .storage()
+                .persistent()
+                .get_ttl(&crate::storage::DataKey::Staker(user.clone(), 0))
+        })
+    };
+    let staker_extend = client.get_ttl_config().staker_extend;
+    assert_eq!(staker_ttl(), staker_extend);
+
+    // Much later, well inside the normal re-extension threshold
+    set_ledger(&t.env, 1100, 100 + 400_000);
+    assert_eq!(staker_ttl(), staker_extend - 400_000);
+    client.extend_my_storage(&user, &0);
+    assert_eq!(staker_ttl(), staker_extend);
+
+    let stranger = Address::generate(&t.env);
+    let result = client.try_extend_my_storage(&stranger, &0);
+    assert_eq!(result, Err(Ok(ContractError::NoStakeFound)));
+}
+
+#[test]
+fn test_set_ttl_config() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    assert_eq!(client.get_ttl_config(), TtlConfig::default());
+
+    let config = TtlConfig {
+        instance_threshold: 10_000,
+        instance_extend: 100_000,
+        persistent_threshold: 10_000,
+        persistent_extend: 200_000,
+        staker_extend: 300_000,
+        historical_root_ttl: 50_000,
+    };
+    client.set_ttl_config(&t.admin, &config);
+    assert_eq!(client.get_config().ttl, config);
+
+    // New stakers pick up the configured extension
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    let ttl = t.env.as_contract(&t.contract_id, || {
+        t.env
+            .storage()
+            .persistent()
+            .get_ttl(&crate::storage::DataKey::Staker(user.clone(), 0))
+    });
+    assert_eq!(ttl, 300_000);
+
+    let inverted = TtlConfig {
+        instance_threshold: 200_000,
+        ..config.clone()
+    };
+    let result = client.try_set_ttl_config(&t.admin, &inverted);
+    assert_eq!(result, Err(Ok(ContractError::InvalidConfig)));
+
+    let too_long = TtlConfig {
+        staker_extend: 20_000_000,
+        ..config
+    };
+    let result = client.try_set_ttl_config(&t.admin, &too_long);
+    assert_eq!(result, Err(Ok(ContractError::InvalidConfig)));
+}
+
+#[test]
+fn test_superseded_root_moves_to_temporary_storage() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+
+    let root1 = BytesN::from_array(&t.env, &[1u8; 32]);
+    let root2 = BytesN::from_array(&t.env, &[2u8; 32]);
+    client.set_merkle_root(&t.admin, &0, &root1, &100);
+    assert_eq!(client.get_historical_root(&0, &1), None);
+
+    set_ledger(&t.env, 1000, 200);
+    client.set_merkle_root(&t.admin, &0, &root2, &200);
+    assert_eq!(client.get_merkle_root(&0).root, root2);
+    assert_eq!(client.get_historical_root(&0, &1).unwrap().root, root1);
+
+    let ttl = t.env.as_contract(&t.contract_id, || {
+        t.env
+            .storage()
+            .temporary()
+            .get_ttl(&crate::storage::DataKey::HistoricalRoot(0, 1))
+    });
+    assert_eq!(ttl, client.get_ttl_config().historical_root_ttl);
+
+    // Stakers may claim against the root until the claim deadline, so it
+    // lives at least that long
+    client.set_claim_deadline(&t.admin, &(90 * 86_400));
+    set_ledger(&t.env, 2000, 300);
+    client.set_merkle_root(&t.admin, &0, &root1, &300);
+    let ttl = t.env.as_contract(&t.contract_id, || {
+        t.env
+            .storage()
+            .temporary()
+            .get_ttl(&crate::storage::DataKey::HistoricalRoot(0, 2))
+    });
+    assert_eq!(ttl, 90 * 17_280);
+}
+
+// ========== version tests ==========
+
+#[test]
+fn test_get_version() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+
+    let info = client.get_version();
+    assert_eq!(
+        info.version,
+        soroban_sdk::String::from_str(&t.env, env!("CARGO_PKG_VERSION"))
+    );
+    assert_eq!(
+        info.git_hash,
+        option_env!("LP_STAKING_GIT_HASH").map(|h| soroban_sdk::String::from_str(&t.env, h))
+    );
+}
+#[test]
+fn test_rate_changes_are_applied_lazily() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    let rate = 462_962_963_i128;
+
+    // Two rate changes without the pool being touched in between
+    set_ledger(&t.env, 1200, 120);
+    client.set_reward_rate(&t.admin, &(rate / 2), &false);
+    set_ledger(&t.env, 1400, 140);
+    client.set_reward_rate(&t.admin, &(rate * 2), &false);
+    assert_eq!(client.get_pool_state(&0).last_reward_time, 1000);
+
+    set_ledger(&t.env, 1500, 150);
+    let expected = 200 * rate + 200 * (rate / 2) + 100 * (rate * 2);
+    assert_eq!(client.pending_reward(&user, &0), expected);
+    assert_eq!(client.claim(&user, &0), expected);
+}
+
+// ========== migration tests ==========
+
+#[test]
+fn test_migrate_from_unversioned_storage() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    assert_eq!(client.get_schema_version(), SCHEMA_VERSION);
+
+    // Simulate a deployment initialized before schema versioning
+    t.env.as_contract(&t.contract_id, || {
+        t.env
+            .storage()
+            .instance()
+            .remove(&crate::storage::DataKey::SchemaVersion);
+    });
+    assert_eq!(client.get_schema_version(), 0);
+
+    assert_eq!(client.migrate(&t.admin), SCHEMA_VERSION);
+    assert_eq!(client.migrate(&t.admin), SCHEMA_VERSION);
+    assert_eq!(client.get_schema_version(), SCHEMA_VERSION);
+
+    let rando = Address::generate(&t.env);
+    assert!(client.try_migrate(&rando).is_err());
+}
+
+#[test]
+fn test_migrate_v1_rebuilds_emission_totals() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    set_ledger(&t.env, 1100, 110);
+    client.checkpoint(&t.admin, &0);
+
+    let totals = t.env.as_contract(&t.contract_id, || {
+        let totals = crate::storage::get_emission_totals(&t.env);
+        // Simulate a v1 deployment: no emission index or totals stored
+        let storage = t.env.storage();
+        storage
+            .instance()
+            .remove(&crate::storage::DataKey::EmissionTotals);
+        storage
+            .persistent()
+            .remove(&crate::storage::DataKey::PoolEmissionIndex(0));
+        crate::storage::set_schema_version(&t.env, 1);
+        totals
+    });
+    assert_eq!(totals.emitting_pools, 1);
+
+    assert_eq!(client.migrate(&t.admin), SCHEMA_VERSION);
+    t.env.as_contract(&t.contract_id, || {
+        assert_eq!(crate::storage::get_emission_totals(&t.env), totals);
+    });
+}
+
+#[test]
+fn test_migrate_v4_combines_pool_entries() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    set_ledger(&t.env, 1100, 110);
+    client.checkpoint(&t.admin, &0);
+    let state = client.get_pool_state(&0);
+    let root = client.get_merkle_root(&0);
+
+    // Split the entry back into the v4 layout
+    t.env.as_contract(&t.contract_id, || {
+        let storage = t.env.storage().persistent();
+        storage.remove(&crate::storage::DataKeyExt::PoolEntry(0));
+        storage.set(&crate::storage::DataKey::PoolState(0), &state);
+        storage.set(&crate::storage::DataKey::MerkleRoot(0), &root);
+        crate::storage::set_schema_version(&t.env, 4);
+    });
+    assert_eq!(client.get_pool_state(&0), state);
+
+    assert_eq!(client.migrate(&t.admin), SCHEMA_VERSION);
+    t.env.as_contract(&t.contract_id, || {
+        let storage = t.env.storage().persistent();
+        assert!(storage.has(&crate::storage::DataKeyExt::PoolEntry(0)));
+        assert!(!storage.has(&crate::storage::DataKey::PoolState(0)));
+        assert!(!storage.has(&crate::storage::DataKey::MerkleRoot(0)));
+    });
+    assert_eq!(client.get_pool_state(&0), state);
+    assert_eq!(client.get_merkle_root(&0), root);
+}
+
+#[test]
+fn test_v2_staker_entries_still_decode() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 1_000_0000000);
+    let staker = client.get_staker_info(&user, &0);
+
+    // Rewrite the entry in the layout stored before cooldowns
+    t.env.as_contract(&t.contract_id, || {
+        let old = crate::storage::StakerInfoV2 {
+            staked_amount: staker.staked_amount,
+            reward_debt: staker.reward_debt,
+            pending_rewards: staker.pending_rewards,
+            epoch_id: staker.epoch_id,
+        };
+        t.env
+            .storage()
+            .persistent()
+            .set(&crate::storage::DataKey::Staker(user.clone(), 0), &old);
+    });
+    assert_eq!(client.get_staker_info(&user, &0), staker);
+
+    set_ledger(&t.env, 1100, 110);
+    assert_eq!(client.claim(&user, &0), 100 * 462_962_963);
+}
+
+// ========== config tests ==========
+
+#[test]
+fn test_get_config() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let keeper = Address::generate(&t.env);
+    client.set_keeper(&t.admin, &Some(keeper.clone()));
+    client.set_min_runway_days(&t.admin, &7);
+    client.pause(&t.admin);
+
+    let config = client.get_config();
+    assert_eq!(config.admin, t.admin);
+    assert_eq!(config.lmnr_token, t.lmnr_token);
+    assert_eq!(config.reward_rate_per_sec, 462_962_963);
+    assert!(config.paused);
+    assert!(!config.activated);
+    assert_eq!(config.keeper, Some(keeper));
+    assert_eq!(config.epoch_listeners.len(), 0);
+    assert_eq!(config.min_runway_days, 7);
+    assert!(!config.partial_payouts);
+    assert!(!config.hide_claim_destination);
+}
+
+// ========== prune tests ==========
+
+#[test]
+fn test_prune_empty_staker_pays_bounty() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    client.set_prune_bounty(&t.admin, &1_0000000);
+
+    let pruner = Address::generate(&t.env);
+    set_ledger(&t.env, 1100, 110);
+    client.unstake(&user, &0);
+
+    // Pending rewards keep the entry alive
+    let result = client.try_prune_staker(&pruner, &user, &0);
+    assert_eq!(result, Err(Ok(ContractError::StakerNotEmpty)));
+
+    client.claim(&user, &0);
+    assert_eq!(client.prune_staker(&pruner, &user, &0), 1_0000000);
+    assert_eq!(
+        token::Client::new(&t.env, &t.lmnr_token).balance(&pruner),
+        1_0000000
+    );
+    assert_eq!(client.get_accounting().total_bounties, 1_0000000);
+
+    let result = client.try_prune_staker(&pruner, &user, &0);
+    assert_eq!(result, Err(Ok(ContractError::NoStakeFound)));
+}
+
+#[test]
+fn test_prune_active_staker_fails() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+
+    let result = client.try_prune_staker(&Address::generate(&t.env), &user, &0);
+    assert_eq!(result, Err(Ok(ContractError::StakerNotEmpty)));
+}
+
+#[test]
+fn test_prune_bounty_skipped_when_only_owed_rewards_remain() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let staker = Address::generate(&t.env);
+    stake_single(&t, &staker, 10_000_0000000);
+    client.set_prune_bounty(&t.admin, &1_0000000);
+
+    // A second, empty entry created and zeroed by the admin
+    let idle = Address::generate(&t.env);
+    client.update_stake(&t.admin, &idle, &0, &1);
+    client.update_stake(&t.admin, &idle, &0, &0);
+
+    // Accrual now exceeds the funded balance
+    set_ledger(&t.env, 3000, 300);
+    client.checkpoint(&t.admin, &0);
+    let pruner = Address::generate(&t.env);
+    assert_eq!(client.prune_staker(&pruner, &idle, &0), 0);
+    assert!(client.try_get_staker_info(&idle, &0).is_err());
+}
+
+// ========== global stats tests ==========
+
+#[test]
+fn test_global_stats() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 2));
+    client.update_stake(&t.admin, &user, &1, &2_000_0000000);
+
+    set_ledger(&t.env, 1100, 110);
+    let claimed = client.claim(&user, &0);
+
+    let stats = client.get_global_stats();
+    assert_eq!(stats.total_pools, 2);
+    assert_eq!(stats.total_stakers, 2);
+    assert_eq!(stats.total_staked, 12_000_0000000);
+    assert_eq!(stats.total_distributed, claimed);
+    assert_eq!(stats.reward_rate_per_sec, 462_962_963);
+}
+
+// ========== epoch listener tests ==========
+
+#[test]
+fn test_epoch_listener_notified() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+
+    let failing_id = t.env.register(FailingListener, ());
+    let recorder_id = t.env.register(RecordingListener, ());
+    client.add_epoch_listener(&t.admin, &failing_id);
+    client.add_epoch_listener(&t.admin, &recorder_id);
+    client.add_epoch_listener(&t.admin, &recorder_id); // duplicate is a no-op
+    assert_eq!(client.get_epoch_listeners().len(), 2);
+
+    // The failing listener must not block the root post or later listeners
+    let root = BytesN::from_array(&t.env, &[3u8; 32]);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+    assert_eq!(client.get_merkle_root(&0).root, root);
+
+    let recorder = RecordingListenerClient::new(&t.env, &recorder_id);
+    assert_eq!(recorder.last(), Some((0, 1, root)));
+
+    client.remove_epoch_listener(&t.admin, &recorder_id);
+    assert_eq!(client.get_epoch_listeners().len(), 1);
+}
+
+#[test]
+fn test_epoch_listener_limit() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    for _ in 0..5 {
+        client.add_epoch_listener(&t.admin, &Address::generate(&t.env));
+    }
+    let result = client.try_add_epoch_listener(&t.admin, &Address::generate(&t.env));
+    assert!(result.is_err());
+
+    let rando = Address::generate(&t.env);
+    let result = client.try_add_epoch_listener(&rando, &Address::generate(&t.env));
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_root_post_emits_epoch_summary() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+
+    set_ledger(&t.env, 2000, 110);
+    let root = BytesN::from_array(&t.env, &[9u8; 32]);
+    client.set_merkle_root(&t.admin, &0, &root, &105);
+    let event = t.env.events().all().last().unwrap();
+    assert_eq!(event.1, (events::EPOCH_ROTATED, 0u32).into_val(&t.env));
+    let data: EpochRotated = event.2.into_val(&t.env);
+
+    let state = client.get_pool_state(&0);
+    assert_eq!(
+        data,
+        EpochRotated {
+            old_epoch_id: 1,
+            new_epoch_id: 2,
+            total_staked: 10_000_0000000,
+            acc_reward_per_share: state.prev_acc_reward_per_share,
+            snapshot_ledger: 105,
+            root,
+        }
+    );
+    assert!(data.acc_reward_per_share > 0);
+}
+
+// ========== runway tests ==========
+
+#[test]
+fn test_set_reward_rate_requires_runway() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+
+    // 50k LMNR covers one day for one emitting pool at up to ~5_787_037/sec
+    client.set_min_runway_days(&t.admin, &1);
+    let result = client.try_set_reward_rate(&t.admin, &462_962_963, &false);
+    assert_eq!(result, Err(Ok(ContractError::InsufficientRunway)));
+
+    client.set_reward_rate(&t.admin, &5_000_000, &false);
+    assert_eq!(client.get_reward_rate(), 5_000_000);
+
+    // A rate whose runway overflows is refused rather than trapping
+    let result = client.try_set_reward_rate(&t.admin, &(i128::MAX / 2), &false);
+    assert_eq!(result, Err(Ok(ContractError::InsufficientRunway)));
+
+    // Emergency override skips the check
+    client.set_reward_rate(&t.admin, &462_962_963, &true);
+    assert_eq!(client.get_reward_rate(), 462_962_963);
+}
+
+#[test]
+fn test_rate_bounds_reject_fat_fingered_rate() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 2));
+    client.set_rate_bounds(&t.admin, &1_000_000_000, &100_000_0000000);
+
+    // An extra zero breaks the per-second ceiling, even when forced
+    let result = client.try_set_reward_rate(&t.admin, &4_629_629_630, &true);
+    assert_eq!(result, Err(Ok(ContractError::RateTooHigh)));
+    let result = client.try_set_reward_rate(&t.admin, &-1, &true);
+    assert_eq!(result, Err(Ok(ContractError::InvalidAmount)));
+
+    // 100 LMNR/sec is within the rate ceiling but 8.64M LMNR/day is not
+    let result = client.try_set_reward_rate(&t.admin, &1_000_000_000, &true);
+    assert_eq!(result, Err(Ok(ContractError::RateTooHigh)));
+
+    client.set_reward_rate_per_day(&t.admin, &40_000_0000000, &false);
+    assert_eq!(client.get_reward_rate(), 40_000_0000000 / 86_400);
+    assert_eq!(client.get_config().rate_bounds.max_rate, 1_000_000_000);
+
+    // With no per-second ceiling, a rate whose daily emission overflows is
+    // still refused rather than trapping
+    client.set_rate_bounds(&t.admin, &0, &100_000_0000000);
+    let result = client.try_set_reward_rate(&t.admin, &(i128::MAX / 2), &true);
+    assert_eq!(result, Err(Ok(ContractError::RateTooHigh)));
+}
+
+#[test]
+fn test_rate_history_pages_through_changes() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let initial = client.get_rate_history(&0, &10);
+    assert_eq!(initial.len(), 1);
+    assert_eq!(initial.get(0).unwrap().rate, 462_962_963);
+
+    set_ledger(&t.env, 1100, 110);
+    client.set_reward_rate(&t.admin, &1_000, &false);
+    set_ledger(&t.env, 1200, 120);
+    client.set_reward_rate(&t.admin, &2_000, &false);
+
+    let history = client.get_rate_history(&0, &10);
+    assert_eq!(history.len(), 3);
+    assert_eq!(history.get(0).unwrap(), initial.get(0).unwrap());
+    let first = history.get(1).unwrap();
+    assert_eq!((first.timestamp, first.rate), (1100, 1_000));
+    let second = history.get(2).unwrap();
+    assert_eq!((second.timestamp, second.rate), (1200, 2_000));
+
+    let page = client.get_rate_history(&2, &10);
+    assert_eq!(page.len(), 1);
+    assert_eq!(page.get(0).unwrap(), second);
+    assert_eq!(client.get_rate_history(&3, &10).len(), 0);
+}
+
+#[test]
+fn test_runway_ignores_pools_without_stake() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+
+    client.set_min_runway_days(&t.admin, &30);
+    client.set_reward_rate(&t.admin, &462_962_963, &false);
+    assert_eq!(client.get_reward_rate(), 462_962_963);
+}
+
+#[test]
+fn test_get_runway_tracks_unowed_balance() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    assert_eq!(client.get_runway(), u64::MAX);
+
+    let user = Address::generate(&t.env);
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, 1_000_0000000, 1);
+    let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+    client.stake(&user, &0, &1_000_0000000, &proofs.get(0).unwrap());
+    let runway = client.get_runway();
+    assert_eq!(runway, 50_000_0000000 / 462_962_963);
+
+    // Accrued rewards are owed, so the runway shrinks as time passes
+    set_ledger(&t.env, 1100, 110);
+    assert_eq!(client.get_runway(), runway - 100);
+}
+
+#[test]
+fn test_runway_counts_only_global_rate_pools() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 1_000_0000000);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 2));
+    let other = Address::generate(&t.env);
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 1, &other, 1_000_0000000, 1);
+    let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
+    client.set_merkle_root(&t.admin, &1, &root, &100);
+    client.stake(&other, &1, &1_000_0000000, &proofs.get(0).unwrap());
+    assert_eq!(client.get_runway(), 50_000_0000000 / (2 * 462_962_963));
+
+    // A pool on its own budget draws nothing at the global rate
+    client.set_epoch_budget(&t.admin, &1, &1_000_0000000, &86_400);
+    assert_eq!(client.get_runway(), 50_000_0000000 / 462_962_963);
+    client.set_min_runway_days(&t.admin, &1);
+    client.set_reward_rate(&t.admin, &4_000_000, &false);
+    client.set_epoch_budget(&t.admin, &1, &0, &0);
+    let result = client.try_set_reward_rate(&t.admin, &4_000_000, &false);
+    assert_eq!(result, Err(Ok(ContractError::InsufficientRunway)));
+
+    // A rate too large to price has no runway rather than trapping
+    client.set_reward_rate(&t.admin, &(i128::MAX / 2 + 1), &true);
+    assert_eq!(client.get_runway(), 0);
+}
+
+// ========== LP decimals / precision tests ==========
+
+#[test]
+fn test_precision_for_decimals() {
+    let full = 1_000_000_000_000_000_000;
+    assert_eq!(rewards::precision_for_decimals(7, 7), full);
+    assert_eq!(rewards::precision_for_decimals(0, 7), full);
+    assert_eq!(rewards::precision_for_decimals(12, 7), 10_000_000_000_000);
+    assert_eq!(rewards::precision_for_decimals(18, 7), 1_000_000_000);
+    assert_eq!(rewards::precision_for_decimals(7, 6), full);
+    assert_eq!(rewards::precision_for_decimals(7, 18), 1_000_000_000);
+}
+
+#[test]
+fn test_eighteen_decimal_pool_rewards() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    client.set_pool_lp_decimals(&t.admin, &0, &18);
+    assert_eq!(client.get_pool_config(&0).lp_decimals, 18);
+
+    // Two stakers holding 1 and 3 whole 18-decimal LP tokens
+    let user1 = Address::generate(&t.env);
+    let user2 = Address::generate(&t.env);
+    let bal1: i128 = 1_000_000_000_000_000_000;
+    let bal2: i128 = 3_000_000_000_000_000_000;
+    let leaf1 = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user1, bal1, 1);
+    let leaf2 = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user2, bal2, 1);
+    let (root, proofs) = build_merkle_tree(&t.env, &[leaf1, leaf2]);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+    client.stake(&user1, &0, &bal1, &proofs.get(0).unwrap());
+    client.stake(&user2, &0, &bal2, &proofs.get(1).unwrap());
+    client.set_reward_rate(&t.admin, &4_000_000, &false);
+
+    // 1000 seconds -> 4_000_000_000 stroops, split 1:3 at 1e9 precision
+    set_ledger(&t.env, 2000, 200);
+    assert_eq!(client.pending_reward(&user1, &0), 1_000_000_000);
+    assert_eq!(client.pending_reward(&user2, &0), 3_000_000_000);
+    assert_eq!(client.claim(&user1, &0), 1_000_000_000);
+}
+
+#[test]
+fn test_set_pool_lp_decimals_after_stake_fails() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+
+    let result = client.try_set_pool_lp_decimals(&t.admin, &0, &18);
+    assert_eq!(result, Err(Ok(ContractError::PoolInUse)));
+
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 2));
+    let result = client.try_set_pool_lp_decimals(&t.admin, &1, &19);
+    assert_eq!(result, Err(Ok(ContractError::InvalidConfig)));
+}
+
+// ========== pool cap tests ==========
+
+#[test]
+fn test_pool_cap_limits_stake() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    client.set_pool_max_staked(&t.admin, &0, &15_000_0000000);
+
+    let alice = Address::generate(&t.env);
+    let bob = Address::generate(&t.env);
+    let leaves = [
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &alice, 10_000_0000000, 1),
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &bob, 10_000_0000000, 1),
+    ];
+    let (root, proofs) = build_merkle_tree(&t.env, &leaves);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+
+    client.stake(&alice, &0, &10_000_0000000, &proofs.get(0).unwrap());
+    let result = client.try_stake(&bob, &0, &10_000_0000000, &proofs.get(1).unwrap());
+    assert_eq!(result, Err(Ok(ContractError::PoolCapExceeded)));
+    assert_eq!(client.get_pool_state(&0).total_staked, 10_000_0000000);
+
+    // Lifting the cap lets the stake through
+    client.set_pool_max_staked(&t.admin, &0, &0);
+    client.stake(&bob, &0, &10_000_0000000, &proofs.get(1).unwrap());
+    assert_eq!(client.get_pool_state(&0).total_staked, 20_000_0000000);
+}
+
+#[test]
+fn test_pool_cap_limits_update_stake() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    client.set_pool_max_staked(&t.admin, &0, &5_000_0000000);
+
+    // Already over a lowered cap: may shrink, may not grow
+    let result = client.try_update_stake(&t.admin, &user, &0, &11_000_0000000);
+    assert_eq!(result, Err(Ok(ContractError::PoolCapExceeded)));
+    client.update_stake(&t.admin, &user, &0, &8_000_0000000);
+    assert_eq!(client.get_pool_state(&0).total_staked, 8_000_0000000);
+
+    let result = client.try_set_pool_max_staked(&t.admin, &0, &-1);
+    assert_eq!(result, Err(Ok(ContractError::InvalidAmount)));
+}
+
+// ========== minimum stake tests ==========
+
+#[test]
+fn test_min_stake_global_and_pool_override() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    client.set_min_stake(&t.admin, &1_000_0000000);
+    assert_eq!(client.get_config().min_stake, 1_000_0000000);
+
+    let small = Address::generate(&t.env);
+    let large = Address::generate(&t.env);
+    let leaves = [
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &small, 500_0000000, 1),
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &large, 2_000_0000000, 1),
+    ];
+    let (root, proofs) = build_merkle_tree(&t.env, &leaves);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+
+    let result = client.try_stake(&small, &0, &500_0000000, &proofs.get(0).unwrap());
+    assert_eq!(result, Err(Ok(ContractError::BelowMinimumStake)));
+    client.stake(&large, &0, &2_000_0000000, &proofs.get(1).unwrap());
+
+    // A pool-level minimum takes precedence over the global one
+    client.set_pool_min_stake(&t.admin, &0, &Some(100_0000000));
+    client.stake(&small, &0, &500_0000000, &proofs.get(0).unwrap());
+    assert_eq!(client.get_pool_state(&0).total_staked, 2_500_0000000);
+
+    client.set_pool_min_stake(&t.admin, &0, &None);
+    assert_eq!(client.get_pool_config(&0).min_stake, None);
+}
+
+#[test]
+fn test_min_stake_rejects_negative() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+
+    let result = client.try_set_min_stake(&t.admin, &-1);
+    assert_eq!(result, Err(Ok(ContractError::InvalidAmount)));
+    let result = client.try_set_pool_min_stake(&t.admin, &0, &Some(-1));
+    assert_eq!(result, Err(Ok(ContractError::InvalidAmount)));
+}
+
+// ========== blocklist tests ==========
+
+#[test]
+fn test_blocked_address_cannot_stake() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let user = Address::generate(&t.env);
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, 10_000_0000000, 1);
+    let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+
+    client.block_address(&t.admin, &user, &true);
+    assert_eq!(client.get_blocked(&user), Some(true));
+    let result = client.try_stake(&user, &0, &10_000_0000000, &proofs.get(0).unwrap());
+    assert_eq!(result, Err(Ok(ContractError::AddressBlocked)));
+
+    client.unblock_address(&t.admin, &user);
+    assert_eq!(client.get_blocked(&user), None);
+    client.stake(&user, &0, &10_000_0000000, &proofs.get(0).unwrap());
+}
+
+#[test]
+fn test_frozen_claims_kept_until_unblock() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    set_ledger(&t.env, 1100, 110);
+
+    client.block_address(&t.admin, &user, &true);
+    let result = client.try_claim(&user, &0);
+    assert_eq!(result, Err(Ok(ContractError::ClaimsFrozen)));
+
+    client.unblock_address(&t.admin, &user);
+    assert_eq!(client.claim(&user, &0), 100 * 462_962_963);
+}
+
+#[test]
+fn test_forfeited_claims_release_liability() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    set_ledger(&t.env, 1100, 110);
+
+    client.block_address(&t.admin, &user, &false);
+    let users = Vec::from_array(&t.env, [user.clone()]);
+    let results = client.distribute(&t.admin, &0, &users, &true);
+    assert_eq!(results, Vec::from_array(&t.env, [ITEM_OK]));
+    assert_eq!(client.get_pool_liability(&0), 0);
+    assert_eq!(client.pending_reward(&user, &0), 0);
+    assert_eq!(token::Client::new(&t.env, &t.lmnr_token).balance(&user), 0);
+}
+
+#[test]
+fn test_claim_to_blocked_destination_fails() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    set_ledger(&t.env, 1100, 110);
+
+    let destination = Address::generate(&t.env);
+    client.block_address(&t.admin, &destination, &true);
+    let result = client.try_claim_to(&user, &0, &destination);
+    assert_eq!(result, Err(Ok(ContractError::AddressBlocked)));
+}
+
+// ========== compliance hook tests ==========
+
+#[test]
+fn test_compliance_hook_gates_stake_and_claim() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let hook_id = t.env.register(DenylistHook, ());
+    let hook = DenylistHookClient::new(&t.env, &hook_id);
+    client.set_compliance_hook(&t.admin, &Some(hook_id.clone()));
+    assert_eq!(client.get_config().compliance_hook, Some(hook_id));
+
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    set_ledger(&t.env, 1100, 110);
+
+    let destination = Address::generate(&t.env);
+    hook.deny(&destination);
+    let result = client.try_claim_to(&user, &0, &destination);
+    assert_eq!(result, Err(Ok(ContractError::NotCompliant)));
+
+    hook.deny(&user);
+    let result = client.try_claim(&user, &0);
+    assert_eq!(result, Err(Ok(ContractError::NotCompliant)));
+
+    client.set_compliance_hook(&t.admin, &None);
+    assert_eq!(client.claim(&user, &0), 100 * 462_962_963);
+}
+
+#[test]
+fn test_broken_compliance_hook_fails_closed() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let user = Address::generate(&t.env);
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, 10_000_0000000, 1);
+    let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+
+    // FailingListener has no `check` entrypoint
+    let broken = t.env.register(FailingListener, ());
+    client.set_compliance_hook(&t.admin, &Some(broken));
+    let result = client.try_stake(&user, &0, &10_000_0000000, &proofs.get(0).unwrap());
+    assert_eq!(result, Err(Ok(ContractError::NotCompliant)));
+}
+
+// ========== stake hook tests ==========
+
+#[test]
+fn test_stake_hook_notified() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let hook_id = t.env.register(RecordingStakeHook, ());
+    let hook = RecordingStakeHookClient::new(&t.env, &hook_id);
+    client.set_stake_hook(&t.admin, &Some(hook_id));
+
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    assert_eq!(hook.last_stake(), Some((user.clone(), 0, 10_000_0000000)));
+
+    client.update_stake(&t.admin, &user, &0, &4_000_0000000);
+    assert_eq!(hook.last_stake(), Some((user.clone(), 0, 4_000_0000000)));
+
+    set_ledger(&t.env, 1100, 110);
+    let claimed = client.claim(&user, &0);
+    assert_eq!(hook.last_claim(), Some((user.clone(), 0, claimed)));
+
+    client.unstake(&user, &0);
+    assert_eq!(hook.last_stake(), Some((user, 0, 0)));
+}
+
+#[test]
+fn test_failing_stake_hook_does_not_block() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let broken = t.env.register(FailingListener, ());
+    client.set_stake_hook(&t.admin, &Some(broken));
+
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    set_ledger(&t.env, 1100, 110);
+    assert_eq!(client.claim(&user, &0), 100 * 462_962_963);
+}
+
+// ========== signed root tests ==========
+
+#[test]
+fn test_signed_root_needs_threshold_of_oracles() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let keys = [11u8, 12, 13].map(|seed| SigningKey::from_bytes(&[seed; 32]));
+    let public = |key: &SigningKey| BytesN::from_array(&t.env, &key.verifying_key().to_bytes());
+    let root = BytesN::from_array(&t.env, &[5u8; 32]);
+    let sign = |key: &SigningKey, epoch_id: u64, ledger: u32| {
+        sign_root(&t, key, 0, epoch_id, &root, ledger)
+    };
+
+    let both = Vec::from_array(&t.env, [sign(&keys[0], 1, 100), sign(&keys[1], 1, 100)]);
+    let result = client.try_set_merkle_root_signed(&0, &root, &100, &both);
+    assert_eq!(result, Err(Ok(ContractError::Unauthorized)));
+
+    let mut oracles = RootOracles {
+        keys: Vec::from_array(&t.env, keys.each_ref().map(public)),
+        threshold: 2,
+    };
+    client.set_root_oracles(&t.admin, &Some(oracles.clone()));
+    assert_eq!(client.get_root_oracles(), Some(oracles.clone()));
+
+    // One signer, the same signer twice, or an outside key fall short
+    let outsider = SigningKey::from_bytes(&[14u8; 32]);
+    for signatures in [
+        Vec::from_array(&t.env, [sign(&keys[0], 1, 100)]),
+        Vec::from_array(&t.env, [sign(&keys[0], 1, 100), sign(&keys[0], 1, 100)]),
+        Vec::from_array(&t.env, [sign(&keys[0], 1, 100), sign(&outsider, 1, 100)]),
+    ] {
+        let result = client.try_set_merkle_root_signed(&0, &root, &100, &signatures);
+        assert_eq!(result, Err(Ok(ContractError::Unauthorized)));
+    }
+
+    client.set_merkle_root_signed(&0, &root, &100, &both);
+    let posted = client.get_merkle_root(&0);
+    assert_eq!((posted.epoch_id, posted.root), (1, root.clone()));
+
+    // Epoch 1 signatures cannot open epoch 2
+    set_ledger(&t.env, 1100, 110);
+    let result = client.try_set_merkle_root_signed(&0, &root, &100, &both);
+    assert!(result.is_err());
+    let fresh = Vec::from_array(&t.env, [sign(&keys[1], 2, 110), sign(&keys[2], 2, 110)]);
+    client.set_merkle_root_signed(&0, &root, &110, &fresh);
+    assert_eq!(client.get_merkle_root(&0).epoch_id, 2);
+
+    oracles.threshold = 4;
+    let result = client.try_set_root_oracles(&t.admin, &Some(oracles.clone()));
+    assert_eq!(result, Err(Ok(ContractError::InvalidConfig)));
+    oracles.threshold = 2;
+    oracles.keys.set(2, public(&keys[0]));
+    let result = client.try_set_root_oracles(&t.admin, &Some(oracles));
+    assert_eq!(result, Err(Ok(ContractError::InvalidConfig)));
+}
+
+// ========== revenue tests ==========
+
+#[test]
+fn test_revenue_shared_at_rotation_and_paid_with_claim() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let alice = Address::generate(&t.env);
+    let bob = Address::generate(&t.env);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let post = |epoch_id: u64, sequence: u32| {
+        let leaves = [
+            merkle::compute_leaf(&t.env, &t.contract_id, 0, &alice, 1_000_0000000, epoch_id),
+            merkle::compute_leaf(&t.env, &t.contract_id, 0, &bob, 3_000_0000000, epoch_id),
+        ];
+        let (root, proofs) = build_merkle_tree(&t.env, &leaves);
+        client.set_merkle_root(&t.admin, &0, &root, &sequence);
+        proofs
+    };
+    let proofs = post(1, 100);
+    client.stake(&alice, &0, &1_000_0000000, &proofs.get(0).unwrap());
+    client.stake(&bob, &0, &3_000_0000000, &proofs.get(1).unwrap());
+
+    let depositor = Address::generate(&t.env);
+    let usdc = t
+        .env
+        .register_stellar_asset_contract_v2(Address::generate(&t.env))
+        .address();
+    token::StellarAssetClient::new(&t.env, &usdc).mint(&depositor, &400_0000000);
+    let result = client.try_deposit_revenue(&depositor, &t.lmnr_token, &0, &400_0000000);
+    assert_eq!(result, Err(Ok(ContractError::InvalidConfig)));
+    client.deposit_revenue(&depositor, &usdc, &0, &400_0000000);
+    let pot = || client.get_revenue_pots(&0).get(usdc.clone()).unwrap();
+    assert_eq!(pot().pending, 400_0000000);
+    assert!(client.pending_revenue(&alice, &0).is_empty());
+
+    // Shared by weight when the next root is posted
+    set_ledger(&t.env, 1100, 110);
+    post(2, 110);
+    assert_eq!(pot().pending, 0);
+    let owed = |user: &Address| client.pending_revenue(user, &0).get(usdc.clone());
+    assert_eq!(owed(&alice), Some(100_0000000));
+    assert_eq!(owed(&bob), Some(300_0000000));
+
+    let usdc_client = token::Client::new(&t.env, &usdc);
+    client.claim(&alice, &0);
+    assert_eq!(usdc_client.balance(&alice), 100_0000000);
+    assert!(client.pending_revenue(&alice, &0).is_empty());
+
+    // Revenue owed outlives the stake
+    client.unstake(&bob, &0);
+    let paid = client.claim_revenue(&bob, &0);
+    assert_eq!(paid.get(usdc.clone()), Some(300_0000000));
+    assert_eq!(usdc_client.balance(&bob), 300_0000000);
+    let result = client.try_claim_revenue(&bob, &0);
+    assert_eq!(result, Err(Ok(ContractError::NoRewardsToClaim)));
+
+    let result = client.try_rescue_tokens(&t.admin, &usdc, &t.admin, &1);
+    assert_eq!(result, Err(Ok(ContractError::CannotRescueRewardToken)));
+}
+
+#[test]
+fn test_sponsor_campaign_pays_by_weight_and_reclaims_rest() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let alice = Address::generate(&t.env);
+    let bob = Address::generate(&t.env);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let leaves = [
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &alice, 1_000_0000000, 1),
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &bob, 3_000_0000000, 1),
+    ];
+    let (root, proofs) = build_merkle_tree(&t.env, &leaves);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+    client.stake(&alice, &0, &1_000_0000000, &proofs.get(0).unwrap());
+    client.stake(&bob, &0, &3_000_0000000, &proofs.get(1).unwrap());
+
+    let sponsor = Address::generate(&t.env);
+    let bonus = t
+        .env
+        .register_stellar_asset_contract_v2(Address::generate(&t.env))
+        .address();
+    token::StellarAssetClient::new(&t.env, &bonus).mint(&sponsor, &1_000_0000000);
+    let result = client.try_create_campaign(&sponsor, &0, &bonus, &1_000_0000000, &900, &2000);
+    assert_eq!(result, Err(Ok(ContractError::InvalidConfig)));
+    let id = client.create_campaign(&sponsor, &0, &bonus, &1_000_0000000, &1000, &2000);
+    assert_eq!(client.get_campaign(&0, &id).unwrap().sponsor, sponsor);
+
+    // Halfway through, a quarter of the release is Alice's
+    set_ledger(&t.env, 1500, 110);
+    assert_eq!(
+        client.pending_campaign_rewards(&alice, &0).get(id),
+        Some(125_0000000)
+    );
+    client.claim(&alice, &0);
+    let bonus_client = token::Client::new(&t.env, &bonus);
+    assert_eq!(bonus_client.balance(&alice), 125_0000000);
+
+    set_ledger(&t.env, 2000, 120);
+    client.unstake(&bob, &0);
+    let paid = client.claim_campaigns(&bob, &0);
+    assert_eq!(paid.get(id), Some(750_0000000));
+
+    // Alice's unclaimed share goes back to the sponsor after the window
+    let result = client.try_reclaim_campaign(&sponsor, &0, &id);
+    assert_eq!(result, Err(Ok(ContractError::TimelockNotElapsed)));
+    set_ledger(&t.env, 2000 + 30 * 86_400, 130);
+    let result = client.try_reclaim_campaign(&alice, &0, &id);
+    assert_eq!(result, Err(Ok(ContractError::Unauthorized)));
+    assert_eq!(client.reclaim_campaign(&sponsor, &0, &id), 125_0000000);
+    assert_eq!(bonus_client.balance(&sponsor), 125_0000000);
+    assert!(client.get_campaign(&0, &id).is_none());
+    assert!(client.pending_campaign_rewards(&alice, &0).is_empty());
+    let result = client.try_reclaim_campaign(&sponsor, &0, &id);
+    assert_eq!(result, Err(Ok(ContractError::CampaignNotFound)));
+}
+
+#[test]
+fn test_rejected_share_transfers_stay_owed_without_blocking_claims() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 1_000_0000000);
+
+    let sponsor = Address::generate(&t.env);
+    let bonus_sac = t
+        .env
+        .register_stellar_asset_contract_v2(Address::generate(&t.env));
+    bonus_sac.issuer().set_flag(IssuerFlags::RevocableFlag);
+    let bonus = bonus_sac.address();
+    let bonus_admin = token::StellarAssetClient::new(&t.env, &bonus);
+    bonus_admin.mint(&sponsor, &1_000_0000000);
+    let id = client.create_campaign(&sponsor, &0, &bonus, &1_000_0000000, &1000, &2000);
+
+    // A frozen campaign token leaves its share owed; LMNR still pays
+    set_ledger(&t.env, 1500, 110);
+    bonus_admin.set_authorized(&user, &false);
+    assert!(client.claim(&user, &0) > 0);
+    let bonus_client = token::Client::new(&t.env, &bonus);
+    assert_eq!(bonus_client.balance(&user), 0);
+    let owed = client.pending_campaign_rewards(&user, &0).get(id);
+    assert_eq!(owed, Some(500_0000000));
+
+    // A frozen LMNR trustline is skipped by the batch instead of failing it
+    set_ledger(&t.env, 1600, 120);
+    let lmnr_admin = token::StellarAssetClient::new(&t.env, &t.lmnr_token);
+    lmnr_admin.set_authorized(&user, &false);
+    let users = Vec::from_array(&t.env, [user.clone()]);
+    assert_eq!(client.claim_batch(&t.admin, &0, &users), 0);
+    assert!(client.pending_reward(&user, &0) > 0);
+
+    bonus_admin.set_authorized(&user, &true);
+    let paid = client.claim_campaigns(&user, &0);
+    assert_eq!(paid.get(id), Some(600_0000000));
+    assert_eq!(bonus_client.balance(&user), 600_0000000);
+}
+
+#[test]
+fn test_claim_all_pays_every_stream() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 1_000_0000000);
+
+    let sponsor = Address::generate(&t.env);
+    let sponsor_campaign = |amount: i128, end: u64| {
+        let bonus = t
+            .env
+            .register_stellar_asset_contract_v2(Address::generate(&t.env))
+            .address();
+        token::StellarAssetClient::new(&t.env, &bonus).mint(&sponsor, &amount);
+        client.create_campaign(&sponsor, &0, &bonus, &amount, &1000, &end);
+        bonus
+    };
+    let tokens = [
+        sponsor_campaign(100_0000000, 1100),
+        sponsor_campaign(200_0000000, 1200),
+    ];
+
+    set_ledger(&t.env, 1100, 110);
+    let payouts = client.claim_all(&user, &0);
+    let expected = [
+        (t.lmnr_token.clone(), 100 * 462_962_963),
+        (tokens[0].clone(), 100_0000000),
+        (tokens[1].clone(), 100_0000000),
+    ];
+    assert_eq!(payouts.len(), expected.len() as u32);
+    for (payout, (token, amount)) in payouts.iter().zip(expected) {
+        assert_eq!(payout, Payout { token, amount });
+    }
+
+    let result = client.try_claim_all(&user, &0);
+    assert_eq!(result, Err(Ok(ContractError::NoRewardsToClaim)));
+}
+
+// ========== deposit pool tests ==========
+
+// Helper: register a deposit-mode pool backed by a fresh LP token and mint
+// `amount` of it to `user`. Returns (pool_index, lp_token).
+fn setup_deposit_pool(t: &TestEnv, user: &Address, amount: i128) -> (u32, Address) {
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let lp_token = t
+        .env
+        .register_stellar_asset_contract_v2(Address::generate(&t.env))
+        .address();
+    token::StellarAssetClient::new(&t.env, &lp_token).mint(user, &amount);
+    (client.add_deposit_pool(&t.admin, &lp_token), lp_token)
+}
+
+#[test]
+fn test_deposit_pool_stake_and_unstake() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    let (pool, lp_token) = setup_deposit_pool(&t, &user, 10_000_0000000);
+    let lp = token::Client::new(&t.env, &lp_token);
+
+    client.stake_deposit(&user, &pool, &6_000_0000000);
+    client.stake_deposit(&user, &pool, &4_000_0000000);
+    assert_eq!(lp.balance(&t.contract_id), 10_000_0000000);
+    assert_eq!(client.get_pool_state(&pool).total_staked, 10_000_0000000);
+
+    // No epochs: rewards keep accruing without any Merkle root
+    set_ledger(&t.env, 1100, 110);
+    assert_eq!(client.pending_reward(&user, &pool), 100 * 462_962_963);
+
+    client.unstake(&user, &pool);
+    assert_eq!(lp.balance(&user), 10_000_0000000);
+    assert_eq!(lp.balance(&t.contract_id), 0);
+    assert_eq!(client.claim(&user, &pool), 100 * 462_962_963);
+}
+
+#[test]
+fn test_deposit_pool_rejects_merkle_operations() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    let (pool, lp_token) = setup_deposit_pool(&t, &user, 10_000_0000000);
+
+    let root = BytesN::from_array(&t.env, &[7u8; 32]);
+    let result = client.try_set_merkle_root(&t.admin, &pool, &root, &100);
+    assert_eq!(result, Err(Ok(ContractError::WrongPoolMode)));
+    let result = client.try_update_stake(&t.admin, &user, &pool, &1);
+    assert_eq!(result, Err(Ok(ContractError::WrongPoolMode)));
+
+    // Re-registering the same token is a duplicate pool
+    let result = client.try_add_deposit_pool(&t.admin, &lp_token);
+    assert_eq!(result, Err(Ok(ContractError::PoolAlreadyExists)));
+
+    // Escrowed LP can't be rescued
+    client.stake_deposit(&user, &pool, &1_000_0000000);
+    let result = client.try_rescue_tokens(&t.admin, &lp_token, &t.admin, &1_000_0000000);
+    assert_eq!(result, Err(Ok(ContractError::CannotRescueRewardToken)));
+}
+
+#[test]
+fn test_stake_deposit_on_merkle_pool_fails() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+
+    let user = Address::generate(&t.env);
+    let result = client.try_stake_deposit(&user, &0, &1_000_0000000);
+    assert_eq!(result, Err(Ok(ContractError::WrongPoolMode)));
+}
+
+#[test]
+fn test_add_aqua_pool_reads_share_token() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let share_token = t
+        .env
+        .register_stellar_asset_contract_v2(Address::generate(&t.env))
+        .address();
+    let aqua_id = t.env.register(MockAquaPool, ());
+    MockAquaPoolClient::new(&t.env, &aqua_id).init(&share_token);
+
+    let pool = client.add_aqua_pool(&t.admin, &aqua_id);
+    let config = client.get_pool_config(&pool);
+    assert_eq!(config.lp_token, Some(share_token.clone()));
+    assert_eq!(config.amm_pool, Some(aqua_id));
+    assert_eq!(config.lp_decimals, 7);
+
+    let user = Address::generate(&t.env);
+    token::StellarAssetClient::new(&t.env, &share_token).mint(&user, &1_000_0000000);
+    client.stake_deposit(&user, &pool, &1_000_0000000);
+    assert_eq!(client.get_pool_state(&pool).total_staked, 1_000_0000000);
+}
+
+#[test]
+fn test_add_aqua_pool_rejects_non_pool() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+
+    let not_a_pool = t.env.register(FailingListener, ());
+    let result = client.try_add_aqua_pool(&t.admin, &not_a_pool);
+    assert_eq!(result, Err(Ok(ContractError::InvalidAmmPool)));
+
+    // A pool whose share token isn't a token
+    let aqua_id = t.env.register(MockAquaPool, ());
+    MockAquaPoolClient::new(&t.env, &aqua_id).init(&not_a_pool);
+    let result = client.try_add_aqua_pool(&t.admin, &aqua_id);
+    assert_eq!(result, Err(Ok(ContractError::InvalidAmmPool)));
+}
+
+#[test]
+fn test_hybrid_pool_shares_reward_stream() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let sdex_user = Address::generate(&t.env);
+    stake_single(&t, &sdex_user, 10_000_0000000);
+
+    let amm_user = Address::generate(&t.env);
+    let lp_token = t
+        .env
+        .register_stellar_asset_contract_v2(Address::generate(&t.env))
+        .address();
+    token::StellarAssetClient::new(&t.env, &lp_token).mint(&amm_user, &10_000_0000000);
+    let side = client.add_hybrid_deposit(&t.admin, &0, &lp_token);
+    assert_eq!(client.get_pool_config(&side).shared_with, Some(0));
+
+    client.stake_deposit(&amm_user, &side, &10_000_0000000);
+    assert_eq!(client.get_pool_state(&0).total_staked, 20_000_0000000);
+
+    // Revenue deposited on either side lands in the shared pots
+    let usdc = t
+        .env
+        .register_stellar_asset_contract_v2(Address::generate(&t.env))
+        .address();
+    token::StellarAssetClient::new(&t.env, &usdc).mint(&amm_user, &100_0000000);
+    client.deposit_revenue(&amm_user, &usdc, &side, &100_0000000);
+    assert_eq!(client.get_revenue_pots(&side), client.get_revenue_pots(&0));
+    assert_eq!(
+        client.get_revenue_pots(&side).get(usdc).unwrap().pending,
+        100_0000000
+    );
+
+    // One pool's worth of emissions, split evenly across the two venues
+    set_ledger(&t.env, 1100, 110);
+    let half = 100 * 462_962_963 / 2;
+    assert_eq!(client.pending_reward(&sdex_user, &0), half);
+    assert_eq!(client.pending_reward(&amm_user, &side), half);
+
+    // An epoch rollover doesn't stale the deposit side
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, &sdex_user, 10_000_0000000, 2);
+    let (root, _) = build_merkle_tree(&t.env, &[leaf]);
+    client.set_merkle_root(&t.admin, &0, &root, &110);
+    set_ledger(&t.env, 1200, 120);
+    assert_eq!(client.claim(&amm_user, &side), 2 * half);
+    assert_eq!(client.get_pool_liability(&0), 2 * half);
+
+    client.unstake(&amm_user, &side);
+    assert_eq!(client.get_pool_state(&0).total_staked, 10_000_0000000);
+    assert_eq!(
+        token::Client::new(&t.env, &lp_token).balance(&amm_user),
+        10_000_0000000
+    );
+}
+
+#[test]
+fn test_hybrid_deposit_requires_merkle_pool() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    let (deposit_pool, _) = setup_deposit_pool(&t, &user, 0);
+
+    let lp_token = t
+        .env
+        .register_stellar_asset_contract_v2(Address::generate(&t.env))
+        .address();
+    let result = client.try_add_hybrid_deposit(&t.admin, &deposit_pool, &lp_token);
+    assert_eq!(result, Err(Ok(ContractError::WrongPoolMode)));
+}
+
+// ========== emissions controller tests ==========
+
+#[test]
+fn test_notify_reward_schedules_pool_emissions() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    client.set_reward_rate(&t.admin, &0, &true);
+
+    let controller = Address::generate(&t.env);
+    token::StellarAssetClient::new(&t.env, &t.lmnr_token).mint(&controller, &2_000_0000000);
+    client.set_emissions_controller(&t.admin, &Some(controller.clone()));
+    assert_eq!(client.get_config().emissions_controller, Some(controller));
+
+    // 1000 LMNR over 1000s
+    client.notify_reward(&0, &1_000_0000000, &1000);
+    assert_eq!(client.get_reward_schedule(&0).unwrap().rate, 1_0000000);
+
+    set_ledger(&t.env, 1500, 150);
+    assert_eq!(client.pending_reward(&user, &0), 500_0000000);
+
+    // Unemitted scheduled rewards are not withdrawable surplus
+    let result = client.try_withdraw(&t.admin, &50_000_0000001);
+    assert_eq!(result, Err(Ok(ContractError::InsufficientRewardBalance)));
+
+    // Leftover 500 rolls into the next schedule: 1500 over 1000s
+    client.notify_reward(&0, &1_000_0000000, &1000);
+    assert_eq!(client.get_reward_schedule(&0).unwrap().rate, 1_5000000);
+
+    set_ledger(&t.env, 3000, 300);
+    assert_eq!(client.claim(&user, &0), 2_000_0000000);
+    client.withdraw(&t.admin, &50_000_0000000);
+}
+
+#[test]
+fn test_notify_reward_requires_controller() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+
+    let result = client.try_notify_reward(&0, &1_000_0000000, &1000);
+    assert_eq!(result, Err(Ok(ContractError::Unauthorized)));
+
+    client.set_emissions_controller(&t.admin, &Some(Address::generate(&t.env)));
+    let result = client.try_notify_reward(&0, &1_000_0000000, &0);
+    assert_eq!(result, Err(Ok(ContractError::InvalidConfig)));
+}
+
+// ========== rate oracle tests ==========
+
+// Helper: on the next ledger, post an epoch root proving `user` at
+// `lp_balance` and re-stake.
+fn rotate_and_restake(t: &TestEnv, user: &Address, lp_balance: i128, epoch_id: u64) {
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let sequence = t.env.ledger().sequence() + 1;
+    set_ledger(&t.env, t.env.ledger().timestamp(), sequence);
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, user, lp_balance, epoch_id);
+    let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
+    client.set_merkle_root(&t.admin, &0, &root, &sequence);
+    client.stake(user, &0, &lp_balance, &proofs.get(0).unwrap());
+}
+
+#[test]
+fn test_oracle_rate_targets_apr_on_rotation() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    client.set_reward_rate(&t.admin, &0, &true);
+
+    // 10,000 LP at 10 LMNR each, 10% APR
+    let oracle_id = t.env.register(MockPriceOracle, ());
+    MockPriceOracleClient::new(&t.env, &oracle_id).set_price(&10_0000000);
+    let config = RateOracle {
+        oracle: oracle_id,
+        target_apr_bps: 1000,
+        min_rate: 0,
+        max_rate: 1_0000000,
+    };
+    client.set_rate_oracle(&t.admin, &Some(config.clone()));
+    assert_eq!(client.get_rate_oracle(), Some(config.clone()));
+
+    rotate_and_restake(&t, &user, 10_000_0000000, 2);
+    let rate = 10_000_0000000 / (365 * 86_400);
+    assert_eq!(client.get_oracle_rate(&0), rate);
+
+    set_ledger(&t.env, 1100, 110);
+    assert_eq!(client.pending_reward(&user, &0), 100 * rate);
+
+    // Bounds clamp the target
+    client.set_rate_oracle(
+        &t.admin,
+        &Some(RateOracle {
+            max_rate: 1000,
+            ..config
+        }),
+    );
+    rotate_and_restake(&t, &user, 10_000_0000000, 3);
+    assert_eq!(client.get_oracle_rate(&0), 1000);
+
+    // Clearing the oracle zeroes the rate at the next rotation
+    client.set_rate_oracle(&t.admin, &None);
+    rotate_and_restake(&t, &user, 10_000_0000000, 4);
+    assert_eq!(client.get_oracle_rate(&0), 0);
+    set_ledger(&t.env, 1200, 120);
+    assert_eq!(client.claim(&user, &0), 100 * rate);
+}
+
+#[test]
+fn test_rate_oracle_rejects_bad_bounds() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let config = RateOracle {
+        oracle: Address::generate(&t.env),
+        target_apr_bps: 1000,
+        min_rate: 10,
+        max_rate: 5,
+    };
+    let result = client.try_set_rate_oracle(&t.admin, &Some(config));
+    assert_eq!(result, Err(Ok(ContractError::InvalidConfig)));
+}
+
+// ========== minimum claim tests ==========
+
+#[test]
+fn test_min_claim_keeps_dust_accruing() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    client.set_min_claim(&t.admin, &(150 * 462_962_963));
+    assert_eq!(client.get_config().min_claim, 150 * 462_962_963);
+
+    set_ledger(&t.env, 1100, 110);
+    assert!(!client.is_claimable(&user, &0));
+    let result = client.try_claim(&user, &0);
+    assert_eq!(result, Err(Ok(ContractError::BelowMinimumClaim)));
+
+    set_ledger(&t.env, 1200, 120);
+    assert!(client.is_claimable(&user, &0));
+    assert_eq!(client.claim(&user, &0), 200 * 462_962_963);
+
+    let result = client.try_set_min_claim(&t.admin, &-1);
+    assert_eq!(result, Err(Ok(ContractError::InvalidAmount)));
+}
+
+// ========== idle reward carry tests ==========
+
+#[test]
+fn test_idle_rewards_carry_and_roll_over() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let user = Address::generate(&t.env);
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, 10_000_0000000, 1);
+    let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+
+    // Nobody staked for 100s: the pool's emissions are held, not dropped
+    set_ledger(&t.env, 1100, 110);
+    let idle = 100 * 462_962_963;
+    assert_eq!(client.get_pool_carry(&0), idle);
+    assert_eq!(client.get_solvency().0, idle);
+    client.stake(&user, &0, &10_000_0000000, &proofs.get(0).unwrap());
+
+    assert_eq!(client.rollover_carry(&t.admin, &0, &100), idle);
+    assert_eq!(client.get_pool_carry(&0), 0);
+    set_ledger(&t.env, 1200, 120);
+    assert_eq!(client.claim(&user, &0), 2 * idle);
+
+    let result = client.try_rollover_carry(&t.admin, &0, &100);
+    assert_eq!(result, Err(Ok(ContractError::InvalidAmount)));
+}
+
+#[test]
+fn test_reclaim_carry_frees_withdrawable_balance() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    set_ledger(&t.env, 1100, 110);
+
+    let idle = 100 * 462_962_963;
+    let result = client.try_withdraw(&t.admin, &50_000_0000000);
+    assert_eq!(result, Err(Ok(ContractError::InsufficientRewardBalance)));
+    assert_eq!(client.reclaim_carry(&t.admin, &0), idle);
+
+    // A removed pool stops carrying
+    client.remove_pool(&t.admin, &0);
+    set_ledger(&t.env, 1200, 120);
+    assert_eq!(client.get_pool_carry(&0), 0);
+    client.withdraw(&t.admin, &50_000_0000000);
+}
+
+// ========== epoch budget tests ==========
+
+#[test]
+fn test_epoch_budget_streams_between_roots() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let budget: i128 = 1_000_0000000;
+    client.set_epoch_budget(&t.admin, &0, &budget, &1000);
+
+    let user = Address::generate(&t.env);
+    rotate_and_restake(&t, &user, 10_000_0000000, 1);
+
+    // Half the epoch: half the budget and nothing from the global rate
+    set_ledger(&t.env, 1500, t.env.ledger().sequence());
+    assert_eq!(client.pending_reward(&user, &0), budget / 2);
+
+    // An early root rolls the unpaid half into the next epoch's stream
+    rotate_and_restake(&t, &user, 10_000_0000000, 2);
+    set_ledger(&t.env, 2500, t.env.ledger().sequence());
+    assert_eq!(client.pending_reward(&user, &0), 2 * budget);
+
+    // Stream ran out: nothing more until the next root
+    set_ledger(&t.env, 3000, t.env.ledger().sequence());
+    assert_eq!(client.pending_reward(&user, &0), 2 * budget);
+    assert_eq!(client.claim(&user, &0), 2 * budget);
+}
+
+#[test]
+fn test_epoch_budget_requires_duration() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+
+    let result = client.try_set_epoch_budget(&t.admin, &0, &1_000_0000000, &0);
+    assert_eq!(result, Err(Ok(ContractError::InvalidConfig)));
+    let result = client.try_set_epoch_budget(&t.admin, &0, &-1, &1000);
+    assert_eq!(result, Err(Ok(ContractError::InvalidAmount)));
+}
+
+// ========== partner reward token tests ==========
+
+#[test]
+fn test_partner_pool_pays_its_own_token() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let partner = t
+        .env
+        .register_stellar_asset_contract_v2(Address::generate(&t.env))
+        .address();
+    token::StellarAssetClient::new(&t.env, &partner).mint(&t.admin, &1_000_0000000);
+    client.add_pool_with_reward_token(&t.admin, &make_pool_id(&t.env, 1), &partner);
+
+    let user = Address::generate(&t.env);
+    rotate_and_restake(&t, &user, 10_000_0000000, 1);
+    client.fund_pool(&t.admin, &0, &1_000_0000000, &1000);
+
+    // Only the partner schedule accrues; the LMNR books are untouched
+    set_ledger(&t.env, 1500, t.env.ledger().sequence());
+    assert_eq!(client.pending_reward(&user, &0), 500_0000000);
+    assert_eq!(client.get_solvency().0, 0);
+    assert_eq!(
+        client.get_token_solvency(&partner),
+        (1_000_0000000, 1_000_0000000, 0)
+    );
+    let result = client.try_rescue_tokens(&t.admin, &partner, &t.admin, &1);
+    assert_eq!(result, Err(Ok(ContractError::InsufficientRewardBalance)));
+
+    assert_eq!(client.claim(&user, &0), 500_0000000);
+    let partner_client = token::Client::new(&t.env, &partner);
+    assert_eq!(partner_client.balance(&user), 500_0000000);
+    assert_eq!(token::Client::new(&t.env, &t.lmnr_token).balance(&user), 0);
+    assert_eq!(client.get_accounting().total_claimed, 0);
+    assert_eq!(client.get_user_claimed(&user, &0), 500_0000000);
+}
+
+#[test]
+fn test_partner_pool_with_eighteen_decimal_token() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let partner = t.env.register(WideToken, ());
+    let one = 1_000_000_000_000_000_000_i128;
+    WideTokenClient::new(&t.env, &partner).mint(&t.admin, &(1_000 * one));
+    client.add_pool_with_reward_token(&t.admin, &make_pool_id(&t.env, 1), &partner);
+    assert_eq!(client.get_reward_decimals(&0), 18);
+
+    let user = Address::generate(&t.env);
+    rotate_and_restake(&t, &user, 10_000_0000000, 1);
+    client.fund_pool(&t.admin, &0, &(1_000 * one), &1000);
+
+    // 500 tokens in 1e18 units times a 1e18 precision would overflow i128
+    set_ledger(&t.env, 1500, t.env.ledger().sequence());
+    assert_eq!(client.pending_reward(&user, &0), 500 * one);
+    assert_eq!(client.claim(&user, &0), 500 * one);
+    let partner_client = WideTokenClient::new(&t.env, &partner);
+    assert_eq!(partner_client.balance(&user), 500 * one);
+}
+
+#[test]
+fn test_partner_pool_rejects_lmnr_and_lmnr_funding() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let result =
+        client.try_add_pool_with_reward_token(&t.admin, &make_pool_id(&t.env, 1), &t.lmnr_token);
+    assert_eq!(result, Err(Ok(ContractError::InvalidConfig)));
+
+    // LMNR pools take earmarks through `fund_pool`, not schedules
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let result = client.try_fund_pool(&t.admin, &0, &1_000_0000000, &1000);
+    assert_eq!(result, Err(Ok(ContractError::InvalidConfig)));
+}
+
+#[test]
+fn test_earmarked_funding_is_held_back_from_withdraw() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 1_000_0000000);
+    client.fund_pool(&t.admin, &0, &10_000_0000000, &0);
+    assert_eq!(client.get_pool_earmark(&0), 10_000_0000000);
+    assert_eq!(client.get_accounting().total_funded, 10_000_0000000);
+
+    // The earmark stays on top of the unearmarked 50,000
+    let result = client.try_withdraw(&t.admin, &50_000_0000001);
+    assert_eq!(result, Err(Ok(ContractError::InsufficientRewardBalance)));
+
+    // Claims from the pool draw the earmark down
+    set_ledger(&t.env, 1100, 110);
+    let paid = client.claim(&user, &0);
+    assert_eq!(client.get_pool_earmark(&0), 10_000_0000000 - paid);
+    client.withdraw(&t.admin, &50_000_0000000);
+}
+
+#[test]
+fn test_zero_rate_time_is_booked_as_unaccrued() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 1_000_0000000);
+    client.set_reward_rate(&t.admin, &0, &true);
+    let unaccrued = |env: &Env| {
+        let topics: Vec<Val> = (events::TIME_UNACCRUED, 0u32).into_val(env);
+        let event = env.events().all().iter().find(|event| event.1 == topics);
+        event.map(|event| -> TimeUnaccrued { event.2.into_val(env) })
+    };
+
+    // Nothing funded for the pool: the time simply had no budget
+    set_ledger(&t.env, 1100, 110);
+    client.checkpoint(&t.admin, &0);
+    let event = unaccrued(&t.env).unwrap();
+    assert_eq!(event.seconds, 100);
+    assert!(!event.paused);
+
+    // With LMNR earmarked, the same gap is emissions paused
+    client.fund_pool(&t.admin, &0, &10_000_0000000, &0);
+    set_ledger(&t.env, 1400, 140);
+    client.checkpoint(&t.admin, &0);
+    assert!(unaccrued(&t.env).unwrap().paused);
+    let time = client.get_unaccrued_time(&0);
+    assert_eq!(time.unbudgeted, 100);
+    assert_eq!(time.paused, 300);
+    assert_eq!(client.get_pool_earmark(&0), 10_000_0000000);
+
+    // Accruing time is not booked
+    client.set_reward_rate(&t.admin, &462_962_963, &true);
+    set_ledger(&t.env, 1500, 150);
+    client.checkpoint(&t.admin, &0);
+    assert!(unaccrued(&t.env).is_none());
+    assert_eq!(client.get_unaccrued_time(&0), time);
+}
+
+// ========== slash tests ==========
+
+#[test]
+fn test_slash_staker_removes_stake_and_logs() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let honest = Address::generate(&t.env);
+    let cheat = Address::generate(&t.env);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let post = |epoch_id: u64, sequence: u32| {
+        let leaves = [
+            merkle::compute_leaf(&t.env, &t.contract_id, 0, &honest, 1_000_0000000, epoch_id),
+            merkle::compute_leaf(&t.env, &t.contract_id, 0, &cheat, 9_000_0000000, epoch_id),
+        ];
+        let (root, proofs) = build_merkle_tree(&t.env, &leaves);
+        client.set_merkle_root(&t.admin, &0, &root, &sequence);
+        proofs
+    };
+    let proofs = post(1, 100);
+    client.stake(&honest, &0, &1_000_0000000, &proofs.get(0).unwrap());
+    client.stake(&cheat, &0, &9_000_0000000, &proofs.get(1).unwrap());
+    client.fund_pool(&t.admin, &0, &10_000_0000000, &0);
+
+    // Revenue shared at the next rotation and a running campaign
+    let sponsor = Address::generate(&t.env);
+    let usdc = t
+        .env
+        .register_stellar_asset_contract_v2(Address::generate(&t.env))
+        .address();
+    let usdc_admin = token::StellarAssetClient::new(&t.env, &usdc);
+    usdc_admin.mint(&sponsor, &1_400_0000000);
+    client.deposit_revenue(&sponsor, &usdc, &0, &400_0000000);
+    let id = client.create_campaign(&sponsor, &0, &usdc, &1_000_0000000, &1000, &2000);
+
+    set_ledger(&t.env, 1100, 110);
+    post(2, 110);
+    let forfeited = client.pending_reward(&cheat, &0);
+    assert!(forfeited > 0);
+    assert_eq!(client.pending_revenue(&cheat, &0).get(usdc.clone()), Some(360_0000000));
+    assert!(client.pending_campaign_rewards(&cheat, &0).get(id).is_some());
+    let liability = client.get_pool_liability(&0);
+    let earmark = client.get_pool_earmark(&0);
+
+    client.slash_staker(&t.admin, &cheat, &0, &7);
+    assert_eq!(client.pending_reward(&cheat, &0), 0);
+    assert_eq!(client.get_pool_state(&0).total_staked, 1_000_0000000);
+    assert_eq!(client.get_pool_liability(&0), liability - forfeited);
+    assert_eq!(client.get_pool_earmark(&0), earmark - forfeited);
+
+    // Shares are forfeited too; the revenue goes back to its pot
+    assert!(client.pending_revenue(&cheat, &0).is_empty());
+    assert!(client.pending_campaign_rewards(&cheat, &0).is_empty());
+    let result = client.try_claim_revenue(&cheat, &0);
+    assert_eq!(result, Err(Ok(ContractError::NoRewardsToClaim)));
+    let pot = client.get_revenue_pots(&0).get(usdc.clone()).unwrap();
+    assert_eq!(pot.pending, 360_0000000);
+
+    // What is left of the entry holds nothing and can be pruned
+    let info = client.get_staker_info(&cheat, &0);
+    assert_eq!((info.staked_amount, info.pending_rewards), (0, 0));
+    client.prune_staker(&honest, &cheat, &0);
+    assert!(client.try_get_staker_info(&cheat, &0).is_err());
+
+    let log = client.get_slash_log();
+    assert_eq!(log.len(), 1);
+    let record = log.get(0).unwrap();
+    assert_eq!(record.user, cheat);
+    assert_eq!(record.reason_code, 7);
+    assert_eq!(record.stake, 9_000_0000000);
+    assert_eq!(record.forfeited, forfeited);
+    assert_eq!(record.timestamp, 1100);
+
+    let result = client.try_slash_staker(&t.admin, &cheat, &0, &7);
+    assert_eq!(result, Err(Ok(ContractError::NoStakeFound)));
+}
+
+// ========== correction root tests ==========
+
+#[test]
+fn test_verified_updates_need_correction_proof() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 1_000_0000000);
+    client.set_verified_updates(&t.admin, &0, &true);
+
+    let result = client.try_update_stake(&t.admin, &user, &0, &2_000_0000000);
+    assert_eq!(result, Err(Ok(ContractError::WrongPoolMode)));
+    let empty = Vec::new(&t.env);
+    let result = client.try_update_stake_verified(&t.admin, &user, &0, &2_000_0000000, &empty);
+    assert_eq!(result, Err(Ok(ContractError::NoMerkleRoot)));
+
+    set_ledger(&t.env, 1100, 110);
+    let result = client.try_set_correction_root(&t.admin, &0, &make_pool_id(&t.env, 9), &99);
+    assert_eq!(result, Err(Ok(ContractError::SnapshotNotIncreasing)));
+
+    let leaves = [
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, 2_000_0000000, 1),
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &t.admin, 1, 1),
+    ];
+    let (root, proofs) = build_merkle_tree(&t.env, &leaves);
+    client.set_correction_root(&t.admin, &0, &root, &105);
+    let proof = proofs.get(0).unwrap();
+
+    // Only the corrected balance verifies
+    let result = client.try_update_stake_verified(&t.admin, &user, &0, &3_000_0000000, &proof);
+    assert_eq!(result, Err(Ok(ContractError::InvalidProof)));
+    client.update_stake_verified(&t.admin, &user, &0, &2_000_0000000, &proof);
+    assert_eq!(
+        client.get_staker_info(&user, &0).staked_amount,
+        2_000_0000000
+    );
+
+    // The next main root retires the correction
+    rotate_and_restake(&t, &user, 2_000_0000000, 2);
+    let result = client.try_update_stake_verified(&t.admin, &user, &0, &2_000_0000000, &proof);
+    assert_eq!(result, Err(Ok(ContractError::StaleEpoch)));
+
+    client.set_verified_updates(&t.admin, &0, &false);
+    client.update_stake(&t.admin, &user, &0, &500_0000000);
+    assert_eq!(client.get_staker_info(&user, &0).staked_amount, 500_0000000);
+}
+
+// ========== pool id migration tests ==========
+
+#[test]
+fn test_migrate_pool_id_keeps_staker_state() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 1_000_0000000);
+    set_ledger(&t.env, 1100, 110);
+    let pending = client.pending_reward(&user, &0);
+
+    let reissued = make_pool_id(&t.env, 2);
+    client.migrate_pool_id(&t.admin, &0, &reissued);
+    assert_eq!(client.get_pool_id(&0), reissued);
+    assert_eq!(client.get_pool_index(&reissued), Some(0));
+    assert_eq!(client.get_pool_index(&make_pool_id(&t.env, 1)), None);
+    assert_eq!(client.pending_reward(&user, &0), pending);
+    assert_eq!(
+        client.get_staker_info(&user, &0).staked_amount,
+        1_000_0000000
+    );
+
+    // The old hash is free again; the new one is taken
+    let result = client.try_migrate_pool_id(&t.admin, &0, &reissued);
+    assert_eq!(result, Err(Ok(ContractError::PoolAlreadyExists)));
+    assert_eq!(client.add_pool(&t.admin, &make_pool_id(&t.env, 1)), 1);
+}
+
+// ========== admin log tests ==========
+
+#[test]
+fn test_admin_log_records_root_posts_and_rate_changes() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let root = make_pool_id(&t.env, 9);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+    set_ledger(&t.env, 1100, 110);
+    client.set_reward_rate(&t.admin, &1_000, &false);
+
+    // A rejected call leaves no entry
+    let result = client.try_set_rate_bounds(&t.admin, &-1, &0);
+    assert_eq!(result, Err(Ok(ContractError::InvalidAmount)));
+
+    let log = client.get_admin_log();
+    assert_eq!(log.len(), 2);
+    let post = log.get(0).unwrap();
+    assert_eq!(post.action, Symbol::new(&t.env, "root"));
+    assert_eq!(post.caller, t.admin);
+    assert_eq!(post.timestamp, 1000);
+    let args: soroban_sdk::Val = (0u32, root, 100u32).into_val(&t.env);
+    let expected: BytesN<32> = t.env.crypto().sha256(&args.to_xdr(&t.env)).into();
+    assert_eq!(post.args_hash, expected);
+    let rate = log.get(1).unwrap();
+    assert_eq!(rate.action, Symbol::new(&t.env, "rate"));
+    assert_eq!(rate.timestamp, 1100);
+
+    // The log keeps the most recent 50 entries
+    for rate in 0..60 {
+        client.set_reward_rate(&t.admin, &rate, &true);
+    }
+    let log = client.get_admin_log();
+    assert_eq!(log.len(), 50);
+    assert_eq!(log.get(0).unwrap().action, Symbol::new(&t.env, "rate"));
+}
+
+#[test]
+fn test_admin_log_records_access_and_fund_changes() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let user = Address::generate(&t.env);
+    let hook = Address::generate(&t.env);
+
+    client.block_address(&t.admin, &user, &true);
+    client.unblock_address(&t.admin, &user);
+    client.set_stake_hook(&t.admin, &Some(hook));
+    client.set_partial_payouts(&t.admin, &true);
+    client.pause_pool(&t.admin, &0);
+    client.pause(&t.admin);
+    client.queue_emergency_withdraw(&t.admin, &1_000);
+    client.unpause(&t.admin);
+    client.remove_pool(&t.admin, &0);
+
+    let log = client.get_admin_log();
+    let expected = [
+        "block",
+        "unblock",
+        "stake_hk",
+        "partial",
+        "pool_paus",
+        "pause",
+        "emrg_q",
+        "unpause",
+        "rm_pool",
+    ];
+    assert_eq!(log.len(), expected.len() as u32);
+    for (entry, action) in log.iter().zip(expected) {
+        assert_eq!(entry.action, Symbol::new(&t.env, action));
+    }
+}
+
+// ========== rounding remainder tests ==========
+
+#[test]
+fn test_rounding_remainder_conserves_emissions() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+
+    // Odd stakes so no update divides evenly
+    let users = [
+        Address::generate(&t.env),
+        Address::generate(&t.env),
+        Address::generate(&t.env),
+    ];
+    let amounts = [1_0000001, 3_3333333, 7_7777777];
+    let leaves = [
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &users[0], amounts[0], 1),
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &users[1], amounts[1], 1),
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &users[2], amounts[2], 1),
+    ];
+    let (root, proofs) = build_merkle_tree(&t.env, &leaves);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+    for i in 0..3 {
+        client.stake(&users[i], &0, &amounts[i], &proofs.get(i as u32).unwrap());
+    }
+
+    for step in 1..=40u64 {
+        set_ledger(&t.env, 1000 + 7 * step, 100 + step as u32);
+        client.checkpoint(&t.admin, &0);
+    }
+
+    // Every emitted stroop is booked, and claims lose at most one per staker
+    let emitted = 280 * 462_962_963;
+    assert_eq!(client.get_pool_liability(&0), emitted);
+    let mut claimed = 0;
+    for user in users.iter() {
+        claimed += client.claim(user, &0);
+    }
+    assert!(claimed <= emitted && claimed >= emitted - 3);
+}
+
+// ========== epoch cadence tests ==========
+
+#[test]
+fn test_epoch_cadence_window() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    client.set_epoch_cadence(&t.admin, &0, &3600, &86_400);
+    let root = BytesN::from_array(&t.env, &[1u8; 32]);
+
+    // The first root is not bound by the window
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+
+    set_ledger(&t.env, 1060, 106);
+    let result = client.try_set_merkle_root(&t.admin, &0, &root, &106);
+    assert_eq!(result, Err(Ok(ContractError::RootTooSoon)));
+
+    set_ledger(&t.env, 4600, 460);
+    client.set_merkle_root(&t.admin, &0, &root, &460);
+    assert_eq!(client.get_merkle_root(&0).epoch_id, 2);
+
+    set_ledger(&t.env, 4600 + 86_401, 9100);
+    let result = client.try_set_merkle_root(&t.admin, &0, &root, &9100);
+    assert_eq!(result, Err(Ok(ContractError::RootTooLate)));
+
+    // Widening the window lets the late root through
+    client.set_epoch_cadence(&t.admin, &0, &3600, &0);
+    client.set_merkle_root(&t.admin, &0, &root, &9100);
+    assert_eq!(client.get_merkle_root(&0).epoch_id, 3);
+}
+
+#[test]
+fn test_epoch_cadence_rejects_inverted_window() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let result = client.try_set_epoch_cadence(&t.admin, &0, &7200, &3600);
+    assert_eq!(result, Err(Ok(ContractError::InvalidConfig)));
+    assert_eq!(client.get_pool_config(&0).min_epoch_interval, 0);
+}
+
+#[test]
+fn test_snapshot_ledger_must_advance() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let root = BytesN::from_array(&t.env, &[1u8; 32]);
+
+    let result = client.try_set_merkle_root(&t.admin, &0, &root, &101);
+    assert_eq!(result, Err(Ok(ContractError::SnapshotInFuture)));
+    client.set_merkle_root(&t.admin, &0, &root, &90);
+
+    set_ledger(&t.env, 1100, 110);
+    for snapshot_ledger in [80, 90] {
+        let result = client.try_set_merkle_root(&t.admin, &0, &root, &snapshot_ledger);
+        assert_eq!(result, Err(Ok(ContractError::SnapshotNotIncreasing)));
+    }
+    client.set_merkle_root(&t.admin, &0, &root, &110);
+    assert_eq!(client.get_merkle_root(&0).snapshot_ledger, 110);
+}
+
+// ========== root expiry tests ==========
+
+#[test]
+fn test_stake_rejects_expired_root() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    client.set_max_root_age(&t.admin, &0, &86_400);
+
+    let user = Address::generate(&t.env);
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, 10_000_0000000, 1);
+    let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+    assert!(!client.is_root_stale(&0));
+
+    set_ledger(&t.env, 1000 + 86_401, 8700);
+    assert!(client.is_root_stale(&0));
+    let result = client.try_stake(&user, &0, &10_000_0000000, &proofs.get(0).unwrap());
+    assert_eq!(result, Err(Ok(ContractError::RootExpired)));
+
+    // Lifting the limit accepts the same proof again
+    client.set_max_root_age(&t.admin, &0, &0);
+    assert!(!client.is_root_stale(&0));
+    client.stake(&user, &0, &10_000_0000000, &proofs.get(0).unwrap());
+    assert_eq!(
+        client.get_staker_info(&user, &0).staked_amount,
+        10_000_0000000
+    );
+}
+
+// ========== staker expiry tests ==========
+
+#[test]
+fn test_expire_staker_after_missed_epochs() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    client.set_expire_after_epochs(&t.admin, &0, &2);
+
+    let alice = Address::generate(&t.env);
+    let bob = Address::generate(&t.env);
+    let leaves = [
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &alice, 10_000_0000000, 1),
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &bob, 10_000_0000000, 1),
+    ];
+    let (root, proofs) = build_merkle_tree(&t.env, &leaves);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+    client.stake(&alice, &0, &10_000_0000000, &proofs.get(0).unwrap());
+    client.stake(&bob, &0, &10_000_0000000, &proofs.get(1).unwrap());
+
+    // Alice stops re-proving; one missed epoch is not enough
+    set_ledger(&t.env, 1100, 110);
+    rotate_and_restake(&t, &bob, 10_000_0000000, 2);
+    let earned = client.pending_reward(&alice, &0);
+    assert!(earned > 0);
+    let result = client.try_expire_staker(&t.admin, &alice, &0);
+    assert_eq!(result, Err(Ok(ContractError::StakerNotExpired)));
+
+    set_ledger(&t.env, 1200, 120);
+    rotate_and_restake(&t, &bob, 10_000_0000000, 3);
+    client.expire_staker(&t.admin, &alice, &0);
+
+    assert_eq!(client.get_pool_state(&0).total_staked, 10_000_0000000);
+    assert_eq!(client.get_staker_count(&0), 1);
+    let staker = client.get_staker_info(&alice, &0);
+    assert_eq!(staker.staked_amount, 0);
+    assert_eq!(client.claim(&alice, &0), staker.pending_rewards);
+
+    let result = client.try_expire_staker(&t.admin, &alice, &0);
+    assert_eq!(result, Err(Ok(ContractError::NoStakeFound)));
+}
+
+#[test]
+fn test_expire_staker_disabled_by_default() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+
+    let other = Address::generate(&t.env);
+    for epoch_id in 2..5 {
+        rotate_and_restake(&t, &other, 5_000_0000000, epoch_id);
+    }
+    let result = client.try_expire_staker(&t.admin, &user, &0);
+    assert_eq!(result, Err(Ok(ContractError::StakerNotExpired)));
+}
+
+#[test]
+fn test_expire_staker_without_epochs_is_not_expired() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+
+    // A stake set by the admin before the pool's first root
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    client.set_expire_after_epochs(&t.admin, &0, &1);
+    client.update_stake(&t.admin, &user, &0, &1_000_0000000);
+    let result = client.try_expire_staker(&t.admin, &user, &0);
+    assert_eq!(result, Err(Ok(ContractError::StakerNotExpired)));
+
+    let (pool, _) = setup_deposit_pool(&t, &user, 1_000_0000000);
+    client.stake_deposit(&user, &pool, &1_000_0000000);
+    let result = client.try_expire_staker(&t.admin, &user, &pool);
+    assert_eq!(result, Err(Ok(ContractError::StakerNotExpired)));
+}
+
+#[test]
+fn test_sweep_expired_after_claim_deadline() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    client.set_claim_deadline(&t.admin, &1000);
+
+    let alice = Address::generate(&t.env);
+    let bob = Address::generate(&t.env);
+    let leaves = [
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &alice, 10_000_0000000, 1),
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &bob, 10_000_0000000, 1),
+    ];
+    let (root, proofs) = build_merkle_tree(&t.env, &leaves);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+    client.stake(&alice, &0, &10_000_0000000, &proofs.get(0).unwrap());
+    client.stake(&bob, &0, &10_000_0000000, &proofs.get(1).unwrap());
+
+    // Alice goes quiet after epoch 1; Bob keeps re-proving
+    set_ledger(&t.env, 1100, 110);
+    rotate_and_restake(&t, &bob, 10_000_0000000, 2);
+    let earned = client.pending_reward(&alice, &0);
+    assert!(earned > 0);
+
+    set_ledger(&t.env, 1999, 120);
+    let result = client.try_sweep_expired(&t.admin, &alice, &0);
+    assert_eq!(result, Err(Ok(ContractError::StakerNotExpired)));
+
+    set_ledger(&t.env, 2000, 130);
+    let liability = client.get_pool_liability(&0);
+    assert_eq!(client.sweep_expired(&t.admin, &alice, &0), earned);
+    assert_eq!(client.get_pool_liability(&0), liability - earned);
+    assert_eq!(client.pending_reward(&alice, &0), 0);
+    let result = client.try_claim(&alice, &0);
+    assert_eq!(result, Err(Ok(ContractError::NoRewardsToClaim)));
+
+    // Bob's re-proof at 1100 keeps his rewards claimable until 2100
+    let result = client.try_sweep_expired(&t.admin, &bob, &0);
+    assert_eq!(result, Err(Ok(ContractError::StakerNotExpired)));
+    client.claim(&bob, &0);
+    set_ledger(&t.env, 2100, 140);
+    let result = client.try_sweep_expired(&t.admin, &bob, &0);
+    assert_eq!(result, Err(Ok(ContractError::StakerNotExpired)));
+
+    // Turning the deadline off stops sweeps
+    set_ledger(&t.env, 3000, 150);
+    client.set_claim_deadline(&t.admin, &0);
+    let result = client.try_sweep_expired(&t.admin, &bob, &0);
+    assert_eq!(result, Err(Ok(ContractError::StakerNotExpired)));
+}
+
+#[test]
+fn test_poke_deadlines_warns_before_expiry() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    let user = Address::generate(&t.env);
+    let other = Address::generate(&t.env);
+    stake_single(&t, &user, 10_000_0000000);
+    client.set_reward_rate(&t.admin, &1_000, &true);
+    assert_eq!(client.get_claim_deadline(&user, &0), None);
+
+    client.set_claim_deadline(&t.admin, &(30 * 86_400));
+    let deadline = 1000 + 30 * 86_400;
+    assert_eq!(client.get_claim_deadline(&user, &0), Some(deadline));
+    assert_eq!(client.get_claim_deadline(&other, &0), None);
+
+    let users = Vec::from_array(&t.env, [user.clone(), other.clone()]);
+    set_ledger(&t.env, deadline - 8 * 86_400, 110);
+    assert_eq!(client.poke_deadlines(&t.admin, &0, &users), 0);
+
+    set_ledger(&t.env, deadline - 6 * 86_400, 120);
+    assert_eq!(client.poke_deadlines(&t.admin, &0, &users), 1);
+    let event = t.env.events().all().last().unwrap();
+    let data: ExpiringSoon = event.2.into_val(&t.env);
+    assert_eq!(data.deadline, deadline);
+    assert_eq!(data.pending, client.pending_reward(&user, &0));
+
+    let result = client.try_poke_deadlines(&other, &0, &users);
+    assert_eq!(result, Err(Ok(ContractError::Unauthorized)));
+
+    // Claiming restarts the clock
+    client.claim(&user, &0);
+    let restarted = deadline - 6 * 86_400 + 30 * 86_400;
+    assert_eq!(client.get_claim_deadline(&user, &0), Some(restarted));
+}
+
+// ========== budget tests ==========
+
+// Ceilings for hot paths, set about 1.5x above what they meter today. Only
+// host work is metered when tests run natively, so these track storage and
+// crypto costs rather than the wasm's own instructions. Raise them only
+// with a reason; they exist to catch regressions. Stake's memory ceiling
+// went up with the combined pool entry: each state read now also decodes
+// the root, which costs host memory but saves a ledger entry per call.
+const STAKE_CPU_CEILING: u64 = 1_300_000;
+const STAKE_MEM_CEILING: u64 = 200_000;
+const CLAIM_CPU_CEILING: u64 = 1_500_000;
+const CLAIM_MEM_CEILING: u64 = 260_000;
+const SET_ROOT_CPU_CEILING: u64 = 550_000;
+const SET_ROOT_MEM_CEILING: u64 = 85_000;
+
+// Helper: root and `depth`-level proof for `leaf` with synthetic siblings.
+fn deep_proof(env: &Env, leaf: &BytesN<32>, depth: u8) -> (BytesN<32>, Vec<BytesN<32>>) {
+    let mut proof = Vec::new(env);
+    let mut node = leaf.clone();
+    for level in 0..depth {
+        let sibling = BytesN::from_array(env, &[level + 1; 32]);
+        node = merkle::hash_pair(env, &node, &sibling);
+        proof.push_back(sibling);
+    }
+    (node, proof)
+}
+
+// Helper: (cpu instructions, memory bytes) metered since the last reset.
+fn budget_used(env: &Env) -> (u64, u64) {
+    let budget = env.cost_estimate().budget();
+    (budget.cpu_instruction_cost(), budget.memory_bytes_cost())
+}
+
+#[test]
+fn test_hot_path_budgets() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let user = Address::generate(&t.env);
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, 10_000_0000000, 1);
+    let (root, proof) = deep_proof(&t.env, &leaf, 20);
+
+    t.env.cost_estimate().budget().reset_default();
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+    let (cpu, mem) = budget_used(&t.env);
+    assert!(cpu <= SET_ROOT_CPU_CEILING, "set_merkle_root cpu {cpu}");
+    assert!(mem <= SET_ROOT_MEM_CEILING, "set_merkle_root mem {mem}");
+
+    t.env.cost_estimate().budget().reset_default();
+    client.stake(&user, &0, &10_000_0000000, &proof);
+    let (cpu, mem) = budget_used(&t.env);
+    assert!(cpu <= STAKE_CPU_CEILING, "stake cpu {cpu}");
+    assert!(mem <= STAKE_MEM_CEILING, "stake mem {mem}");
+
+    set_ledger(&t.env, 1100, 110);
+    t.env.cost_estimate().budget().reset_default();
+    client.claim(&user, &0);
+    let (cpu, mem) = budget_used(&t.env);
+    assert!(cpu <= CLAIM_CPU_CEILING, "claim cpu {cpu}");
+    assert!(mem <= CLAIM_MEM_CEILING, "claim mem {mem}");
+}
+
+// ========== proof diagnostics tests ==========
+
+#[test]
+fn test_stake_reports_why_proof_failed() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 2));
+
+    let user = Address::generate(&t.env);
+    let other = Address::generate(&t.env);
+    let balance = 10_000_0000000;
+    let leaves = [
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, balance, 1),
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &other, balance, 1),
+    ];
+    let (root, epoch1_proofs) = build_merkle_tree(&t.env, &leaves);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+    let pool1_leaves = [
+        merkle::compute_leaf(&t.env, &t.contract_id, 1, &user, balance, 1),
+        merkle::compute_leaf(&t.env, &t.contract_id, 1, &other, balance, 1),
+    ];
+    let (root, pool1_proofs) = build_merkle_tree(&t.env, &pool1_leaves);
+    client.set_merkle_root(&t.admin, &1, &root, &100);
+
+    set_ledger(&t.env, 1000, 110);
+    let leaves = [
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, balance, 2),
+        merkle::compute_leaf(&t.env, &t.contract_id, 0, &other, balance, 2),
+    ];
+    let (root, epoch2_proofs) = build_merkle_tree(&t.env, &leaves);
+    client.set_merkle_root(&t.admin, &0, &root, &110);
+
+    let stale = client.try_stake(&user, &0, &balance, &epoch1_proofs.get(0).unwrap());
+    assert_eq!(stale, Err(Ok(ContractError::StaleEpoch)));
+    let wrong_pool = client.try_stake(&user, &0, &balance, &pool1_proofs.get(0).unwrap());
+    assert_eq!(wrong_pool, Err(Ok(ContractError::PoolMismatch)));
+    let wrong_balance = client.try_stake(&user, &0, &(balance + 1), &epoch2_proofs.get(0).unwrap());
+    assert_eq!(wrong_balance, Err(Ok(ContractError::InvalidProof)));
+
+    // Only the nearest pools are tried, however many are registered
+    for seed in 3..=6 {
+        client.add_pool(&t.admin, &make_pool_id(&t.env, seed));
+    }
+    let far_leaves = [
+        merkle::compute_leaf(&t.env, &t.contract_id, 5, &user, balance, 1),
+        merkle::compute_leaf(&t.env, &t.contract_id, 5, &other, balance, 1),
+    ];
+    let (root, far_proofs) = build_merkle_tree(&t.env, &far_leaves);
+    client.set_merkle_root(&t.admin, &5, &root, &110);
+    let far_pool = client.try_stake(&user, &0, &balance, &far_proofs.get(0).unwrap());
+    assert_eq!(far_pool, Err(Ok(ContractError::InvalidProof)));
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 4, &other, balance, 1);
+    let (root, _) = build_merkle_tree(&t.env, &[leaf]);
+    client.set_merkle_root(&t.admin, &4, &root, &110);
+    let near_pool = client.try_stake(&user, &4, &balance, &far_proofs.get(0).unwrap());
+    assert_eq!(near_pool, Err(Ok(ContractError::PoolMismatch)));
+
+    client.stake(&user, &0, &balance, &epoch2_proofs.get(0).unwrap());
+    assert_eq!(client.get_staker_info(&user, &0).epoch_id, 2);
+}
+
+#[test]
+fn test_stake_rejects_proof_over_max_depth() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let user = Address::generate(&t.env);
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, 10_000_0000000, 1);
+
+    let (root, proof) = deep_proof(&t.env, &leaf, merkle::MAX_PROOF_DEPTH as u8 + 1);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+    let result = client.try_stake(&user, &0, &10_000_0000000, &proof);
+    assert_eq!(result, Err(Ok(ContractError::ProofTooLong)));
+
+    // A proof at the cap still verifies
+    set_ledger(&t.env, 1000, 110);
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, 10_000_0000000, 2);
+    let (root, proof) = deep_proof(&t.env, &leaf, merkle::MAX_PROOF_DEPTH as u8);
+    client.set_merkle_root(&t.admin, &0, &root, &110);
+    client.stake(&user, &0, &10_000_0000000, &proof);
+    assert_eq!(
+        client.get_staker_info(&user, &0).staked_amount,
+        10_000_0000000
+    );
+}
+
+#[test]
+fn test_leaf_bound_to_deployment_and_network() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let user = Address::generate(&t.env);
+    let balance = 10_000_0000000;
+
+    // A snapshot built for another deployment does not verify here
+    let other_deployment = t.env.register(LpStakingContract, ());
+    let leaf = merkle::compute_leaf(&t.env, &other_deployment, 0, &user, balance, 1);
+    let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+    let result = client.try_stake(&user, &0, &balance, &proofs.get(0).unwrap());
+    assert_eq!(result, Err(Ok(ContractError::InvalidProof)));
+
+    // The same position hashes differently under another network passphrase
+    let home = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, balance, 1);
+    let mut ledger = t.env.ledger().get();
+    ledger.network_id = [7u8; 32];
+    t.env.ledger().set(ledger);
+    let foreign = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, balance, 1);
+    assert_ne!(foreign, home);
+}
+
+#[test]
+fn test_stake_packed_proof() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    let user = Address::generate(&t.env);
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, 10_000_0000000, 1);
+    let (root, proof) = deep_proof(&t.env, &leaf, 20);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+    let packed = pack_proof(&t.env, &proof);
+    assert_eq!(packed.len(), 20 * 32);
+
+    // A trailing partial sibling is malformed
+    let mut ragged = packed.clone();
+    ragged.push_back(0);
+    let result = client.try_stake_packed(&user, &0, &10_000_0000000, &ragged);
+    assert_eq!(result, Err(Ok(ContractError::InvalidProof)));
+    let result = client.try_stake_packed(&user, &0, &1_000_0000000, &packed);
+    assert_eq!(result, Err(Ok(ContractError::InvalidProof)));
+
+    client.stake_packed(&user, &0, &10_000_0000000, &packed);
+    assert_eq!(
+        client.get_staker_info(&user, &0).staked_amount,
+        10_000_0000000
+    );
+
+    // Same depth cap as a list proof
+    set_ledger(&t.env, 1000, 110);
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, 10_000_0000000, 2);
+    let (root, proof) = deep_proof(&t.env, &leaf, merkle::MAX_PROOF_DEPTH as u8 + 1);
+    client.set_merkle_root(&t.admin, &0, &root, &110);
+    let packed = pack_proof(&t.env, &proof);
+    let result = client.try_stake_packed(&user, &0, &10_000_0000000, &packed);
+    assert_eq!(result, Err(Ok(ContractError::ProofTooLong)));
+}
+
+// ========== positional proof tests ==========
+
+#[test]
+fn test_stake_positional_pool() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+    client.set_positional_proofs(&t.admin, &0, &true);
+    assert!(client.get_pool_config(&0).positional_proofs);
+
+    let users = [
+        Address::generate(&t.env),
+        Address::generate(&t.env),
+        Address::generate(&t.env),
+    ];
+    let leaves: alloc::vec::Vec<BytesN<32>> = users
+        .iter()
+        .map(|user| merkle::compute_leaf(&t.env, &t.contract_id, 0, user, 1_000_0000000, 1))
+        .collect();
+    let (root, proofs, paths) = build_positional_merkle_tree(&t.env, &leaves);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+
+    // Sorted-pair staking is refused on a positional pool
+    let result = client.try_stake(&users[0], &0, &1_000_0000000, &proofs.get(0).unwrap());
+    assert_eq!(result, Err(Ok(ContractError::WrongPoolMode)));
+
+    for (i, user) in users.iter().enumerate() {
+        let i = i as u32;
+        client.stake_positional(
+            user,
+            &0,
+            &1_000_0000000,
+            &proofs.get(i).unwrap(),
+            &paths.get(i).unwrap(),
+        );
+    }
+    assert_eq!(client.get_pool_state(&0).total_staked, 3_000_0000000);
+}
+
+#[test]
+fn test_stake_positional_rejected_on_sorted_pool() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+    client.add_pool(&t.admin, &make_pool_id(&t.env, 1));
+
+    let user = Address::generate(&t.env);
+    let leaf = merkle::compute_leaf(&t.env, &t.contract_id, 0, &user, 1_000_0000000, 1);
+    let (root, proofs) = build_merkle_tree(&t.env, &[leaf]);
+    client.set_merkle_root(&t.admin, &0, &root, &100);
+
+    let result =
+        client.try_stake_positional(&user, &0, &1_000_0000000, &proofs.get(0).unwrap(), &0);
+    assert_eq!(result, Err(Ok(ContractError::WrongPoolMode)));
+}
+
+// ========== rescue_tokens tests ==========
+
+#[test]
+fn test_rescue_tokens() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+
+    let stray_token = t.env.register_stellar_asset_contract_v2(Address::generate(&t.env)).address();
+    token::StellarAssetClient::new(&t.env, &stray_token).mint(&t.contract_id, &500_0000000);
+
+    let owner = Address::generate(&t.env);
+    client.rescue_tokens(&t.admin, &stray_token, &owner, &500_0000000);
+
+    let stray_client = token::Client::new(&t.env, &stray_token);
+    assert_eq!(stray_client.balance(&owner), 500_0000000);
+    assert_eq!(stray_client.balance(&t.contract_id), 0);
+}
+
+#[test]
+fn test_rescue_reward_token_fails() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+
+    let result = client.try_rescue_tokens(&t.admin, &t.lmnr_token, &t.admin, &1_0000000);
+    assert_eq!(result, Err(Ok(ContractError::CannotRescueRewardToken)));
+    assert_eq!(client.reward_balance(), 50_000_0000000);
+}
+
+#[test]
+fn test_rescue_tokens_non_admin_fails() {
+    let t = setup_env();
+    let client = LpStakingContractClient::new(&t.env, &t.contract_id);
+
+    let stray_token = t.env.register_stellar_asset_contract_v2(Address::generate(&t.env)).address();
+    token::StellarAssetClient::new(&t.env, &stray_token).mint(&t.contract_id, &500_0000000);
+
+    let rando = Address::generate(&t.env);
+    let result = client.try_rescue_tokens(&rando, &stray_token, &rando, &500_0000000);
+    assert!(result.is_err());
+}
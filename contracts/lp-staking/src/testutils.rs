@@ -0,0 +1,127 @@
+#![cfg(any(test, feature = "testutils"))]
+//! Helpers for contracts integrating with lp-staking to build valid roots
+//! and proofs in their own test environments. Enabled by the `testutils`
+//! feature.
+
+use soroban_sdk::{Bytes, BytesN, Env, Vec};
+
+pub use crate::merkle::{
+    compute_identity_leaf, compute_indexed_leaf, compute_leaf, compute_twab_leaf,
+};
+use crate::merkle::{hash_ordered, hash_pair};
+
+/// Build a Merkle tree over `leaves` the way the snapshot indexer does
+/// (`staking-site/lib/merkle.ts`) and return `(root, proofs)`, with
+/// `proofs` in leaf order.
+///
+/// Adjacent nodes are hashed pairwise level by level; a node left without a
+/// partner is promoted to the next level unchanged. A single leaf is its own
+/// root with an empty proof.
+pub fn build_merkle_tree(env: &Env, leaves: &[BytesN<32>]) -> (BytesN<32>, Vec<Vec<BytesN<32>>>) {
+    assert!(
+        !leaves.is_empty(),
+        "build_merkle_tree needs at least one leaf"
+    );
+
+    let mut level = Vec::new(env);
+    let mut proofs = Vec::new(env);
+    for leaf in leaves {
+        level.push_back(leaf.clone());
+        proofs.push_back(Vec::new(env));
+    }
+
+    // Position of each leaf's ancestor in the current level
+    let mut positions = Vec::new(env);
+    for i in 0..leaves.len() as u32 {
+        positions.push_back(i);
+    }
+
+    while level.len() > 1 {
+        for leaf in 0..proofs.len() {
+            let position = positions.get(leaf).unwrap();
+            let sibling = position ^ 1;
+            if sibling < level.len() {
+                let mut proof = proofs.get(leaf).unwrap();
+                proof.push_back(level.get(sibling).unwrap());
+                proofs.set(leaf, proof);
+            }
+            positions.set(leaf, position / 2);
+        }
+
+        let mut next = Vec::new(env);
+        for i in (0..level.len()).step_by(2) {
+            let node = level.get(i).unwrap();
+            match level.get(i + 1) {
+                Some(partner) => next.push_back(hash_pair(env, &node, &partner)),
+                None => next.push_back(node),
+            }
+        }
+        level = next;
+    }
+
+    (level.get(0).unwrap(), proofs)
+}
+
+/// Pack `proof` into the single `Bytes` argument `stake_packed` takes:
+/// each sibling's 32 bytes, leaf level first.
+pub fn pack_proof(env: &Env, proof: &Vec<BytesN<32>>) -> Bytes {
+    let mut packed = Bytes::new(env);
+    for sibling in proof.iter() {
+        packed.append(&sibling.into());
+    }
+    packed
+}
+
+/// Positional counterpart of [`build_merkle_tree`] for pools with
+/// positional proofs enabled: pairs are hashed left to right without
+/// sorting. Returns `(root, proofs, paths)`, where `paths[i]` holds the
+/// direction bits `stake_positional` expects for leaf `i`.
+pub fn build_positional_merkle_tree(
+    env: &Env,
+    leaves: &[BytesN<32>],
+) -> (BytesN<32>, Vec<Vec<BytesN<32>>>, Vec<u32>) {
+    assert!(
+        !leaves.is_empty(),
+        "build_positional_merkle_tree needs at least one leaf"
+    );
+
+    let mut level = Vec::new(env);
+    let mut proofs = Vec::new(env);
+    let mut paths = Vec::new(env);
+    let mut positions = Vec::new(env);
+    for (i, leaf) in leaves.iter().enumerate() {
+        level.push_back(leaf.clone());
+        proofs.push_back(Vec::new(env));
+        paths.push_back(0u32);
+        positions.push_back(i as u32);
+    }
+
+    while level.len() > 1 {
+        for leaf in 0..proofs.len() {
+            let position = positions.get(leaf).unwrap();
+            let sibling = position ^ 1;
+            if sibling < level.len() {
+                let mut proof = proofs.get(leaf).unwrap();
+                if position & 1 == 1 {
+                    // Sibling is the left child
+                    paths.set(leaf, paths.get(leaf).unwrap() | (1 << proof.len()));
+                }
+                proof.push_back(level.get(sibling).unwrap());
+                proofs.set(leaf, proof);
+            }
+            positions.set(leaf, position / 2);
+        }
+
+        let mut next = Vec::new(env);
+        for i in (0..level.len()).step_by(2) {
+            let node = level.get(i).unwrap();
+            match level.get(i + 1) {
+                Some(right) => next.push_back(hash_ordered(env, &node, &right)),
+                None => next.push_back(node),
+            }
+        }
+        level = next;
+    }
+
+    (level.get(0).unwrap(), proofs, paths)
+}
@@ -0,0 +1,125 @@
+//! Switching the LMNR payout token for a redeployed one (see
+//! `migrate_reward_token`). What the contract owes or budgets in LMNR is
+//! restated in the new token at the exchange ratio: pool and global books
+//! all at once, staker entries as they are next read.
+
+use soroban_sdk::{Address, Env};
+
+use crate::rewards;
+use crate::storage::{self, RateCheckpoint, StakerInfo};
+
+/// Fixed point of `exchange_ratio`: new-token stroops per old-token stroop
+/// times 1e7, so 1_0000000 swaps one for one.
+pub const RATIO_SCALE: i128 = 10_000_000;
+
+pub fn convert(amount: i128, ratio: i128) -> i128 {
+    amount * ratio / RATIO_SCALE
+}
+
+/// Restate every pool-level and global LMNR amount at `ratio`. Every pool
+/// must have been updated to now first, so no accrual straddles the switch.
+/// Lifetime per-user claim totals and claim stats stay in the units they
+/// were paid in.
+pub fn rescale_books(env: &Env, ratio: i128) {
+    let rate = convert(storage::get_reward_rate(env), ratio);
+    storage::set_reward_rate(env, rate);
+    storage::set_rate_checkpoint(
+        env,
+        &RateCheckpoint {
+            timestamp: env.ledger().timestamp(),
+            rate,
+            cumulative: convert(rewards::emission_index(env), ratio),
+        },
+    );
+
+    for i in 0..storage::get_pool_count(env) {
+        let emission_index = storage::get_pool_emission_index(env, i);
+        storage::set_pool_emission_index(env, i, convert(emission_index, ratio));
+        if !storage::pays_lmnr(env, i) {
+            continue;
+        }
+
+        let mut state = storage::get_pool_state(env, i);
+        state.acc_reward_per_share = convert(state.acc_reward_per_share, ratio);
+        state.prev_acc_reward_per_share = convert(state.prev_acc_reward_per_share, ratio);
+        storage::set_pool_state(env, i, &state);
+
+        let remainder = storage::get_pool_remainder(env, i);
+        storage::set_pool_remainder(env, i, convert(remainder, ratio));
+        let liability = storage::get_pool_liability(env, i);
+        storage::set_pool_liability(env, i, convert(liability, ratio));
+        let earmark = storage::get_pool_earmark(env, i);
+        storage::set_pool_earmark(env, i, convert(earmark, ratio));
+        let carry = storage::get_pool_carry(env, i);
+        storage::set_pool_carry(env, i, convert(carry, ratio));
+        let oracle_rate = storage::get_oracle_rate(env, i);
+        storage::set_oracle_rate(env, i, convert(oracle_rate, ratio));
+
+        if let Some(mut schedule) = storage::get_reward_schedule(env, i) {
+            schedule.rate = convert(schedule.rate, ratio);
+            storage::set_reward_schedule(env, i, &schedule);
+        }
+        let mut config = storage::get_pool_config(env, i);
+        if config.epoch_budget > 0 {
+            config.epoch_budget = convert(config.epoch_budget, ratio);
+            storage::set_pool_config(env, i, &config);
+        }
+    }
+    rewards::rebuild_emission_totals(env);
+
+    let mut accounting = storage::get_accounting(env);
+    accounting.total_funded = convert(accounting.total_funded, ratio);
+    accounting.total_claimed = convert(accounting.total_claimed, ratio);
+    accounting.total_withdrawn = convert(accounting.total_withdrawn, ratio);
+    accounting.total_bounties = convert(accounting.total_bounties, ratio);
+    storage::set_accounting(env, &accounting);
+
+    let mut bounds = storage::get_rate_bounds(env);
+    bounds.max_rate = convert(bounds.max_rate, ratio);
+    bounds.max_daily_emission = convert(bounds.max_daily_emission, ratio);
+    storage::set_rate_bounds(env, &bounds);
+
+    if let Some(mut oracle) = storage::get_rate_oracle(env) {
+        oracle.min_rate = convert(oracle.min_rate, ratio);
+        oracle.max_rate = convert(oracle.max_rate, ratio);
+        storage::set_rate_oracle(env, &Some(oracle));
+    }
+
+    let mut bounties = storage::get_keeper_bounties(env);
+    bounties.checkpoint = convert(bounties.checkpoint, ratio);
+    bounties.expire = convert(bounties.expire, ratio);
+    bounties.sweep = convert(bounties.sweep, ratio);
+    storage::set_keeper_bounties(env, &bounties);
+    storage::set_prune_bounty(env, convert(storage::get_prune_bounty(env), ratio));
+    storage::set_min_claim(env, convert(storage::get_min_claim(env), ratio));
+
+    if let Some(mut pending) = storage::get_pending_withdrawal(env) {
+        pending.amount = convert(pending.amount, ratio);
+        storage::set_pending_withdrawal(env, &pending);
+    }
+}
+
+/// Restate `staker`'s reward debt and pending rewards for each migration
+/// since its entry was last written. Partner-token pools are left as is.
+pub fn convert_staker(env: &Env, user: &Address, pool_index: u32, staker: &mut StakerInfo) {
+    let ratios = storage::get_token_migrations(env);
+    if ratios.is_empty() {
+        return;
+    }
+    let era = storage::get_staker_era(env, user, pool_index);
+    if era >= ratios.len() {
+        return;
+    }
+    let state_pool = storage::get_pool_config(env, pool_index)
+        .shared_with
+        .unwrap_or(pool_index);
+    if !storage::pays_lmnr(env, state_pool) {
+        return;
+    }
+
+    for i in era..ratios.len() {
+        let ratio = ratios.get(i).unwrap();
+        staker.reward_debt = convert(staker.reward_debt, ratio);
+        staker.pending_rewards = convert(staker.pending_rewards, ratio);
+    }
+}
@@ -0,0 +1,99 @@
+// Runs the stake/claim/epoch flow against the compiled contract rather than
+// the native build, catching wasm-only failures (XDR limits, budget). Build
+// the artifact first, then run with the testutils feature:
+//
+//   stellar contract build
+//   cargo test --features testutils --test wasm
+//
+// LP_STAKING_WASM overrides the artifact path.
+#![allow(clippy::inconsistent_digit_grouping)]
+
+use lp_staking::testutils::{build_merkle_tree, compute_leaf};
+use lp_staking::LpStakingContractClient;
+use soroban_sdk::testutils::{Address as _, Ledger, LedgerInfo};
+use soroban_sdk::{token, Address, BytesN, Env};
+
+const DEFAULT_WASM: &str = "../target/wasm32-unknown-unknown/release/lp_staking.wasm";
+const RATE: i128 = 462_962_963;
+
+fn load_wasm() -> Vec<u8> {
+    let path = std::env::var("LP_STAKING_WASM")
+        .unwrap_or_else(|_| format!("{}/{}", env!("CARGO_MANIFEST_DIR"), DEFAULT_WASM));
+    std::fs::read(&path)
+        .unwrap_or_else(|err| panic!("{path}: {err}; run `stellar contract build` first"))
+}
+
+fn set_ledger(env: &Env, timestamp: u64, sequence_number: u32) {
+    env.ledger().set(LedgerInfo {
+        timestamp,
+        protocol_version: 22,
+        sequence_number,
+        network_id: [0u8; 32],
+        base_reserve: 10,
+        min_temp_entry_ttl: 100,
+        min_persistent_entry_ttl: 100,
+        max_entry_ttl: 10_000_000,
+    });
+}
+
+#[test]
+fn test_wasm_stake_claim_epoch_flow() {
+    let env = Env::default();
+    env.mock_all_auths();
+    set_ledger(&env, 1000, 100);
+
+    let wasm = load_wasm();
+    let contract_id = env.register(wasm.as_slice(), ());
+    let client = LpStakingContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let lmnr_token = env
+        .register_stellar_asset_contract_v2(Address::generate(&env))
+        .address();
+    client.initialize(&admin, &lmnr_token, &RATE);
+    token::StellarAssetClient::new(&env, &lmnr_token).mint(&admin, &50_000_0000000);
+    client.fund(&admin, &50_000_0000000);
+    client.add_pool(&admin, &BytesN::from_array(&env, &[1u8; 32]));
+
+    // Epoch 1: three LPs prove their balances
+    let users = [
+        Address::generate(&env),
+        Address::generate(&env),
+        Address::generate(&env),
+    ];
+    let balances = [1_000_0000000, 3_000_0000000, 6_000_0000000];
+    let leaves: Vec<BytesN<32>> = (0..3)
+        .map(|i| compute_leaf(&env, &contract_id, 0, &users[i], balances[i], 1))
+        .collect();
+    let (root, proofs) = build_merkle_tree(&env, &leaves);
+    client.set_merkle_root(&admin, &0, &root, &100);
+    for i in 0..3 {
+        client.stake(&users[i], &0, &balances[i], &proofs.get(i as u32).unwrap());
+    }
+    assert_eq!(client.get_pool_state(&0).total_staked, 10_000_0000000);
+
+    set_ledger(&env, 1100, 110);
+    let paid = client.claim(&users[2], &0);
+    assert_eq!(paid, 100 * RATE * 6 / 10);
+
+    // Epoch 2: only the first LP re-proves; the others keep what they earned
+    let leaf = compute_leaf(&env, &contract_id, 0, &users[0], balances[0], 2);
+    let (root, proofs) = build_merkle_tree(&env, &[leaf]);
+    client.set_merkle_root(&admin, &0, &root, &110);
+    client.stake(&users[0], &0, &balances[0], &proofs.get(0).unwrap());
+    assert_eq!(client.get_merkle_root(&0).epoch_id, 2);
+
+    set_ledger(&env, 1200, 120);
+    assert!(client.claim(&users[0], &0) > 0);
+    assert_eq!(client.claim(&users[1], &0), 100 * RATE * 3 / 10);
+
+    client.unstake(&users[0], &0);
+    assert_eq!(
+        client.get_pool_state(&0).total_staked,
+        balances[1] + balances[2]
+    );
+
+    let token_client = token::Client::new(&env, &lmnr_token);
+    let claimed = client.get_accounting().total_claimed;
+    assert_eq!(token_client.balance(&contract_id), 50_000_0000000 - claimed);
+}
@@ -171,9 +171,13 @@ export interface Client {
   /**
    * Construct and simulate a set_reward_rate transaction. Returns an `AssembledTransaction` object which will have a `result` field containing the result of the simulation. If this transaction changes contract state, you will need to call `signAndSend()` on the returned object.
    * Update the global reward rate (LMNR stroops per second).
-   * Updates all active pools' accumulators before changing rate.
+   * Records a `RateCheckpoint`; pools pick the new rate up on their next
+   * update, having accrued at the old one until now.
+   * The rate must be within the `set_rate_bounds` ceilings. Unless
+   * `force` is set, the balance not already owed to stakers must also
+   * cover the configured minimum runway at the new rate.
    */
-  set_reward_rate: ({admin, new_rate}: {admin: string, new_rate: i128}, options?: MethodOptions) => Promise<AssembledTransaction<Result<void>>>
+  set_reward_rate: ({admin, new_rate, force}: {admin: string, new_rate: i128, force: boolean}, options?: MethodOptions) => Promise<AssembledTransaction<Result<void>>>
 
 }
 export class Client extends ContractClient {
@@ -208,7 +212,7 @@ export class Client extends ContractClient {
         "AAAAAAAAACtRdWVyeSBjdXJyZW50IGVwb2NoIE1lcmtsZSByb290IGZvciBhIHBvb2wuAAAAAA9nZXRfbWVya2xlX3Jvb3QAAAAAAQAAAAAAAAAKcG9vbF9pbmRleAAAAAAABAAAAAEAAAfQAAAADk1lcmtsZVJvb3REYXRhAAA=",
         "AAAAAAAAAB9RdWVyeSBzdGFrZSBkZXRhaWxzIGZvciBhIHVzZXIuAAAAAA9nZXRfc3Rha2VyX2luZm8AAAAAAgAAAAAAAAAEdXNlcgAAABMAAAAAAAAACnBvb2xfaW5kZXgAAAAAAAQAAAABAAAH0AAAAApTdGFrZXJJbmZvAAA=",
         "AAAAAAAAAHZQb3N0IGEgbmV3IE1lcmtsZSByb290IGZvciBhIHBvb2wncyBMUCBzbmFwc2hvdHMuClJlc2V0cyB0b3RhbF9zdGFrZWQg4oCUIGFsbCB1c2VycyBtdXN0IHJlLXByb3ZlIHRvIGNvbnRpbnVlIGVhcm5pbmcuAAAAAAAPc2V0X21lcmtsZV9yb290AAAAAAQAAAAAAAAABWFkbWluAAAAAAAAEwAAAAAAAAAKcG9vbF9pbmRleAAAAAAABAAAAAAAAAAEcm9vdAAAA+4AAAAgAAAAAAAAAA9zbmFwc2hvdF9sZWRnZXIAAAAABAAAAAEAAAPpAAAD7QAAAAAAAAfQAAAADUNvbnRyYWN0RXJyb3IAAAA=",
-        "AAAAAAAAAHVVcGRhdGUgdGhlIGdsb2JhbCByZXdhcmQgcmF0ZSAoTE1OUiBzdHJvb3BzIHBlciBzZWNvbmQpLgpVcGRhdGVzIGFsbCBhY3RpdmUgcG9vbHMnIGFjY3VtdWxhdG9ycyBiZWZvcmUgY2hhbmdpbmcgcmF0ZS4AAAAAAAAPc2V0X3Jld2FyZF9yYXRlAAAAAAIAAAAAAAAABWFkbWluAAAAAAAAEwAAAAAAAAAIbmV3X3JhdGUAAAALAAAAAQAAA+kAAAPtAAAAAAAAB9AAAAANQ29udHJhY3RFcnJvcgAAAA==",
+        "AAAAAAAAAWRVcGRhdGUgdGhlIGdsb2JhbCByZXdhcmQgcmF0ZSAoTE1OUiBzdHJvb3BzIHBlciBzZWNvbmQpLgpSZWNvcmRzIGEgYFJhdGVDaGVja3BvaW50YDsgcG9vbHMgcGljayB0aGUgbmV3IHJhdGUgdXAgb24gdGhlaXIgbmV4dAp1cGRhdGUsIGhhdmluZyBhY2NydWVkIGF0IHRoZSBvbGQgb25lIHVudGlsIG5vdy4KVGhlIHJhdGUgbXVzdCBiZSB3aXRoaW4gdGhlIGBzZXRfcmF0ZV9ib3VuZHNgIGNlaWxpbmdzLiBVbmxlc3MKYGZvcmNlYCBpcyBzZXQsIHRoZSBiYWxhbmNlIG5vdCBhbHJlYWR5IG93ZWQgdG8gc3Rha2VycyBtdXN0IGFsc28KY292ZXIgdGhlIGNvbmZpZ3VyZWQgbWluaW11bSBydW53YXkgYXQgdGhlIG5ldyByYXRlLgAAAA9zZXRfcmV3YXJkX3JhdGUAAAAAAwAAAAAAAAAFYWRtaW4AAAAAAAATAAAAAAAAAAhuZXdfcmF0ZQAAAAsAAAAAAAAABWZvcmNlAAAAAAAAAQAAAAEAAAPpAAAD7QAAAAAAAAfQAAAADUNvbnRyYWN0RXJyb3IAAAA=",
         "AAAABAAAAAAAAAAAAAAADUNvbnRyYWN0RXJyb3IAAAAAAAANAAAAAAAAABJBbHJlYWR5SW5pdGlhbGl6ZWQAAAAAAAEAAAAAAAAADk5vdEluaXRpYWxpemVkAAAAAAACAAAAAAAAAAxVbmF1dGhvcml6ZWQAAAADAAAAAAAAABFQb29sQWxyZWFkeUV4aXN0cwAAAAAAAAQAAAAAAAAADFBvb2xOb3RGb3VuZAAAAAUAAAAAAAAADEludmFsaWRQcm9vZgAAAAYAAAAAAAAAFkFscmVhZHlTdGFrZWRUaGlzRXBvY2gAAAAAAAcAAAAAAAAADE5vU3Rha2VGb3VuZAAAAAgAAAAAAAAAEE5vUmV3YXJkc1RvQ2xhaW0AAAAJAAAAAAAAABlJbnN1ZmZpY2llbnRSZXdhcmRCYWxhbmNlAAAAAAAACgAAAAAAAAANSW52YWxpZEFtb3VudAAAAAAAAAsAAAAAAAAADE5vTWVya2xlUm9vdAAAAAwAAAAAAAAAClN0YWxlRXBvY2gAAAAAAA0=",
         "AAAAAgAAAAAAAAAAAAAAB0RhdGFLZXkAAAAACQAAAAAAAAAAAAAABUFkbWluAAAAAAAAAAAAAAAAAAAJTG1uclRva2VuAAAAAAAAAAAAAAAAAAAQUmV3YXJkUmF0ZVBlclNlYwAAAAAAAAAAAAAACVBvb2xDb3VudAAAAAAAAAEAAAAAAAAABlBvb2xJZAAAAAAAAQAAAAQAAAABAAAAAAAAAAtQb29sSWRJbmRleAAAAAABAAAD7gAAACAAAAABAAAAAAAAAAlQb29sU3RhdGUAAAAAAAABAAAABAAAAAEAAAAAAAAACk1lcmtsZVJvb3QAAAAAAAEAAAAEAAAAAQAAAAAAAAAGU3Rha2VyAAAAAAACAAAAEwAAAAQ=",
         "AAAAAQAAAAAAAAAAAAAACVBvb2xTdGF0ZQAAAAAAAAQAAAAAAAAAFGFjY19yZXdhcmRfcGVyX3NoYXJlAAAACwAAAAAAAAAQbGFzdF9yZXdhcmRfdGltZQAAAAYAAAAAAAAAGXByZXZfYWNjX3Jld2FyZF9wZXJfc2hhcmUAAAAAAAALAAAAAAAAAAx0b3RhbF9zdGFrZWQAAAAL",
@@ -200,6 +200,7 @@ export function AdminDashboard() {
       const tx = await client.set_reward_rate({
         admin: publicKey,
         new_rate: stroopsPerSec,
+        force: false,
       });
       console.log("[Admin] set_reward_rate: simulation done, calling signAndSend...");
       await tx.signAndSend();
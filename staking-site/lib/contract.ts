@@ -13,6 +13,9 @@ import { assembleTransaction } from "@stellar/stellar-sdk/rpc";
 
 // Creit-Tech Stellar Router V0 — batches multiple contract calls into one tx
 const STELLAR_ROUTER_V0 = "CBZV3HBP672BV7FF3ZILVT4CNPW3N5V2WTJ2LAGOAYW5R7L2D5SLUDFZ";
+
+// Addresses the contract accepts per seed_from_legacy call
+const SEED_PAGE_SIZE = 25;
 import {
   AssembledTransaction,
   Client as ContractClient,
@@ -34,14 +37,20 @@ export interface PoolState {
 }
 
 export interface StakerInfo {
+  cooldown_until: bigint;
   epoch_id: bigint;
   pending_rewards: bigint;
   reward_debt: bigint;
   staked_amount: bigint;
+  streak: number;
+  weight: bigint;
 }
 
 export interface MerkleRootData {
+  declared_total: bigint;
+  epoch_duration: bigint;
   epoch_id: bigint;
+  leaf_version: number;
   posted_at: bigint;
   root: Buffer;
   snapshot_ledger: number;
@@ -128,7 +137,7 @@ export interface StakingClient {
     options?: any
   ): Promise<AssembledTransaction<any>>;
   set_reward_rate(
-    args: { admin: string; new_rate: bigint },
+    args: { admin: string; new_rate: bigint; force: boolean },
     options?: any
   ): Promise<AssembledTransaction<any>>;
   fund(
@@ -139,6 +148,14 @@ export interface StakingClient {
     args: { admin: string; new_wasm_hash: Buffer },
     options?: any
   ): Promise<AssembledTransaction<any>>;
+  seed_from_legacy(
+    args: { admin: string; legacy: string; page: string[] },
+    options?: any
+  ): Promise<AssembledTransaction<number>>;
+  activate(
+    args: { admin: string },
+    options?: any
+  ): Promise<AssembledTransaction<any>>;
 }
 
 type SignTransactionFn = (
@@ -415,7 +432,8 @@ export function createAdminClient(): StakingClient & {
   rawSetMerkleRoot: (
     poolIndex: number,
     root: Buffer,
-    snapshotLedger: number
+    snapshotLedger: number,
+    declaredTotal: bigint
   ) => Promise<Api.GetSuccessfulTransactionResponse>;
   rawUpdateStake: (
     poolIndex: number,
@@ -498,19 +516,21 @@ export function createAdminClient(): StakingClient & {
   };
 
   /**
-   * Build set_merkle_root transaction entirely from scratch.
+   * Build set_merkle_root_with_total transaction entirely from scratch.
    * This is the nuclear option — zero dependency on ContractClient for signing.
    */
   const rawSetMerkleRoot = (
     poolIndex: number,
     root: Buffer,
-    snapshotLedger: number
+    snapshotLedger: number,
+    declaredTotal: bigint
   ) => {
-    return rawInvokeContract(keypair, "set_merkle_root", [
+    return rawInvokeContract(keypair, "set_merkle_root_with_total", [
       new Address(keypair.publicKey()).toScVal(), // admin
       nativeToScVal(poolIndex, { type: "u32" }), // pool_index
       xdr.ScVal.scvBytes(root), // root: BytesN<32>
       nativeToScVal(snapshotLedger, { type: "u32" }), // snapshot_ledger
+      nativeToScVal(declaredTotal, { type: "i128" }), // declared_total
     ]);
   };
 
@@ -677,3 +697,36 @@ export function createUserClient(
 ): StakingClient {
   return makeClient({ publicKey, signTransaction });
 }
+
+/**
+ * Seed a fresh deployment from the legacy contract before activation: an
+ * empty page copies the pools, then `stakers` (taken from the snapshot
+ * tooling, as the legacy contract keeps no registry) are copied 25 at a
+ * time. Pause the legacy contract first. Returns records copied.
+ */
+export async function seedFromLegacy(
+  publicKey: string,
+  signTransaction: SignTransactionFn,
+  legacyContractId: string,
+  stakers: string[],
+  onProgress?: (page: number, copied: number) => void
+): Promise<number> {
+  const client = createUserClient(publicKey, signTransaction);
+  const pages: string[][] = [[]];
+  for (let i = 0; i < stakers.length; i += SEED_PAGE_SIZE) {
+    pages.push(stakers.slice(i, i + SEED_PAGE_SIZE));
+  }
+  let total = 0;
+  for (let page = 0; page < pages.length; page++) {
+    const tx = await client.seed_from_legacy({
+      admin: publicKey,
+      legacy: legacyContractId,
+      page: pages[page],
+    });
+    const { result } = await tx.signAndSend();
+    const copied = Number(result);
+    onProgress?.(page, copied);
+    total += copied;
+  }
+  return total;
+}
@@ -5,7 +5,7 @@
 
 import { Horizon } from "@stellar/stellar-sdk";
 import { put, list } from "@vercel/blob";
-import { HORIZON_URL } from "./constants";
+import { CONTRACT_ID, HORIZON_URL } from "./constants";
 import { createAdminClient } from "./contract";
 import { buildMerkleTree, computeLeaf } from "./merkle";
 
@@ -89,7 +89,7 @@ export async function processPool(snapshot: PoolSnapshot): Promise<{
 
   // Compute leaves
   const leaves = holders.map((h) =>
-    computeLeaf(poolIndex, h.address, h.balance, nextEpochId)
+    computeLeaf(CONTRACT_ID, poolIndex, h.address, h.balance, nextEpochId)
   );
 
   // Build tree
@@ -118,7 +118,14 @@ export async function processPool(snapshot: PoolSnapshot): Promise<{
     `Pool ${poolIndex}: posting root ${rootHex} (epoch ${nextEpochId}, ${holders.length} holders)`
   );
 
-  await adminClient.rawSetMerkleRoot(poolIndex, tree.root, ledger);
+  // Declare the snapshot's total so the contract caps what can be proven
+  const declaredTotal = holders.reduce((sum, h) => sum + h.balance, 0n);
+  await adminClient.rawSetMerkleRoot(
+    poolIndex,
+    tree.root,
+    ledger,
+    declaredTotal
+  );
 
   // Reconcile staker balances via batched router call (one batch per pool)
   const currentBalances = new Map<string, bigint>();
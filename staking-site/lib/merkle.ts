@@ -2,15 +2,26 @@
  * TypeScript Merkle tree implementation that matches the Rust contract's
  * merkle.rs byte-for-byte. Used by the indexer to build trees off-chain.
  *
- * Leaf:  SHA-256(0x00 || pool_index_u32_be || user_address_scval_xdr || lp_balance_i128_be || epoch_id_u64_be)
+ * Header: 0x00/0x02/0x03 prefix || leaf_version || network_id || contract_address_scval_xdr || pool_index_u32_be
+ * Leaf:  SHA-256(header(0x00) || user_address_scval_xdr || lp_balance_i128_be || epoch_id_u64_be)
+ * TWAB leaf (version 2): SHA-256(header(0x00) || user_address_scval_xdr || twab_i128_be || epoch_duration_u64_be || epoch_id_u64_be)
+ * Identity leaf: SHA-256(header(0x02) || identity_hash || lp_balance_i128_be || epoch_id_u64_be)
+ * Indexed leaf: SHA-256(header(0x03) || user_address_scval_xdr || leaf_index_u32_be || lp_balance_i128_be || epoch_id_u64_be)
  * Node:  SHA-256(0x01 || min(left, right) || max(left, right))
+ *
+ * network_id is SHA-256 of the network passphrase, binding leaves to one network.
  */
 
 import { createHash } from "crypto";
 import { Address } from "@stellar/stellar-sdk";
+import { NETWORK_PASSPHRASE } from "./constants";
 
 const LEAF_PREFIX = 0x00;
 const NODE_PREFIX = 0x01;
+const IDENTITY_LEAF_PREFIX = 0x02;
+const INDEXED_LEAF_PREFIX = 0x03;
+const LEAF_VERSION = 1; // must match merkle::LEAF_VERSION
+const TWAB_LEAF_VERSION = 2; // must match merkle::TWAB_LEAF_VERSION
 
 function sha256(data: Buffer): Buffer {
   return createHash("sha256").update(data).digest();
@@ -32,23 +43,43 @@ function bigintToI128BE(value: bigint): Buffer {
   return buf;
 }
 
+/**
+ * Fields shared by every leaf kind, matching Rust's leaf_header().
+ */
+function leafHeader(
+  prefix: number,
+  contractId: string,
+  poolIndex: number,
+  version: number = LEAF_VERSION
+): Buffer {
+  const networkId = sha256(Buffer.from(NETWORK_PASSPHRASE));
+  const contractXdr = new Address(contractId).toScVal().toXDR();
+
+  // pool_index as u32 big-endian
+  const poolBuf = Buffer.alloc(4);
+  poolBuf.writeUInt32BE(poolIndex);
+
+  return Buffer.concat([
+    Buffer.from([prefix, version]),
+    networkId,
+    contractXdr,
+    poolBuf,
+  ]);
+}
+
 /**
  * Compute a Merkle leaf hash identical to the Rust contract's compute_leaf().
  *
  * The address is serialized as ScVal XDR — this matches Rust's `address.to_xdr(env)`.
  */
 export function computeLeaf(
+  contractId: string,
   poolIndex: number,
   userAddress: string,
   lpBalance: bigint,
   epochId: bigint
 ): Buffer {
-  // 0x00 prefix
-  const prefix = Buffer.from([LEAF_PREFIX]);
-
-  // pool_index as u32 big-endian
-  const poolBuf = Buffer.alloc(4);
-  poolBuf.writeUInt32BE(poolIndex);
+  const header = leafHeader(LEAF_PREFIX, contractId, poolIndex);
 
   // user address -> ScVal XDR bytes (matches Rust's Address::to_xdr)
   const addr = new Address(userAddress);
@@ -61,7 +92,124 @@ export function computeLeaf(
   const epochBuf = Buffer.alloc(8);
   epochBuf.writeBigUInt64BE(epochId);
 
-  return sha256(Buffer.concat([prefix, poolBuf, addrXdr, balBuf, epochBuf]));
+  return sha256(Buffer.concat([header, addrXdr, balBuf, epochBuf]));
+}
+
+/**
+ * Compute a TWAB leaf identical to the Rust contract's compute_twab_leaf(),
+ * for roots posted with set_twab_root. `twabBalance` is the user's
+ * time-weighted average LP balance over the epoch.
+ */
+export function computeTwabLeaf(
+  contractId: string,
+  poolIndex: number,
+  userAddress: string,
+  twabBalance: bigint,
+  epochDuration: bigint,
+  epochId: bigint
+): Buffer {
+  const header = leafHeader(
+    LEAF_PREFIX,
+    contractId,
+    poolIndex,
+    TWAB_LEAF_VERSION
+  );
+
+  const addrXdr = new Address(userAddress).toScVal().toXDR();
+
+  const balBuf = bigintToI128BE(twabBalance);
+
+  const durationBuf = Buffer.alloc(8);
+  durationBuf.writeBigUInt64BE(epochDuration);
+
+  const epochBuf = Buffer.alloc(8);
+  epochBuf.writeBigUInt64BE(epochId);
+
+  return sha256(Buffer.concat([header, addrXdr, balBuf, durationBuf, epochBuf]));
+}
+
+/**
+ * Identity hash committed to by identity leaves: SHA-256(ed25519 public key).
+ * Matches Rust's identity_hash().
+ */
+export function identityHash(publicKey: Buffer): Buffer {
+  return sha256(publicKey);
+}
+
+/**
+ * Compute an identity-committed leaf identical to the Rust contract's
+ * compute_identity_leaf(). Used for LPs who bound a stable identity key.
+ */
+export function computeIdentityLeaf(
+  contractId: string,
+  poolIndex: number,
+  identity: Buffer,
+  lpBalance: bigint,
+  epochId: bigint
+): Buffer {
+  const header = leafHeader(IDENTITY_LEAF_PREFIX, contractId, poolIndex);
+
+  const balBuf = bigintToI128BE(lpBalance);
+
+  const epochBuf = Buffer.alloc(8);
+  epochBuf.writeBigUInt64BE(epochId);
+
+  return sha256(Buffer.concat([header, identity, balBuf, epochBuf]));
+}
+
+/**
+ * Compute an indexed leaf identical to the Rust contract's
+ * compute_indexed_leaf(). Used when one address holds several positions in
+ * a pool's snapshot; indices run from 0 to 63 per address.
+ */
+export function computeIndexedLeaf(
+  contractId: string,
+  poolIndex: number,
+  userAddress: string,
+  leafIndex: number,
+  lpBalance: bigint,
+  epochId: bigint
+): Buffer {
+  const header = leafHeader(INDEXED_LEAF_PREFIX, contractId, poolIndex);
+
+  const addrXdr = new Address(userAddress).toScVal().toXDR();
+
+  const indexBuf = Buffer.alloc(4);
+  indexBuf.writeUInt32BE(leafIndex);
+
+  const balBuf = bigintToI128BE(lpBalance);
+
+  const epochBuf = Buffer.alloc(8);
+  epochBuf.writeBigUInt64BE(epochId);
+
+  return sha256(Buffer.concat([header, addrXdr, indexBuf, balBuf, epochBuf]));
+}
+
+/**
+ * Message each root oracle signs with its ed25519 key for
+ * set_merkle_root_signed(), matching Rust's root_attestation_payload():
+ * contract_address_scval_xdr || pool_index_u32_be || epoch_id_u64_be || root || snapshot_ledger_u32_be.
+ * epochId is the epoch the root opens (current epoch + 1).
+ */
+export function rootAttestationPayload(
+  contractId: string,
+  poolIndex: number,
+  epochId: bigint,
+  root: Buffer,
+  snapshotLedger: number
+): Buffer {
+  const contractXdr = new Address(contractId).toScVal().toXDR();
+
+  const poolBuf = Buffer.alloc(4);
+  poolBuf.writeUInt32BE(poolIndex);
+
+  const epochBuf = Buffer.alloc(8);
+  epochBuf.writeBigUInt64BE(epochId);
+
+  const ledgerBuf = Buffer.alloc(4);
+  ledgerBuf.writeUInt32BE(snapshotLedger);
+
+  return Buffer.concat([contractXdr, poolBuf, epochBuf, root, ledgerBuf]);
 }
 
 /**